@@ -30,7 +30,7 @@ CREATE TABLE "user" (
     email TEXT NOT NULL,  -- Can be empty, for instance with system accounts.
     email_is_alias BOOLEAN,
     email_verified_at TIMESTAMP WITH TIME ZONE,
-    password TEXT NOT NULL,
+    password TEXT,  -- NULL if the user cannot sign in with a password.
     multi_factor_secret TEXT,
     multi_factor_recovery_codes TEXT[],
     locales TEXT[] NOT NULL,
@@ -73,6 +73,67 @@ CREATE TABLE user_bot_owner (
     PRIMARY KEY (bot_user_id, human_user_id)
 );
 
+-- Identities at external OpenID Connect providers which can be used to log in.
+CREATE TABLE user_external_identity (
+    user_external_identity_id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES "user"(user_id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    provider TEXT NOT NULL,  -- Name of the provider in the configuration
+    subject TEXT NOT NULL CHECK (length(subject) <= 255),  -- The "sub" claim, unique per issuer
+    email TEXT,
+
+    UNIQUE (provider, subject),
+    UNIQUE (user_id, provider)
+);
+
+-- Resized copies of each user's avatar, re-encoded into standard sizes and formats.
+-- The largest PNG copy is also what user.avatar_s3_hash points to.
+CREATE TABLE user_avatar (
+    user_id BIGINT NOT NULL REFERENCES "user"(user_id),
+    size SMALLINT NOT NULL,
+    format TEXT NOT NULL,
+    s3_hash BYTEA NOT NULL,
+
+    PRIMARY KEY (user_id, size, format),
+    CHECK (size > 0),
+    CHECK (format IN ('png', 'webp')),
+    CHECK (length(s3_hash) = 64)   -- SHA-512 hash size
+);
+
+-- The history of each user's names, recorded whenever they are renamed.
+CREATE TABLE user_name_change (
+    user_name_change_id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES "user"(user_id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    old_name TEXT NOT NULL,
+    old_slug TEXT NOT NULL,
+    new_name TEXT NOT NULL,
+    new_slug TEXT NOT NULL,
+    used_token BOOLEAN NOT NULL  -- Whether a name change token was consumed
+);
+
+-- Requests by users to delete their accounts.
+--
+-- Deletion is carried out by a background job once scheduled_at has passed,
+-- according to the deletion policy in the configuration. Until then the
+-- request can be canceled, and only one request per user may be pending.
+CREATE TABLE user_deletion (
+    user_deletion_id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES "user"(user_id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    scheduled_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    canceled_at TIMESTAMP WITH TIME ZONE,
+    completed_at TIMESTAMP WITH TIME ZONE,
+
+    CHECK (scheduled_at >= created_at),
+    CHECK (canceled_at IS NULL OR completed_at IS NULL)
+);
+
+CREATE UNIQUE INDEX user_deletion_pending
+    ON user_deletion (user_id)
+    WHERE canceled_at IS NULL AND completed_at IS NULL;
+
 --
 -- Site
 --
@@ -173,11 +234,69 @@ CREATE TABLE session (
     user_id BIGINT NOT NULL REFERENCES "user"(user_id),
     created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
     expires_at TIMESTAMP WITH TIME ZONE NOT NULL CHECK (expires_at > created_at),
-    ip_address TEXT NOT NULL,  -- TODO change to INET
+    last_used_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    ip_address INET NOT NULL,
     user_agent TEXT NOT NULL,
     restricted BOOLEAN NOT NULL
 );
 
+CREATE TABLE api_token (
+    api_token_id BIGSERIAL PRIMARY KEY,
+    bot_user_id BIGINT NOT NULL REFERENCES "user"(user_id),
+    created_by BIGINT NOT NULL REFERENCES "user"(user_id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    expires_at TIMESTAMP WITH TIME ZONE CHECK (expires_at > created_at),
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    revoked_by BIGINT REFERENCES "user"(user_id),
+    name TEXT NOT NULL CHECK (length(name) > 0),
+    token_hash BYTEA NOT NULL UNIQUE CHECK (length(token_hash) = 32),  -- SHA-256 hash size
+    scopes TEXT[] NOT NULL CHECK (cardinality(scopes) > 0),
+
+    CHECK ((revoked_at IS NULL) = (revoked_by IS NULL))
+);
+
+-- Third-party applications which users can grant access to their accounts.
+CREATE TABLE oauth_client (
+    oauth_client_id BIGSERIAL PRIMARY KEY,
+    created_by BIGINT NOT NULL REFERENCES "user"(user_id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    deleted_at TIMESTAMP WITH TIME ZONE,
+    name TEXT NOT NULL CHECK (length(name) > 0),
+    client_id TEXT NOT NULL UNIQUE,
+    client_secret_hash BYTEA CHECK (length(client_secret_hash) = 32),  -- SHA-256 hash size, NULL for public clients
+    redirect_uris TEXT[] NOT NULL CHECK (cardinality(redirect_uris) > 0),
+    scopes TEXT[] NOT NULL CHECK (cardinality(scopes) > 0)  -- The most any user can grant this client
+);
+
+-- Each row is an access token and the refresh token to renew it.
+-- Refreshing revokes the row and issues a new one.
+CREATE TABLE oauth_token (
+    oauth_token_id BIGSERIAL PRIMARY KEY,
+    oauth_client_id BIGINT NOT NULL REFERENCES oauth_client(oauth_client_id),
+    user_id BIGINT NOT NULL REFERENCES "user"(user_id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    scopes TEXT[] NOT NULL CHECK (cardinality(scopes) > 0),
+    access_token_hash BYTEA NOT NULL UNIQUE CHECK (length(access_token_hash) = 32),  -- SHA-256 hash size
+    access_expires_at TIMESTAMP WITH TIME ZONE NOT NULL CHECK (access_expires_at > created_at),
+    refresh_token_hash BYTEA NOT NULL UNIQUE CHECK (length(refresh_token_hash) = 32),  -- SHA-256 hash size
+    refresh_expires_at TIMESTAMP WITH TIME ZONE NOT NULL CHECK (refresh_expires_at >= access_expires_at)
+);
+
+CREATE TABLE webauthn_credential (
+    webauthn_credential_id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES "user"(user_id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    name TEXT NOT NULL CHECK (length(name) > 0 AND length(name) <= 100),
+    credential_id BYTEA NOT NULL UNIQUE CHECK (length(credential_id) <= 1023),  -- Maximum size per the WebAuthn spec
+    passkey JSON NOT NULL,  -- Serialized webauthn-rs passkey, with public key and counter
+
+    UNIQUE (user_id, name)
+);
+
 --
 -- Page
 --
@@ -281,6 +400,18 @@ CREATE TABLE page_revision (
         tags
     }'),
 
+    -- Ensure hidden fields are valid, see PageHiddenField
+    CHECK (hidden <@ '{
+        wikitext,
+        compiled,
+        comments,
+        user,
+        title,
+        alt_title,
+        slug,
+        tags
+    }'),
+
     -- Ensure first revision reports all changes
     --
     -- This is implemented  by seeing if it's a superset or equal to all valid values.
@@ -430,18 +561,70 @@ CREATE TABLE blob_pending (
     expected_length BIGINT NOT NULL CHECK (expected_length >= 0),
     s3_path TEXT NOT NULL CHECK (length(s3_path) > 1),
     s3_hash BYTEA,  -- NULL means not yet moved, NOT NULL means deleted from s3_path
-    presign_url TEXT NOT NULL CHECK (length(presign_url) > 1),
+    presign_url TEXT CHECK (presign_url IS NULL OR length(presign_url) > 1),  -- NULL for multipart uploads
+    multipart_upload_id TEXT,  -- S3 upload ID, only set for multipart uploads
+    multipart_part_size BIGINT CHECK (multipart_part_size IS NULL OR multipart_part_size > 0),
+    multipart_etags TEXT[],  -- ETag of each part, empty string if not yet uploaded
+
+    CHECK (expires_at > created_at),                  -- expiration time is not in the relative past
+    CHECK (length(external_id) = 24),                 -- default length for a cuid2
+    CHECK (s3_hash IS NULL OR length(s3_hash) = 64),  -- SHA-512 hash size, if present
+
+    -- Either a single presigned upload, or a multipart upload with all its fields set
+    CHECK ((presign_url IS NULL) = (multipart_upload_id IS NOT NULL)),
+    CHECK ((multipart_upload_id IS NULL) = (multipart_part_size IS NULL)),
+    CHECK ((multipart_upload_id IS NULL) = (multipart_etags IS NULL))
+);
+
+-- Perceptual hashes of image blobs, for finding near-duplicate images
+CREATE TABLE blob_perceptual_hash (
+    s3_hash BYTEA PRIMARY KEY CHECK (length(s3_hash) = 64),  -- SHA-512 hash size
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    perceptual_hash BIGINT NOT NULL  -- 64-bit difference hash, compared by Hamming distance
+);
 
-    CHECK (expires_at > created_at),                 -- expiration time is not in the relative past
-    CHECK (length(external_id) = 24),                -- default length for a cuid2
-    CHECK (s3_hash IS NULL OR length(s3_hash) = 64)  -- SHA-512 hash size, if present
+-- Media metadata extracted from image blobs
+CREATE TABLE blob_media (
+    s3_hash BYTEA PRIMARY KEY CHECK (length(s3_hash) = 64),  -- SHA-512 hash size
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    width INTEGER NOT NULL CHECK (width > 0),
+    height INTEGER NOT NULL CHECK (height > 0),
+    duration_ms INTEGER CHECK (duration_ms IS NULL OR duration_ms >= 0)  -- Only for animated images
+);
+
+-- Scaled-down copies of image blobs, stored as WebP blobs of their own
+CREATE TABLE blob_thumbnail (
+    source_hash BYTEA NOT NULL REFERENCES blob_media(s3_hash),
+    size SMALLINT NOT NULL,  -- Largest dimension the thumbnail fits within
+    s3_hash BYTEA NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+
+    PRIMARY KEY (source_hash, size),
+    CHECK (size > 0),
+    CHECK (width > 0 AND width <= size),
+    CHECK (height > 0 AND height <= size),
+    CHECK (length(s3_hash) = 64)  -- SHA-512 hash size
 );
 
 -- Manages blobs which are prohibited from being uploaded
 CREATE TABLE blob_blacklist (
     s3_hash BYTEA PRIMARY KEY CHECK (length(s3_hash) = 64),  -- SHA-512 hash size
     created_at TIMESTAMP WITH TIME ZONE NULL DEFAULT now(),
-    created_by BIGINT NOT NULL REFERENCES "user"(user_id)
+    created_by BIGINT NOT NULL REFERENCES "user"(user_id),
+    perceptual_hash BIGINT  -- If set, near-duplicates of this image are also prohibited
+);
+
+-- Results of scanning blobs for malware
+CREATE TABLE blob_scan (
+    s3_hash BYTEA PRIMARY KEY CHECK (length(s3_hash) = 64),  -- SHA-512 hash size
+    scanned_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    scanner TEXT NOT NULL,
+    infected BOOLEAN NOT NULL,
+    signature TEXT,  -- Name of the detected threat, if infected
+    uploaded_by BIGINT REFERENCES "user"(user_id),  -- NULL when rescanning stored blobs
+
+    CHECK (infected = (signature IS NOT NULL))
 );
 
 --
@@ -474,6 +657,7 @@ CREATE TABLE file (
     updated_at TIMESTAMP WITH TIME ZONE,
     deleted_at TIMESTAMP WITH TIME ZONE,
     from_wikidot BOOLEAN NOT NULL DEFAULT false,
+    copied_from_revision_id BIGINT, -- set for copies, constraint added after file_revision
     name TEXT NOT NULL,
     page_id BIGINT NOT NULL REFERENCES page(page_id),
     site_id BIGINT NOT NULL REFERENCES site(site_id),
@@ -513,6 +697,17 @@ CREATE TABLE file_revision (
         licensing
     }'),
 
+    -- Ensure hidden fields are valid, see FileHiddenField
+    CHECK (hidden <@ '{
+        comments,
+        user,
+        name,
+        s3_hash,
+        mime,
+        size,
+        licensing
+    }'),
+
     -- Ensure first revision reports all changes
     --
     -- This is implemented  by seeing if it's a superset or equal to all valid values.
@@ -539,6 +734,29 @@ CREATE TABLE file_revision (
     UNIQUE (file_id, page_id, revision_number)
 );
 
+-- Add foreign key constraint for copied_from_revision_id
+ALTER TABLE file ADD CONSTRAINT file_copied_from_revision_id_fk
+    FOREIGN KEY (copied_from_revision_id) REFERENCES file_revision(revision_id);
+
+-- Storage quota overrides for sites and users, set by platform admins.
+--
+-- Without a row, the default quota from the configuration applies.
+-- If maximum_bytes is NULL, then storage is unlimited.
+CREATE TABLE storage_quota (
+    storage_quota_id BIGSERIAL PRIMARY KEY,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP WITH TIME ZONE,
+    site_id BIGINT REFERENCES site(site_id),
+    user_id BIGINT REFERENCES "user"(user_id),
+    maximum_bytes BIGINT,
+
+    UNIQUE (site_id),
+    UNIQUE (user_id),
+
+    CHECK ((site_id IS NULL) != (user_id IS NULL)),       -- Either a site or a user quota
+    CHECK (maximum_bytes IS NULL OR maximum_bytes >= 0)   -- Cannot be negative
+);
+
 --
 -- Direct Messages
 --
@@ -663,3 +881,55 @@ CREATE TABLE filter (
 
     UNIQUE (site_id, regex, deleted_at)
 );
+
+--
+-- Exports
+--
+
+CREATE TYPE export_destination AS ENUM (
+    's3',
+    'local'
+);
+
+-- Tracks requested site and page exports, which are written by a background job.
+--
+-- If page_ids is NULL, then the whole site is exported.
+-- If site_id is NULL, then it is instead an export of the creating user's account data.
+-- Once the archive has been written, completed_at and the archive_* columns are set.
+-- If the export job gave up, then error is set instead.
+CREATE TABLE export (
+    export_id TEXT PRIMARY KEY,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    created_by BIGINT NOT NULL REFERENCES "user"(user_id),
+    completed_at TIMESTAMP WITH TIME ZONE,
+    site_id BIGINT REFERENCES site(site_id),
+    page_ids BIGINT[],
+    destination export_destination NOT NULL,
+    archive_hash BYTEA CHECK (length(archive_hash) = 64),  -- SHA-512 hash size
+    archive_size BIGINT,
+    error TEXT,
+
+    CHECK (length(export_id) = 24),  -- default length for a cuid2
+    CHECK ((completed_at IS NULL) = (archive_hash IS NULL)),
+    CHECK ((completed_at IS NULL) = (archive_size IS NULL)),
+    CHECK (completed_at IS NULL OR error IS NULL),
+    CHECK (site_id IS NOT NULL OR page_ids IS NULL)  -- account exports don't have pages
+);
+
+-- Tracks requested zip archives of the current files on a page, which are written by a background job.
+--
+-- Once the archive has been stored as a blob, completed_at and archive_hash are set.
+-- If the job gave up, then error is set instead.
+CREATE TABLE file_archive (
+    file_archive_id BIGSERIAL PRIMARY KEY,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    created_by BIGINT NOT NULL REFERENCES "user"(user_id),
+    completed_at TIMESTAMP WITH TIME ZONE,
+    site_id BIGINT NOT NULL REFERENCES site(site_id),
+    page_id BIGINT NOT NULL REFERENCES page(page_id),
+    archive_hash BYTEA CHECK (length(archive_hash) = 64),  -- SHA-512 hash size
+    error TEXT,
+
+    CHECK ((completed_at IS NULL) = (archive_hash IS NULL)),
+    CHECK (completed_at IS NULL OR error IS NULL)
+);
@@ -28,24 +28,30 @@
 
 use crate::config::{Config, Secrets};
 use crate::endpoints::{
-    auth::*, blob::*, category::*, domain::*, email::*, file::*, file_revision::*,
-    info::*, link::*, locale::*, message::*, misc::*, page::*, page_revision::*,
-    parent::*, site::*, site_member::*, text::*, user::*, user_bot::*, view::*, vote::*,
+    auth::*, blob::*, category::*, domain::*, email::*, export::*, file::*,
+    file_revision::*, info::*, link::*, locale::*, message::*, misc::*, oauth::*,
+    page::*, page_revision::*, parent::*, quota::*, site::*, site_member::*, text::*,
+    user::*, user_bot::*, view::*, vote::*,
 };
 use crate::locales::Localizations;
-use crate::services::blob::MimeAnalyzer;
+use crate::services::api_token::ApiAction;
+use crate::services::blob::{
+    BlobScanner, BlobStorage, LocalStorage, MimeAnalyzer, S3Storage,
+};
 use crate::services::job::JobWorker;
-use crate::services::{into_rpc_error, ServiceContext};
+use crate::services::oidc::OidcClient;
+use crate::services::session::ScopedToken;
+use crate::services::{into_rpc_error, ServiceContext, SessionService};
+use crate::types::StorageBackend;
 use crate::utils::debug_pointer;
 use crate::{database, redis as redis_db};
+use anyhow::anyhow;
 use jsonrpsee::server::{RpcModule, Server, ServerHandle};
 use jsonrpsee::types::error::ErrorObjectOwned;
 use rsmq_async::PooledRsmq;
-use s3::bucket::Bucket;
 use sea_orm::{DatabaseConnection, TransactionTrait};
 use std::fmt::{self, Debug};
 use std::sync::Arc;
-use std::time::Duration;
 
 pub type ServerState = Arc<ServerStateInner>;
 
@@ -56,7 +62,9 @@ pub struct ServerStateInner {
     pub rsmq: PooledRsmq,
     pub localizations: Localizations,
     pub mime_analyzer: MimeAnalyzer,
-    pub s3_bucket: Box<Bucket>,
+    pub oidc: OidcClient,
+    pub scanner: BlobScanner,
+    pub storage: Box<dyn BlobStorage>,
 }
 
 impl Debug for ServerStateInner {
@@ -68,7 +76,9 @@ impl Debug for ServerStateInner {
             .field("rsmq", &debug_pointer(&self.rsmq))
             .field("localizations", &self.localizations)
             .field("mime_analyzer", &self.mime_analyzer)
-            .field("s3_bucket", &self.s3_bucket)
+            .field("oidc", &self.oidc)
+            .field("scanner", &self.scanner)
+            .field("storage", &self.storage)
             .finish()
     }
 }
@@ -91,22 +101,37 @@ pub async fn build_server_state(
     // Load magic data and start MIME thread
     let mime_analyzer = MimeAnalyzer::spawn();
 
-    // Create S3 bucket
-    info!("Opening S3 bucket");
+    // Set up client for external identity providers
+    let oidc = OidcClient::new()?;
 
-    let s3_bucket = {
-        let mut bucket = Bucket::new(
-            &secrets.s3_bucket,
-            secrets.s3_region.clone(),
-            secrets.s3_credentials.clone(),
-        )?;
+    // Set up malware scanning
+    let scanner = BlobScanner::new(&config);
 
-        if secrets.s3_path_style {
-            bucket = bucket.with_path_style();
-        }
+    // Open blob storage
+    let storage: Box<dyn BlobStorage> = match config.storage_backend {
+        StorageBackend::S3 => {
+            info!("Opening S3 bucket");
 
-        bucket.request_timeout = Some(Duration::from_millis(500));
-        bucket
+            let secrets = secrets.s3.as_ref().ok_or_else(|| {
+                anyhow!("S3 blob storage is configured, but S3_BUCKET is not set")
+            })?;
+
+            Box::new(S3Storage::new(secrets, &config.files_domain_no_dot)?)
+        }
+        StorageBackend::Local => {
+            info!(
+                "Opening local blob storage at {}",
+                config.local_storage_path.display(),
+            );
+
+            let storage = LocalStorage::open(
+                &config.local_storage_path,
+                &config.files_domain_no_dot,
+            )
+            .await?;
+
+            Box::new(storage)
+        }
     };
 
     // Build server state
@@ -117,7 +142,9 @@ pub async fn build_server_state(
         rsmq,
         localizations,
         mime_analyzer,
-        s3_bucket,
+        oidc,
+        scanner,
+        storage,
     });
 
     // Start workers listening to the job queue (requires ServerState)
@@ -139,7 +166,29 @@ async fn build_module(app_state: ServerState) -> anyhow::Result<RpcModule<Server
     let mut module = RpcModule::new(app_state);
 
     macro_rules! register {
-        ($name:expr, $method:ident $(,)?) => {{
+        // Methods which do not act on behalf of a user, so any token may be used.
+        ($name:expr, $method:ident, public $(,)?) => {
+            register!(@method $name, $method, |_ctx, _params| {})
+        };
+
+        // Methods which act on behalf of a user, so any scoped token
+        // the request was made with must grant this API action.
+        ($name:expr, $method:ident, $action:ident $(,)?) => {
+            register!(@method $name, $method, |ctx, params| {
+                SessionService::check_scope(ctx, params.parse()?, ApiAction::$action)
+                    .await?;
+            })
+        };
+
+        // All other methods, which cannot be used with a scoped token at all.
+        ($name:expr, $method:ident $(,)?) => {
+            register!(@method $name, $method, |ctx, params| {
+                let ScopedToken { scoped_token } = params.parse().unwrap_or_default();
+                SessionService::check_unscoped(ctx.config(), scoped_token.as_deref())?;
+            })
+        };
+
+        (@method $name:expr, $method:ident, |$ctx:ident, $params:ident| $check:block) => {{
             // Register async method.
             //
             // Contains a wrapper around each to set up state, convert error types,
@@ -160,9 +209,15 @@ async fn build_module(app_state: ServerState) -> anyhow::Result<RpcModule<Server
                     .database
                     .transaction(move |txn| {
                         Box::pin(async move {
-                            // Run the endpoint's implementation, and convert from
+                            // Check the token the request was made with may be used,
+                            // then run the endpoint's implementation, and convert from
                             // ServiceError to an RPC error.
                             let ctx = ServiceContext::new(&state, &txn);
+                            {
+                                let $ctx = &ctx;
+                                let $params = &params;
+                                $check
+                            }
                             $method(&ctx, params).await.map_err(ErrorObjectOwned::from)
                         })
                     })
@@ -173,96 +228,155 @@ async fn build_module(app_state: ServerState) -> anyhow::Result<RpcModule<Server
     }
 
     // Miscellaneous
-    register!("ping", ping);
-    register!("echo", echo);
-    register!("error", yield_error);
-    register!("config", config_dump);
-    register!("normalize", normalize_method);
+    register!("ping", ping, public);
+    register!("echo", echo, public);
+    register!("error", yield_error, public);
+    register!("config", config_dump, public);
+    register!("normalize", normalize_method, public);
 
     // Server Information
-    register!("info", server_info);
+    register!("info", server_info, public);
 
     // Localization
-    register!("locale", locale_info);
-    register!("translate", translate_strings);
+    register!("locale", locale_info, public);
+    register!("translate", translate_strings, public);
 
     // Web server
-    register!("page_view", page_view);
-    register!("user_view", user_view);
+    register!("page_view", page_view, public);
+    register!("user_view", user_view, public);
     register!("admin_view", admin_view);
 
     // Authentication
-    register!("login", auth_login);
+    register!("login", auth_login, public);
+    register!("login_webauthn", auth_login_webauthn, public);
     register!("logout", auth_logout);
-    register!("session_get", auth_session_get);
+    register!("session_get", auth_session_get, public);
     register!("session_get_others", auth_session_get_others);
     register!("session_invalidate_others", auth_session_invalidate_others);
     register!("session_renew", auth_session_renew);
-    register!("mfa_verify", auth_mfa_verify);
+    register!("mfa_verify", auth_mfa_verify, public);
     register!("mfa_setup", auth_mfa_setup);
     register!("mfa_disable", auth_mfa_disable);
     register!("mfa_reset_recovery", auth_mfa_reset_recovery);
+    register!("webauthn_register_start", auth_webauthn_register_start);
+    register!("webauthn_register_finish", auth_webauthn_register_finish);
+    register!("webauthn_list", auth_webauthn_list);
+    register!("webauthn_delete", auth_webauthn_delete);
+    register!(
+        "webauthn_authenticate_start",
+        auth_webauthn_authenticate_start,
+        public,
+    );
+    register!("oidc_provider_list", auth_oidc_provider_list, public);
+    register!("oidc_login_start", auth_oidc_login_start, public);
+    register!("oidc_link_start", auth_oidc_link_start);
+    register!("oidc_finish", auth_oidc_finish, public);
+    register!("oidc_identity_list", auth_oidc_identity_list);
+    register!("oidc_identity_unlink", auth_oidc_identity_unlink);
+
+    // OAuth
+    register!("oauth_client_create", oauth_client_create);
+    register!("oauth_client_list", oauth_client_list);
+    register!("oauth_client_delete", oauth_client_delete);
+    register!("oauth_authorize_start", oauth_authorize_start, public);
+    register!("oauth_authorize_finish", oauth_authorize_finish);
+    register!("oauth_token", oauth_token, public);
+    register!("oauth_token_introspect", oauth_token_introspect, public);
+    register!("oauth_token_revoke", oauth_token_revoke, public);
+    register!("oauth_grant_list", oauth_grant_list);
+    register!("oauth_grant_revoke", oauth_grant_revoke);
 
     // Site
     register!("site_create", site_create);
-    register!("site_get", site_get);
+    register!("site_get", site_get, public);
     register!("site_update", site_update);
-    register!("site_from_domain", site_get_from_domain);
+    register!("site_from_domain", site_get_from_domain, public);
+    register!("site_recent_changes", site_recent_changes, public);
+    register!("site_recent_changes_feed", site_recent_changes_feed, public);
 
     // Site custom domain
     register!("custom_domain_create", site_custom_domain_create);
-    register!("custom_domain_get", site_custom_domain_get);
+    register!("custom_domain_get", site_custom_domain_get, public);
     register!("custom_domain_delete", site_custom_domain_delete);
 
     // Site membership
     register!("member_set", membership_set);
-    register!("member_get", membership_get);
+    register!("member_get", membership_get, public);
     register!("member_delete", membership_delete);
 
+    // Site staff
+    register!("staff_set", staff_set);
+    register!("staff_get", staff_get, public);
+    register!("staff_delete", staff_delete);
+
+    // Export
+    register!("export_create", export_create);
+    register!("export_get", export_get);
+
     // Category
-    register!("category_get", category_get);
-    register!("category_get_all", category_get_all);
+    register!("category_get", category_get, public);
+    register!("category_get_all", category_get_all, public);
 
     // Page
-    register!("page_create", page_create);
-    register!("page_get", page_get);
-    register!("page_get_direct", page_get_direct);
-    register!("page_get_deleted", page_get_deleted);
-    register!("page_get_score", page_get_score);
-    register!("page_get_files", page_get_files);
-    register!("page_edit", page_edit);
-    register!("page_delete", page_delete);
-    register!("page_move", page_move);
-    register!("page_rollback", page_rollback);
-    register!("page_rerender", page_rerender);
-    register!("page_restore", page_restore);
-    register!("page_set_layout", page_set_layout);
+    register!("page_create", page_create, EditPages);
+    register!("page_get", page_get, public);
+    register!("page_get_direct", page_get_direct, public);
+    register!("page_get_deleted", page_get_deleted, public);
+    register!("page_get_all", page_get_all, public);
+    register!("page_get_score", page_get_score, public);
+    register!("page_get_files", page_get_files, public);
+    register!("page_edit", page_edit, EditPages);
+    register!("page_delete", page_delete, EditPages);
+    register!("page_move", page_move, EditPages);
+    register!("page_rollback", page_rollback, EditPages);
+    register!("page_rerender", page_rerender, EditPages);
+    register!("page_restore", page_restore, EditPages);
+    register!("page_set_layout", page_set_layout, EditPages);
 
     // Page revisions
     register!("page_revision_create", page_revision_edit);
-    register!("page_revision_get", page_revision_get);
-    register!("page_revision_count", page_revision_count);
-    register!("page_revision_range", page_revision_range);
+    register!("page_revision_get", page_revision_get, public);
+    register!("page_revision_count", page_revision_count, public);
+    register!("page_revision_range", page_revision_range, public);
+    register!("page_revision_diff", page_revision_diff, public);
 
     // Page links
-    register!("page_get_links_from", page_links_from_get);
-    register!("page_get_links_to", page_links_to_get);
-    register!("page_get_links_to_missing", page_links_to_missing_get);
-    register!("page_get_urls_from", page_links_external_from);
-    register!("page_get_urls_to", page_links_external_to);
+    register!("page_get_links_from", page_links_from_get, public);
+    register!("page_get_links_to", page_links_to_get, public);
+    register!(
+        "page_get_links_to_missing",
+        page_links_to_missing_get,
+        public
+    );
+    register!("page_get_urls_from", page_links_external_from, public);
+    register!("page_get_urls_to", page_links_external_to, public);
 
     // Page parents
-    register!("parent_set", parent_set);
-    register!("parent_get", parent_get);
-    register!("parent_remove", parent_remove);
-    register!("parent_relationships_get", parent_relationships_get);
-    register!("parent_get_all", parent_get_all);
-    register!("parent_update", parent_update);
+    register!("parent_set", parent_set, EditPages);
+    register!("parent_get", parent_get, public);
+    register!("parent_remove", parent_remove, EditPages);
+    register!("parent_relationships_get", parent_relationships_get, public);
+    register!("parent_get_all", parent_get_all, public);
+    register!("parent_update", parent_update, EditPages);
 
     // Blob data
-    register!("blob_get", blob_get);
-    register!("blob_upload", blob_upload);
-    register!("blob_cancel", blob_cancel);
+    register!("blob_get", blob_get, public);
+    register!("blob_upload", blob_upload, UploadFiles);
+    register!("blob_cancel", blob_cancel, UploadFiles);
+    register!("blob_multipart_upload", blob_multipart_upload, UploadFiles);
+    register!("blob_multipart_part", blob_multipart_part, UploadFiles);
+    register!(
+        "blob_multipart_part_finish",
+        blob_multipart_part_finish,
+        UploadFiles
+    );
+    register!(
+        "blob_multipart_complete",
+        blob_multipart_complete,
+        UploadFiles
+    );
+    register!("blob_local_get", blob_local_get, public);
+    register!("blob_local_put", blob_local_put, public);
 
     // Blob hard deletion
     register!("blob_hard_delete_preview", blob_hard_delete_preview);
@@ -273,55 +387,84 @@ async fn build_module(app_state: ServerState) -> anyhow::Result<RpcModule<Server
     register!("blob_blacklist_remove", blob_blacklist_remove);
     register!("blob_blacklist_check", blob_blacklist_check);
 
+    // Blob malware scanning
+    register!("blob_scan_get", blob_scan_get);
+    register!("blob_scan_infected", blob_scan_infected);
+    register!("blob_rescan", blob_rescan);
+    register!("blob_perceptual_backfill", blob_perceptual_backfill);
+
     // Files
-    register!("file_create", file_create);
-    register!("file_edit", file_edit);
-    register!("file_get", file_get);
-    register!("file_delete", file_delete);
-    register!("file_move", file_move);
-    register!("file_restore", file_restore);
-    register!("file_rollback", file_rollback);
+    register!("file_create", file_create, UploadFiles);
+    register!("file_edit", file_edit, UploadFiles);
+    register!("file_get", file_get, public);
+    register!("file_delete", file_delete, UploadFiles);
+    register!("file_move", file_move, UploadFiles);
+    register!("file_copy", file_copy, UploadFiles);
+    register!("file_restore", file_restore, UploadFiles);
+    register!("file_rollback", file_rollback, UploadFiles);
+    register!("file_image_url", file_image_url, public);
+    register!("file_licensing_search", file_licensing_search, public);
+    register!("file_archive_import", file_archive_import, UploadFiles);
+    register!("file_archive_create", file_archive_create, ReadPages);
+    register!("file_archive_get", file_archive_get, public);
 
     // File revisions
-    register!("file_revision_get", file_revision_get);
+    register!("file_revision_get", file_revision_get, public);
     register!("file_revision_edit", file_revision_edit);
-    register!("file_revision_count", file_revision_count);
-    register!("file_revision_range", file_revision_range);
+    register!("file_revision_count", file_revision_count, public);
+    register!("file_revision_range", file_revision_range, public);
+    register!("file_revision_diff", file_revision_diff, public);
+
+    // Storage quotas
+    register!("storage_quota_get", storage_quota_get);
+    register!("storage_quota_set", storage_quota_set);
+    register!("storage_quota_reset", storage_quota_reset);
 
     // Text
     register!("text_create", text_create);
-    register!("text_get", text_get);
+    register!("text_get", text_get, public);
 
     // User
     register!("user_create", user_create);
     register!("user_import", user_import);
-    register!("user_get", user_get);
-    register!("user_edit", user_edit);
+    register!("user_get", user_get, public);
+    register!("user_edit", user_edit, EditProfile);
     register!("user_delete", user_delete);
     register!("user_add_name_change", user_add_name_change);
+    register!("user_name_history", user_name_history, public);
+    register!("user_alias_release", user_alias_release);
+    register!("user_contributions", user_contributions, public);
+    register!("user_export_create", user_export_create);
+    register!("user_export_list", user_export_list);
+    register!("user_deletion_request", user_deletion_request);
+    register!("user_deletion_get", user_deletion_get);
+    register!("user_deletion_cancel", user_deletion_cancel);
 
     // Bot user
     register!("bot_user_create", bot_user_create);
     register!("bot_user_get", bot_user_get);
     register!("bot_user_owner_set", bot_user_owner_set);
     register!("bot_user_owner_remove", bot_user_owner_remove);
+    register!("bot_token_create", bot_token_create);
+    register!("bot_token_list", bot_token_list);
+    register!("bot_token_revoke", bot_token_revoke);
 
     // Direct messages
-    register!("message_draft_create", message_draft_create);
-    register!("message_draft_edit", message_draft_edit);
-    register!("message_draft_delete", message_draft_delete);
-    register!("message_draft_send", message_draft_send);
+    register!("message_draft_create", message_draft_create, SendMessages);
+    register!("message_draft_edit", message_draft_edit, SendMessages);
+    register!("message_draft_delete", message_draft_delete, SendMessages);
+    register!("message_draft_send", message_draft_send, SendMessages);
 
     // Email
-    register!("email_validate", validate_email);
+    register!("email_validate", validate_email, public);
 
     // Votes
-    register!("vote_set", vote_set);
-    register!("vote_get", vote_get);
-    register!("vote_remove", vote_remove);
+    register!("vote_set", vote_set, Vote);
+    register!("vote_get", vote_get, public);
+    register!("vote_remove", vote_remove, Vote);
     register!("vote_action", vote_action);
-    register!("vote_list", vote_list_get);
-    register!("vote_list_count", vote_list_count);
+    register!("vote_list", vote_list_get, public);
+    register!("vote_list_count", vote_list_count, public);
 
     // Return
     Ok(module)
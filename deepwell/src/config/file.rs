@@ -19,6 +19,10 @@
  */
 
 use super::Config;
+use crate::types::{
+    DeletionPolicies, OidcProvider, RateLimit, ScannerBackend, SessionBinding,
+    SessionBindingAction, StorageBackend,
+};
 use anyhow::Result;
 use femme::LevelFilter;
 use ftml::layout::Layout;
@@ -92,8 +96,13 @@ struct Database {
 #[serde(rename_all = "kebab-case")]
 struct Security {
     authentication_fail_delay_ms: u64,
+    platform_admins: Vec<i64>,
     session: Session,
+    api_token: ApiToken,
+    oauth: Oauth,
     mfa: Mfa,
+    oidc: Oidc,
+    rate_limit: RateLimits,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -103,6 +112,28 @@ struct Session {
     token_length: usize,
     duration_session_minutes: u64,
     duration_login_minutes: u64,
+    binding: SessionBinding,
+    binding_action: SessionBindingAction,
+    binding_ipv4_prefix: u8,
+    binding_ipv6_prefix: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct ApiToken {
+    token_prefix: String,
+    token_length: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct Oauth {
+    access_token_prefix: String,
+    refresh_token_prefix: String,
+    token_length: usize,
+    authorization_code_timeout_secs: u64,
+    access_token_duration_minutes: u64,
+    refresh_token_duration_days: u64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -112,6 +143,31 @@ struct Mfa {
     recovery_code_length: usize,
     time_step: u64,
     time_skew: i64,
+    webauthn_name: String,
+    webauthn_timeout_secs: u64,
+    webauthn_allow_any_port: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct Oidc {
+    state_timeout_secs: u64,
+
+    #[serde(default, rename = "provider")]
+    providers: Vec<OidcProvider>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct RateLimits {
+    login_ip: RateLimit,
+    login_global: RateLimit,
+    mfa_verify: RateLimit,
+    user_create: RateLimit,
+    email_validate: RateLimit,
+    message_send: RateLimit,
+    lockout_failures: RateLimit,
+    lockout_duration_minutes: u64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -124,8 +180,10 @@ struct Job {
     max_delay_poll_secs: u64,
     prune_session_secs: u64,
     prune_text_secs: u64,
+    prune_pending_blob_secs: u64,
     name_change_refill_secs: u64,
     lift_expired_punishments_secs: u64,
+    export_process_secs: u64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -180,16 +238,44 @@ struct User {
     maximum_name_changes: u8,
     refill_name_change_days: u64,
     minimum_name_bytes: usize,
+    alias_release_cooldown_days: i64,
+    deletion: UserDeletion,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+struct UserDeletion {
+    grace_period_days: i64,
+
+    #[serde(flatten)]
+    policy: DeletionPolicies,
 }
 
 // NOTE: Name conflict with std::fs::File
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case")]
 struct FileSection {
+    storage_backend: StorageBackend,
+    local_storage_path: PathBuf,
+    scanner: ScannerBackend,
+    scanner_socket: PathBuf,
+    scanner_command: Vec<String>,
+    perceptual_hash_threshold: u32,
     presigned_path_length: usize,
     presigned_expiration_minutes: u32,
+    presigned_download_expiration_minutes: u32,
+    multipart_part_size_mb: i64,
+    multipart_expiration_hours: i64,
     maximum_blob_size_kb: i64,
+    default_site_quota_mb: i64,
+    default_user_quota_mb: i64,
     maximum_avatar_size_kb: i64,
+    maximum_avatar_dimension: u32,
+    avatar_sizes: Vec<u16>,
+    thumbnail_sizes: Vec<u16>,
+    maximum_archive_entries: usize,
+    maximum_archive_size_mb: u64,
+    export_path: PathBuf,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -254,12 +340,31 @@ impl ConfigFile {
             security:
                 Security {
                     authentication_fail_delay_ms,
+                    platform_admins,
                     session:
                         Session {
                             token_prefix,
                             token_length,
                             duration_session_minutes,
                             duration_login_minutes,
+                            binding: session_binding,
+                            binding_action: session_binding_action,
+                            binding_ipv4_prefix: session_binding_ipv4_prefix,
+                            binding_ipv6_prefix: session_binding_ipv6_prefix,
+                        },
+                    api_token:
+                        ApiToken {
+                            token_prefix: api_token_prefix,
+                            token_length: api_token_length,
+                        },
+                    oauth:
+                        Oauth {
+                            access_token_prefix: oauth_access_token_prefix,
+                            refresh_token_prefix: oauth_refresh_token_prefix,
+                            token_length: oauth_token_length,
+                            authorization_code_timeout_secs: oauth_code_timeout_secs,
+                            access_token_duration_minutes: oauth_access_token_minutes,
+                            refresh_token_duration_days: oauth_refresh_token_days,
                         },
                     mfa:
                         Mfa {
@@ -267,6 +372,25 @@ impl ConfigFile {
                             recovery_code_length,
                             time_step,
                             time_skew,
+                            webauthn_name,
+                            webauthn_timeout_secs,
+                            webauthn_allow_any_port,
+                        },
+                    oidc:
+                        Oidc {
+                            state_timeout_secs: oidc_state_timeout_secs,
+                            providers: oidc_providers,
+                        },
+                    rate_limit:
+                        RateLimits {
+                            login_ip: rate_limit_login_ip,
+                            login_global: rate_limit_login_global,
+                            mfa_verify: rate_limit_mfa_verify,
+                            user_create: rate_limit_user_create,
+                            email_validate: rate_limit_email_validate,
+                            message_send: rate_limit_message_send,
+                            lockout_failures,
+                            lockout_duration_minutes,
                         },
                 },
             domain:
@@ -283,8 +407,10 @@ impl ConfigFile {
                     max_delay_poll_secs: job_max_poll_delay_secs,
                     prune_session_secs: job_prune_session_secs,
                     prune_text_secs: job_prune_text_secs,
+                    prune_pending_blob_secs: job_prune_pending_blob_secs,
                     name_change_refill_secs: job_name_change_refill_secs,
                     lift_expired_punishments_secs: job_lift_expired_punishments_secs,
+                    export_process_secs: job_export_process_secs,
                 },
             locale: Locale {
                 path: localization_path,
@@ -313,13 +439,36 @@ impl ConfigFile {
                     maximum_name_changes,
                     refill_name_change_days,
                     minimum_name_bytes,
+                    alias_release_cooldown_days: user_alias_release_cooldown_days,
+                    deletion:
+                        UserDeletion {
+                            grace_period_days: user_deletion_grace_period_days,
+                            policy: user_deletion_policy,
+                        },
                 },
             file:
                 FileSection {
+                    storage_backend,
+                    local_storage_path,
+                    scanner,
+                    scanner_socket,
+                    scanner_command,
+                    perceptual_hash_threshold,
                     presigned_path_length,
                     presigned_expiration_minutes,
+                    presigned_download_expiration_minutes,
+                    multipart_part_size_mb,
+                    multipart_expiration_hours,
                     maximum_blob_size_kb,
+                    default_site_quota_mb,
+                    default_user_quota_mb,
                     maximum_avatar_size_kb,
+                    maximum_avatar_dimension,
+                    avatar_sizes,
+                    thumbnail_sizes,
+                    maximum_archive_entries,
+                    maximum_archive_size_mb,
+                    export_path,
                 },
             message:
                 Message {
@@ -340,6 +489,10 @@ impl ConfigFile {
             job_prune_text_secs < RSMQ_DELAY_LIMIT,
             "Text prune job period time too long",
         );
+        assert!(
+            job_prune_pending_blob_secs < RSMQ_DELAY_LIMIT,
+            "Pending blob prune job period time too long",
+        );
         assert!(
             job_name_change_refill_secs < RSMQ_DELAY_LIMIT,
             "Name change refill job period time too long",
@@ -348,6 +501,141 @@ impl ConfigFile {
             job_lift_expired_punishments_secs < RSMQ_DELAY_LIMIT,
             "Expired punishment cleanup job period time too long",
         );
+        assert!(
+            job_export_process_secs < RSMQ_DELAY_LIMIT,
+            "Export job process time too long",
+        );
+        assert!(
+            user_alias_release_cooldown_days >= 0,
+            "User alias release cooldown is negative",
+        );
+        assert!(
+            user_deletion_grace_period_days >= 0,
+            "User deletion grace period is negative",
+        );
+        assert!(
+            (user_deletion_grace_period_days as u64) * 24 * 60 * 60 < RSMQ_DELAY_LIMIT,
+            "User deletion grace period too long",
+        );
+        assert!(
+            presigned_download_expiration_minutes > 0
+                && presigned_download_expiration_minutes <= 7 * 24 * 60,
+            "Presigned download expiration must be between 1 minute and 7 days",
+        );
+        assert!(
+            multipart_part_size_mb >= 5,
+            "Multipart upload part size is smaller than the S3 minimum of 5 MiB",
+        );
+        assert!(
+            maximum_blob_size_kb / (multipart_part_size_mb * 1024) < 10000,
+            "Multipart upload part size too small for the maximum blob size",
+        );
+        assert!(
+            multipart_expiration_hours > 0,
+            "Multipart upload expiration is not positive",
+        );
+        assert!(
+            scanner != ScannerBackend::Command || !scanner_command.is_empty(),
+            "Scanner command is empty",
+        );
+        assert!(
+            perceptual_hash_threshold <= 64,
+            "Perceptual hash threshold exceeds the number of bits in the hash",
+        );
+        assert!(
+            default_site_quota_mb >= 0 && default_user_quota_mb >= 0,
+            "Default storage quotas cannot be negative",
+        );
+        assert!(!avatar_sizes.is_empty(), "No avatar sizes specified");
+        assert!(
+            avatar_sizes
+                .iter()
+                .all(|&size| size > 0 && u32::from(size) <= maximum_avatar_dimension),
+            "Avatar sizes must be nonzero and no larger than the maximum dimension",
+        );
+        assert!(
+            avatar_sizes.iter().all(|&size| size <= i16::MAX as u16),
+            "Avatar sizes must fit in the database column (at most {})",
+            i16::MAX,
+        );
+        assert!(
+            thumbnail_sizes.iter().all(|&size| size > 0 && size <= 4096),
+            "Thumbnail sizes must be between 1 and 4096 pixels",
+        );
+        assert!(
+            maximum_archive_entries > 0,
+            "Maximum archive entries must be nonzero",
+        );
+        assert!(
+            maximum_archive_size_mb > 0,
+            "Maximum archive size must be nonzero",
+        );
+        assert!(
+            session_binding_ipv4_prefix <= 32,
+            "Session binding IPv4 prefix longer than an address",
+        );
+        assert!(
+            session_binding_ipv6_prefix <= 128,
+            "Session binding IPv6 prefix longer than an address",
+        );
+
+        {
+            let prefixes = [
+                &token_prefix,
+                &api_token_prefix,
+                &oauth_access_token_prefix,
+                &oauth_refresh_token_prefix,
+            ];
+
+            for (index, prefix) in prefixes.iter().enumerate() {
+                for other in &prefixes[index + 1..] {
+                    assert!(
+                        !prefix.starts_with(other.as_str())
+                            && !other.starts_with(prefix.as_str()),
+                        "Session, API, and OAuth token prefixes overlap",
+                    );
+                }
+            }
+        }
+
+        assert!(
+            oauth_access_token_minutes > 0
+                && oauth_refresh_token_days * 24 * 60 >= oauth_access_token_minutes,
+            "OAuth refresh tokens must last at least as long as access tokens",
+        );
+
+        for (index, provider) in oidc_providers.iter().enumerate() {
+            assert!(
+                !provider.name.is_empty()
+                    && provider
+                        .name
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b == b'-'),
+                "OpenID Connect provider name must be alphanumeric or dashes",
+            );
+            assert!(
+                oidc_providers[..index]
+                    .iter()
+                    .all(|other| other.name != provider.name),
+                "OpenID Connect provider name is not unique",
+            );
+            assert!(
+                provider.scopes.iter().any(|scope| scope == "openid"),
+                "OpenID Connect provider does not request the 'openid' scope",
+            );
+        }
+
+        for rate_limit in [
+            rate_limit_login_ip,
+            rate_limit_login_global,
+            rate_limit_mfa_verify,
+            rate_limit_user_create,
+            rate_limit_email_validate,
+            rate_limit_message_send,
+            lockout_failures,
+        ] {
+            assert!(rate_limit.window_secs > 0, "Rate limit window is empty");
+        }
 
         // Prefix domains with '.' so we can do easy subdomain checks
         // and concatenations.
@@ -380,6 +668,7 @@ impl ConfigFile {
             authentication_fail_delay: StdDuration::from_millis(
                 authentication_fail_delay_ms,
             ),
+            platform_admins,
             session_token_prefix: token_prefix,
             session_token_length: token_length,
             normal_session_duration: time_duration!(
@@ -390,10 +679,41 @@ impl ConfigFile {
                 from_secs,
                 duration_login_minutes * 60,
             ),
+            session_binding,
+            session_binding_action,
+            session_binding_ipv4_prefix,
+            session_binding_ipv6_prefix,
+            api_token_prefix,
+            api_token_length,
+            oauth_access_token_prefix,
+            oauth_refresh_token_prefix,
+            oauth_token_length,
+            oauth_code_timeout: StdDuration::from_secs(oauth_code_timeout_secs),
+            oauth_access_token_duration: time_duration!(
+                from_secs,
+                oauth_access_token_minutes * 60,
+            ),
+            oauth_refresh_token_duration: time_duration!(
+                from_secs,
+                oauth_refresh_token_days * 24 * 60 * 60,
+            ),
             recovery_code_count,
             recovery_code_length,
             totp_time_step: time_step,
             totp_time_skew: time_skew,
+            webauthn_name,
+            webauthn_timeout: StdDuration::from_secs(webauthn_timeout_secs),
+            webauthn_allow_any_port,
+            oidc_state_timeout: StdDuration::from_secs(oidc_state_timeout_secs),
+            oidc_providers,
+            rate_limit_login_ip,
+            rate_limit_login_global,
+            rate_limit_mfa_verify,
+            rate_limit_user_create,
+            rate_limit_email_validate,
+            rate_limit_message_send,
+            lockout_failures,
+            lockout_duration: StdDuration::from_secs(lockout_duration_minutes * 60),
             job_workers,
             job_max_attempts,
             job_work_delay: StdDuration::from_millis(job_work_delay_ms),
@@ -401,10 +721,12 @@ impl ConfigFile {
             job_max_poll_delay: StdDuration::from_secs(job_max_poll_delay_secs),
             job_prune_session: StdDuration::from_secs(job_prune_session_secs),
             job_prune_text: StdDuration::from_secs(job_prune_text_secs),
+            job_prune_pending_blob: StdDuration::from_secs(job_prune_pending_blob_secs),
             job_name_change_refill: StdDuration::from_secs(job_name_change_refill_secs),
             job_lift_expired_punishments: StdDuration::from_secs(
                 job_lift_expired_punishments_secs,
             ),
+            job_export_process: StdDuration::from_secs(job_export_process_secs),
             render_timeout: StdDuration::from_millis(render_timeout_ms),
             rerender_skip: rerender_skip
                 .iter()
@@ -442,10 +764,34 @@ impl ConfigFile {
                 ))
             },
             minimum_name_bytes,
+            user_alias_release_cooldown: TimeDuration::days(
+                user_alias_release_cooldown_days,
+            ),
+            user_deletion_grace_period: TimeDuration::days(
+                user_deletion_grace_period_days,
+            ),
+            user_deletion_policy,
+            storage_backend,
+            local_storage_path,
+            scanner_backend: scanner,
+            scanner_socket,
+            scanner_command,
+            perceptual_hash_threshold,
             presigned_path_length,
             presigned_expiry_secs: presigned_expiration_minutes * 60,
+            presigned_download_expiry_secs: presigned_download_expiration_minutes * 60,
+            multipart_part_size: multipart_part_size_mb * 1024 * 1024,
+            multipart_expiry: TimeDuration::hours(multipart_expiration_hours),
             maximum_blob_size: maximum_blob_size_kb * 1024,
+            default_site_quota: quota_bytes(default_site_quota_mb),
+            default_user_quota: quota_bytes(default_user_quota_mb),
             maximum_avatar_size: maximum_avatar_size_kb * 1024,
+            maximum_avatar_dimension,
+            avatar_sizes,
+            thumbnail_sizes,
+            maximum_archive_entries,
+            maximum_archive_size: maximum_archive_size_mb * 1024 * 1024,
+            export_path,
             maximum_message_subject_bytes,
             maximum_message_body_bytes,
             maximum_message_recipients,
@@ -470,6 +816,27 @@ fn prefix_domain(domain: String) -> (String, String) {
     }
 }
 
+#[cfg(test)]
+impl Config {
+    /// Loads the example configuration file, for use in tests.
+    pub fn example() -> Self {
+        let path =
+            PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/config.example.toml"));
+        let (config_file, extra) =
+            ConfigFile::load(path).expect("Unable to load example configuration");
+
+        config_file.into_config(extra)
+    }
+}
+
+/// Converts a storage quota in MiB to bytes, where zero means unlimited.
+fn quota_bytes(quota_mb: i64) -> Option<i64> {
+    match quota_mb {
+        0 => None,
+        _ => Some(quota_mb * 1024 * 1024),
+    }
+}
+
 #[test]
 fn test_prefix_domain() {
     macro_rules! check {
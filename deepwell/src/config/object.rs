@@ -19,6 +19,10 @@
  */
 
 use super::file::ConfigFile;
+use crate::types::{
+    DeletionPolicies, OidcProvider, RateLimit, ScannerBackend, SessionBinding,
+    SessionBindingAction, StorageBackend,
+};
 use anyhow::Result;
 use femme::LevelFilter;
 use ftml::layout::Layout;
@@ -69,7 +73,6 @@ pub struct Config {
     pub files_domain: String,
 
     /// The files domain, but without a leading `.`
-    #[allow(dead_code)] // TEMP
     pub files_domain_no_dot: String,
 
     /// Whether to auto-restart on configuration file change.
@@ -92,6 +95,12 @@ pub struct Config {
     /// The duration to sleep after failed authentication attempts.
     pub authentication_fail_delay: StdDuration,
 
+    /// The IDs of users who administer the platform.
+    ///
+    /// These users may perform actions which affect all sites,
+    /// such as appointing site staff.
+    pub platform_admins: Vec<i64>,
+
     /// Fixed prefix for all session tokens.
     pub session_token_prefix: String,
 
@@ -104,6 +113,42 @@ pub struct Config {
     /// How long restricted sessions last before expiry.
     pub restricted_session_duration: TimeDuration,
 
+    /// How strictly sessions are tied to the IP address and user agent.
+    pub session_binding: SessionBinding,
+
+    /// What to do with a session used from somewhere it is not bound to.
+    pub session_binding_action: SessionBindingAction,
+
+    /// IPv4 prefix length used to compare addresses for subnet binding.
+    pub session_binding_ipv4_prefix: u8,
+
+    /// IPv6 prefix length used to compare addresses for subnet binding.
+    pub session_binding_ipv6_prefix: u8,
+
+    /// Fixed prefix for all bot API tokens.
+    pub api_token_prefix: String,
+
+    /// Length of randomly-generated segment in bot API tokens.
+    pub api_token_length: usize,
+
+    /// The prefix for all OAuth access tokens.
+    pub oauth_access_token_prefix: String,
+
+    /// The prefix for all OAuth refresh tokens.
+    pub oauth_refresh_token_prefix: String,
+
+    /// How long OAuth tokens and client secrets are, not including the prefix.
+    pub oauth_token_length: usize,
+
+    /// How long an OAuth authorization code can be exchanged for tokens.
+    pub oauth_code_timeout: StdDuration,
+
+    /// How long OAuth access tokens last before they must be refreshed.
+    pub oauth_access_token_duration: TimeDuration,
+
+    /// How long OAuth refresh tokens last.
+    pub oauth_refresh_token_duration: TimeDuration,
+
     /// The number of recovery codes to have per user.
     pub recovery_code_count: usize,
 
@@ -116,6 +161,47 @@ pub struct Config {
     /// How much leniency should be allowed for TOTP.
     pub totp_time_skew: i64,
 
+    /// Name of the relying party shown by WebAuthn authenticators.
+    pub webauthn_name: String,
+
+    /// How long a WebAuthn ceremony has to be completed in.
+    pub webauthn_timeout: StdDuration,
+
+    /// Whether WebAuthn ceremonies may come from an origin on any port.
+    ///
+    /// Only meant for local development.
+    pub webauthn_allow_any_port: bool,
+
+    /// How long a user has to complete logging in with an OpenID Connect provider.
+    pub oidc_state_timeout: StdDuration,
+
+    /// The external OpenID Connect providers users can log in with.
+    pub oidc_providers: Vec<OidcProvider>,
+
+    /// Rate limit for login attempts from a single IP address.
+    pub rate_limit_login_ip: RateLimit,
+
+    /// Rate limit for login attempts across all requesters.
+    pub rate_limit_login_global: RateLimit,
+
+    /// Rate limit for MFA verification attempts for a single user.
+    pub rate_limit_mfa_verify: RateLimit,
+
+    /// Rate limit for new user account creation.
+    pub rate_limit_user_create: RateLimit,
+
+    /// Rate limit for validating email addresses with the external service.
+    pub rate_limit_email_validate: RateLimit,
+
+    /// Rate limit for sending messages by a single user.
+    pub rate_limit_message_send: RateLimit,
+
+    /// How many failed logins to an account trigger a lockout.
+    pub lockout_failures: RateLimit,
+
+    /// How long an account stays locked after too many failed logins.
+    pub lockout_duration: StdDuration,
+
     /// The number of job workers to run in this process.
     pub job_workers: NonZeroU16,
 
@@ -139,12 +225,18 @@ pub struct Config {
     /// How often to run the "prune unused text" recurring job.
     pub job_prune_text: StdDuration,
 
+    /// How often to run the "prune expired pending blobs" recurring job.
+    pub job_prune_pending_blob: StdDuration,
+
     /// How often to run the "refill name change tokens" recurring job.
     pub job_name_change_refill: StdDuration,
 
     /// How often to run the "lift expired punishments" recurring job.
     pub job_lift_expired_punishments: StdDuration,
 
+    /// How long an export job may run before the queue retries it.
+    pub job_export_process: StdDuration,
+
     /// Maximum run time for a render request.
     pub render_timeout: StdDuration,
 
@@ -200,18 +292,85 @@ pub struct Config {
     /// Minimum length of bytes in a username.
     pub minimum_name_bytes: usize,
 
-    /// Length of randomly-generated portion of S3 presigned URLs.
+    /// How long after a user is renamed before their old name can be released.
+    pub user_alias_release_cooldown: TimeDuration,
+
+    /// How long after a user requests deletion of their account before it happens.
+    pub user_deletion_grace_period: TimeDuration,
+
+    /// What happens to each class of a user's data when their account is deleted.
+    pub user_deletion_policy: DeletionPolicies,
+
+    /// Where blobs are stored.
+    pub storage_backend: StorageBackend,
+
+    /// Directory where blobs are kept, when using local storage.
+    pub local_storage_path: PathBuf,
+
+    /// What checks uploaded blobs for malware.
+    pub scanner_backend: ScannerBackend,
+
+    /// Path to the clamd socket, when using clamd for scanning.
+    pub scanner_socket: PathBuf,
+
+    /// Program and arguments to run, when using a command for scanning.
+    pub scanner_command: Vec<String>,
+
+    /// Maximum Hamming distance between perceptual hashes for images to be near-duplicates.
+    pub perceptual_hash_threshold: u32,
+
+    /// Length of randomly-generated portion of presigned upload paths.
     pub presigned_path_length: usize,
 
-    /// How long S3 presigned URLs will last before expiry.
+    /// How long presigned upload URLs will last before expiry.
     pub presigned_expiry_secs: u32,
 
+    /// How long presigned download URLs will last before expiry.
+    pub presigned_download_expiry_secs: u32,
+
+    /// Size of each part in a multipart upload, except the last.
+    pub multipart_part_size: i64,
+
+    /// How long a multipart upload can be resumed before it expires.
+    pub multipart_expiry: TimeDuration,
+
     /// Maximum size of a blob globally.
     pub maximum_blob_size: i64,
 
+    /// Default storage quota for each site, in bytes.
+    ///
+    /// If `None`, then storage is unlimited.
+    pub default_site_quota: Option<i64>,
+
+    /// Default storage quota for each user, in bytes.
+    ///
+    /// If `None`, then storage is unlimited.
+    pub default_user_quota: Option<i64>,
+
     /// Maximum size of a user's avatar image.
     pub maximum_avatar_size: i64,
 
+    /// Maximum width or height of a user's avatar image, in pixels.
+    pub maximum_avatar_dimension: u32,
+
+    /// Square sizes, in pixels, that avatars are resized to.
+    pub avatar_sizes: Vec<u16>,
+
+    /// Sizes, in pixels, that thumbnails of image files fit within.
+    pub thumbnail_sizes: Vec<u16>,
+
+    /// Maximum number of files which can be imported from one archive.
+    pub maximum_archive_entries: usize,
+
+    /// Maximum total size, in bytes, of the files in an archive.
+    ///
+    /// This applies both to files extracted from an imported archive,
+    /// and to files written to an archive of a page's files.
+    pub maximum_archive_size: u64,
+
+    /// Directory where exports with a local destination are written.
+    pub export_path: PathBuf,
+
     /// Maximum size of the subject line allowed in a direct message.
     pub maximum_message_subject_bytes: usize,
 
@@ -34,12 +34,11 @@ use crate::services::page::{CreatePage, PageService};
 use crate::services::site::{CreateSite, CreateSiteOutput, SiteService};
 use crate::services::user::{CreateUser, CreateUserOutput, UpdateUserBody, UserService};
 use crate::services::ServiceContext;
-use crate::types::{Maybe, Reference};
+use crate::types::{FileLicensing, Maybe, Reference};
 use anyhow::Result;
 use sea_orm::{
     ConnectionTrait, DatabaseBackend, DatabaseTransaction, Statement, TransactionTrait,
 };
-use serde_json::Value as JsonValue;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs;
@@ -293,7 +292,7 @@ pub async fn seed(state: &ServerState) -> Result<()> {
                             direct_upload: Some(data),
                             revision_comments: str!(),
                             user_id: SYSTEM_USER_ID,
-                            licensing: JsonValue::Null,
+                            licensing: FileLicensing::unknown(),
                             bypass_filter: true,
                         },
                     )
@@ -20,19 +20,32 @@
 
 use super::prelude::*;
 use crate::models::session::Model as SessionModel;
+use crate::models::user_external_identity::Model as UserExternalIdentityModel;
 use crate::services::authentication::{
     AuthenticateUserOutput, AuthenticationService, LoginUser, LoginUserMfa,
-    LoginUserOutput, MultiFactorAuthenticateUser,
+    LoginUserOutput, LoginUserWebauthn, MultiFactorAuthenticateUser,
 };
 use crate::services::mfa::{
     MultiFactorConfigure, MultiFactorResetOutput, MultiFactorSetupOutput,
 };
+use crate::services::oidc::{
+    FinishOidcLogin, FinishOidcOutput, GetExternalIdentities, OidcAuthenticated,
+    OidcProviderOutput, StartOidcLink, StartOidcLogin, StartOidcOutput,
+    UnlinkExternalIdentity,
+};
+use crate::services::rate_limit::{RateLimitAction, RateLimitScope};
 use crate::services::session::{
-    CreateSession, GetOtherSessions, GetOtherSessionsOutput, InvalidateOtherSessions,
-    RenewSession,
+    CreateSession, GetOtherSessions, GetOtherSessionsOutput, GetSession,
+    InvalidateOtherSessions, RenewSession,
 };
 use crate::services::user::GetUser;
+use crate::services::webauthn::{
+    DeleteCredential, GetCredentials, RegisterCredential, StartAuthentication,
+    StartCeremonyOutput, StartRegistration, WebauthnCredentialOutput,
+};
 use crate::services::Error;
+use crate::types::Reference;
+use webauthn_rs::prelude::{CreationChallengeResponse, RequestChallengeResponse};
 
 pub async fn auth_login(
     ctx: &ServiceContext<'_>,
@@ -54,21 +67,40 @@ pub async fn auth_login(
         return Err(Error::EmptyPassword);
     }
 
+    // Limit login attempts, both from this requester and overall
+    let config = ctx.config();
+    RateLimitService::check(
+        ctx,
+        RateLimitAction::Login,
+        RateLimitScope::Ip(ip_address),
+        config.rate_limit_login_ip,
+    )
+    .await?;
+
+    RateLimitService::check(
+        ctx,
+        RateLimitAction::Login,
+        RateLimitScope::Global,
+        config.rate_limit_login_global,
+    )
+    .await?;
+
     // All authentication issue should return the same error.
     //
     // If anything went wrong, only allow a generic backend failure
     // to avoid leaking internal state. However since we are an internal
     // API
     //
-    // The only three possible responses to this method should be:
+    // The only four possible responses to this method should be:
     // * success
     // * invalid authentication
+    // * account locked
     // * server error
     let result = AuthenticationService::auth_password(ctx, authenticate).await;
     let AuthenticateUserOutput { needs_mfa, user_id } = match result {
         Ok(output) => output,
         Err(mut error) => {
-            if !matches!(error, Error::InvalidAuthentication) {
+            if !matches!(error, Error::InvalidAuthentication | Error::AccountLocked) {
                 error!("Unexpected error during user authentication: {error}");
                 error = Error::AuthenticationBackend(Box::new(error));
             }
@@ -99,6 +131,69 @@ pub async fn auth_login(
     })
 }
 
+/// Logs in with a WebAuthn credential, without a password.
+///
+/// The authenticator must have verified the user (e.g. with a PIN or
+/// biometrics), so this counts as both factors and the session
+/// produced is not restricted.
+pub async fn auth_login_webauthn(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<LoginUserOutput> {
+    let LoginUserWebauthn {
+        assertion,
+        ip_address,
+        user_agent,
+    } = params.parse()?;
+
+    let config = ctx.config();
+    RateLimitService::check(
+        ctx,
+        RateLimitAction::Login,
+        RateLimitScope::Ip(ip_address),
+        config.rate_limit_login_ip,
+    )
+    .await?;
+
+    RateLimitService::check(
+        ctx,
+        RateLimitAction::Login,
+        RateLimitScope::Global,
+        config.rate_limit_login_global,
+    )
+    .await?;
+
+    // Same as with password logins, only generic errors are returned
+    let user_id = match AuthenticationService::auth_webauthn(ctx, &assertion).await {
+        Ok(user_id) => user_id,
+        Err(mut error) => {
+            if !matches!(error, Error::InvalidAuthentication | Error::AccountLocked) {
+                error!("Unexpected error during WebAuthn authentication: {error}");
+                error = Error::AuthenticationBackend(Box::new(error));
+            }
+
+            return Err(error);
+        }
+    };
+
+    info!("WebAuthn authentication for user ID {user_id} succeeded");
+    let session_token = SessionService::create(
+        ctx,
+        CreateSession {
+            user_id,
+            ip_address,
+            user_agent,
+            restricted: false,
+        },
+    )
+    .await?;
+
+    Ok(LoginUserOutput {
+        session_token,
+        needs_mfa: false,
+    })
+}
+
 pub async fn auth_logout(
     ctx: &ServiceContext<'_>,
     params: Params<'static>,
@@ -111,12 +206,48 @@ pub async fn auth_logout(
 ///
 /// This is how framerail determines the user ID this user is acting as,
 /// among other information.
+///
+/// The session's binding is checked against the requester's IP address
+/// and user agent, so the returned session may have become restricted,
+/// or `None` if it was invalidated.
+///
+/// Scoped tokens (bot API tokens and OAuth access tokens) are also accepted
+/// here, in which case a session for the token's user is returned, provided
+/// the token grants the requested scope.
 pub async fn auth_session_get(
     ctx: &ServiceContext<'_>,
     params: Params<'static>,
 ) -> Result<Option<SessionModel>> {
-    let session_token: String = params.one()?;
-    SessionService::get_optional(ctx, &session_token).await
+    let GetSession {
+        session_token,
+        ip_address,
+        user_agent,
+        scope,
+    } = params.parse()?;
+
+    if SessionService::is_scoped_token(ctx.config(), &session_token) {
+        let scoped =
+            SessionService::get_scoped(ctx, &session_token, ip_address, &user_agent)
+                .await?;
+
+        return match (scoped, scope) {
+            (Some(scoped), Some(scope)) if scoped.grants(scope) => {
+                Ok(Some(scoped.session))
+            }
+            (Some(_), _) => {
+                warn!("Token does not grant the requested scope ({scope:?})");
+                Ok(None)
+            }
+            (None, _) => Ok(None),
+        };
+    }
+
+    match SessionService::get_optional(ctx, &session_token).await? {
+        None => Ok(None),
+        Some(session) => {
+            SessionService::check_binding(ctx, session, ip_address, &user_agent).await
+        }
+    }
 }
 
 pub async fn auth_session_renew(
@@ -152,9 +283,15 @@ pub async fn auth_session_get_others(
         }
     };
 
+    // Session tokens are not included, since they would let
+    // whoever has the current session take over the others.
+    let config = ctx.config();
     Ok(GetOtherSessionsOutput {
-        current,
-        others: sessions,
+        current: SessionService::describe(config, &current),
+        others: sessions
+            .iter()
+            .map(|session| SessionService::describe(config, session))
+            .collect(),
     })
 }
 
@@ -176,9 +313,9 @@ pub async fn auth_mfa_verify(
 ) -> Result<String> {
     let LoginUserMfa {
         session_token,
-        totp_or_code,
         ip_address,
         user_agent,
+        factor,
     } = params.parse()?;
 
     info!("Verifying user's MFA for login (temporary session token {session_token})",);
@@ -187,12 +324,12 @@ pub async fn auth_mfa_verify(
         ctx,
         MultiFactorAuthenticateUser {
             session_token: &session_token,
-            totp_or_code: &totp_or_code,
+            factor: &factor,
         },
     )
     .await?;
 
-    SessionService::renew(
+    SessionService::upgrade(
         ctx,
         RenewSession {
             old_session_token: session_token,
@@ -222,18 +359,7 @@ pub async fn auth_mfa_disable(
         session_token,
     } = params.parse()?;
 
-    let user = SessionService::get_user(ctx, &session_token, false).await?;
-    if user.user_id != user_id {
-        error!(
-            "Passed user ID ({}) does not match session token ({})",
-            user_id, user.user_id,
-        );
-
-        return Err(Error::SessionUserId {
-            active_user_id: user_id,
-            session_user_id: user.user_id,
-        });
-    }
+    let user = check_session(ctx, &session_token, user_id).await?;
 
     MfaService::disable(ctx, user.user_id).await
 }
@@ -247,18 +373,207 @@ pub async fn auth_mfa_reset_recovery(
         session_token,
     } = params.parse()?;
 
-    let user = SessionService::get_user(ctx, &session_token, false).await?;
-    if user.user_id != user_id {
-        error!(
-            "Passed user ID ({}) does not match session token ({})",
-            user_id, user.user_id,
-        );
-
-        return Err(Error::SessionUserId {
-            active_user_id: user_id,
-            session_user_id: user.user_id,
-        });
-    }
+    let user = check_session(ctx, &session_token, user_id).await?;
 
     MfaService::reset_recovery_codes(ctx, &user).await
 }
+
+pub async fn auth_webauthn_register_start(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<StartCeremonyOutput<CreationChallengeResponse>> {
+    let StartRegistration {
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    let user = check_session(ctx, &session_token, user_id).await?;
+    WebauthnService::start_registration(ctx, &user).await
+}
+
+pub async fn auth_webauthn_register_finish(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<WebauthnCredentialOutput> {
+    let input: RegisterCredential = params.parse()?;
+    check_session(ctx, &input.session_token, input.user_id).await?;
+    let credential = WebauthnService::finish_registration(ctx, input).await?;
+    Ok(credential.into())
+}
+
+pub async fn auth_webauthn_list(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Vec<WebauthnCredentialOutput>> {
+    let GetCredentials {
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    let user = check_session(ctx, &session_token, user_id).await?;
+    let credentials = WebauthnService::get_all(ctx, user.user_id).await?;
+    Ok(credentials.into_iter().map(Into::into).collect())
+}
+
+pub async fn auth_webauthn_delete(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<()> {
+    let DeleteCredential {
+        user_id,
+        session_token,
+        webauthn_credential_id,
+    } = params.parse()?;
+
+    let user = check_session(ctx, &session_token, user_id).await?;
+
+    WebauthnService::delete(ctx, user.user_id, webauthn_credential_id).await
+}
+
+/// Begins a WebAuthn authentication ceremony.
+///
+/// With a restricted session token, this is for completing an MFA login
+/// through `mfa_verify`. Without one, this is for `login_webauthn`.
+pub async fn auth_webauthn_authenticate_start(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<StartCeremonyOutput<RequestChallengeResponse>> {
+    let StartAuthentication { session_token } = params.parse()?;
+    let user_id = match session_token {
+        None => None,
+        Some(session_token) => {
+            let user = SessionService::get_user(ctx, &session_token, true).await?;
+            Some(user.user_id)
+        }
+    };
+
+    WebauthnService::start_authentication(ctx, user_id).await
+}
+
+pub async fn auth_oidc_provider_list(
+    ctx: &ServiceContext<'_>,
+    _params: Params<'static>,
+) -> Result<Vec<OidcProviderOutput>> {
+    Ok(OidcService::get_providers(ctx.config()))
+}
+
+/// Begins logging in with an external identity provider.
+pub async fn auth_oidc_login_start(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<StartOidcOutput> {
+    let StartOidcLogin { provider } = params.parse()?;
+    OidcService::start(ctx, &provider, None).await
+}
+
+/// Begins linking an external identity to the current user.
+pub async fn auth_oidc_link_start(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<StartOidcOutput> {
+    let StartOidcLink {
+        provider,
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    let user = check_session(ctx, &session_token, user_id).await?;
+
+    OidcService::start(ctx, &provider, Some(user.user_id)).await
+}
+
+/// Finishes logging in or linking, once the provider has sent the user back.
+///
+/// Like a password login, if the user has MFA set up then the session
+/// produced is restricted until they verify it with `mfa_verify`.
+pub async fn auth_oidc_finish(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<FinishOidcOutput> {
+    let input: FinishOidcLogin = params.parse()?;
+    let ip_address = input.ip_address;
+    let user_agent = input.user_agent.clone();
+
+    let config = ctx.config();
+    RateLimitService::check(
+        ctx,
+        RateLimitAction::Login,
+        RateLimitScope::Ip(ip_address),
+        config.rate_limit_login_ip,
+    )
+    .await?;
+
+    RateLimitService::check(
+        ctx,
+        RateLimitAction::Login,
+        RateLimitScope::Global,
+        config.rate_limit_login_global,
+    )
+    .await?;
+
+    let (user_id, created) = match AuthenticationService::auth_oidc(ctx, input).await? {
+        OidcAuthenticated::Login { user_id, created } => (user_id, created),
+        OidcAuthenticated::Linked { user_id, provider } => {
+            return Ok(FinishOidcOutput::Linked { user_id, provider });
+        }
+    };
+
+    let user = UserService::get(ctx, Reference::Id(user_id)).await?;
+    let needs_mfa = AuthenticationService::needs_mfa(
+        ctx,
+        user_id,
+        user.multi_factor_secret.is_some(),
+    )
+    .await?;
+
+    info!(
+        "External authentication for user ID {user_id} succeeded (login complete: {})",
+        !needs_mfa,
+    );
+
+    let session_token = SessionService::create(
+        ctx,
+        CreateSession {
+            user_id,
+            ip_address,
+            user_agent,
+            restricted: needs_mfa,
+        },
+    )
+    .await?;
+
+    Ok(FinishOidcOutput::Login {
+        session_token,
+        needs_mfa,
+        user_id,
+        created,
+    })
+}
+
+pub async fn auth_oidc_identity_list(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Vec<UserExternalIdentityModel>> {
+    let GetExternalIdentities {
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    let user = check_session(ctx, &session_token, user_id).await?;
+    OidcService::get_all(ctx, user.user_id).await
+}
+
+pub async fn auth_oidc_identity_unlink(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<()> {
+    let UnlinkExternalIdentity {
+        provider,
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    let user = check_session(ctx, &session_token, user_id).await?;
+
+    OidcService::unlink(ctx, user.user_id, &provider).await
+}
@@ -20,23 +20,23 @@
 
 use super::prelude::*;
 use crate::hash::slice_to_blob_hash;
+use crate::models::blob_scan::Model as BlobScanModel;
 use crate::services::blob::{
-    BlobMetadata, CancelBlobUpload, GetBlobOutput, HardDelete, HardDeleteOutput,
-    StartBlobUpload, StartBlobUploadOutput,
+    BlobMetadata, CancelBlobUpload, CompleteMultipartBlobUpload, FinishBlobUploadPart,
+    GetBlobOutput, GetBlobUploadPart, GetBlobUploadPartOutput, GetLocalBlob,
+    GetLocalBlobOutput, HardDelete, HardDeleteOutput, PutLocalBlob, PutLocalBlobOutput,
+    StartBlobUpload, StartBlobUploadOutput, StartMultipartBlobUploadOutput,
 };
-use crate::services::Result;
+use crate::services::{JobService, Result};
 use crate::types::Bytes;
 
-/// Temporary endpoint to get any blob by hash.
-/// Primarily for user avatars, which have no other
-/// way of getting the data at the moment.
+/// Gets a download URL and metadata for any blob by hash.
 pub async fn blob_get(
     ctx: &ServiceContext<'_>,
     params: Params<'static>,
 ) -> Result<GetBlobOutput> {
     info!("Getting blob for S3 hash");
     let hash: Bytes = params.parse()?;
-    let data = BlobService::get(ctx, hash.as_ref()).await?;
 
     let BlobMetadata {
         mime,
@@ -44,8 +44,10 @@ pub async fn blob_get(
         created_at,
     } = BlobService::get_metadata(ctx, hash.as_ref()).await?;
 
+    let url = BlobService::get_download_url(ctx, hash.as_ref(), &mime, None).await?;
+
     Ok(GetBlobOutput {
-        data,
+        url,
         mime,
         size,
         created_at,
@@ -77,6 +79,61 @@ pub async fn blob_upload(
     BlobService::start_upload(ctx, input).await
 }
 
+/// Starts a new multipart upload, for large blobs.
+pub async fn blob_multipart_upload(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<StartMultipartBlobUploadOutput> {
+    info!("Creating new pending multipart blob upload");
+    let input: StartBlobUpload = params.parse()?;
+    BlobService::start_multipart_upload(ctx, input).await
+}
+
+/// Gets a presign URL to upload one part of a multipart upload.
+pub async fn blob_multipart_part(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<GetBlobUploadPartOutput> {
+    let input: GetBlobUploadPart = params.parse()?;
+    BlobService::presign_upload_part(ctx, input).await
+}
+
+/// Records the ETag of an uploaded part of a multipart upload.
+pub async fn blob_multipart_part_finish(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<()> {
+    let input: FinishBlobUploadPart = params.parse()?;
+    BlobService::finish_upload_part(ctx, input).await
+}
+
+/// Assembles the parts of a multipart upload into the pending blob.
+pub async fn blob_multipart_complete(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<()> {
+    let input: CompleteMultipartBlobUpload = params.parse()?;
+    BlobService::complete_multipart_upload(ctx, input).await
+}
+
+/// Reads a chunk of a blob through a presigned URL, when using local storage.
+pub async fn blob_local_get(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<GetLocalBlobOutput> {
+    let input: GetLocalBlob = params.parse()?;
+    BlobService::local_get(ctx, input).await
+}
+
+/// Writes a chunk of a blob through a presigned URL, when using local storage.
+pub async fn blob_local_put(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<PutLocalBlobOutput> {
+    let input: PutLocalBlob = params.parse()?;
+    BlobService::local_put(ctx, input).await
+}
+
 pub async fn blob_blacklist_add(
     ctx: &ServiceContext<'_>,
     params: Params<'static>,
@@ -85,14 +142,21 @@ pub async fn blob_blacklist_add(
     struct AddBlacklist {
         s3_hash: Bytes<'static>,
         user_id: i64,
+
+        #[serde(default)]
+        perceptual: bool,
     }
 
-    let AddBlacklist { s3_hash, user_id } = params.parse()?;
+    let AddBlacklist {
+        s3_hash,
+        user_id,
+        perceptual,
+    } = params.parse()?;
     let s3_hash = slice_to_blob_hash(s3_hash.as_ref());
 
     BlobService::check_hash_not_empty(s3_hash)?;
     BlobService::check_hash_in_use(ctx, s3_hash).await?;
-    BlobService::add_blacklist(ctx, s3_hash, user_id).await
+    BlobService::add_blacklist(ctx, s3_hash, user_id, perceptual).await
 }
 
 pub async fn blob_blacklist_remove(
@@ -123,6 +187,41 @@ pub async fn blob_blacklist_check(
     BlobService::on_blacklist(ctx, s3_hash).await
 }
 
+pub async fn blob_scan_get(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Option<BlobScanModel>> {
+    #[derive(Deserialize, Debug)]
+    struct GetBlobScan {
+        s3_hash: Bytes<'static>,
+    }
+
+    let GetBlobScan { s3_hash } = params.parse()?;
+    let s3_hash = slice_to_blob_hash(s3_hash.as_ref());
+    BlobService::get_scan(ctx, s3_hash).await
+}
+
+pub async fn blob_scan_infected(
+    ctx: &ServiceContext<'_>,
+    _params: Params<'static>,
+) -> Result<Vec<BlobScanModel>> {
+    BlobService::get_infected_scans(ctx).await
+}
+
+pub async fn blob_rescan(
+    ctx: &ServiceContext<'_>,
+    _params: Params<'static>,
+) -> Result<()> {
+    JobService::queue_rescan_blobs(ctx).await
+}
+
+pub async fn blob_perceptual_backfill(
+    ctx: &ServiceContext<'_>,
+    _params: Params<'static>,
+) -> Result<()> {
+    JobService::queue_backfill_perceptual_hashes(ctx).await
+}
+
 pub async fn blob_hard_delete_preview(
     ctx: &ServiceContext<'_>,
     params: Params<'static>,
@@ -130,11 +229,17 @@ pub async fn blob_hard_delete_preview(
     #[derive(Deserialize, Debug)]
     struct HardDeletePreview {
         s3_hash: Bytes<'static>,
+
+        #[serde(default)]
+        near_duplicates: bool,
     }
 
-    let HardDeletePreview { s3_hash } = params.parse()?;
+    let HardDeletePreview {
+        s3_hash,
+        near_duplicates,
+    } = params.parse()?;
     let s3_hash = slice_to_blob_hash(s3_hash.as_ref());
-    BlobService::hard_delete_preview(ctx, s3_hash).await
+    BlobService::hard_delete_preview(ctx, s3_hash, near_duplicates).await
 }
 
 pub async fn blob_hard_delete_confirm(
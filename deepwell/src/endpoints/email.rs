@@ -22,11 +22,11 @@ use super::prelude::*;
 use crate::services::email::{EmailService, EmailValidationOutput};
 
 pub async fn validate_email(
-    _ctx: &ServiceContext<'_>,
+    ctx: &ServiceContext<'_>,
     params: Params<'static>,
 ) -> Result<EmailValidationOutput> {
     let email: String = params.one()?;
     info!("Validating user email: {email}");
-    let output = EmailService::validate(&email).await?;
+    let output = EmailService::validate(ctx, &email).await?;
     Ok(output)
 }
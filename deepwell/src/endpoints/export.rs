@@ -0,0 +1,54 @@
+/*
+ * endpoints/export.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use crate::services::export::{
+    CreateExport, CreateExportOutput, GetExport, GetExportOutput,
+};
+
+pub async fn export_create(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<CreateExportOutput> {
+    let input: CreateExport = params.parse()?;
+    ExportService::create(ctx, input).await
+}
+
+pub async fn export_get(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Option<GetExportOutput>> {
+    let GetExport {
+        export_id,
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    info!("Getting export ID {export_id} for user ID {user_id}");
+    check_session(ctx, &session_token, user_id).await?;
+
+    match ExportService::get_for_user(ctx, &export_id, user_id).await? {
+        None => Ok(None),
+        Some(export) => {
+            let url = ExportService::get_download_url(ctx, &export).await?;
+            Ok(Some(GetExportOutput { export, url }))
+        }
+    }
+}
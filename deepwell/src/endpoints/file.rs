@@ -22,13 +22,19 @@ use super::prelude::*;
 use crate::models::file::Model as FileModel;
 use crate::models::file_revision::Model as FileRevisionModel;
 use crate::services::file::{
-    CreateFile, CreateFileOutput, DeleteFile, DeleteFileOutput, EditFile, EditFileOutput,
-    GetFileDetails, GetFileOutput, MoveFile, MoveFileOutput, RestoreFile,
-    RestoreFileOutput, RollbackFile,
+    CopyFile, CopyFileOutput, CreateFile, CreateFileOutput, DeleteFile, DeleteFileOutput,
+    EditFile, EditFileOutput, GetFileDetails, GetFileOutput, GetSiteFilesByLicense,
+    MoveFile, MoveFileOutput, RestoreFile, RestoreFileOutput, RollbackFile,
+    SiteFileLicensing,
 };
-use crate::services::Result;
+use crate::services::file_archive::{
+    CreateFileArchive, CreateFileArchiveOutput, GetFileArchive, GetFileArchiveOutput,
+    ImportFileArchive, ImportFileArchiveOutput,
+};
+use crate::services::thumbnail::GetImageUrl;
 use crate::services::{BlobService, FileRevisionService};
-use crate::types::{Bytes, FileDetails};
+use crate::services::{Error, Result};
+use crate::types::{parse_hidden_fields, FileDetails, FileHiddenField, FileLicensing};
 
 pub async fn file_get(
     ctx: &ServiceContext<'_>,
@@ -73,6 +79,78 @@ pub async fn file_create(
     FileService::create(ctx, input).await
 }
 
+pub async fn file_archive_import(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<ImportFileArchiveOutput> {
+    let input: ImportFileArchive = params.parse()?;
+
+    info!(
+        "Importing file archive to page ID {} in site ID {}",
+        input.page_id, input.site_id,
+    );
+
+    FileArchiveService::import(ctx, input).await
+}
+
+pub async fn file_archive_create(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<CreateFileArchiveOutput> {
+    let input: CreateFileArchive = params.parse()?;
+    FileArchiveService::create(ctx, input).await
+}
+
+pub async fn file_archive_get(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Option<GetFileArchiveOutput>> {
+    let GetFileArchive { file_archive_id } = params.parse()?;
+    info!("Getting file archive ID {file_archive_id}");
+
+    match FileArchiveService::get_optional(ctx, file_archive_id).await? {
+        None => Ok(None),
+        Some(file_archive) => {
+            let url = FileArchiveService::get_url(ctx, &file_archive).await?;
+            Ok(Some(GetFileArchiveOutput { file_archive, url }))
+        }
+    }
+}
+
+pub async fn file_licensing_search(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Vec<SiteFileLicensing>> {
+    let input: GetSiteFilesByLicense = params.parse()?;
+    FileService::get_by_license(ctx, input).await
+}
+
+pub async fn file_copy(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<CopyFileOutput> {
+    let input: CopyFile = params.parse()?;
+
+    info!(
+        "Copying file {:?} from page ID {} in site ID {} to page {:?} in site ID {}",
+        input.file,
+        input.page_id,
+        input.site_id,
+        input.destination_page,
+        input.destination_site_id,
+    );
+
+    FileService::copy(ctx, input).await
+}
+
+pub async fn file_image_url(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Option<String>> {
+    let input: GetImageUrl = params.parse()?;
+    ThumbnailService::get_image_url(ctx, input).await
+}
+
 pub async fn file_edit(
     ctx: &ServiceContext<'_>,
     params: Params<'static>,
@@ -149,7 +227,61 @@ async fn build_file_response(
     revision: FileRevisionModel,
     details: FileDetails,
 ) -> Result<GetFileOutput> {
-    let data = BlobService::get_maybe(ctx, details.data, &revision.s3_hash).await?;
+    // Strip hidden fields
+    //
+    // Content fields cannot be hidden on the latest revision, except
+    // for the blob when a file has been hard deleted.
+    let hidden_fields: Vec<FileHiddenField> = parse_hidden_fields(&revision.hidden)?;
+    let mut revision_user_id = Some(revision.user_id);
+    let mut revision_comments = Some(revision.comments);
+    let mut fetch_url = details.url;
+    let mut fetch_media = details.media;
+
+    for field in &hidden_fields {
+        match field {
+            FileHiddenField::User => revision_user_id = None,
+            FileHiddenField::Comments => revision_comments = None,
+            FileHiddenField::S3Hash => {
+                fetch_url = false;
+                fetch_media = false;
+            }
+            _ => (),
+        }
+    }
+
+    // Infected blobs are not served while they await review
+    if fetch_url || fetch_media {
+        match BlobService::check_not_infected(ctx, &revision.s3_hash).await {
+            Ok(()) => (),
+            Err(Error::BlobInfected(_)) => {
+                fetch_url = false;
+                fetch_media = false;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    let url = if fetch_url {
+        let url = BlobService::get_download_url(
+            ctx,
+            &revision.s3_hash,
+            &revision.mime,
+            Some(&file.name),
+        )
+        .await?;
+
+        Some(url)
+    } else {
+        None
+    };
+
+    let copied_from = FileService::get_copy_source(ctx, &file).await?;
+    let media = if fetch_media {
+        ThumbnailService::get_media(ctx, &revision.s3_hash, true).await?
+    } else {
+        None
+    };
+
     Ok(GetFileOutput {
         file_id: file.file_id,
         file_created_at: file.created_at,
@@ -160,13 +292,15 @@ async fn build_file_response(
         revision_type: revision.revision_type,
         revision_created_at: revision.created_at,
         revision_number: revision.revision_number,
-        revision_user_id: revision.user_id,
+        revision_user_id,
         name: file.name,
-        data: data.map(Bytes::from),
+        url,
         mime: revision.mime,
         size: revision.size,
-        licensing: revision.licensing,
-        revision_comments: revision.comments,
-        hidden_fields: revision.hidden,
+        licensing: FileLicensing::from_json(&revision.licensing),
+        revision_comments,
+        hidden_fields,
+        copied_from,
+        media,
     })
 }
@@ -19,10 +19,10 @@
  */
 
 use super::prelude::*;
-use crate::models::file_revision::Model as FileRevisionModel;
 use crate::services::file::GetFile;
 use crate::services::file_revision::{
-    FileRevisionCountOutput, GetFileRevision, GetFileRevisionRange, UpdateFileRevision,
+    FileRevisionCountOutput, FileRevisionDiffOutput, FileRevisionModelFiltered,
+    GetFileRevision, GetFileRevisionDiff, GetFileRevisionRange, UpdateFileRevision,
 };
 
 pub async fn file_revision_count(
@@ -50,7 +50,7 @@ pub async fn file_revision_count(
 pub async fn file_revision_get(
     ctx: &ServiceContext<'_>,
     params: Params<'static>,
-) -> Result<Option<FileRevisionModel>> {
+) -> Result<Option<FileRevisionModelFiltered>> {
     let input: GetFileRevision = params.parse()?;
 
     info!(
@@ -58,21 +58,42 @@ pub async fn file_revision_get(
         input.revision_number, input.file_id, input.page_id,
     );
 
-    FileRevisionService::get_optional(ctx, input).await
+    match FileRevisionService::get_optional(ctx, input).await? {
+        Some(revision) => FileRevisionService::filter(revision).map(Some),
+        None => Ok(None),
+    }
 }
 
 pub async fn file_revision_range(
     ctx: &ServiceContext<'_>,
     params: Params<'static>,
-) -> Result<Vec<FileRevisionModel>> {
+) -> Result<Vec<FileRevisionModelFiltered>> {
     let input: GetFileRevisionRange = params.parse()?;
-    FileRevisionService::get_range(ctx, input).await
+    let revisions = FileRevisionService::get_range(ctx, input).await?;
+    FileRevisionService::filter_all(revisions)
+}
+
+pub async fn file_revision_diff(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<FileRevisionDiffOutput> {
+    let input: GetFileRevisionDiff = params.parse()?;
+
+    info!(
+        "Getting diff of file revisions {} to {} for file ID {} in site ID {}",
+        input.from_revision_number,
+        input.to_revision_number,
+        input.file_id,
+        input.site_id,
+    );
+
+    FileRevisionService::diff(ctx, input).await
 }
 
 pub async fn file_revision_edit(
     ctx: &ServiceContext<'_>,
     params: Params<'static>,
-) -> Result<FileRevisionModel> {
+) -> Result<FileRevisionModelFiltered> {
     let input: UpdateFileRevision = params.parse()?;
 
     info!(
@@ -80,5 +101,6 @@ pub async fn file_revision_edit(
         input.revision_id, input.file_id, input.page_id,
     );
 
-    FileRevisionService::update(ctx, input).await
+    let revision = FileRevisionService::update(ctx, input).await?;
+    FileRevisionService::filter(revision)
 }
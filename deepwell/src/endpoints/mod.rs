@@ -29,14 +29,17 @@
 
 #[allow(unused_imports)]
 mod prelude {
+    pub use super::{check_platform_admin, check_session};
     pub use crate::api::ServerState;
     pub use crate::services::{
-        AliasService, BlobService, CategoryService, DomainService, Error as ServiceError,
-        FileRevisionService, FileService, LinkService, MessageReportService,
-        MessageService, MfaService, PageRevisionService, PageService, ParentService,
+        AliasService, ApiTokenService, AvatarService, BlobService, CategoryService,
+        ContributionService, DomainService, Error as ServiceError, ExportService,
+        FileArchiveService, FileRevisionService, FileService, LinkService,
+        MessageReportService, MessageService, MfaService, OauthService, OidcService,
+        PageRevisionService, PageService, ParentService, QuotaService, RateLimitService,
         RelationService, RenderService, Result, ScoreService, ServiceContext,
         SessionService, SettingsService, SiteService, StdResult, TextService,
-        UserService, ViewService, VoteService,
+        ThumbnailService, UserService, ViewService, VoteService, WebauthnService,
     };
     pub use jsonrpsee::types::params::Params;
     pub use std::convert::TryFrom;
@@ -47,6 +50,7 @@ pub mod blob;
 pub mod category;
 pub mod domain;
 pub mod email;
+pub mod export;
 pub mod file;
 pub mod file_revision;
 pub mod info;
@@ -54,9 +58,11 @@ pub mod link;
 pub mod locale;
 pub mod message;
 pub mod misc;
+pub mod oauth;
 pub mod page;
 pub mod page_revision;
 pub mod parent;
+pub mod quota;
 pub mod site;
 pub mod site_member;
 pub mod text;
@@ -64,3 +70,47 @@ pub mod user;
 pub mod user_bot;
 pub mod view;
 pub mod vote;
+
+use crate::models::user::Model as UserModel;
+use crate::services::{Error, Result, ServiceContext, SessionService};
+
+/// Verifies that the session token belongs to the passed user.
+///
+/// Restricted sessions and scoped tokens are not accepted, so neither bots
+/// nor OAuth clients can perform account management on a user's behalf.
+pub async fn check_session(
+    ctx: &ServiceContext<'_>,
+    session_token: &str,
+    user_id: i64,
+) -> Result<UserModel> {
+    let user = SessionService::get_user(ctx, session_token, false).await?;
+    if user.user_id != user_id {
+        error!(
+            "Passed user ID ({}) does not match session token ({})",
+            user_id, user.user_id,
+        );
+
+        return Err(Error::SessionUserId {
+            active_user_id: user_id,
+            session_user_id: user.user_id,
+        });
+    }
+
+    Ok(user)
+}
+
+/// Verifies that the session token belongs to the passed user,
+/// and that they are an administrator of the platform.
+pub async fn check_platform_admin(
+    ctx: &ServiceContext<'_>,
+    session_token: &str,
+    user_id: i64,
+) -> Result<UserModel> {
+    let user = check_session(ctx, session_token, user_id).await?;
+    if !ctx.config().platform_admins.contains(&user.user_id) {
+        error!("User ID {} is not a platform administrator", user.user_id);
+        return Err(Error::NotPlatformAdmin);
+    }
+
+    Ok(user)
+}
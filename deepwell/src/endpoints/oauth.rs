@@ -0,0 +1,146 @@
+/*
+ * endpoints/oauth.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use crate::services::oauth::{
+    CreateOauthClient, CreateOauthClientOutput, DeleteOauthClient,
+    FinishOauthAuthorization, GetOauthClients, GetOauthGrants, OauthClientOutput,
+    OauthClientTokenRequest, OauthConsentOutput, OauthGrantOutput, OauthIntrospectOutput,
+    OauthRedirectOutput, OauthTokenOutput, OauthTokenRequest, RevokeOauthGrant,
+    StartOauthAuthorization,
+};
+
+pub async fn oauth_client_create(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<CreateOauthClientOutput> {
+    let input: CreateOauthClient = params.parse()?;
+    check_session(ctx, &input.session_token, input.user_id).await?;
+    OauthService::create_client(ctx, input).await
+}
+
+pub async fn oauth_client_list(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Vec<OauthClientOutput>> {
+    let GetOauthClients {
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    check_session(ctx, &session_token, user_id).await?;
+    let clients = OauthService::get_clients(ctx, user_id).await?;
+    Ok(clients.into_iter().map(OauthClientOutput::from).collect())
+}
+
+pub async fn oauth_client_delete(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<()> {
+    let DeleteOauthClient {
+        oauth_client_id,
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    check_session(ctx, &session_token, user_id).await?;
+    OauthService::delete_client(ctx, oauth_client_id, user_id).await
+}
+
+pub async fn oauth_authorize_start(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<OauthConsentOutput> {
+    let StartOauthAuthorization {
+        request,
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    check_session(ctx, &session_token, user_id).await?;
+    OauthService::start_authorization(ctx, &request).await
+}
+
+pub async fn oauth_authorize_finish(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<OauthRedirectOutput> {
+    let FinishOauthAuthorization {
+        request,
+        user_id,
+        session_token,
+        approve,
+    } = params.parse()?;
+
+    check_session(ctx, &session_token, user_id).await?;
+    OauthService::finish_authorization(ctx, &request, user_id, approve).await
+}
+
+pub async fn oauth_token(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<OauthTokenOutput> {
+    let input: OauthTokenRequest = params.parse()?;
+    OauthService::exchange(ctx, input).await
+}
+
+pub async fn oauth_token_introspect(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<OauthIntrospectOutput> {
+    let input: OauthClientTokenRequest = params.parse()?;
+    OauthService::introspect(ctx, input).await
+}
+
+pub async fn oauth_token_revoke(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<()> {
+    let input: OauthClientTokenRequest = params.parse()?;
+    OauthService::revoke_token(ctx, input).await
+}
+
+pub async fn oauth_grant_list(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Vec<OauthGrantOutput>> {
+    let GetOauthGrants {
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    check_session(ctx, &session_token, user_id).await?;
+    OauthService::get_grants(ctx, user_id).await
+}
+
+pub async fn oauth_grant_revoke(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<()> {
+    let RevokeOauthGrant {
+        oauth_client_id,
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    check_session(ctx, &session_token, user_id).await?;
+    OauthService::revoke_grant(ctx, oauth_client_id, user_id).await?;
+    Ok(())
+}
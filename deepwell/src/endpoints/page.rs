@@ -20,17 +20,25 @@
 
 use super::prelude::*;
 use crate::models::file::Model as FileModel;
+use crate::models::file_revision::Model as FileRevisionModel;
 use crate::models::page::Model as PageModel;
-use crate::services::file::{GetFileOutput, GetPageFiles};
+use crate::services::file::{FileCopySource, GetFileOutput, GetPageFiles};
 use crate::services::page::{
     CreatePage, CreatePageOutput, DeletePage, DeletePageOutput, EditPage, EditPageOutput,
-    GetDeletedPageOutput, GetPageAnyDetails, GetPageDirect, GetPageOutput,
+    GetDeletedPageOutput, GetPageAnyDetails, GetPageDirect, GetPageList, GetPageOutput,
     GetPageReference, GetPageReferenceDetails, GetPageScoreOutput, GetPageSlug, MovePage,
     MovePageOutput, RestorePage, RestorePageOutput, RollbackPage, SetPageLayout,
 };
-use crate::services::{Result, TextService};
-use crate::types::{FileOrder, PageDetails, Reference};
+use crate::services::thumbnail::FileMedia;
+use crate::services::{Result, TextService, ThumbnailService};
+use crate::types::{
+    parse_hidden_fields, FileHiddenField, FileLicensing, FileOrder, PageDetails,
+    PageHiddenField, PageOrder, Reference,
+};
 use futures::future::try_join_all;
+use futures::try_join;
+use sea_orm::query::Order;
+use std::collections::HashMap;
 
 pub async fn page_create(
     ctx: &ServiceContext<'_>,
@@ -97,6 +105,28 @@ pub async fn page_get_deleted(
     Ok(result)
 }
 
+pub async fn page_get_all(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Vec<PageModel>> {
+    let GetPageList {
+        site_id,
+        category,
+        deleted,
+        order,
+        descending,
+        pagination,
+    } = params.parse()?;
+
+    info!("Getting pages in site ID {site_id} (category {category:?})");
+    let order = PageOrder {
+        column: order,
+        direction: if descending { Order::Desc } else { Order::Asc },
+    };
+
+    PageService::get_all(ctx, site_id, category, deleted, order, pagination).await
+}
+
 pub async fn page_get_score(
     ctx: &ServiceContext<'_>,
     params: Params<'static>,
@@ -120,27 +150,47 @@ pub async fn page_get_files(
         page_id,
         site_id,
         deleted,
+        pagination,
     } = params.parse()?;
 
     info!("Getting files for page ID {page_id} in site ID {site_id}");
-    let get_page_files = FileService::get_all(
+    let files = FileService::get_all(
         ctx,
         site_id,
         page_id,
         deleted.to_option().copied(),
         FileOrder::default(),
+        pagination,
     )
-    .await?
-    .into_iter()
-    .map(|file| build_page_file_output(ctx, file));
+    .await?;
 
-    let result = try_join_all(get_page_files)
-        .await?
-        .into_iter()
-        .flatten()
-        .collect();
+    let revisions = try_join_all(files.iter().map(|file| {
+        FileRevisionService::get_latest(ctx, file.site_id, file.page_id, file.file_id)
+    }))
+    .await?;
+
+    // Fetch copy sources and media for all files at once,
+    // leaving out media for revisions with a hidden hash
+    let mut media_hashes = Vec::new();
+    for revision in &revisions {
+        let hidden_fields: Vec<FileHiddenField> = parse_hidden_fields(&revision.hidden)?;
+        if !hidden_fields.contains(&FileHiddenField::S3Hash) {
+            media_hashes.push(revision.s3_hash.clone());
+        }
+    }
 
-    Ok(result)
+    let (copy_sources, media) = try_join!(
+        FileService::get_copy_sources(ctx, &files),
+        ThumbnailService::get_media_many(ctx, &media_hashes),
+    )?;
+
+    files
+        .into_iter()
+        .zip(revisions)
+        .map(|(file, revision)| {
+            build_page_file_output(file, revision, &copy_sources, &media)
+        })
+        .collect()
 }
 
 pub async fn page_edit(
@@ -248,6 +298,22 @@ async fn build_page_output(
         CategoryService::get(ctx, page.site_id, Reference::from(page.page_category_id))
             .await?;
 
+    // Strip hidden fields
+    //
+    // Content fields cannot be hidden on the latest revision,
+    // see PageRevisionService::update().
+    let hidden_fields: Vec<PageHiddenField> = parse_hidden_fields(&revision.hidden)?;
+    let mut revision_user_id = Some(revision.user_id);
+    let mut revision_comments = Some(revision.comments);
+
+    for field in &hidden_fields {
+        match field {
+            PageHiddenField::User => revision_user_id = None,
+            PageHiddenField::Comments => revision_comments = None,
+            _ => (),
+        }
+    }
+
     // Get text data, if requested
     let (wikitext, compiled_html) = try_join!(
         TextService::get_maybe(ctx, details.wikitext, &revision.wikitext_hash),
@@ -275,13 +341,13 @@ async fn build_page_output(
         revision_type: revision.revision_type,
         revision_created_at: revision.created_at,
         revision_number: revision.revision_number,
-        revision_user_id: revision.user_id,
+        revision_user_id,
         wikitext,
         compiled_html,
         compiled_at: revision.compiled_at,
         compiled_generator: revision.compiled_generator,
-        revision_comments: revision.comments,
-        hidden_fields: revision.hidden,
+        revision_comments,
+        hidden_fields,
         title: revision.title,
         alt_title: revision.alt_title,
         slug: revision.slug,
@@ -311,7 +377,7 @@ async fn build_page_deleted_output(
         page_revision_count: revision.revision_number,
         site_id: page.site_id,
         discussion_thread_id: page.discussion_thread_id,
-        hidden_fields: revision.hidden,
+        hidden_fields: parse_hidden_fields(&revision.hidden)?,
         title: revision.title,
         alt_title: revision.alt_title,
         slug: revision.slug,
@@ -320,17 +386,41 @@ async fn build_page_deleted_output(
     }))
 }
 
-async fn build_page_file_output(
-    ctx: &ServiceContext<'_>,
+fn build_page_file_output(
     file: FileModel,
-) -> Result<Option<GetFileOutput>> {
-    // Get file revision
-    let revision =
-        FileRevisionService::get_latest(ctx, file.site_id, file.page_id, file.file_id)
-            .await?;
+    revision: FileRevisionModel,
+    copy_sources: &HashMap<i64, FileCopySource>,
+    media: &HashMap<Vec<u8>, FileMedia>,
+) -> Result<GetFileOutput> {
+    // Strip hidden fields
+    let hidden_fields: Vec<FileHiddenField> = parse_hidden_fields(&revision.hidden)?;
+    let mut revision_user_id = Some(revision.user_id);
+    let mut revision_comments = Some(revision.comments);
+    let mut show_media = true;
+
+    for field in &hidden_fields {
+        match field {
+            FileHiddenField::User => revision_user_id = None,
+            FileHiddenField::Comments => revision_comments = None,
+            FileHiddenField::S3Hash => show_media = false,
+            _ => (),
+        }
+    }
+
+    let copied_from = file
+        .copied_from_revision_id
+        .and_then(|revision_id| copy_sources.get(&revision_id))
+        .cloned();
+
+    // Like the file URL, thumbnail URLs are left out of listings
+    let media = if show_media {
+        media.get(&revision.s3_hash).cloned()
+    } else {
+        None
+    };
 
     // Build result struct
-    Ok(Some(GetFileOutput {
+    Ok(GetFileOutput {
         file_id: file.file_id,
         file_created_at: file.created_at,
         file_updated_at: file.updated_at,
@@ -340,13 +430,15 @@ async fn build_page_file_output(
         revision_type: revision.revision_type,
         revision_created_at: revision.created_at,
         revision_number: revision.revision_number,
-        revision_user_id: revision.user_id,
+        revision_user_id,
         name: file.name,
-        data: None,
+        url: None,
         mime: revision.mime,
         size: revision.size,
-        licensing: revision.licensing,
-        revision_comments: revision.comments,
-        hidden_fields: revision.hidden,
-    }))
+        licensing: FileLicensing::from_json(&revision.licensing),
+        revision_comments,
+        hidden_fields,
+        copied_from,
+        media,
+    })
 }
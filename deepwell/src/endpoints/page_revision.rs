@@ -19,14 +19,13 @@
  */
 
 use super::prelude::*;
-use crate::models::page_revision::Model as PageRevisionModel;
 use crate::services::page::GetPageReference;
 use crate::services::page_revision::{
-    GetPageRevision, GetPageRevisionDetails, GetPageRevisionRangeDetails,
-    PageRevisionCountOutput, PageRevisionModelFiltered, UpdatePageRevisionDetails,
+    GetPageRevision, GetPageRevisionDetails, GetPageRevisionDiff,
+    GetPageRevisionRangeDetails, PageRevisionCountOutput, PageRevisionDiffOutput,
+    PageRevisionModelFiltered, UpdatePageRevisionDetails,
 };
-use crate::services::{Result, TextService};
-use crate::types::PageDetails;
+use crate::services::Result;
 
 pub async fn page_revision_count(
     ctx: &ServiceContext<'_>,
@@ -72,7 +71,8 @@ pub async fn page_revision_get(
     match revision {
         None => Ok(None),
         Some(revision) => {
-            let revision = filter_and_populate_revision(ctx, revision, details).await?;
+            let revision =
+                PageRevisionService::filter_and_populate(ctx, revision, details).await?;
             Ok(Some(revision))
         }
     }
@@ -95,7 +95,7 @@ pub async fn page_revision_edit(
         PageRevisionService::get_direct(ctx, revision_id),
     )?;
 
-    filter_and_populate_revision(ctx, revision, details).await
+    PageRevisionService::filter_and_populate(ctx, revision, details).await
 }
 
 pub async fn page_revision_range(
@@ -104,101 +104,22 @@ pub async fn page_revision_range(
 ) -> Result<Vec<PageRevisionModelFiltered>> {
     let GetPageRevisionRangeDetails { input, details } = params.parse()?;
     let revisions = PageRevisionService::get_range(ctx, input).await?;
-    filter_and_populate_revisions(ctx, revisions, details).await
+    PageRevisionService::filter_and_populate_all(ctx, revisions, details).await
 }
 
-// Helper functions
-
-async fn filter_and_populate_revision(
-    ctx: &ServiceContext<'_>,
-    model: PageRevisionModel,
-    mut details: PageDetails,
-) -> Result<PageRevisionModelFiltered> {
-    let PageRevisionModel {
-        revision_id,
-        revision_type,
-        created_at,
-        updated_at,
-        from_wikidot,
-        revision_number,
-        page_id,
-        site_id,
-        user_id,
-        changes,
-        wikitext_hash,
-        compiled_hash,
-        compiled_at,
-        compiled_generator,
-        comments,
-        hidden,
-        title,
-        mut alt_title,
-        slug,
-        tags,
-    } = model;
-
-    // Strip hidden fields
-    let mut comments = Some(comments);
-    let mut title = Some(title);
-    // alt-title is already Option and we're not doubling up
-    let mut slug = Some(slug);
-    let mut tags = Some(tags);
-
-    for field in &hidden {
-        // TODO hidden fields aren't standardized yet
-        match field.as_str() {
-            "wikitext" => details.wikitext = false,
-            "compiled" => details.compiled_html = false,
-            "comments" => comments = None,
-            "title" => title = None,
-            "alt_title" => alt_title = None,
-            "slug" => slug = None,
-            "tags" => tags = None,
-            _ => panic!("Unknown field name in hidden: {field}"),
-        }
-    }
-
-    // Get text data, if requested
-    let (wikitext, compiled_html) = try_join!(
-        TextService::get_maybe(ctx, details.wikitext, &wikitext_hash),
-        TextService::get_maybe(ctx, details.compiled_html, &compiled_hash),
-    )?;
-
-    Ok(PageRevisionModelFiltered {
-        revision_id,
-        revision_type,
-        created_at,
-        updated_at,
-        from_wikidot,
-        revision_number,
-        page_id,
-        site_id,
-        user_id,
-        changes,
-        wikitext,
-        compiled_html,
-        compiled_at,
-        compiled_generator,
-        comments,
-        hidden,
-        title,
-        alt_title,
-        slug,
-        tags,
-    })
-}
-
-async fn filter_and_populate_revisions(
+pub async fn page_revision_diff(
     ctx: &ServiceContext<'_>,
-    revisions: Vec<PageRevisionModel>,
-    details: PageDetails,
-) -> Result<Vec<PageRevisionModelFiltered>> {
-    let mut f_revisions = Vec::new();
+    params: Params<'static>,
+) -> Result<PageRevisionDiffOutput> {
+    let input: GetPageRevisionDiff = params.parse()?;
 
-    for revision in revisions {
-        let f_revision = filter_and_populate_revision(ctx, revision, details).await?;
-        f_revisions.push(f_revision)
-    }
+    info!(
+        "Getting diff of revisions {} to {} for page ID {} in site ID {}",
+        input.from_revision_number,
+        input.to_revision_number,
+        input.page_id,
+        input.site_id,
+    );
 
-    Ok(f_revisions)
+    PageRevisionService::diff(ctx, input).await
 }
@@ -0,0 +1,46 @@
+/*
+ * endpoints/quota.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use crate::services::quota::{QuotaOwner, SetStorageQuota, StorageUsageOutput};
+
+pub async fn storage_quota_get(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<StorageUsageOutput> {
+    let owner: QuotaOwner = params.parse()?;
+    QuotaService::get_report(ctx, owner).await
+}
+
+pub async fn storage_quota_set(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<()> {
+    let input: SetStorageQuota = params.parse()?;
+    QuotaService::set(ctx, input).await
+}
+
+pub async fn storage_quota_reset(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<()> {
+    let owner: QuotaOwner = params.parse()?;
+    QuotaService::reset(ctx, owner).await
+}
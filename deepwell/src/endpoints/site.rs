@@ -21,6 +21,10 @@
 use super::prelude::*;
 use crate::models::sea_orm_active_enums::AliasType;
 use crate::models::site::Model as SiteModel;
+use crate::services::contribution::{
+    ContributionsOutput, GetSiteRecentChanges, GetSiteRecentChangesFeed,
+    SiteRecentChangesFeedOutput,
+};
 use crate::services::site::{
     CreateSite, CreateSiteOutput, GetSite, GetSiteOutput, UpdateSite,
 };
@@ -69,3 +73,25 @@ pub async fn site_update(
     info!("Updating site {:?}", site);
     SiteService::update(ctx, site, body, user_id).await
 }
+
+pub async fn site_recent_changes(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<ContributionsOutput> {
+    let input: GetSiteRecentChanges = params.parse()?;
+    info!("Getting recent changes for site ID {}", input.site_id);
+    ContributionService::get_site_recent(ctx, input).await
+}
+
+pub async fn site_recent_changes_feed(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<SiteRecentChangesFeedOutput> {
+    let input: GetSiteRecentChangesFeed = params.parse()?;
+    info!(
+        "Getting recent changes {:?} feed for site ID {}",
+        input.format, input.input.site_id,
+    );
+
+    ContributionService::get_site_recent_feed(ctx, input).await
+}
@@ -20,7 +20,10 @@
 
 use super::prelude::*;
 use crate::models::relation::Model as RelationModel;
-use crate::services::relation::{CreateSiteMember, GetSiteMember, RemoveSiteMember};
+use crate::services::relation::{
+    AppointSiteStaff, CreateSiteMember, CreateSiteStaff, DismissSiteStaff, GetSiteMember,
+    GetSiteStaff, RemoveSiteMember, RemoveSiteStaff,
+};
 
 pub async fn membership_get(
     ctx: &ServiceContext<'_>,
@@ -45,3 +48,55 @@ pub async fn membership_delete(
     let input: RemoveSiteMember = params.parse()?;
     RelationService::remove_site_member(ctx, input).await
 }
+
+pub async fn staff_get(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Option<RelationModel>> {
+    let input: GetSiteStaff = params.parse()?;
+    RelationService::get_optional_site_staff(ctx, input).await
+}
+
+pub async fn staff_set(ctx: &ServiceContext<'_>, params: Params<'static>) -> Result<()> {
+    let AppointSiteStaff {
+        site_id,
+        user_id,
+        created_by,
+        session_token,
+    } = params.parse()?;
+
+    check_platform_admin(ctx, &session_token, created_by).await?;
+    RelationService::create_site_staff(
+        ctx,
+        CreateSiteStaff {
+            site_id,
+            user_id,
+            created_by,
+            metadata: (),
+        },
+    )
+    .await
+}
+
+pub async fn staff_delete(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<RelationModel> {
+    let DismissSiteStaff {
+        site_id,
+        user_id,
+        removed_by,
+        session_token,
+    } = params.parse()?;
+
+    check_platform_admin(ctx, &session_token, removed_by).await?;
+    RelationService::remove_site_staff(
+        ctx,
+        RemoveSiteStaff {
+            site_id,
+            user_id,
+            removed_by,
+        },
+    )
+    .await
+}
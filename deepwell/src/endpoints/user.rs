@@ -19,11 +19,23 @@
  */
 
 use super::prelude::*;
-use crate::models::sea_orm_active_enums::AliasType;
+use crate::models::alias::Model as AliasModel;
+use crate::models::export::Model as ExportModel;
+use crate::models::sea_orm_active_enums::{AliasType, UserType};
 use crate::models::user::Model as UserModel;
+use crate::models::user_deletion::Model as UserDeletionModel;
+use crate::models::user_name_change::Model as UserNameChangeModel;
+use crate::services::alias::ReleaseUserAlias;
+use crate::services::contribution::{ContributionsOutput, GetUserContributions};
+use crate::services::export::{
+    CreateAccountExport, CreateExportOutput, GetAccountExports,
+};
+use crate::services::rate_limit::{RateLimitAction, RateLimitScope};
 use crate::services::user::{
     CreateUser, CreateUserOutput, GetUser, GetUserOutput, UpdateUser,
 };
+use crate::services::user_deletion::UserDeletionRequest;
+use crate::services::{Error, UserDeletionService};
 
 pub async fn user_create(
     ctx: &ServiceContext<'_>,
@@ -31,6 +43,22 @@ pub async fn user_create(
 ) -> Result<CreateUserOutput> {
     info!("Creating new regular user");
     let input: CreateUser = params.parse()?;
+
+    // Accounts without passwords are only created
+    // when signing in with an external identity.
+    if input.user_type == UserType::Regular && input.password.is_empty() {
+        error!("User submitted empty password when creating account");
+        return Err(Error::EmptyPassword);
+    }
+
+    RateLimitService::check(
+        ctx,
+        RateLimitAction::UserCreate,
+        RateLimitScope::Global,
+        ctx.config().rate_limit_user_create,
+    )
+    .await?;
+
     UserService::create(ctx, input).await
 }
 
@@ -54,8 +82,13 @@ pub async fn user_get(
         Some(user) => {
             let aliases =
                 AliasService::get_all(ctx, AliasType::User, user.user_id).await?;
+            let avatars = AvatarService::get_all(ctx, user.user_id).await?;
 
-            Ok(Some(GetUserOutput { user, aliases }))
+            Ok(Some(GetUserOutput {
+                user,
+                aliases,
+                avatars,
+            }))
         }
     }
 }
@@ -91,3 +124,99 @@ pub async fn user_add_name_change(
     let user = UserService::get(ctx, reference).await?;
     UserService::add_name_change_token(ctx, &user).await
 }
+
+pub async fn user_name_history(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Vec<UserNameChangeModel>> {
+    let GetUser { user: reference } = params.parse()?;
+    info!("Getting name history for user {:?}", reference);
+    UserService::get_name_history(ctx, reference).await
+}
+
+pub async fn user_alias_release(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<AliasModel> {
+    let ReleaseUserAlias { slug } = params.parse()?;
+    info!("Releasing user alias '{slug}'");
+    AliasService::release(ctx, &slug).await
+}
+
+pub async fn user_contributions(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<ContributionsOutput> {
+    let input: GetUserContributions = params.parse()?;
+    info!(
+        "Getting contributions for user {:?} (site ID {:?})",
+        input.user, input.site_id,
+    );
+
+    ContributionService::get_user(ctx, input).await
+}
+
+pub async fn user_export_create(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<CreateExportOutput> {
+    let CreateAccountExport {
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    check_session(ctx, &session_token, user_id).await?;
+    ExportService::create_account(ctx, user_id).await
+}
+
+pub async fn user_export_list(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Vec<ExportModel>> {
+    let GetAccountExports {
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    check_session(ctx, &session_token, user_id).await?;
+    ExportService::get_account_exports(ctx, user_id).await
+}
+
+pub async fn user_deletion_request(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<UserDeletionModel> {
+    let UserDeletionRequest {
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    check_session(ctx, &session_token, user_id).await?;
+    UserDeletionService::request(ctx, user_id).await
+}
+
+pub async fn user_deletion_get(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Option<UserDeletionModel>> {
+    let UserDeletionRequest {
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    check_session(ctx, &session_token, user_id).await?;
+    UserDeletionService::get_pending(ctx, user_id).await
+}
+
+pub async fn user_deletion_cancel(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<UserDeletionModel> {
+    let UserDeletionRequest {
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    check_session(ctx, &session_token, user_id).await?;
+    UserDeletionService::cancel(ctx, user_id).await
+}
@@ -21,11 +21,15 @@
 use super::prelude::*;
 use crate::models::sea_orm_active_enums::UserType;
 use crate::models::user_bot_owner::Model as UserBotOwnerModel;
+use crate::services::api_token::{
+    ApiTokenOutput, CreateApiToken, CreateApiTokenOutput, ListApiTokens, RevokeApiToken,
+};
 use crate::services::user::{CreateUser, CreateUserOutput, GetUser, UpdateUserBody};
 use crate::services::user_bot_owner::{
     BotOwner, BotUserOutput, CreateBotOwner, CreateBotUser, RemoveBotOwner,
     RemoveBotOwnerOutput, UserBotOwnerService,
 };
+use crate::services::Error;
 use crate::types::{Maybe, Reference};
 
 pub async fn bot_user_create(
@@ -38,15 +42,19 @@ pub async fn bot_user_create(
         locales,
         purpose,
         owners,
-        authorization_token,
         bypass_filter,
         bypass_email_verification,
+        user_id,
+        session_token,
     } = params.parse()?;
 
     info!("Creating new bot user with name '{}'", name);
 
-    // TODO verify auth token
-    let _ = authorization_token;
+    check_session(ctx, &session_token, user_id).await?;
+    if !owners.iter().any(|owner| owner.user_id == user_id) {
+        error!("User ID {user_id} creating bot is not one of its owners");
+        return Err(Error::NotBotOwner);
+    }
 
     // Create bot user
     let output = UserService::create(
@@ -56,7 +64,7 @@ pub async fn bot_user_create(
             name,
             email,
             locales,
-            password: String::new(), // TODO configure user-bot password
+            password: String::new(),
             bypass_filter,
             bypass_email_verification,
         },
@@ -151,3 +159,47 @@ pub async fn bot_user_owner_remove(
     info!("Remove bot owner ({:?} <- {:?})", input.bot, input.human,);
     UserBotOwnerService::remove(ctx, input).await
 }
+
+pub async fn bot_token_create(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<CreateApiTokenOutput> {
+    let input: CreateApiToken = params.parse()?;
+    check_session(ctx, &input.session_token, input.user_id).await?;
+    ApiTokenService::create(ctx, input).await
+}
+
+pub async fn bot_token_list(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<Vec<ApiTokenOutput>> {
+    let ListApiTokens {
+        bot_user_id,
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    check_session(ctx, &session_token, user_id).await?;
+    if !UserBotOwnerService::is_owner(ctx, bot_user_id, user_id).await? {
+        error!("User ID {user_id} is not an owner of bot ID {bot_user_id}");
+        return Err(Error::NotBotOwner);
+    }
+
+    let tokens = ApiTokenService::get_all(ctx, bot_user_id).await?;
+    Ok(tokens.into_iter().map(ApiTokenOutput::from).collect())
+}
+
+pub async fn bot_token_revoke(
+    ctx: &ServiceContext<'_>,
+    params: Params<'static>,
+) -> Result<ApiTokenOutput> {
+    let RevokeApiToken {
+        api_token_id,
+        user_id,
+        session_token,
+    } = params.parse()?;
+
+    check_session(ctx, &session_token, user_id).await?;
+    let token = ApiTokenService::revoke(ctx, api_token_id, user_id).await?;
+    Ok(ApiTokenOutput::from(token))
+}
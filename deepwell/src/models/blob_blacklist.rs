@@ -11,6 +11,7 @@ pub struct Model {
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: TimeDateTimeWithTimeZone,
     pub created_by: i64,
+    pub perceptual_hash: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
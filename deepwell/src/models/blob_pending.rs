@@ -18,8 +18,12 @@ pub struct Model {
     pub s3_path: String,
     #[sea_orm(column_type = "VarBinary(StringLen::None)", nullable)]
     pub s3_hash: Option<Vec<u8>>,
-    #[sea_orm(column_type = "Text")]
-    pub presign_url: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub presign_url: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub multipart_upload_id: Option<String>,
+    pub multipart_part_size: Option<i64>,
+    pub multipart_etags: Option<Vec<String>>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -15,6 +15,7 @@ pub struct Model {
     #[serde(with = "time::serde::rfc3339::option")]
     pub deleted_at: Option<TimeDateTimeWithTimeZone>,
     pub from_wikidot: bool,
+    pub copied_from_revision_id: Option<i64>,
     #[sea_orm(column_type = "Text")]
     pub name: String,
     pub page_id: i64,
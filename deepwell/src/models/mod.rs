@@ -3,9 +3,16 @@
 pub mod prelude;
 
 pub mod alias;
+pub mod api_token;
 pub mod blob_blacklist;
+pub mod blob_media;
 pub mod blob_pending;
+pub mod blob_perceptual_hash;
+pub mod blob_scan;
+pub mod blob_thumbnail;
+pub mod export;
 pub mod file;
+pub mod file_archive;
 pub mod file_revision;
 pub mod filter;
 pub mod message;
@@ -13,6 +20,8 @@ pub mod message_draft;
 pub mod message_recipient;
 pub mod message_record;
 pub mod message_report;
+pub mod oauth_client;
+pub mod oauth_token;
 pub mod page;
 pub mod page_attribution;
 pub mod page_category;
@@ -28,6 +37,12 @@ pub mod sea_orm_active_enums;
 pub mod session;
 pub mod site;
 pub mod site_domain;
+pub mod storage_quota;
 pub mod text;
 pub mod user;
+pub mod user_avatar;
 pub mod user_bot_owner;
+pub mod user_deletion;
+pub mod user_external_identity;
+pub mod user_name_change;
+pub mod webauthn_credential;
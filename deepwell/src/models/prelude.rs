@@ -1,7 +1,9 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
 pub use super::alias::Entity as Alias;
+pub use super::api_token::Entity as ApiToken;
 pub use super::blob_pending::Entity as BlobPending;
+pub use super::export::Entity as Export;
 pub use super::file::Entity as File;
 pub use super::file_revision::Entity as FileRevision;
 pub use super::filter::Entity as Filter;
@@ -10,6 +12,8 @@ pub use super::message_draft::Entity as MessageDraft;
 pub use super::message_recipient::Entity as MessageRecipient;
 pub use super::message_record::Entity as MessageRecord;
 pub use super::message_report::Entity as MessageReport;
+pub use super::oauth_client::Entity as OauthClient;
+pub use super::oauth_token::Entity as OauthToken;
 pub use super::page::Entity as Page;
 pub use super::page_attribution::Entity as PageAttribution;
 pub use super::page_category::Entity as PageCategory;
@@ -24,6 +28,12 @@ pub use super::relation::Entity as Relation;
 pub use super::session::Entity as Session;
 pub use super::site::Entity as Site;
 pub use super::site_domain::Entity as SiteDomain;
+pub use super::storage_quota::Entity as StorageQuota;
 pub use super::text::Entity as Text;
 pub use super::user::Entity as User;
+pub use super::user_avatar::Entity as UserAvatar;
 pub use super::user_bot_owner::Entity as UserBotOwner;
+pub use super::user_deletion::Entity as UserDeletion;
+pub use super::user_external_identity::Entity as UserExternalIdentity;
+pub use super::user_name_change::Entity as UserNameChange;
+pub use super::webauthn_credential::Entity as WebauthnCredential;
@@ -17,6 +17,17 @@ pub enum AliasType {
 #[derive(
     Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Copy, Serialize, Deserialize,
 )]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "export_destination")]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportDestination {
+    #[sea_orm(string_value = "local")]
+    Local,
+    #[sea_orm(string_value = "s3")]
+    S3,
+}
+#[derive(
+    Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Copy, Serialize, Deserialize,
+)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "file_revision_type")]
 #[serde(rename_all = "kebab-case")]
 pub enum FileRevisionType {
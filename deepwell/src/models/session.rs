@@ -13,7 +13,9 @@ pub struct Model {
     pub created_at: TimeDateTimeWithTimeZone,
     #[serde(with = "time::serde::rfc3339")]
     pub expires_at: TimeDateTimeWithTimeZone,
-    #[sea_orm(column_type = "Text")]
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_used_at: TimeDateTimeWithTimeZone,
+    #[sea_orm(column_type = "Inet", select_as = "text", save_as = "inet")]
     pub ip_address: String,
     #[sea_orm(column_type = "Text")]
     pub user_agent: String,
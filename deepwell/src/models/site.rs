@@ -35,6 +35,8 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::export::Entity")]
+    Export,
     #[sea_orm(has_many = "super::file::Entity")]
     File,
     #[sea_orm(has_many = "super::file_revision::Entity")]
@@ -53,6 +55,8 @@ pub enum Relation {
     PageRevision,
     #[sea_orm(has_many = "super::site_domain::Entity")]
     SiteDomain,
+    #[sea_orm(has_one = "super::storage_quota::Entity")]
+    StorageQuota,
     #[sea_orm(
         belongs_to = "super::site_domain::Entity",
         from = "Column::CustomDomain",
@@ -63,6 +67,12 @@ pub enum Relation {
     SiteDomainCustomDomain,
 }
 
+impl Related<super::export::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Export.def()
+    }
+}
+
 impl Related<super::file::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::File.def()
@@ -117,6 +127,12 @@ impl Related<super::site_domain::Entity> for Entity {
     }
 }
 
+impl Related<super::storage_quota::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StorageQuota.def()
+    }
+}
+
 impl Related<super::message::Entity> for Entity {
     fn to() -> RelationDef {
         super::message_report::Relation::Message.def()
@@ -31,8 +31,8 @@ pub struct Model {
     pub email_is_alias: Option<bool>,
     #[serde(with = "time::serde::rfc3339::option")]
     pub email_verified_at: Option<TimeDateTimeWithTimeZone>,
-    #[sea_orm(column_type = "Text")]
-    pub password: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub password: Option<String>,
     #[sea_orm(column_type = "Text", nullable)]
     pub multi_factor_secret: Option<String>,
     pub multi_factor_recovery_codes: Option<Vec<String>>,
@@ -58,6 +58,8 @@ pub enum Relation {
     Alias,
     #[sea_orm(has_many = "super::blob_pending::Entity")]
     BlobPending,
+    #[sea_orm(has_many = "super::export::Entity")]
+    Export,
     #[sea_orm(has_many = "super::file_revision::Entity")]
     FileRevision,
     #[sea_orm(has_many = "super::message::Entity")]
@@ -68,6 +70,10 @@ pub enum Relation {
     MessageRecipient,
     #[sea_orm(has_many = "super::message_record::Entity")]
     MessageRecord,
+    #[sea_orm(has_many = "super::oauth_client::Entity")]
+    OauthClient,
+    #[sea_orm(has_many = "super::oauth_token::Entity")]
+    OauthToken,
     #[sea_orm(has_many = "super::page_attribution::Entity")]
     PageAttribution,
     #[sea_orm(has_many = "super::page_lock::Entity")]
@@ -76,6 +82,18 @@ pub enum Relation {
     PageRevision,
     #[sea_orm(has_many = "super::session::Entity")]
     Session,
+    #[sea_orm(has_one = "super::storage_quota::Entity")]
+    StorageQuota,
+    #[sea_orm(has_many = "super::user_avatar::Entity")]
+    UserAvatar,
+    #[sea_orm(has_many = "super::user_deletion::Entity")]
+    UserDeletion,
+    #[sea_orm(has_many = "super::user_external_identity::Entity")]
+    UserExternalIdentity,
+    #[sea_orm(has_many = "super::user_name_change::Entity")]
+    UserNameChange,
+    #[sea_orm(has_many = "super::webauthn_credential::Entity")]
+    WebauthnCredential,
 }
 
 impl Related<super::alias::Entity> for Entity {
@@ -90,6 +108,12 @@ impl Related<super::blob_pending::Entity> for Entity {
     }
 }
 
+impl Related<super::export::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Export.def()
+    }
+}
+
 impl Related<super::file_revision::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::FileRevision.def()
@@ -120,6 +144,18 @@ impl Related<super::message_record::Entity> for Entity {
     }
 }
 
+impl Related<super::oauth_client::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OauthClient.def()
+    }
+}
+
+impl Related<super::oauth_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OauthToken.def()
+    }
+}
+
 impl Related<super::page_attribution::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::PageAttribution.def()
@@ -144,4 +180,40 @@ impl Related<super::session::Entity> for Entity {
     }
 }
 
+impl Related<super::storage_quota::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StorageQuota.def()
+    }
+}
+
+impl Related<super::user_avatar::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserAvatar.def()
+    }
+}
+
+impl Related<super::user_deletion::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserDeletion.def()
+    }
+}
+
+impl Related<super::user_external_identity::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserExternalIdentity.def()
+    }
+}
+
+impl Related<super::user_name_change::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserNameChange.def()
+    }
+}
+
+impl Related<super::webauthn_credential::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WebauthnCredential.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
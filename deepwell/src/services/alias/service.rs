@@ -168,7 +168,6 @@ impl AliasService {
     }
 
     #[inline]
-    #[allow(dead_code)] // TEMP
     pub async fn get(
         ctx: &ServiceContext<'_>,
         alias_type: AliasType,
@@ -240,6 +239,32 @@ impl AliasService {
         Ok(())
     }
 
+    /// Releases an old user name, so it can be claimed by someone else.
+    ///
+    /// The alias must have existed for at least the configured cooldown,
+    /// which is measured from when the user stopped using that name.
+    pub async fn release(ctx: &ServiceContext<'_>, slug: &str) -> Result<AliasModel> {
+        let txn = ctx.transaction();
+        let alias = Self::get(ctx, AliasType::User, slug).await?;
+
+        info!(
+            "Releasing user alias ID {} ('{}') for user ID {}",
+            alias.alias_id, alias.slug, alias.target_id,
+        );
+
+        let releasable_at = alias.created_at + ctx.config().user_alias_release_cooldown;
+        if releasable_at > now() {
+            error!(
+                "Cannot release user alias '{}' until {}",
+                alias.slug, releasable_at,
+            );
+            return Err(Error::AliasCooldown);
+        }
+
+        Alias::delete_by_id(alias.alias_id).exec(txn).await?;
+        Ok(alias)
+    }
+
     /// Removes all aliases for this target.
     ///
     /// # Returns
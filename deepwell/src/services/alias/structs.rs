@@ -36,3 +36,8 @@ pub struct CreateAliasOutput {
     pub alias_id: i64,
     pub slug: String,
 }
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReleaseUserAlias {
+    pub slug: String,
+}
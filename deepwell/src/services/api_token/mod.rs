@@ -0,0 +1,41 @@
+/*
+ * services/api_token/mod.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The API token service, for credentials used by bots.
+//!
+//! Bot users do not have passwords. Instead, their owners issue them
+//! API tokens, each of which is limited to a set of scopes and may expire.
+//! Like session tokens, they are long random strings with a fixed prefix,
+//! but only their hash is stored.
+//!
+//! API tokens can be passed anywhere a client's session token is accepted,
+//! provided they have the scope needed for that request.
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use super::super::prelude::*;
+    pub use super::structs::*;
+}
+
+mod service;
+mod structs;
+
+pub use self::service::ApiTokenService;
+pub use self::structs::*;
@@ -0,0 +1,301 @@
+/*
+ * services/api_token/service.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use crate::models::api_token::{self, Entity as ApiToken, Model as ApiTokenModel};
+use crate::models::sea_orm_active_enums::UserType;
+use crate::models::session::Model as SessionModel;
+use crate::services::session::ScopedSession;
+use crate::services::{UserBotOwnerService, UserService};
+use crate::utils::{assert_is_csprng, LAST_USED_PRECISION};
+use rand::distributions::{Alphanumeric, DistString};
+use rand::thread_rng;
+use sea_orm::UpdateResult;
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+
+#[derive(Debug)]
+pub struct ApiTokenService;
+
+impl ApiTokenService {
+    /// Issues a new API token to a bot user.
+    ///
+    /// The creating user must be one of the bot's owners.
+    /// This does not verify the session, the caller is responsible for that.
+    ///
+    /// # Returns
+    /// The new token. Only its hash is stored, so this is the only
+    /// time it is available.
+    pub async fn create(
+        ctx: &ServiceContext<'_>,
+        CreateApiToken {
+            bot_user_id,
+            user_id,
+            name,
+            scopes,
+            expires_at,
+            ..
+        }: CreateApiToken,
+    ) -> Result<CreateApiTokenOutput> {
+        info!(
+            "Creating API token '{name}' for bot ID {bot_user_id} by user ID {user_id}"
+        );
+
+        if name.is_empty() || scopes.is_empty() {
+            error!("API token must have a name and at least one scope");
+            return Err(Error::BadRequest);
+        }
+
+        let now = now();
+        if let Some(expires_at) = expires_at {
+            if expires_at <= now {
+                error!("API token expiry is in the past: {expires_at}");
+                return Err(Error::BadRequest);
+            }
+        }
+
+        UserService::get_with_user_type(ctx, Reference::Id(bot_user_id), UserType::Bot)
+            .await?;
+
+        Self::check_owner(ctx, bot_user_id, user_id).await?;
+
+        let txn = ctx.transaction();
+        let token = Self::new_token(ctx.config());
+        let model = api_token::ActiveModel {
+            bot_user_id: Set(bot_user_id),
+            created_by: Set(user_id),
+            created_at: Set(now),
+            expires_at: Set(expires_at),
+            name: Set(name),
+            token_hash: Set(Self::hash_token(&token)),
+            scopes: Set(scopes.into_iter().map(String::from).collect()),
+            ..Default::default()
+        };
+
+        let ApiTokenModel { api_token_id, .. } = model.insert(txn).await?;
+        Ok(CreateApiTokenOutput {
+            api_token_id,
+            token,
+        })
+    }
+
+    /// Securely generates a new API token.
+    ///
+    /// Example generated token: `wjbot:Qe1hbw0TaTH7CnAy1SlKXXdjq0KhGO6hZzqs8s6dnTPLmBuYR0ejR6PVuszrTKiS`.
+    fn new_token(config: &Config) -> String {
+        debug!("Generating a new API token");
+        let mut rng = thread_rng();
+        assert_is_csprng(&rng);
+
+        let mut token = Alphanumeric.sample_string(&mut rng, config.api_token_length);
+        token.insert_str(0, &config.api_token_prefix);
+
+        token
+    }
+
+    fn hash_token(token: &str) -> Vec<u8> {
+        Sha256::digest(token.as_bytes()).to_vec()
+    }
+
+    /// Determines if this token is an API token, rather than a session token.
+    ///
+    /// This only checks the prefix, not whether the token is valid.
+    #[inline]
+    pub fn is_api_token(config: &Config, token: &str) -> bool {
+        token.starts_with(&config.api_token_prefix)
+    }
+
+    /// Gets all API tokens issued to a bot user, including revoked or expired ones.
+    pub async fn get_all(
+        ctx: &ServiceContext<'_>,
+        bot_user_id: i64,
+    ) -> Result<Vec<ApiTokenModel>> {
+        info!("Getting all API tokens for bot ID {bot_user_id}");
+
+        let txn = ctx.transaction();
+        let tokens = ApiToken::find()
+            .filter(api_token::Column::BotUserId.eq(bot_user_id))
+            .order_by_asc(api_token::Column::ApiTokenId)
+            .all(txn)
+            .await?;
+
+        Ok(tokens)
+    }
+
+    /// Revokes an API token, so it can no longer be used.
+    ///
+    /// The revoking user must be one of the bot's owners.
+    /// This does not verify the session, the caller is responsible for that.
+    pub async fn revoke(
+        ctx: &ServiceContext<'_>,
+        api_token_id: i64,
+        user_id: i64,
+    ) -> Result<ApiTokenModel> {
+        info!("Revoking API token ID {api_token_id} by user ID {user_id}");
+
+        let txn = ctx.transaction();
+        let token = ApiToken::find_by_id(api_token_id)
+            .filter(api_token::Column::RevokedAt.is_null())
+            .one(txn)
+            .await?
+            .ok_or(Error::ApiTokenNotFound)?;
+
+        Self::check_owner(ctx, token.bot_user_id, user_id).await?;
+
+        let model = api_token::ActiveModel {
+            api_token_id: Set(api_token_id),
+            revoked_at: Set(Some(now())),
+            revoked_by: Set(Some(user_id)),
+            ..Default::default()
+        };
+
+        let token = model.update(txn).await?;
+        Ok(token)
+    }
+
+    /// Revokes every API token a user has created.
+    ///
+    /// # Returns
+    /// The number of tokens which were revoked.
+    pub async fn revoke_created_by(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+    ) -> Result<u64> {
+        info!("Revoking all API tokens created by user ID {user_id}");
+
+        let txn = ctx.transaction();
+        let UpdateResult { rows_affected } = ApiToken::update_many()
+            .set(api_token::ActiveModel {
+                revoked_at: Set(Some(now())),
+                revoked_by: Set(Some(user_id)),
+                ..Default::default()
+            })
+            .filter(
+                Condition::all()
+                    .add(api_token::Column::CreatedBy.eq(user_id))
+                    .add(api_token::Column::RevokedAt.is_null()),
+            )
+            .exec(txn)
+            .await?;
+
+        debug!("{rows_affected} API tokens were revoked");
+        Ok(rows_affected)
+    }
+
+    async fn check_owner(
+        ctx: &ServiceContext<'_>,
+        bot_user_id: i64,
+        user_id: i64,
+    ) -> Result<()> {
+        if UserBotOwnerService::is_owner(ctx, bot_user_id, user_id).await? {
+            Ok(())
+        } else {
+            error!("User ID {user_id} is not an owner of bot ID {bot_user_id}");
+            Err(Error::NotBotOwner)
+        }
+    }
+
+    /// Looks up an active API token, and marks it as used.
+    ///
+    /// Like sessions, the last use time is only updated once it is stale,
+    /// so that every request does not write to the token.
+    ///
+    /// Yields `None` if the token does not exist, was revoked, or is expired.
+    pub async fn authenticate(
+        ctx: &ServiceContext<'_>,
+        token: &str,
+    ) -> Result<Option<ApiTokenModel>> {
+        debug!("Looking up API token");
+
+        let txn = ctx.transaction();
+        let now = now();
+        let token = ApiToken::find()
+            .filter(
+                Condition::all()
+                    .add(api_token::Column::TokenHash.eq(Self::hash_token(token)))
+                    .add(api_token::Column::RevokedAt.is_null())
+                    .add(
+                        Condition::any()
+                            .add(api_token::Column::ExpiresAt.is_null())
+                            .add(api_token::Column::ExpiresAt.gt(now)),
+                    ),
+            )
+            .one(txn)
+            .await?;
+
+        let token = match token {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+
+        if let Some(last_used_at) = token.last_used_at {
+            if now - last_used_at < LAST_USED_PRECISION {
+                return Ok(Some(token));
+            }
+        }
+
+        let model = api_token::ActiveModel {
+            api_token_id: Set(token.api_token_id),
+            last_used_at: Set(Some(now)),
+            ..Default::default()
+        };
+
+        let token = model.update(txn).await?;
+        Ok(Some(token))
+    }
+
+    /// Produces a session for a request made with an API token.
+    ///
+    /// This lets API tokens be used wherever session tokens are, but unlike
+    /// sessions, nothing is stored and it is not bound to a network or device.
+    /// See `SessionService::get_scoped()`.
+    pub async fn get_session(
+        ctx: &ServiceContext<'_>,
+        token: &str,
+        ip_address: IpAddr,
+        user_agent: &str,
+    ) -> Result<Option<ScopedSession>> {
+        let model = match Self::authenticate(ctx, token).await? {
+            Some(model) => model,
+            None => return Ok(None),
+        };
+
+        let scopes = ApiScope::parse_all(&model.scopes);
+        let now = now();
+        let session_expiry = now + ctx.config().normal_session_duration;
+        let expires_at = match model.expires_at {
+            Some(expires_at) => expires_at.min(session_expiry),
+            None => session_expiry,
+        };
+
+        let session = SessionModel {
+            session_token: str!(token),
+            user_id: model.bot_user_id,
+            created_at: now,
+            expires_at,
+            last_used_at: now,
+            ip_address: ip_address.to_string(),
+            user_agent: str!(user_agent),
+            restricted: false,
+        };
+
+        Ok(Some(ScopedSession { session, scopes }))
+    }
+}
@@ -0,0 +1,237 @@
+/*
+ * services/api_token/structs.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::models::api_token::Model as ApiTokenModel;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use strum_macros::{EnumString, IntoStaticStr};
+use time::OffsetDateTime;
+
+/// The kinds of actions an API token can be allowed to perform.
+#[derive(EnumString, IntoStaticStr, Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ApiAction {
+    ReadPages,
+    EditPages,
+    Vote,
+    UploadFiles,
+    EditProfile,
+    SendMessages,
+}
+
+impl ApiAction {
+    #[inline]
+    pub fn name(self) -> &'static str {
+        self.into()
+    }
+}
+
+/// A permission granted to an API token.
+///
+/// This is an action, optionally limited to one site.
+/// As a string, it is written as `action` or `action@site_id`,
+/// for instance `edit-pages@42`.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct ApiScope {
+    pub action: ApiAction,
+    pub site_id: Option<i64>,
+}
+
+impl ApiScope {
+    /// Determines if this scope permits what another scope requires.
+    ///
+    /// A scope without a site covers that action on all sites.
+    pub fn grants(self, required: ApiScope) -> bool {
+        self.action == required.action
+            && (self.site_id.is_none() || self.site_id == required.site_id)
+    }
+
+    /// Determines if any of these scopes permit what is required.
+    pub fn any_grants(scopes: &[ApiScope], required: ApiScope) -> bool {
+        scopes.iter().any(|scope| scope.grants(required))
+    }
+
+    /// Parses the scopes stored for a token, skipping any which are invalid.
+    pub fn parse_all(scopes: &[String]) -> Vec<ApiScope> {
+        let mut parsed = Vec::with_capacity(scopes.len());
+        for scope in scopes {
+            match scope.parse() {
+                Ok(scope) => parsed.push(scope),
+                Err(error) => warn!("Skipping invalid stored scope: {error}"),
+            }
+        }
+
+        parsed
+    }
+}
+
+impl Display for ApiScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.site_id {
+            None => write!(f, "{}", self.action.name()),
+            Some(site_id) => write!(f, "{}@{}", self.action.name(), site_id),
+        }
+    }
+}
+
+impl FromStr for ApiScope {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (action, site_id) = match value.split_once('@') {
+            None => (value, None),
+            Some((action, site_id)) => match site_id.parse() {
+                Ok(site_id) => (action, Some(site_id)),
+                Err(_) => return Err(format!("Invalid site ID in API scope: {value}")),
+            },
+        };
+
+        match action.parse() {
+            Ok(action) => Ok(ApiScope { action, site_id }),
+            Err(_) => Err(format!("Invalid action in API scope: {value}")),
+        }
+    }
+}
+
+impl TryFrom<String> for ApiScope {
+    type Error = String;
+
+    #[inline]
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ApiScope> for String {
+    #[inline]
+    fn from(scope: ApiScope) -> String {
+        scope.to_string()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreateApiToken {
+    pub bot_user_id: i64,
+    pub user_id: i64,
+    pub session_token: String,
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CreateApiTokenOutput {
+    pub api_token_id: i64,
+
+    /// The token itself, which is only available now.
+    pub token: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ListApiTokens {
+    pub bot_user_id: i64,
+    pub user_id: i64,
+    pub session_token: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RevokeApiToken {
+    pub api_token_id: i64,
+    pub user_id: i64,
+    pub session_token: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ApiTokenOutput {
+    pub api_token_id: i64,
+    pub bot_user_id: i64,
+    pub created_by: i64,
+    pub name: String,
+    pub scopes: Vec<String>,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_used_at: Option<OffsetDateTime>,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub revoked_at: Option<OffsetDateTime>,
+
+    pub revoked_by: Option<i64>,
+}
+
+impl From<ApiTokenModel> for ApiTokenOutput {
+    fn from(model: ApiTokenModel) -> Self {
+        ApiTokenOutput {
+            api_token_id: model.api_token_id,
+            bot_user_id: model.bot_user_id,
+            created_by: model.created_by,
+            name: model.name,
+            scopes: model.scopes,
+            created_at: model.created_at,
+            expires_at: model.expires_at,
+            last_used_at: model.last_used_at,
+            revoked_at: model.revoked_at,
+            revoked_by: model.revoked_by,
+        }
+    }
+}
+
+#[test]
+fn scopes() {
+    macro_rules! check {
+        ($input:expr, $action:expr, $site_id:expr $(,)?) => {{
+            let scope: ApiScope = $input.parse().expect("Unable to parse API scope");
+            assert_eq!(
+                scope.action, $action,
+                "Actual action doesn't match expected"
+            );
+            assert_eq!(
+                scope.site_id, $site_id,
+                "Actual site ID doesn't match expected"
+            );
+            assert_eq!(scope.to_string(), $input, "Scope doesn't round-trip");
+        }};
+    }
+
+    check!("read-pages", ApiAction::ReadPages, None);
+    check!("edit-pages@42", ApiAction::EditPages, Some(42));
+    check!("vote@1", ApiAction::Vote, Some(1));
+    check!("send-messages", ApiAction::SendMessages, None);
+
+    assert!("".parse::<ApiScope>().is_err());
+    assert!("delete-everything".parse::<ApiScope>().is_err());
+    assert!("edit-pages@".parse::<ApiScope>().is_err());
+    assert!("edit-pages@site".parse::<ApiScope>().is_err());
+
+    let scope = |value: &str| value.parse::<ApiScope>().unwrap();
+    assert!(scope("edit-pages").grants(scope("edit-pages@42")));
+    assert!(scope("edit-pages@42").grants(scope("edit-pages@42")));
+    assert!(!scope("edit-pages@42").grants(scope("edit-pages@7")));
+    assert!(!scope("edit-pages@42").grants(scope("edit-pages")));
+    assert!(!scope("read-pages").grants(scope("edit-pages@42")));
+}
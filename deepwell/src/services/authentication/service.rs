@@ -19,8 +19,19 @@
  */
 
 use super::prelude::*;
+use crate::constants::SYSTEM_USER_ID;
 use crate::models::user::{self, Entity as User, Model as UserModel};
-use crate::services::{MfaService, PasswordService, SessionService};
+use crate::services::job::Job;
+use crate::services::message::CreateMessageDraft;
+use crate::services::oidc::{FinishOidcLogin, OidcAuthenticated};
+use crate::services::rate_limit::{RateLimitAction, RateLimitScope};
+use crate::services::webauthn::WebauthnAssertion;
+use crate::services::{
+    JobService, MessageService, MfaService, OidcService, PasswordService,
+    RateLimitService, SessionService, UserService, WebauthnService,
+};
+use crate::utils::validate_locale;
+use fluent::{FluentArgs, FluentValue};
 
 #[derive(Debug)]
 pub struct AuthenticationService;
@@ -36,19 +47,105 @@ impl AuthenticationService {
         }: AuthenticateUser,
     ) -> Result<AuthenticateUserOutput> {
         let auth = Self::get_user_auth(ctx, &name_or_email).await?;
-        PasswordService::verify(ctx, &password, &auth.password_hash).await?;
 
-        // User not found, return authentication failure
-        if !auth.valid {
-            return Err(Error::InvalidAuthentication);
+        // Failures are counted per account, or against the name given if
+        // there is no such user, so that they can be locked out either way.
+        let account = if auth.valid {
+            RateLimitScope::User(auth.user_id)
+        } else {
+            RateLimitScope::Account(&name_or_email)
+        };
+
+        Self::check_locked(ctx, account).await?;
+
+        let result = PasswordService::verify(ctx, &password, &auth.password_hash)
+            .await
+            .and_then(|()| {
+                // User not found, return authentication failure
+                if auth.valid {
+                    Ok(())
+                } else {
+                    Err(Error::InvalidAuthentication)
+                }
+            });
+
+        match result {
+            Ok(()) => {
+                RateLimitService::clear(ctx, RateLimitAction::LoginFailure, account)
+                    .await?;
+            }
+            Err(Error::InvalidAuthentication) => {
+                Self::record_failure(ctx, account).await?;
+                return Err(Error::InvalidAuthentication);
+            }
+            Err(error) => return Err(error),
         }
 
+        let needs_mfa =
+            Self::needs_mfa(ctx, auth.user_id, auth.multi_factor_secret.is_some())
+                .await?;
+
         Ok(AuthenticateUserOutput {
-            needs_mfa: auth.multi_factor_secret.is_some(),
+            needs_mfa,
             user_id: auth.user_id,
         })
     }
 
+    /// Verifies a passwordless login with a WebAuthn credential.
+    ///
+    /// This is subject to the same lockout as password logins, counted
+    /// against the user the credential claims to belong to.
+    ///
+    /// # Returns
+    /// The ID of the authenticated user.
+    pub async fn auth_webauthn(
+        ctx: &ServiceContext<'_>,
+        assertion: &WebauthnAssertion,
+    ) -> Result<i64> {
+        let account = RateLimitScope::User(WebauthnService::identify(assertion)?);
+        Self::check_locked(ctx, account).await?;
+
+        match WebauthnService::authenticate(ctx, None, assertion).await {
+            Ok(user_id) => {
+                RateLimitService::clear(ctx, RateLimitAction::LoginFailure, account)
+                    .await?;
+                Ok(user_id)
+            }
+            Err(Error::InvalidAuthentication) => {
+                Self::record_failure(ctx, account).await?;
+                Err(Error::InvalidAuthentication)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Finishes a login with an external identity provider.
+    ///
+    /// The provider is responsible for verifying the user, but the login is
+    /// still refused if the account was locked after failed attempts.
+    pub async fn auth_oidc(
+        ctx: &ServiceContext<'_>,
+        input: FinishOidcLogin,
+    ) -> Result<OidcAuthenticated> {
+        let output = OidcService::authenticate(ctx, input).await?;
+        if let OidcAuthenticated::Login { user_id, .. } = output {
+            Self::check_locked(ctx, RateLimitScope::User(user_id)).await?;
+        }
+
+        Ok(output)
+    }
+
+    /// Determines if a user must verify a second factor to finish logging in.
+    ///
+    /// Either TOTP or WebAuthn credentials can be used as a second factor.
+    pub async fn needs_mfa(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+        has_totp: bool,
+    ) -> Result<bool> {
+        Ok(has_totp || WebauthnService::has_credentials(ctx, user_id).await?)
+    }
+
     /// Verifies the TOTP code for a user, after they have logged in.
     ///
     /// # Returns
@@ -57,7 +154,7 @@ impl AuthenticationService {
         ctx: &ServiceContext<'_>,
         MultiFactorAuthenticateUser {
             session_token,
-            totp_or_code,
+            factor,
         }: MultiFactorAuthenticateUser<'_>,
     ) -> Result<UserModel> {
         // Get associated user model from the session
@@ -66,21 +163,122 @@ impl AuthenticationService {
         // in the middle of logging in still
         let user = SessionService::get_user(ctx, session_token, true).await?;
 
+        RateLimitService::check(
+            ctx,
+            RateLimitAction::MfaVerify,
+            RateLimitScope::User(user.user_id),
+            ctx.config().rate_limit_mfa_verify,
+        )
+        .await?;
+
         // Process input, verifying depending on type
-        match totp_or_code.parse() {
-            // If the value is a positive integer, treat it as a TOTP
-            Ok(totp) => MfaService::verify(ctx, &user, totp).await?,
-
-            // Otherwise treat it as a recovery code string
-            //
-            // We don't need to validate it for length because
-            // we want consistent time checks on recovery codes anyways.
-            Err(_) => MfaService::verify_recovery(ctx, &user, totp_or_code).await?,
+        match factor {
+            SecondFactor::TotpOrCode(totp_or_code) => match totp_or_code.parse() {
+                // If the value is a positive integer, treat it as a TOTP
+                Ok(totp) => MfaService::verify(ctx, &user, totp).await?,
+
+                // Otherwise treat it as a recovery code string
+                //
+                // We don't need to validate it for length because
+                // we want consistent time checks on recovery codes anyways.
+                Err(_) => MfaService::verify_recovery(ctx, &user, totp_or_code).await?,
+            },
+            SecondFactor::Webauthn(assertion) => {
+                WebauthnService::authenticate(ctx, Some(user.user_id), assertion).await?;
+            }
         }
 
         Ok(user)
     }
 
+    /// Refuses to log into an account which has been locked.
+    async fn check_locked(
+        ctx: &ServiceContext<'_>,
+        account: RateLimitScope<'_>,
+    ) -> Result<()> {
+        if RateLimitService::is_locked(ctx, account).await? {
+            warn!("Refusing login attempt to locked account ({account})");
+            PasswordService::failure_sleep(ctx.config()).await;
+            return Err(Error::AccountLocked);
+        }
+
+        Ok(())
+    }
+
+    /// Records a failed login, locking the account if there have been too many.
+    ///
+    /// Existing users are notified of the lockout through a job, since
+    /// the transaction for this request will be rolled back.
+    async fn record_failure(
+        ctx: &ServiceContext<'_>,
+        account: RateLimitScope<'_>,
+    ) -> Result<()> {
+        let config = ctx.config();
+        let permitted = RateLimitService::attempt(
+            ctx,
+            RateLimitAction::LoginFailure,
+            account,
+            config.lockout_failures,
+        )
+        .await?;
+
+        if !permitted {
+            RateLimitService::lock(ctx, account, config.lockout_duration).await?;
+            RateLimitService::clear(ctx, RateLimitAction::LoginFailure, account).await?;
+
+            if let RateLimitScope::User(user_id) = account {
+                JobService::queue_job(ctx, &Job::NotifyAccountLocked { user_id }, None)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends a message to a user informing them their account was locked.
+    pub async fn notify_locked(ctx: &ServiceContext<'_>, user_id: i64) -> Result<()> {
+        let config = ctx.config();
+        let user = UserService::get(ctx, Reference::Id(user_id)).await?;
+
+        // Use the user's preferred locale, falling back to English
+        let locale = user.locales.first().map(String::as_str).unwrap_or("en");
+        let locales = [validate_locale(locale)?];
+
+        let mut args = FluentArgs::new();
+        args.set(
+            "minutes",
+            FluentValue::from(config.lockout_duration.as_secs() / 60),
+        );
+
+        let localization = ctx.localization();
+        let subject = localization.translate(
+            &locales,
+            "wiki-auth-account-locked.subject",
+            &args,
+        )?;
+        let wikitext =
+            localization.translate(&locales, "wiki-auth-account-locked.body", &args)?;
+
+        let draft = MessageService::create_draft(
+            ctx,
+            CreateMessageDraft {
+                user_id: SYSTEM_USER_ID,
+                recipients: vec![user_id],
+                carbon_copy: vec![],
+                blind_carbon_copy: vec![],
+                locale: str!(locale),
+                subject: subject.into_owned(),
+                wikitext: wikitext.into_owned(),
+                reply_to: None,
+                forwarded_from: None,
+            },
+        )
+        .await?;
+
+        MessageService::send(ctx, &draft.external_id).await?;
+        Ok(())
+    }
+
     /// Gets user information from the database, or return a dummy.
     ///
     /// To avoid timing attacks, all aspects of authentication (finding the user,
@@ -19,6 +19,7 @@
  */
 
 use crate::models::user::Model as UserModel;
+use crate::services::webauthn::WebauthnAssertion;
 use std::net::IpAddr;
 
 #[derive(Deserialize, Debug, Clone)]
@@ -48,18 +49,38 @@ pub struct LoginUserOutput {
     pub needs_mfa: bool,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct LoginUserWebauthn {
+    pub assertion: WebauthnAssertion,
+    pub ip_address: IpAddr,
+    pub user_agent: String,
+}
+
+/// The second factor used to complete a login.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum SecondFactor {
+    /// Either a TOTP, or a recovery code.
+    TotpOrCode(String),
+
+    /// An assertion from a registered WebAuthn credential.
+    Webauthn(WebauthnAssertion),
+}
+
 #[derive(Debug, Clone)]
 pub struct MultiFactorAuthenticateUser<'a> {
     pub session_token: &'a str,
-    pub totp_or_code: &'a str,
+    pub factor: &'a SecondFactor,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct LoginUserMfa {
     pub session_token: String,
-    pub totp_or_code: String,
     pub ip_address: IpAddr,
     pub user_agent: String,
+
+    #[serde(flatten)]
+    pub factor: SecondFactor,
 }
 
 /// Password hash to compute against when a user does not exist.
@@ -84,7 +105,9 @@ impl UserAuthInfo {
     pub fn valid(user: UserModel) -> Self {
         UserAuthInfo {
             user_id: user.user_id,
-            password_hash: user.password,
+            // Users without a password are checked against a hash which
+            // cannot match, so that the failure looks like any other.
+            password_hash: user.password.unwrap_or_else(|| str!(INVALID_PASSWORD_HASH)),
             multi_factor_secret: user.multi_factor_secret,
             valid: true,
         }
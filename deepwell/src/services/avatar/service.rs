@@ -0,0 +1,285 @@
+/*
+ * services/avatar/service.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use crate::hash::BlobHash;
+use crate::models::user;
+use crate::models::user_avatar::{self, Entity as UserAvatar, Model as UserAvatarModel};
+use crate::services::BlobService;
+use crate::utils::run_blocking;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+
+#[derive(Debug)]
+pub struct AvatarService;
+
+impl AvatarService {
+    /// Replaces the user's avatar with the given uploaded image.
+    ///
+    /// The image is re-encoded at each configured size and format, with
+    /// each copy uploaded as its own blob, and the largest PNG copy set
+    /// as the user's primary avatar. If `upload` is `None`, then the
+    /// user's avatar is removed.
+    ///
+    /// Neither the uploaded image nor the copies of the previous avatar
+    /// are kept, unless something else refers to them.
+    ///
+    /// # Returns
+    /// The hash of the user's new primary avatar.
+    pub async fn set(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+        upload: Option<BlobHash>,
+    ) -> Result<Option<BlobHash>> {
+        let txn = ctx.transaction();
+        let previous = UserAvatar::find()
+            .filter(user_avatar::Column::UserId.eq(user_id))
+            .all(txn)
+            .await?;
+
+        UserAvatar::delete_many()
+            .filter(user_avatar::Column::UserId.eq(user_id))
+            .exec(txn)
+            .await?;
+
+        let primary_hash = match upload {
+            None => {
+                info!("Removing avatar for user ID {user_id}");
+                None
+            }
+            Some(upload_hash) => {
+                let result = Self::store(ctx, user_id, &upload_hash).await;
+                BlobService::hard_delete_unused(ctx, &upload_hash).await?;
+                result?
+            }
+        };
+
+        let model = user::ActiveModel {
+            user_id: Set(user_id),
+            avatar_s3_hash: Set(primary_hash.map(|hash| hash.to_vec())),
+            ..Default::default()
+        };
+        model.update(txn).await?;
+
+        for UserAvatarModel { s3_hash, .. } in previous {
+            BlobService::hard_delete_unused(ctx, &s3_hash).await?;
+        }
+
+        Ok(primary_hash)
+    }
+
+    /// Stores copies of the uploaded image for each size and format.
+    ///
+    /// # Returns
+    /// The hash of the largest PNG copy.
+    async fn store(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+        upload_hash: &BlobHash,
+    ) -> Result<Option<BlobHash>> {
+        let txn = ctx.transaction();
+        let data = BlobService::get(ctx, upload_hash).await?;
+        info!(
+            "Processing avatar for user ID {user_id} ({} bytes)",
+            data.len()
+        );
+
+        let config = ctx.config();
+        let mime = ctx.mime().get_mime_type(data.clone()).await?;
+        let format = Self::image_format(&mime)?;
+        let maximum_dimension = config.maximum_avatar_dimension;
+
+        let mut sizes = config.avatar_sizes.clone();
+        sizes.sort_unstable();
+
+        // Decoding and resizing are CPU-heavy, so keep them off the async runtime
+        let copies = run_blocking(move || {
+            let image = Self::decode(&data, format, maximum_dimension)?;
+            let mut copies = Vec::with_capacity(sizes.len() * AvatarFormat::ALL.len());
+            for size in sizes {
+                for format in AvatarFormat::ALL {
+                    let bytes = Self::encode(&image, size, format)?;
+                    copies.push((size, format, bytes));
+                }
+            }
+
+            Ok::<_, Error>(copies)
+        })
+        .await?;
+
+        let mut primary_hash = None;
+        for (size, format, bytes) in copies {
+            let output = BlobService::direct_upload(ctx, bytes).await?;
+            let model = user_avatar::ActiveModel {
+                user_id: Set(user_id),
+                // Sizes are checked to fit in an i16 when the config is loaded
+                size: Set(size as i16),
+                format: Set(str!(format.name())),
+                s3_hash: Set(output.s3_hash.to_vec()),
+            };
+            model.insert(txn).await?;
+
+            // Sizes are ascending, so this ends up as the largest
+            if format == AvatarFormat::Png {
+                primary_hash = Some(output.s3_hash);
+            }
+        }
+
+        Ok(primary_hash)
+    }
+
+    /// Gets all the stored copies of the user's avatar, with download URLs.
+    pub async fn get_all(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+    ) -> Result<Vec<AvatarOutput>> {
+        let txn = ctx.transaction();
+        let avatars = UserAvatar::find()
+            .filter(user_avatar::Column::UserId.eq(user_id))
+            .order_by_asc(user_avatar::Column::Size)
+            .order_by_asc(user_avatar::Column::Format)
+            .all(txn)
+            .await?;
+
+        let mut outputs = Vec::with_capacity(avatars.len());
+        for UserAvatarModel {
+            size,
+            format,
+            s3_hash,
+            ..
+        } in avatars
+        {
+            let mime = format!("image/{format}");
+            let url = BlobService::get_download_url(ctx, &s3_hash, &mime, None).await?;
+            outputs.push(AvatarOutput { size, format, url });
+        }
+
+        Ok(outputs)
+    }
+
+    /// Determines the image format from the MIME type, if it's allowed as an avatar.
+    fn image_format(mime: &str) -> Result<ImageFormat> {
+        // Strip parameters, e.g. "image/png; charset=binary"
+        let essence = mime.split(';').next().unwrap_or(mime).trim();
+
+        match essence {
+            "image/png" | "image/jpeg" | "image/gif" | "image/webp" => {
+                Ok(ImageFormat::from_mime_type(essence)
+                    .expect("Allowed avatar MIME type has no image format"))
+            }
+            _ => {
+                error!("Avatar has disallowed MIME type '{mime}'");
+                Err(Error::AvatarInvalid("unsupported image format"))
+            }
+        }
+    }
+
+    /// Decodes the avatar, rejecting it if it's too large.
+    ///
+    /// For animated images, only the first frame is decoded.
+    fn decode(
+        data: &[u8],
+        format: ImageFormat,
+        maximum_dimension: u32,
+    ) -> Result<DynamicImage> {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(maximum_dimension);
+        limits.max_image_height = Some(maximum_dimension);
+
+        let mut reader = ImageReader::with_format(Cursor::new(data), format);
+        reader.limits(limits);
+
+        match reader.decode() {
+            Ok(image) => Ok(image),
+            Err(image::ImageError::Limits(error)) => {
+                error!("Avatar exceeds image limits: {error}");
+                Err(Error::AvatarInvalid("image dimensions too large"))
+            }
+            Err(error) => {
+                error!("Unable to decode avatar: {error}");
+                Err(Error::AvatarInvalid("unable to decode image"))
+            }
+        }
+    }
+
+    /// Crops the image to a square of the given size and encodes it.
+    fn encode(image: &DynamicImage, size: u16, format: AvatarFormat) -> Result<Vec<u8>> {
+        let size = u32::from(size);
+        let resized = image.resize_to_fill(size, size, FilterType::Lanczos3);
+
+        // The WebP encoder only supports 8-bit RGB(A)
+        let resized = DynamicImage::ImageRgba8(resized.into_rgba8());
+
+        let mut buffer = Cursor::new(Vec::new());
+        if let Err(error) = resized.write_to(&mut buffer, format.image_format()) {
+            error!("Unable to encode avatar as {}: {error}", format.name());
+            return Err(Error::AvatarInvalid("unable to encode image"));
+        }
+
+        Ok(buffer.into_inner())
+    }
+}
+
+#[test]
+fn avatar_image_format() {
+    macro_rules! check {
+        ($mime:expr, $expected:expr $(,)?) => {{
+            let actual = AvatarService::image_format($mime).ok();
+            assert_eq!(
+                actual, $expected,
+                "Actual image format doesn't match expected"
+            );
+        }};
+    }
+
+    check!("image/png; charset=binary", Some(ImageFormat::Png));
+    check!("image/jpeg; charset=binary", Some(ImageFormat::Jpeg));
+    check!("image/gif", Some(ImageFormat::Gif));
+    check!("image/webp; charset=binary", Some(ImageFormat::WebP));
+    check!("image/svg+xml; charset=us-ascii", None);
+    check!("text/plain; charset=us-ascii", None);
+}
+
+#[test]
+fn avatar_resize() {
+    const PNG: &[u8] = b"\x89\x50\x4e\x47\x0d\x0a\x1a\x0a\x00\x00\x00\x0d\x49\x48\x44\x52\x00\x00\x00\x01\x00\x00\x00\x01\x08\x06\x00\x00\x00\x1f\x15\xc4\x89\x00\x00\x00\x04\x73\x42\x49\x54\x08\x08\x08\x08\x7c\x08\x64\x88\x00\x00\x00\x0b\x49\x44\x41\x54\x08\x99\x63\xf8\x0f\x04\x00\x09\xfb\x03\xfd\xe3\x55\xf2\x9c\x00\x00\x00\x00\x49\x45\x4e\x44\xae\x42\x60\x82";
+
+    let image =
+        AvatarService::decode(PNG, ImageFormat::Png, 16).expect("Unable to decode");
+
+    for format in AvatarFormat::ALL {
+        let bytes = AvatarService::encode(&image, 64, format).expect("Unable to encode");
+        let output = image::load_from_memory_with_format(&bytes, format.image_format())
+            .expect("Unable to decode encoded avatar");
+
+        assert_eq!(output.width(), 64);
+        assert_eq!(output.height(), 64);
+    }
+
+    // Exceeds maximum dimension
+    let mut bytes = Vec::new();
+    DynamicImage::new_rgba8(32, 8)
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .expect("Unable to encode");
+
+    assert!(AvatarService::decode(&bytes, ImageFormat::Png, 16).is_err());
+    assert!(AvatarService::decode(&bytes, ImageFormat::Png, 32).is_ok());
+}
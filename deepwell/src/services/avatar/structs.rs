@@ -0,0 +1,55 @@
+/*
+ * services/avatar/structs.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use image::ImageFormat;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AvatarOutput {
+    pub size: i16,
+    pub format: String,
+    pub url: String,
+}
+
+/// The image formats that avatars are stored in.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AvatarFormat {
+    Png,
+    Webp,
+}
+
+impl AvatarFormat {
+    pub const ALL: [AvatarFormat; 2] = [AvatarFormat::Png, AvatarFormat::Webp];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AvatarFormat::Png => "png",
+            AvatarFormat::Webp => "webp",
+        }
+    }
+
+    #[inline]
+    pub fn image_format(self) -> ImageFormat {
+        match self {
+            AvatarFormat::Png => ImageFormat::Png,
+            AvatarFormat::Webp => ImageFormat::WebP,
+        }
+    }
+}
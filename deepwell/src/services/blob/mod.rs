@@ -18,29 +18,39 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-//! The blob service, for interfacing with content-addressable S3 objects.
+//! The blob service, for interfacing with content-addressable stored objects.
 //!
-//! This is essentially just a wrapper for how DEEPWELL interacts with S3.
+//! This is essentially just a wrapper for how DEEPWELL interacts with its
+//! storage backend, either S3 or the local filesystem.
 //! Method implementations should instead work with the relevant concept
 //! service instead, for instance the `FileService`.
 
 #[allow(unused_imports)]
 mod prelude {
     pub use super::super::prelude::*;
+    pub use super::perceptual::perceptual_hash;
     pub use super::service::{
         EMPTY_BLOB_HASH, EMPTY_BLOB_MIME, EMPTY_BLOB_TIMESTAMP, PRESIGN_DIRECTORY,
+        QUARANTINE_DIRECTORY,
     };
+    pub use super::storage::{BlobStorage, ResponseHeaders, StorageMetadata};
     pub use super::structs::*;
     pub use crate::hash::{blob_hash_to_hex, sha512_hash, BlobHash};
+    pub use async_trait::async_trait;
 }
 
 mod mime;
+mod perceptual;
+mod scanner;
 mod service;
+mod storage;
 mod structs;
 
 pub use self::mime::MimeAnalyzer;
+pub use self::scanner::{BlobScanner, ScanOutcome};
 pub use self::service::{
     BlobService, EMPTY_BLOB_HASH, EMPTY_BLOB_MIME, EMPTY_BLOB_TIMESTAMP,
-    PRESIGN_DIRECTORY,
+    PRESIGN_DIRECTORY, QUARANTINE_DIRECTORY,
 };
+pub use self::storage::*;
 pub use self::structs::*;
@@ -0,0 +1,96 @@
+/*
+ * services/blob/perceptual.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Perceptual hashing of images, for matching near-duplicates.
+//!
+//! This uses a difference hash ("dHash"), where the image is shrunk to a
+//! 9x8 grayscale thumbnail, and each bit records whether a pixel is brighter
+//! than its neighbour to the right. Because this only depends on the overall
+//! structure of the image, re-encoding, resizing, or slightly adjusting an
+//! image produces a hash within a small Hamming distance of the original.
+
+use crate::utils::{decode_image, image_format};
+use image::imageops::FilterType;
+
+/// Computes the perceptual hash of a blob, if it is a supported image.
+///
+/// The hash is returned as an `i64` so it can be stored in a `BIGINT` column.
+pub fn perceptual_hash(data: &[u8], mime: &str) -> Option<i64> {
+    let image = decode_image(data, image_format(mime)?)?;
+
+    let pixels = image.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = pixels.get_pixel(x, y).0[0];
+            let right = pixels.get_pixel(x + 1, y).0[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+
+    Some(i64::from_ne_bytes(hash.to_ne_bytes()))
+}
+
+#[test]
+fn perceptual_hashes() {
+    use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+    use std::io::Cursor;
+
+    fn hamming_distance(first: i64, second: i64) -> u32 {
+        (first ^ second).count_ones()
+    }
+
+    fn encode(image: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), format)
+            .expect("Unable to encode");
+        bytes
+    }
+
+    // Diagonal gradient, and the same with a band across the middle
+    let gradient = RgbImage::from_fn(64, 64, |x, y| {
+        let value = u8::try_from((x + y) * 2).unwrap();
+        Rgb([value, value, value])
+    });
+    let banded = RgbImage::from_fn(64, 64, |x, y| match y {
+        24..=40 => Rgb([255, 255, 255]),
+        _ => *gradient.get_pixel(63 - x, y),
+    });
+    let gradient = DynamicImage::ImageRgb8(gradient);
+    let banded = DynamicImage::ImageRgb8(banded);
+    let resized = gradient.resize_exact(200, 150, FilterType::Lanczos3);
+
+    let original = perceptual_hash(
+        &encode(&gradient, ImageFormat::Png),
+        "image/png; charset=binary",
+    )
+    .expect("No hash for PNG");
+    let reencoded = perceptual_hash(&encode(&resized, ImageFormat::Jpeg), "image/jpeg")
+        .expect("No hash for JPEG");
+    let different = perceptual_hash(&encode(&banded, ImageFormat::Png), "image/png")
+        .expect("No hash for PNG");
+
+    assert!(hamming_distance(original, reencoded) <= 4);
+    assert!(hamming_distance(original, different) > 16);
+
+    assert_eq!(perceptual_hash(b"Apple banana", "text/plain"), None);
+    assert_eq!(perceptual_hash(b"Not a PNG", "image/png"), None);
+}
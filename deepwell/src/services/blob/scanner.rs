@@ -0,0 +1,223 @@
+/*
+ * services/blob/scanner.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Scans blob contents for malware before they are stored.
+//!
+//! Either a clamd daemon can be used, which is streamed the blob over its
+//! Unix socket using the `INSTREAM` command, or an arbitrary program can be
+//! run, following the exit code convention of `clamscan` and `clamdscan`.
+//!
+//! clamd rejects streams longer than its `StreamMaxLength`, so it must be
+//! configured to at least `maximum-blob-size-kb` or large uploads will fail.
+
+use super::prelude::*;
+use crate::config::Config;
+use crate::types::ScannerBackend;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::process::Command;
+
+/// The largest chunk sent to clamd in one `INSTREAM` message.
+const CLAMD_CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanOutcome {
+    /// No scanner is configured, so the blob was not checked.
+    Skipped,
+
+    /// The blob was checked and nothing was found.
+    Clean,
+
+    /// The blob was detected as malware, with the name of the threat.
+    Infected(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum BlobScanner {
+    Disabled,
+    Clamd { socket: PathBuf },
+    Command { program: String, args: Vec<String> },
+}
+
+impl BlobScanner {
+    pub fn new(config: &Config) -> Self {
+        match config.scanner_backend {
+            ScannerBackend::None => BlobScanner::Disabled,
+            ScannerBackend::Clamd => BlobScanner::Clamd {
+                socket: config.scanner_socket.clone(),
+            },
+            ScannerBackend::Command => {
+                let (program, args) = config
+                    .scanner_command
+                    .split_first()
+                    .expect("Scanner command is empty");
+
+                BlobScanner::Command {
+                    program: program.clone(),
+                    args: args.to_vec(),
+                }
+            }
+        }
+    }
+
+    /// The name recorded for scans performed by this scanner.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlobScanner::Disabled => "none",
+            BlobScanner::Clamd { .. } => "clamd",
+            BlobScanner::Command { .. } => "command",
+        }
+    }
+
+    pub async fn scan(&self, data: &[u8]) -> Result<ScanOutcome> {
+        match self {
+            BlobScanner::Disabled => Ok(ScanOutcome::Skipped),
+            BlobScanner::Clamd { socket } => {
+                info!("Scanning blob ({} bytes) with clamd", data.len());
+                Self::scan_clamd(socket, data).await
+            }
+            BlobScanner::Command { program, args } => {
+                info!("Scanning blob ({} bytes) with {program}", data.len());
+                Self::scan_command(program, args, data).await
+            }
+        }
+    }
+
+    async fn scan_clamd(socket: &PathBuf, data: &[u8]) -> Result<ScanOutcome> {
+        let mut stream = UnixStream::connect(socket).await.map_err(|error| {
+            error!(
+                "Unable to connect to clamd at {}: {error}",
+                socket.display()
+            );
+            Error::ScannerBackend(error.to_string())
+        })?;
+
+        // Each chunk is prefixed with its length, and a zero length ends the stream.
+        stream.write_all(b"zINSTREAM\0").await?;
+        for chunk in data.chunks(CLAMD_CHUNK_SIZE) {
+            let length = u32::try_from(chunk.len()).expect("Chunk size exceeds u32");
+            stream.write_all(&length.to_be_bytes()).await?;
+            stream.write_all(chunk).await?;
+        }
+        stream.write_all(&0u32.to_be_bytes()).await?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        let response = String::from_utf8_lossy(&response);
+        parse_clamd_response(&response)
+    }
+
+    async fn scan_command(
+        program: &str,
+        args: &[String],
+        data: &[u8],
+    ) -> Result<ScanOutcome> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|error| {
+                error!("Unable to run scanner command {program}: {error}");
+                Error::ScannerBackend(error.to_string())
+            })?;
+
+        // Write the blob on a separate task, so large outputs don't deadlock
+        let mut stdin = child.stdin.take().expect("Scanner stdin not piped");
+        let input = data.to_vec();
+        let writer = tokio::spawn(async move {
+            // The scanner may exit early without reading everything
+            let _ = stdin.write_all(&input).await;
+        });
+
+        let output = child.wait_with_output().await?;
+        let _ = writer.await;
+
+        match output.status.code() {
+            Some(0) => Ok(ScanOutcome::Clean),
+            Some(1) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let signature = stdout
+                    .lines()
+                    .rev()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .unwrap_or("unknown");
+
+                Ok(ScanOutcome::Infected(str!(signature)))
+            }
+            _ => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                error!("Scanner command failed ({}): {stderr}", output.status);
+                Err(Error::ScannerBackend(str!(stderr.trim())))
+            }
+        }
+    }
+}
+
+/// Parses the reply to an `INSTREAM` command.
+///
+/// This is of the form `stream: OK`, `stream: <signature> FOUND`,
+/// or `<message> ERROR`, terminated by a null byte.
+fn parse_clamd_response(response: &str) -> Result<ScanOutcome> {
+    let response = response.trim_end_matches('\0').trim();
+    let result = response.strip_prefix("stream:").map(str::trim);
+
+    match result {
+        Some("OK") => Ok(ScanOutcome::Clean),
+        Some(result) if result.ends_with(" FOUND") => {
+            let signature = result.trim_end_matches(" FOUND");
+            Ok(ScanOutcome::Infected(str!(signature)))
+        }
+        _ if response.contains("size limit exceeded") => {
+            error!("Blob is larger than clamd allows, raise its StreamMaxLength setting");
+            Err(Error::ScannerBackend(str!(response)))
+        }
+        _ => {
+            error!("Unexpected response from clamd: {response}");
+            Err(Error::ScannerBackend(str!(response)))
+        }
+    }
+}
+
+#[test]
+fn clamd_responses() {
+    macro_rules! check {
+        ($response:expr, $expected:expr $(,)?) => {{
+            let actual = parse_clamd_response($response).ok();
+            assert_eq!(
+                actual, $expected,
+                "Actual clamd outcome doesn't match expected"
+            );
+        }};
+    }
+
+    check!("stream: OK\0", Some(ScanOutcome::Clean));
+    check!(
+        "stream: Win.Test.EICAR_HDB-1 FOUND\0",
+        Some(ScanOutcome::Infected(str!("Win.Test.EICAR_HDB-1"))),
+    );
+    check!("INSTREAM size limit exceeded. ERROR\0", None);
+    check!("", None);
+}
@@ -19,6 +19,7 @@
  */
 
 use super::prelude::*;
+use super::{BlobScanner, LocalStorage, ScanOutcome};
 use crate::constants::SYSTEM_USER_ID;
 use crate::hash::slice_to_blob_hash;
 use crate::models::blob_blacklist::{
@@ -27,25 +28,32 @@ use crate::models::blob_blacklist::{
 use crate::models::blob_pending::{
     self, Entity as BlobPending, Model as BlobPendingModel,
 };
+use crate::models::blob_perceptual_hash::{self, Entity as BlobPerceptualHash};
+use crate::models::blob_scan::{self, Entity as BlobScan, Model as BlobScanModel};
+use crate::models::blob_thumbnail::{self, Entity as BlobThumbnail};
 use crate::models::file::{self, Entity as File, Model as FileModel};
+use crate::models::file_archive::{self, Entity as FileArchive};
 use crate::models::file_revision::{
     self, Entity as FileRevision, Model as FileRevisionModel,
 };
 use crate::models::page::{self, Entity as Page, Model as PageModel};
 use crate::models::site::{self, Entity as Site, Model as SiteModel};
 use crate::models::user::{self, Entity as User, Model as UserModel};
+use crate::models::user_avatar::{self, Entity as UserAvatar};
 use crate::services::file::{DeleteFile, FileService};
-use crate::utils::assert_is_csprng;
+use crate::services::ThumbnailService;
+use crate::types::FileHiddenField;
+use crate::utils::{assert_is_csprng, run_blocking};
 use bytes::Bytes;
 use cuid2::cuid;
 use futures::TryStreamExt;
 use rand::distributions::{Alphanumeric, DistString};
 use rand::thread_rng;
 use s3::request::request_trait::ResponseData;
-use s3::serde_types::HeadObjectResult;
+use s3::serde_types::{HeadObjectResult, Part};
 use sea_orm::{
-    prelude::*, DatabaseBackend, FromQueryResult, Statement, StreamTrait,
-    TransactionTrait, UpdateResult,
+    prelude::*, sea_query::OnConflict, DatabaseBackend, FromQueryResult, Statement,
+    StreamTrait, TransactionTrait, UpdateResult,
 };
 use sea_query::value::ArrayType;
 use std::collections::{HashMap, HashSet};
@@ -58,11 +66,14 @@ use time::{Duration, OffsetDateTime};
 /// How many samples to provide when providing hard deletion stats.
 const SAMPLE_COUNT: u16 = 10;
 
+/// How many stored blobs are checked in each rescan or backfill job.
+const RESCAN_BATCH_SIZE: u64 = 20;
+
 /// Hash for empty blobs.
 ///
 /// Even though it is not the SHA-512 hash, for simplicity we treat the hash
 /// value with all zeroes to be the blob address for the empty blob.
-/// This empty blob is not actually stored in S3 but instead is a "virtual blob",
+/// This empty blob is not actually stored but instead is a "virtual blob",
 /// considered to have always been present in `BlobService`.
 pub const EMPTY_BLOB_HASH: BlobHash = [
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -78,16 +89,19 @@ pub const EMPTY_BLOB_MIME: &str = "inode/x-empty; charset=binary";
 /// Timestamp is 2019/01/18 at midnight, the date of the first Wikijump commit.
 pub const EMPTY_BLOB_TIMESTAMP: i64 = 1547769600;
 
-/// The subdirectory in the S3 bucket where all pending uploads are kept.
+/// The subdirectory in blob storage where all pending uploads are kept.
 pub const PRESIGN_DIRECTORY: &str = "uploads";
 
+/// The subdirectory in blob storage where blobs detected as malware are kept.
+pub const QUARANTINE_DIRECTORY: &str = "quarantine";
+
 #[derive(Debug)]
 pub struct BlobService;
 
 impl BlobService {
     // File-related operations
 
-    /// Creates an S3 presign URL to allow an end user to upload a blob.
+    /// Creates a presign URL to allow an end user to upload a blob.
     /// This is the start to the upload process for any kind of file.
     ///
     /// # Returns
@@ -99,42 +113,18 @@ impl BlobService {
         info!("Creating upload by {user_id} with promised length {blob_size}");
         let config = ctx.config();
         let txn = ctx.transaction();
+        let blob_size = Self::check_blob_size(ctx, blob_size)?;
 
-        // Convert expected length integer type, then check it
-        let blob_size = i64::try_from(blob_size).map_err(|_| Error::BlobTooBig)?;
-        if blob_size > config.maximum_blob_size {
-            error!(
-                "Blob proposed to upload is too big ({} > {})",
-                blob_size, config.maximum_blob_size,
-            );
-
-            return Err(Error::BlobTooBig);
-        }
-
-        // Generate primary key and random S3 path
+        // Generate primary key and random storage path
         let pending_blob_id = cuid();
-        let s3_path = {
-            let mut path = format!("{PRESIGN_DIRECTORY}/");
-
-            {
-                let mut rng = thread_rng();
-                assert_is_csprng(&rng);
-                Alphanumeric.append_string(
-                    &mut rng,
-                    &mut path,
-                    config.presigned_path_length,
-                );
-            }
-
-            path
-        };
+        let s3_path = Self::generate_upload_path(ctx);
 
         info!("Creating presign upload URL for blob at path {s3_path} with primary key {pending_blob_id}");
 
         // Create presign URL
-        let bucket = ctx.s3_bucket();
-        let presign_url = bucket
-            .presign_put(&s3_path, config.presigned_expiry_secs, None, None)
+        let presign_url = ctx
+            .storage()
+            .presign_put(&s3_path, config.presigned_expiry_secs)
             .await?;
 
         // Get timestamps
@@ -148,7 +138,7 @@ impl BlobService {
             external_id: Set(pending_blob_id),
             expected_length: Set(blob_size),
             s3_path: Set(s3_path),
-            presign_url: Set(presign_url),
+            presign_url: Set(Some(presign_url)),
             created_by: Set(user_id),
             created_at: Set(created_at),
             expires_at: Set(expires_at),
@@ -165,11 +155,273 @@ impl BlobService {
 
         Ok(StartBlobUploadOutput {
             pending_blob_id,
+            presign_url: presign_url.expect("Single upload has no presign URL"),
+            expires_at,
+        })
+    }
+
+    /// Starts a multipart upload, for large blobs.
+    ///
+    /// Instead of a single presign URL, each part is uploaded separately,
+    /// using presign URLs from `presign_upload_part()`. Failed parts can
+    /// be retried individually until the upload expires. Once all parts
+    /// are uploaded, `complete_multipart_upload()` assembles the blob,
+    /// which can then be used like any other pending blob.
+    pub async fn start_multipart_upload(
+        ctx: &ServiceContext<'_>,
+        StartBlobUpload { user_id, blob_size }: StartBlobUpload,
+    ) -> Result<StartMultipartBlobUploadOutput> {
+        info!("Creating multipart upload by {user_id} with promised length {blob_size}",);
+        let config = ctx.config();
+        let txn = ctx.transaction();
+        let blob_size = Self::check_blob_size(ctx, blob_size)?;
+        let part_size = config.multipart_part_size;
+        let part_count = multipart_part_count(blob_size, part_size);
+
+        // Generate primary key and random storage path
+        let pending_blob_id = cuid();
+        let s3_path = Self::generate_upload_path(ctx);
+
+        info!(
+            "Creating multipart upload for blob at path {s3_path} ({part_count} parts) with primary key {pending_blob_id}",
+        );
+
+        let upload_id = ctx.storage().start_multipart(&s3_path).await?;
+
+        // Get timestamps
+        let created_at = now();
+        let expires_at = created_at + config.multipart_expiry;
+
+        // Add pending blob entry
+        let model = blob_pending::ActiveModel {
+            external_id: Set(pending_blob_id),
+            expected_length: Set(blob_size),
+            s3_path: Set(s3_path),
+            presign_url: Set(None),
+            multipart_upload_id: Set(Some(upload_id)),
+            multipart_part_size: Set(Some(part_size)),
+            multipart_etags: Set(Some(vec![String::new(); part_count])),
+            created_by: Set(user_id),
+            created_at: Set(created_at),
+            expires_at: Set(expires_at),
+            ..Default::default()
+        };
+
+        let BlobPendingModel {
+            external_id: pending_blob_id,
+            ..
+        } = model.insert(txn).await?;
+
+        debug!("New multipart upload will last until {expires_at}");
+
+        Ok(StartMultipartBlobUploadOutput {
+            pending_blob_id,
+            part_size,
+            part_count,
+            expires_at,
+        })
+    }
+
+    /// Creates a presign URL to upload one part of a multipart upload.
+    ///
+    /// This can be called again for the same part, if uploading it failed.
+    pub async fn presign_upload_part(
+        ctx: &ServiceContext<'_>,
+        GetBlobUploadPart {
+            user_id,
+            pending_blob_id,
+            part_number,
+        }: GetBlobUploadPart,
+    ) -> Result<GetBlobUploadPartOutput> {
+        info!("Creating presign URL for part {part_number} of pending blob ID {pending_blob_id}");
+
+        let config = ctx.config();
+        let MultipartBlob {
+            s3_path, upload_id, ..
+        } = Self::get_multipart_blob(ctx, user_id, &pending_blob_id, part_number).await?;
+
+        let presign_url = ctx
+            .storage()
+            .presign_part(
+                &s3_path,
+                &upload_id,
+                part_number,
+                config.presigned_expiry_secs,
+            )
+            .await?;
+
+        let expires_at = now()
+            .checked_add(Duration::seconds(i64::from(config.presigned_expiry_secs)))
+            .expect("getting expiration timestamp overflowed");
+
+        Ok(GetBlobUploadPartOutput {
             presign_url,
             expires_at,
         })
     }
 
+    /// Records that one part of a multipart upload has been uploaded.
+    ///
+    /// The ETag is the one returned when the part was uploaded.
+    /// It is verified by the storage backend when the upload is completed.
+    pub async fn finish_upload_part(
+        ctx: &ServiceContext<'_>,
+        FinishBlobUploadPart {
+            user_id,
+            pending_blob_id,
+            part_number,
+            etag,
+        }: FinishBlobUploadPart,
+    ) -> Result<()> {
+        info!("Finishing part {part_number} of pending blob ID {pending_blob_id}");
+
+        let txn = ctx.transaction();
+        Self::get_multipart_blob(ctx, user_id, &pending_blob_id, part_number).await?;
+
+        if etag.is_empty() {
+            error!("Empty ETag for uploaded part {part_number}");
+            return Err(Error::BadRequest);
+        }
+
+        // Update only this part's element, so that parts
+        // finishing concurrently do not overwrite each other.
+        txn.execute(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            "UPDATE blob_pending SET multipart_etags[$1] = $2 WHERE external_id = $3",
+            [
+                i32::try_from(part_number)
+                    .map_err(|_| Error::BlobPartInvalid)?
+                    .into(),
+                etag.into(),
+                pending_blob_id.into(),
+            ],
+        ))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Assembles all of the uploaded parts of a multipart upload.
+    ///
+    /// Afterwards, the pending blob is finalized with `finish_upload()`,
+    /// the same as for a single upload.
+    pub async fn complete_multipart_upload(
+        ctx: &ServiceContext<'_>,
+        CompleteMultipartBlobUpload {
+            user_id,
+            pending_blob_id,
+        }: CompleteMultipartBlobUpload,
+    ) -> Result<()> {
+        info!("Completing multipart upload for pending blob ID {pending_blob_id}");
+
+        let MultipartBlob {
+            s3_path,
+            upload_id,
+            etags,
+        } = Self::get_multipart_blob(ctx, user_id, &pending_blob_id, 1).await?;
+
+        // Already completed
+        let storage = ctx.storage();
+        if storage.head(&s3_path).await?.is_some() {
+            debug!("Multipart upload already completed");
+            return Ok(());
+        }
+
+        if let Some(index) = etags.iter().position(|etag| etag.is_empty()) {
+            error!("Part {} of multipart upload was not uploaded", index + 1);
+            return Err(Error::BlobNotUploaded);
+        }
+
+        storage
+            .complete_multipart(&s3_path, &upload_id, etags)
+            .await
+    }
+
+    /// Gets a multipart pending blob which is still being uploaded.
+    async fn get_multipart_blob(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+        pending_blob_id: &str,
+        part_number: u32,
+    ) -> Result<MultipartBlob> {
+        let txn = ctx.transaction();
+        let row = BlobPending::find_by_id(pending_blob_id).one(txn).await?;
+        let BlobPendingModel {
+            s3_path,
+            s3_hash,
+            created_by,
+            expires_at,
+            multipart_upload_id,
+            multipart_etags,
+            ..
+        } = match row {
+            Some(pending) => pending,
+            None => return Err(Error::BlobNotFound),
+        };
+
+        if user_id != created_by {
+            error!("User mismatch, user ID {user_id} is attempting to use blob uploaded by {created_by}");
+            return Err(Error::BlobWrongUser);
+        }
+
+        let (upload_id, etags) = match (multipart_upload_id, multipart_etags) {
+            (Some(upload_id), Some(etags)) => (upload_id, etags),
+            _ => {
+                error!("Pending blob ID {pending_blob_id} is not a multipart upload");
+                return Err(Error::BlobNotMultipart);
+            }
+        };
+
+        if s3_hash.is_some() || expires_at < now() {
+            error!("Multipart upload for pending blob ID {pending_blob_id} is no longer active");
+            return Err(Error::BlobUploadExpired);
+        }
+
+        if part_number == 0 || part_number as usize > etags.len() {
+            error!(
+                "Part number {} out of range (1 to {})",
+                part_number,
+                etags.len(),
+            );
+            return Err(Error::BlobPartInvalid);
+        }
+
+        Ok(MultipartBlob {
+            s3_path,
+            upload_id,
+            etags,
+        })
+    }
+
+    /// Checks and converts the promised size of a blob to be uploaded.
+    fn check_blob_size(ctx: &ServiceContext<'_>, blob_size: u64) -> Result<i64> {
+        let config = ctx.config();
+        let blob_size = i64::try_from(blob_size).map_err(|_| Error::BlobTooBig)?;
+        if blob_size > config.maximum_blob_size {
+            error!(
+                "Blob proposed to upload is too big ({} > {})",
+                blob_size, config.maximum_blob_size,
+            );
+
+            return Err(Error::BlobTooBig);
+        }
+
+        Ok(blob_size)
+    }
+
+    /// Generates a random storage path for a pending upload.
+    fn generate_upload_path(ctx: &ServiceContext<'_>) -> String {
+        let mut path = format!("{PRESIGN_DIRECTORY}/");
+        let mut rng = thread_rng();
+        assert_is_csprng(&rng);
+        Alphanumeric.append_string(
+            &mut rng,
+            &mut path,
+            ctx.config().presigned_path_length,
+        );
+        path
+    }
+
     async fn get_pending_blob_path(
         ctx: &ServiceContext<'_>,
         user_id: i64,
@@ -182,6 +434,7 @@ impl BlobService {
             s3_hash,
             created_by,
             expected_length,
+            multipart_upload_id,
             ..
         } = match row {
             Some(pending) => pending,
@@ -197,6 +450,7 @@ impl BlobService {
             s3_path,
             expected_length,
             moved_hash: s3_hash,
+            multipart_upload_id,
         })
     }
 
@@ -207,24 +461,83 @@ impl BlobService {
     ) -> Result<()> {
         info!("Cancelling upload for blob for pending ID {pending_blob_id}");
         let txn = ctx.transaction();
-        let PendingBlob { s3_path, .. } =
-            Self::get_pending_blob_path(ctx, user_id, pending_blob_id).await?;
+        let PendingBlob {
+            s3_path,
+            multipart_upload_id,
+            ..
+        } = Self::get_pending_blob_path(ctx, user_id, pending_blob_id).await?;
 
         BlobPending::delete_by_id(pending_blob_id).exec(txn).await?;
 
-        if Self::head(ctx, &s3_path).await?.is_some() {
-            let bucket = ctx.s3_bucket();
-            bucket.delete_object(&s3_path).await?;
+        let storage = ctx.storage();
+        if storage.head(&s3_path).await?.is_some() {
+            storage.delete(&s3_path).await?;
+        } else if let Some(upload_id) = multipart_upload_id {
+            // Not yet completed, discard any uploaded parts
+            storage.abort_multipart(&s3_path, &upload_id).await?;
         }
 
         Ok(())
     }
 
+    /// Deletes all expired pending blobs, along with any uploaded data.
+    ///
+    /// Uploads which were never finished have their objects deleted,
+    /// or if they are multipart uploads which were never completed,
+    /// are aborted so that S3 discards the uploaded parts.
+    pub async fn prune_pending(ctx: &ServiceContext<'_>) -> Result<u64> {
+        info!("Pruning all expired pending blobs");
+
+        let txn = ctx.transaction();
+        let pending_blobs = BlobPending::find()
+            .filter(blob_pending::Column::ExpiresAt.lte(now()))
+            .all(txn)
+            .await?;
+
+        let storage = ctx.storage();
+        let mut pruned = 0;
+        for BlobPendingModel {
+            external_id,
+            s3_path,
+            s3_hash,
+            multipart_upload_id,
+            ..
+        } in pending_blobs
+        {
+            BlobPending::delete_by_id(&external_id).exec(txn).await?;
+            pruned += 1;
+
+            // Already moved to its permanent location, nothing to clean up
+            if s3_hash.is_some() {
+                continue;
+            }
+
+            let result = match storage.head(&s3_path).await {
+                Ok(Some(_)) => storage.delete(&s3_path).await,
+                Ok(None) => match multipart_upload_id {
+                    Some(upload_id) => {
+                        storage.abort_multipart(&s3_path, &upload_id).await
+                    }
+                    None => Ok(()),
+                },
+                Err(error) => Err(error),
+            };
+
+            // Don't fail the whole job because of one upload
+            if let Err(error) = result {
+                warn!("Unable to clean up data for pending blob {external_id}: {error}");
+            }
+        }
+
+        debug!("{pruned} expired pending blobs were pruned");
+        Ok(pruned)
+    }
+
     /// Helper function to do the actual "move" step of blob finalization.
-    /// This is where, after uploading to the presign URL, the S3 object is
+    /// This is where, after uploading to the presign URL, the stored object is
     /// then moved to its permanent location with a hashed name.
     ///
-    /// NOTE: Because S3 changes cannot be rolled back on error, we are
+    /// NOTE: Because storage changes cannot be rolled back on error, we are
     ///       creating a separate transaction here so that `blob_pending`
     ///       changes are persistent even if the outer request fails.
     async fn move_uploaded(
@@ -261,22 +574,16 @@ impl BlobService {
         s3_path: &str,
         expected_length: usize,
     ) -> Result<FinalizeBlobUploadOutput> {
-        let bucket = ctx.s3_bucket();
+        let storage = ctx.storage();
         let txn = ctx.transaction();
 
-        debug!("Download uploaded blob from S3 uploads to get metadata");
-        let response = bucket.get_object(s3_path).await?;
-        let data: Vec<u8> = match response.status_code() {
-            200 => response.into(),
-            404 => {
+        debug!("Download uploaded blob from storage uploads to get metadata");
+        let data = match storage.get(s3_path).await? {
+            Some(data) => data,
+            None => {
                 error!("No blob uploaded at presign path {s3_path}");
                 return Err(Error::BlobNotUploaded);
             }
-            _ => {
-                error!("Unable to retrieve uploaded blob at {s3_path} from S3");
-                let error = s3_error(&response, "finalizing uploaded blob")?;
-                return Err(error);
-            }
         };
 
         if expected_length != data.len() {
@@ -285,7 +592,7 @@ impl BlobService {
                 expected_length,
                 data.len(),
             );
-            bucket.delete_object(&s3_path).await?;
+            storage.delete(s3_path).await?;
             return Err(Error::BlobSizeMismatch {
                 expected: expected_length,
                 actual: data.len(),
@@ -303,55 +610,136 @@ impl BlobService {
             });
         }
 
-        debug!("Updating blob metadata in database and S3");
+        debug!("Updating blob metadata in database and storage");
 
         // If the blob exists, then just delete the uploaded one.
         //
-        // If it doesn't, then we need to move it. However, within S3
-        // we cannot "move" objects, we have to upload and delete the original.
+        // If it doesn't, then we need to move it. However, storage backends
+        // cannot "move" objects, we have to upload and delete the original.
         //
         // In either case, we delete the blob at the temporary upload location.
 
+        let result = match Self::checked_upload(ctx, data, pending_blob_user_id).await {
+            Ok(result) => result,
+            Err(error @ (Error::BlobInfected(_) | Error::BlobBlacklisted(_))) => {
+                // Cancel this pending upload, what they're trying to store shouldn't be on here
+                warn!("Newly-uploaded blob {pending_blob_id} was rejected, cancelling");
+                Self::cancel_upload(ctx, pending_blob_user_id, pending_blob_id).await?;
+                return Err(error);
+            }
+            Err(error) => return Err(error),
+        };
+        storage.delete(s3_path).await?;
+
+        // Update pending blob with hash
+        let model = blob_pending::ActiveModel {
+            external_id: Set(str!(pending_blob_id)),
+            s3_hash: Set(Some(result.s3_hash.to_vec())),
+            ..Default::default()
+        };
+        model.update(txn).await?;
+
+        // Return
+        Ok(result)
+    }
+
+    /// Stores a blob after checking it for malware and against the blacklist.
+    ///
+    /// This is used for blobs from users, whether uploaded directly or
+    /// extracted from an archive. Infected blobs are quarantined instead
+    /// of stored, returning `BlobInfected`, and blacklisted blobs or
+    /// near-duplicates of blacklisted images return `BlobBlacklisted`.
+    pub(crate) async fn checked_upload(
+        ctx: &ServiceContext<'_>,
+        data: Vec<u8>,
+        user_id: i64,
+    ) -> Result<FinalizeBlobUploadOutput> {
+        // Special handling for empty blobs
+        if data.is_empty() {
+            debug!("Blob being stored is empty, special case");
+            return Ok(FinalizeBlobUploadOutput {
+                s3_hash: EMPTY_BLOB_HASH,
+                mime: str!(EMPTY_BLOB_MIME),
+                size: 0,
+                created: false,
+            });
+        }
+
+        // Check for malware before the blob is stored
+        let scan = ctx.scanner().scan(&data).await?;
+        if let ScanOutcome::Infected(ref signature) = scan {
+            let s3_hash = sha512_hash(&data);
+            let hex_hash = blob_hash_to_hex(&s3_hash);
+            warn!(
+                "Blob from user ID {user_id} is infected with {signature} (hash {hex_hash})",
+            );
+
+            // Keep a copy for review
+            Self::quarantine(ctx, &hex_hash, &data).await?;
+            Self::record_scan(ctx, s3_hash, &scan, Some(user_id)).await?;
+            return Err(Error::BlobInfected(signature.clone()));
+        }
+
         let result = Self::direct_upload(ctx, data).await?;
-        bucket.delete_object(&s3_path).await?;
 
         // Check that new blob is not blacklisted
         if Self::on_blacklist(ctx, result.s3_hash).await? {
             let hex_hash = blob_hash_to_hex(&result.s3_hash);
+            error!("Blob from user ID {user_id} is blacklisted (hash {hex_hash})");
+            return Err(Error::BlobBlacklisted(result.s3_hash));
+        }
+
+        // Check that new blob is not a near-duplicate of a blacklisted image
+        if let Some(blacklisted_hash) =
+            Self::on_perceptual_blacklist(ctx, result.s3_hash).await?
+        {
             error!(
-                "Newly-uploaded blob {pending_blob_id} is blacklisted (hash {hex_hash})",
+                "Blob from user ID {user_id} is a near-duplicate of blacklisted blob {}",
+                blob_hash_to_hex(&blacklisted_hash),
             );
 
-            // Cancel this pending upload, what they're trying to store shouldn't be on here
-            Self::cancel_upload(ctx, pending_blob_user_id, pending_blob_id).await?;
+            // Don't keep a stored copy of it either
+            if result.created {
+                Self::hard_delete(ctx, &result.s3_hash).await?;
+            }
 
-            // Finally, return error
             return Err(Error::BlobBlacklisted(result.s3_hash));
         }
 
-        // Update pending blob with hash
-        let model = blob_pending::ActiveModel {
-            external_id: Set(str!(pending_blob_id)),
-            s3_hash: Set(Some(result.s3_hash.to_vec())),
-            ..Default::default()
-        };
-        model.update(txn).await?;
-
-        // Return
+        Self::record_scan(ctx, result.s3_hash, &scan, Some(user_id)).await?;
         Ok(result)
     }
 
-    /// Takes a blob and uploads it to its final destination in S3.
+    /// Takes a blob and uploads it to its final destination in storage.
     ///
     /// This is used in the above `move_uploaded_inner()` method to
-    /// "move" the S3 blob. This is done by uploading to the final
+    /// "move" the stored blob. This is done by uploading to the final
     /// destination, then afterwards, deleting the blob at the temporary
     /// upload location.
     pub(crate) async fn direct_upload(
         ctx: &ServiceContext<'_>,
         data: Vec<u8>,
     ) -> Result<FinalizeBlobUploadOutput> {
-        let bucket = ctx.s3_bucket();
+        Self::upload(ctx, data, true).await
+    }
+
+    /// Takes a blob generated from another one and uploads it to storage.
+    ///
+    /// This is for derived blobs such as thumbnails, which are never matched
+    /// against the blacklist, so no perceptual hash is computed for them.
+    pub(crate) async fn derived_upload(
+        ctx: &ServiceContext<'_>,
+        data: Vec<u8>,
+    ) -> Result<FinalizeBlobUploadOutput> {
+        Self::upload(ctx, data, false).await
+    }
+
+    async fn upload(
+        ctx: &ServiceContext<'_>,
+        data: Vec<u8>,
+        perceptual: bool,
+    ) -> Result<FinalizeBlobUploadOutput> {
+        let storage = ctx.storage();
 
         // Get hash for blob
         let s3_hash = sha512_hash(&data);
@@ -360,16 +748,13 @@ impl BlobService {
         // Convert size to correct integer type
         let size: i64 = data.len().try_into().expect("Buffer size exceeds i64");
 
-        match Self::head(ctx, &hex_hash).await? {
-            Some(result) => {
+        match storage.head(&hex_hash).await? {
+            Some(StorageMetadata { mime, .. }) => {
                 debug!("Blob with hash {hex_hash} already exists");
 
                 // TODO: Should we ever update the mime type?
                 //       In case of changing file formats, etc.
 
-                // Content-Type header should be returned
-                let mime = result.content_type.ok_or(Error::S3Response)?;
-
                 Ok(FinalizeBlobUploadOutput {
                     s3_hash,
                     mime,
@@ -383,25 +768,56 @@ impl BlobService {
                 // Determine MIME type for the new blob
                 let mime = ctx.mime().get_mime_type(data.clone()).await?;
 
-                // Upload S3 object
-                let response = bucket
-                    .put_object_with_content_type(&hex_hash, &data, &mime)
-                    .await?;
+                // Upload stored object
+                storage.put(&hex_hash, &data, &mime).await?;
 
-                // We assume all unexpected statuses are errors, even if 1XX or 2XX
-                match response.status_code() {
-                    200 => Ok(FinalizeBlobUploadOutput {
-                        s3_hash,
-                        mime,
-                        size,
-                        created: true,
-                    }),
-                    _ => s3_error(&response, "creating final S3 blob")?,
+                // Record perceptual hash for images, to find near-duplicates
+                if perceptual {
+                    Self::store_perceptual_hash(ctx, s3_hash, data, mime.clone()).await?;
                 }
+
+                Ok(FinalizeBlobUploadOutput {
+                    s3_hash,
+                    mime,
+                    size,
+                    created: true,
+                })
             }
         }
     }
 
+    /// Computes and records the perceptual hash of a blob, if it is an image.
+    ///
+    /// Decoding an image is expensive, so this is done off the async runtime.
+    async fn store_perceptual_hash(
+        ctx: &ServiceContext<'_>,
+        s3_hash: BlobHash,
+        data: Vec<u8>,
+        mime: String,
+    ) -> Result<()> {
+        let perceptual_hash = run_blocking(move || perceptual_hash(&data, &mime)).await;
+        if let Some(perceptual_hash) = perceptual_hash {
+            let txn = ctx.transaction();
+            let model = blob_perceptual_hash::ActiveModel {
+                s3_hash: Set(s3_hash.to_vec()),
+                perceptual_hash: Set(perceptual_hash),
+                ..Default::default()
+            };
+
+            // May have already been added by a concurrent upload or backfill
+            BlobPerceptualHash::insert(model)
+                .on_conflict(
+                    OnConflict::column(blob_perceptual_hash::Column::S3Hash)
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .exec_without_returning(txn)
+                .await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn finish_upload(
         ctx: &ServiceContext<'_>,
         user_id: i64,
@@ -413,6 +829,7 @@ impl BlobService {
             s3_path,
             expected_length,
             moved_hash,
+            ..
         } = Self::get_pending_blob_path(ctx, user_id, pending_blob_id).await?;
 
         let output = match moved_hash {
@@ -458,8 +875,16 @@ impl BlobService {
     pub async fn hard_delete_preview(
         ctx: &ServiceContext<'_>,
         s3_hash: BlobHash,
+        near_duplicates: bool,
     ) -> Result<HardDeleteOutput> {
-        Self::hard_delete_inner(ctx, HardDeleteInner::DryRun { s3_hash }).await
+        Self::hard_delete_inner(
+            ctx,
+            HardDeleteInner::DryRun {
+                s3_hash,
+                near_duplicates,
+            },
+        )
+        .await
     }
 
     /// Hard deletes the specified blob and all duplicates.
@@ -473,12 +898,28 @@ impl BlobService {
     ///
     /// This method should only be used very rarely to clear content such
     /// as severe copyright violations, abuse content, or comply with court orders.
+    ///
+    /// If `near_duplicates` is set, then all stored images which are perceptually
+    /// similar to this one are deleted too, and future uploads of similar images
+    /// are blacklisted.
     pub async fn hard_delete_all(
         ctx: &ServiceContext<'_>,
-        HardDelete { s3_hash, user_id }: HardDelete,
+        HardDelete {
+            s3_hash,
+            user_id,
+            near_duplicates,
+        }: HardDelete,
     ) -> Result<HardDeleteOutput> {
         let s3_hash = slice_to_blob_hash(s3_hash.as_ref());
-        Self::hard_delete_inner(ctx, HardDeleteInner::Commit { s3_hash, user_id }).await
+        Self::hard_delete_inner(
+            ctx,
+            HardDeleteInner::Commit {
+                s3_hash,
+                user_id,
+                near_duplicates,
+            },
+        )
+        .await
     }
 
     /// Inner implementation, which runs the hard deletion procedure but may not actually delete.
@@ -490,9 +931,16 @@ impl BlobService {
         input: HardDeleteInner,
     ) -> Result<HardDeleteOutput> {
         let txn = ctx.transaction();
-        let (s3_hash, deleter_user_id) = match input {
-            HardDeleteInner::Commit { s3_hash, user_id } => (s3_hash, Some(user_id)),
-            HardDeleteInner::DryRun { s3_hash } => (s3_hash, None),
+        let (s3_hash, near_duplicates, deleter_user_id) = match input {
+            HardDeleteInner::Commit {
+                s3_hash,
+                user_id,
+                near_duplicates,
+            } => (s3_hash, near_duplicates, Some(user_id)),
+            HardDeleteInner::DryRun {
+                s3_hash,
+                near_duplicates,
+            } => (s3_hash, near_duplicates, None),
         };
         // NOTE: Instead of an explicit "dry_run" variable, the value of "is real run"
         //       or "is dry run" is derived from whether "deleter_user_id" is None or not.
@@ -501,6 +949,21 @@ impl BlobService {
 
         Self::check_hash_not_empty(s3_hash)?;
 
+        // Get all blobs being deleted, this one and possibly its near-duplicates
+        let s3_hashes = if near_duplicates {
+            let mut hashes = Self::find_near_duplicates(ctx, s3_hash).await?;
+            hashes.retain(|&hash| hash != s3_hash && hash != EMPTY_BLOB_HASH);
+            hashes.insert(0, s3_hash);
+            hashes
+        } else {
+            vec![s3_hash]
+        };
+
+        let s3_hash_values = s3_hashes
+            .iter()
+            .map(|hash| hash.to_vec())
+            .collect::<Vec<_>>();
+
         let mut revisions = SamplerCounter::new();
         let mut files = SamplerCounter::new();
         let mut pages = SamplerCounter::new();
@@ -547,10 +1010,15 @@ impl BlobService {
                 LEFT OUTER JOIN file_revision AS r2
                     ON (f.file_id = r2.file_id AND r1.revision_number < r2.revision_number)
                 WHERE r2.revision_id IS NULL
-                AND r1.s3_hash = $1
+                AND r1.s3_hash = ANY($1)
                 AND f.deleted_at IS NULL
             "),
-            [Value::from(s3_hash.to_vec())],
+            [Value::Array(
+                ArrayType::Bytes,
+                Some(Box::new(
+                    s3_hash_values.iter().cloned().map(Value::from).collect(),
+                )),
+            )],
         );
 
         {
@@ -593,7 +1061,7 @@ impl BlobService {
         // Go through all the revisions with the matching S3 hash and delete / hide it
         {
             let mut results = FileRevision::find()
-                .filter(file_revision::Column::S3Hash.eq(s3_hash.as_slice()))
+                .filter(file_revision::Column::S3Hash.is_in(s3_hash_values.clone()))
                 .paginate(txn, 20);
 
             while let Some(revs) = results.fetch_and_next().await? {
@@ -606,7 +1074,8 @@ impl BlobService {
                     if deleter_user_id.is_some() {
                         // Amend 'hidden' to add 's3_hash'
                         let hidden = {
-                            let column = str!("s3_hash"); // avoid double-allocating String
+                            // avoid double-allocating String
+                            let column = str!(FileHiddenField::S3Hash.name());
                             let mut hidden = rev.hidden;
                             if !hidden.contains(&column) {
                                 hidden.push(column);
@@ -631,11 +1100,25 @@ impl BlobService {
 
         // Update all users using this blob to remove this as a profile picture
         // (But first getting a set of sample records)
+        //
+        // This includes users where it is any of the resized copies of their avatar.
+
+        let avatar_user_ids: Vec<i64> = UserAvatar::find()
+            .select_only()
+            .column(user_avatar::Column::UserId)
+            .filter(user_avatar::Column::S3Hash.is_in(s3_hash_values.clone()))
+            .into_tuple()
+            .all(txn)
+            .await?;
+
+        let avatar_condition = Condition::any()
+            .add(user::Column::AvatarS3Hash.is_in(s3_hash_values))
+            .add(user::Column::UserId.is_in(avatar_user_ids.iter().copied()));
 
         let sample_user_ids: Vec<i64> = User::find()
             .select_only()
             .column(user::Column::UserId)
-            .filter(user::Column::AvatarS3Hash.eq(s3_hash.as_slice()))
+            .filter(avatar_condition.clone())
             .limit(u64::from(SAMPLE_COUNT))
             .into_tuple()
             .all(txn)
@@ -649,9 +1132,14 @@ impl BlobService {
                     ..Default::default()
                 };
 
+                UserAvatar::delete_many()
+                    .filter(user_avatar::Column::UserId.is_in(avatar_user_ids))
+                    .exec(txn)
+                    .await?;
+
                 User::update_many()
                     .set(model)
-                    .filter(user::Column::AvatarS3Hash.eq(s3_hash.as_slice()))
+                    .filter(avatar_condition)
                     .exec(txn)
                     .await?
                     .rows_affected
@@ -661,7 +1149,7 @@ impl BlobService {
                 User::find()
                     .select_only()
                     .column_as(user::Column::UserId.count(), "count")
-                    .filter(user::Column::AvatarS3Hash.eq(s3_hash.as_slice()))
+                    .filter(avatar_condition)
                     .into_tuple::<i64>() // Postgres cannot return u64 as a column type
                     .one(txn)
                     .await?
@@ -672,12 +1160,17 @@ impl BlobService {
         };
 
         if let Some(user_id) = deleter_user_id {
-            // Delete and blacklist the hash, nobody should be uploading new versions
+            // Delete and blacklist the hashes, nobody should be uploading new versions
             // Only do so if we are actually mutating.
-            try_join!(
-                BlobService::add_blacklist(ctx, s3_hash, user_id),
-                BlobService::hard_delete(ctx, &s3_hash),
-            )?;
+            //
+            // When deleting near-duplicates, the original image is blacklisted
+            // perceptually, so that similar images are rejected in the future.
+            for &hash in &s3_hashes {
+                let perceptual = near_duplicates && hash == s3_hash;
+                BlobService::add_blacklist(ctx, hash, user_id, perceptual).await?;
+                ThumbnailService::delete(ctx, hash).await?;
+                BlobService::hard_delete(ctx, &hash).await?;
+            }
         }
 
         // Finish counting and sampling
@@ -687,6 +1180,10 @@ impl BlobService {
         let (total_sites, sample_site_ids) = sites.finish();
 
         Ok(HardDeleteOutput {
+            s3_hashes: s3_hashes
+                .iter()
+                .map(|hash| blob_hash_to_hex(hash).to_string())
+                .collect(),
             total_revisions,
             total_files,
             total_files_deleted,
@@ -743,12 +1240,20 @@ impl BlobService {
         }
     }
 
+    /// Adds a blob to the blacklist, preventing it from being uploaded.
+    ///
+    /// If `perceptual` is set, then near-duplicates of this image are
+    /// prohibited too, such as re-encoded or resized copies of it.
     pub async fn add_blacklist(
         ctx: &ServiceContext<'_>,
         hash: BlobHash,
         created_by: i64,
+        perceptual: bool,
     ) -> Result<()> {
-        info!("Adding hash {} to blacklist", blob_hash_to_hex(&hash));
+        info!(
+            "Adding hash {} to blacklist (perceptual {perceptual})",
+            blob_hash_to_hex(&hash),
+        );
 
         // This should never happen because the callers already
         // should be calling hash_not_empty()
@@ -757,18 +1262,38 @@ impl BlobService {
             "Empty blob hash passed to add_blacklist()",
         );
 
-        if Self::on_blacklist(ctx, hash).await? {
-            debug!("Already blacklisted, skipping");
-            return Ok(());
-        }
+        let perceptual_hash = if perceptual {
+            match Self::get_perceptual_hash(ctx, hash).await? {
+                Some(perceptual_hash) => Some(perceptual_hash),
+                None => {
+                    error!("Cannot blacklist near-duplicates of a blob which is not an image");
+                    return Err(Error::BlobNotImage);
+                }
+            }
+        } else {
+            None
+        };
 
         let txn = ctx.transaction();
-        let model = blob_blacklist::ActiveModel {
-            s3_hash: Set(hash.to_vec()),
-            created_by: Set(created_by),
-            ..Default::default()
-        };
-        model.insert(txn).await?;
+        match BlobBlacklist::find_by_id(hash.to_vec()).one(txn).await? {
+            // Only update if near-duplicates are being added to the blacklist
+            Some(model) if perceptual_hash.is_some() => {
+                let mut model = model.into_active_model();
+                model.perceptual_hash = Set(perceptual_hash);
+                model.update(txn).await?;
+            }
+            Some(_) => debug!("Already blacklisted, skipping"),
+            None => {
+                let model = blob_blacklist::ActiveModel {
+                    s3_hash: Set(hash.to_vec()),
+                    created_by: Set(created_by),
+                    perceptual_hash: Set(perceptual_hash),
+                    ..Default::default()
+                };
+                model.insert(txn).await?;
+            }
+        }
+
         Ok(())
     }
 
@@ -798,6 +1323,383 @@ impl BlobService {
         Ok(exists)
     }
 
+    /// Checks if a blob is a near-duplicate of any image on the blacklist.
+    ///
+    /// # Returns
+    /// The hash of the matching blacklisted blob, if any.
+    pub async fn on_perceptual_blacklist(
+        ctx: &ServiceContext<'_>,
+        hash: BlobHash,
+    ) -> Result<Option<BlobHash>> {
+        #[derive(Debug, FromQueryResult)]
+        struct Blacklisted {
+            s3_hash: Vec<u8>,
+        }
+
+        let perceptual_hash = match Self::get_perceptual_hash(ctx, hash).await? {
+            Some(perceptual_hash) => perceptual_hash,
+            None => return Ok(None),
+        };
+
+        info!(
+            "Checking if hash {} is a near-duplicate of a blacklisted image",
+            blob_hash_to_hex(&hash),
+        );
+
+        let txn = ctx.transaction();
+        let query = Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            str!(
+                "
+                SELECT s3_hash
+                FROM blob_blacklist
+                WHERE perceptual_hash IS NOT NULL
+                AND bit_count((perceptual_hash # $1)::bit(64)) <= $2
+                LIMIT 1
+            "
+            ),
+            [
+                Value::from(perceptual_hash),
+                Value::from(i64::from(ctx.config().perceptual_hash_threshold)),
+            ],
+        );
+
+        let blacklisted = BlobBlacklist::find()
+            .from_raw_sql(query)
+            .into_model::<Blacklisted>()
+            .one(txn)
+            .await?
+            .map(|Blacklisted { s3_hash }| slice_to_blob_hash(&s3_hash));
+
+        Ok(blacklisted)
+    }
+
+    /// Gets the perceptual hash of a blob, if it is an image.
+    pub async fn get_perceptual_hash(
+        ctx: &ServiceContext<'_>,
+        hash: BlobHash,
+    ) -> Result<Option<i64>> {
+        let txn = ctx.transaction();
+        let perceptual_hash = BlobPerceptualHash::find_by_id(hash.to_vec())
+            .one(txn)
+            .await?
+            .map(|model| model.perceptual_hash);
+
+        Ok(perceptual_hash)
+    }
+
+    /// Finds all stored images which are near-duplicates of this one.
+    ///
+    /// The results include the blob itself.
+    pub async fn find_near_duplicates(
+        ctx: &ServiceContext<'_>,
+        hash: BlobHash,
+    ) -> Result<Vec<BlobHash>> {
+        #[derive(Debug, FromQueryResult)]
+        struct NearDuplicate {
+            s3_hash: Vec<u8>,
+        }
+
+        info!(
+            "Finding near-duplicates of hash {}",
+            blob_hash_to_hex(&hash)
+        );
+
+        let perceptual_hash = match Self::get_perceptual_hash(ctx, hash).await? {
+            Some(perceptual_hash) => perceptual_hash,
+            None => {
+                error!("Cannot find near-duplicates of a blob which is not an image");
+                return Err(Error::BlobNotImage);
+            }
+        };
+
+        let txn = ctx.transaction();
+        let query = Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            str!(
+                "
+                SELECT s3_hash
+                FROM blob_perceptual_hash
+                WHERE bit_count((perceptual_hash # $1)::bit(64)) <= $2
+                ORDER BY s3_hash
+            "
+            ),
+            [
+                Value::from(perceptual_hash),
+                Value::from(i64::from(ctx.config().perceptual_hash_threshold)),
+            ],
+        );
+
+        let hashes = BlobPerceptualHash::find()
+            .from_raw_sql(query)
+            .into_model::<NearDuplicate>()
+            .all(txn)
+            .await?
+            .into_iter()
+            .map(|NearDuplicate { s3_hash }| slice_to_blob_hash(&s3_hash))
+            .collect();
+
+        Ok(hashes)
+    }
+
+    // Malware scanning
+
+    /// Copies an infected blob into the quarantine area of storage.
+    async fn quarantine(
+        ctx: &ServiceContext<'_>,
+        hex_hash: &str,
+        data: &[u8],
+    ) -> Result<()> {
+        let path = format!("{QUARANTINE_DIRECTORY}/{hex_hash}");
+        info!("Quarantining infected blob to {path}");
+
+        let storage = ctx.storage();
+        if storage.head(&path).await?.is_none() {
+            let mime = ctx.mime().get_mime_type(data.to_vec()).await?;
+            storage.put(&path, data, &mime).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Records the result of scanning a blob, replacing any previous result.
+    async fn record_scan(
+        ctx: &ServiceContext<'_>,
+        hash: BlobHash,
+        scan: &ScanOutcome,
+        uploaded_by: Option<i64>,
+    ) -> Result<()> {
+        let signature = match scan {
+            ScanOutcome::Skipped => return Ok(()),
+            ScanOutcome::Clean => None,
+            ScanOutcome::Infected(signature) => Some(signature.clone()),
+        };
+
+        let txn = ctx.transaction();
+        let model = blob_scan::ActiveModel {
+            s3_hash: Set(hash.to_vec()),
+            scanned_at: Set(now()),
+            scanner: Set(str!(ctx.scanner().name())),
+            infected: Set(signature.is_some()),
+            signature: Set(signature),
+            uploaded_by: Set(uploaded_by),
+        };
+
+        // Upsert, since a blob may be scanned concurrently, by
+        // two uploads of the same file or an upload and a rescan.
+        BlobScan::insert(model)
+            .on_conflict(
+                OnConflict::column(blob_scan::Column::S3Hash)
+                    .update_columns([
+                        blob_scan::Column::ScannedAt,
+                        blob_scan::Column::Scanner,
+                        blob_scan::Column::Infected,
+                        blob_scan::Column::Signature,
+                        blob_scan::Column::UploadedBy,
+                    ])
+                    .to_owned(),
+            )
+            .exec(txn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Gets the latest scan result for a blob, if it has been scanned.
+    pub async fn get_scan(
+        ctx: &ServiceContext<'_>,
+        hash: BlobHash,
+    ) -> Result<Option<BlobScanModel>> {
+        let txn = ctx.transaction();
+        let scan = BlobScan::find_by_id(hash.to_vec()).one(txn).await?;
+        Ok(scan)
+    }
+
+    /// Checks that a blob has not been found to be infected.
+    ///
+    /// Infected blobs are kept until they are reviewed and hard deleted,
+    /// so this prevents them from being served in the meantime.
+    pub async fn check_not_infected(ctx: &ServiceContext<'_>, hash: &[u8]) -> Result<()> {
+        let txn = ctx.transaction();
+        let scan = BlobScan::find()
+            .filter(
+                Condition::all()
+                    .add(blob_scan::Column::S3Hash.eq(hash))
+                    .add(blob_scan::Column::Infected.eq(true)),
+            )
+            .one(txn)
+            .await?;
+
+        match scan {
+            None => Ok(()),
+            Some(BlobScanModel { signature, .. }) => {
+                warn!(
+                    "Blob {} is infected, refusing to serve it",
+                    blob_hash_to_hex(hash)
+                );
+                Err(Error::BlobInfected(signature.unwrap_or_default()))
+            }
+        }
+    }
+
+    /// Gets all blobs which were found to be infected, most recent first.
+    pub async fn get_infected_scans(
+        ctx: &ServiceContext<'_>,
+    ) -> Result<Vec<BlobScanModel>> {
+        let txn = ctx.transaction();
+        let scans = BlobScan::find()
+            .filter(blob_scan::Column::Infected.eq(true))
+            .order_by_desc(blob_scan::Column::ScannedAt)
+            .all(txn)
+            .await?;
+
+        Ok(scans)
+    }
+
+    /// Rescans a batch of stored file blobs, for use by the rescan job.
+    ///
+    /// Blobs are processed in hash order, starting after `last_hash`.
+    /// Infected blobs are quarantined and recorded, but not removed,
+    /// that is left for a hard deletion after review.
+    ///
+    /// # Returns
+    /// The hash to continue from, or `None` if all blobs have been rescanned.
+    pub async fn rescan_batch(
+        ctx: &ServiceContext<'_>,
+        last_hash: Option<BlobHash>,
+    ) -> Result<Option<BlobHash>> {
+        if matches!(ctx.scanner(), BlobScanner::Disabled) {
+            warn!("No malware scanner is configured, cannot rescan blobs");
+            return Ok(None);
+        }
+
+        info!(
+            "Rescanning stored blobs after {:?}",
+            last_hash.map(|hash| blob_hash_to_hex(&hash)),
+        );
+
+        let txn = ctx.transaction();
+        let mut condition = Condition::all()
+            .add(file_revision::Column::S3Hash.ne(EMPTY_BLOB_HASH.as_slice()));
+
+        if let Some(hash) = last_hash {
+            condition = condition.add(file_revision::Column::S3Hash.gt(hash.as_slice()));
+        }
+
+        let hashes: Vec<Vec<u8>> = FileRevision::find()
+            .select_only()
+            .column(file_revision::Column::S3Hash)
+            .distinct()
+            .filter(condition)
+            .order_by_asc(file_revision::Column::S3Hash)
+            .limit(RESCAN_BATCH_SIZE)
+            .into_tuple()
+            .all(txn)
+            .await?;
+
+        let mut last_hash = None;
+        for hash in &hashes {
+            let hash = slice_to_blob_hash(hash);
+            let hex_hash = blob_hash_to_hex(&hash);
+            last_hash = Some(hash);
+
+            let data = match ctx.storage().get(&hex_hash).await? {
+                Some(data) => data,
+                None => {
+                    warn!("Stored blob {hex_hash} is missing, cannot rescan");
+                    continue;
+                }
+            };
+
+            let scan = ctx.scanner().scan(&data).await?;
+            if let ScanOutcome::Infected(ref signature) = scan {
+                warn!("Stored blob {hex_hash} is infected with {signature}");
+                Self::quarantine(ctx, &hex_hash, &data).await?;
+            }
+
+            Self::record_scan(ctx, hash, &scan, None).await?;
+        }
+
+        if (hashes.len() as u64) < RESCAN_BATCH_SIZE {
+            info!("Finished rescanning stored blobs");
+            return Ok(None);
+        }
+
+        Ok(last_hash)
+    }
+
+    /// Computes perceptual hashes for a batch of stored images which lack one,
+    /// for use by the backfill job.
+    ///
+    /// This is needed for images uploaded before perceptual hashing was added,
+    /// so that near-duplicates of them can be found and blacklisted.
+    /// Blobs are processed in hash order, starting after `last_hash`.
+    ///
+    /// # Returns
+    /// The hash to continue from, or `None` if all images have been processed.
+    pub async fn backfill_perceptual_batch(
+        ctx: &ServiceContext<'_>,
+        last_hash: Option<BlobHash>,
+    ) -> Result<Option<BlobHash>> {
+        #[derive(Debug, FromQueryResult)]
+        struct Image {
+            s3_hash: Vec<u8>,
+            mime: String,
+        }
+
+        info!(
+            "Backfilling perceptual hashes for stored images after {:?}",
+            last_hash.map(|hash| blob_hash_to_hex(&hash)),
+        );
+
+        let txn = ctx.transaction();
+        let images = FileRevision::find()
+            .from_raw_sql(Statement::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                r#"
+                SELECT DISTINCT ON (s3_hash) s3_hash, mime
+                FROM file_revision AS revision
+                WHERE s3_hash > $1
+                AND mime LIKE 'image/%'
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM blob_perceptual_hash AS perceptual
+                    WHERE perceptual.s3_hash = revision.s3_hash
+                )
+                ORDER BY s3_hash
+                LIMIT $2
+                "#,
+                [
+                    Value::from(last_hash.unwrap_or(EMPTY_BLOB_HASH).to_vec()),
+                    Value::from(RESCAN_BATCH_SIZE),
+                ],
+            ))
+            .into_model::<Image>()
+            .all(txn)
+            .await?;
+
+        let mut last_hash = None;
+        for Image { s3_hash, mime } in &images {
+            let hash = slice_to_blob_hash(s3_hash);
+            let hex_hash = blob_hash_to_hex(&hash);
+            last_hash = Some(hash);
+
+            match ctx.storage().get(&hex_hash).await? {
+                Some(data) => {
+                    Self::store_perceptual_hash(ctx, hash, data, mime.clone()).await?;
+                }
+                None => warn!("Stored blob {hex_hash} is missing, cannot hash it"),
+            }
+        }
+
+        if (images.len() as u64) < RESCAN_BATCH_SIZE {
+            info!("Finished backfilling perceptual hashes");
+            return Ok(None);
+        }
+
+        Ok(last_hash)
+    }
+
     // Getters
 
     pub async fn get_optional(
@@ -810,15 +1712,9 @@ impl BlobService {
             return Ok(Some(Vec::new()));
         }
 
-        // Retrieve blob from S3
-        let bucket = ctx.s3_bucket();
+        // Retrieve blob from storage
         let hex_hash = blob_hash_to_hex(hash);
-        let response = bucket.get_object(&hex_hash).await?;
-        match response.status_code() {
-            200 => Ok(Some(response.into())),
-            404 => Ok(None),
-            _ => s3_error(&response, "fetching S3 blob"),
-        }
+        ctx.storage().get(&hex_hash).await
     }
 
     #[inline]
@@ -840,28 +1736,20 @@ impl BlobService {
             }));
         }
 
-        // Retrieve metadata from S3
+        // Retrieve metadata from storage
         let hex_hash = blob_hash_to_hex(hash);
-        match Self::head(ctx, &hex_hash).await? {
-            None => Ok(None),
-            Some(result) => {
-                // Headers should be passed in
-                let size = result.content_length.ok_or(Error::S3Response)?;
-                let mime = result.content_type.ok_or(Error::S3Response)?;
-                let created_at = {
-                    let timestamp = result.last_modified.ok_or(Error::S3Response)?;
-
-                    OffsetDateTime::parse(&timestamp, &Rfc2822)
-                        .map_err(|_| Error::S3Response)?
-                };
-
-                Ok(Some(BlobMetadata {
-                    mime,
-                    size,
-                    created_at,
-                }))
-            }
-        }
+        let metadata = ctx.storage().head(&hex_hash).await?;
+        Ok(metadata.map(
+            |StorageMetadata {
+                 size,
+                 mime,
+                 created_at,
+             }| BlobMetadata {
+                mime,
+                size,
+                created_at,
+            },
+        ))
     }
 
     #[inline]
@@ -880,46 +1768,106 @@ impl BlobService {
             return Ok(true);
         }
 
-        // Fetch existence from S3
+        // Fetch existence from storage
         let hex_hash = blob_hash_to_hex(hash);
-        let result = Self::head(ctx, &hex_hash).await?;
+        let result = ctx.storage().head(&hex_hash).await?;
         Ok(result.is_some())
     }
 
-    /// Possibly retrieve blob contents, if a flag is set.
+    /// Creates a presigned URL to download a blob from the files domain.
     ///
-    /// This utility conditionally retrieves the
-    /// text given by the specified hash only
-    /// if the flag `should_fetch` is true.
-    /// Otherwise, it does no action, returning `None`.
-    pub async fn get_maybe(
+    /// The response's `Content-Type` is set from the stored MIME type, and
+    /// if a file name is given, it is used for the `Content-Disposition`.
+    ///
+    /// A generated URL is reused for half of its lifetime, so that repeated
+    /// requests for the same blob get the same URL, which clients can cache.
+    pub async fn get_download_url(
         ctx: &ServiceContext<'_>,
-        should_fetch: bool,
         hash: &[u8],
-    ) -> Result<Option<Vec<u8>>> {
-        if should_fetch {
-            let data = Self::get(ctx, hash).await?;
-            Ok(Some(data))
-        } else {
-            Ok(None)
+        mime: &str,
+        filename: Option<&str>,
+    ) -> Result<String> {
+        // Empty blobs are not actually stored
+        if hash == EMPTY_BLOB_HASH {
+            return Ok(format!("data:{},", mime.replace(' ', "")));
+        }
+
+        Self::check_not_infected(ctx, hash).await?;
+
+        let hex_hash = blob_hash_to_hex(&slice_to_blob_hash(hash));
+        let key = format!(
+            "blob-download:{hex_hash}:{mime}:{}",
+            filename.unwrap_or_default(),
+        );
+
+        let mut redis = ctx.redis_connect().await?;
+        let cached: Option<String> =
+            redis::cmd("GET").arg(&key).query_async(&mut redis).await?;
+
+        if let Some(url) = cached {
+            debug!("Reusing presigned download URL for blob {hex_hash}");
+            return Ok(url);
         }
+
+        debug!("Creating presigned download URL for blob {hex_hash}");
+
+        let expiry_secs = ctx.config().presigned_download_expiry_secs;
+        let url = Self::presign_download(ctx, &hex_hash, mime, filename).await?;
+
+        redis::cmd("SET")
+            .arg(&key)
+            .arg(&url)
+            .arg("EX")
+            .arg(expiry_secs / 2)
+            .query_async::<_, ()>(&mut redis)
+            .await?;
+
+        Ok(url)
     }
 
-    async fn head(
+    /// Creates a short-lived download URL for an object in storage.
+    ///
+    /// Unlike `get_download_url()`, this is not cached,
+    /// and can be used for objects other than blobs.
+    pub async fn presign_download(
         ctx: &ServiceContext<'_>,
         path: &str,
-    ) -> Result<Option<HeadObjectResult>> {
-        let bucket = ctx.s3_bucket();
-        let (result, status) = bucket.head_object(path).await?;
+        mime: &str,
+        filename: Option<&str>,
+    ) -> Result<String> {
+        let expiry_secs = ctx.config().presigned_download_expiry_secs;
+        let cache_control = format!("private, max-age={expiry_secs}, immutable");
+        let content_disposition = filename.map(content_disposition);
+        let headers = ResponseHeaders {
+            content_type: mime,
+            cache_control: &cache_control,
+            content_disposition: content_disposition.as_deref(),
+        };
 
-        match status {
-            200 | 204 => Ok(Some(result)),
-            404 => Ok(None),
-            _ => {
-                let response = ResponseData::new(Bytes::new(), status, HashMap::new());
-                s3_error(&response, "heading S3 blob")
-            }
-        }
+        ctx.storage().presign_get(path, expiry_secs, headers).await
+    }
+
+    /// Reads a chunk of a blob through a presigned download URL, for local storage.
+    pub async fn local_get(
+        ctx: &ServiceContext<'_>,
+        input: GetLocalBlob,
+    ) -> Result<GetLocalBlobOutput> {
+        Self::local_storage(ctx)?.serve_get(input).await
+    }
+
+    /// Writes a chunk of a blob through a presigned upload URL, for local storage.
+    pub async fn local_put(
+        ctx: &ServiceContext<'_>,
+        input: PutLocalBlob,
+    ) -> Result<PutLocalBlobOutput> {
+        Self::local_storage(ctx)?.serve_put(input).await
+    }
+
+    fn local_storage<'a>(ctx: &'a ServiceContext<'_>) -> Result<&'a LocalStorage> {
+        ctx.storage().as_local().ok_or_else(|| {
+            error!("Blob storage is not local, cannot serve presigned URLs");
+            Error::BadRequest
+        })
     }
 
     pub async fn hard_delete(ctx: &ServiceContext<'_>, hash: &[u8]) -> Result<()> {
@@ -932,41 +1880,84 @@ impl BlobService {
             return Ok(());
         }
 
-        // Delete from S3
-        let bucket = ctx.s3_bucket();
+        // Delete perceptual hash, if it's an image
+        let txn = ctx.transaction();
+        BlobPerceptualHash::delete_by_id(hash.to_vec())
+            .exec(txn)
+            .await?;
+
+        // Delete from storage
         let hex_hash = blob_hash_to_hex(hash);
+        ctx.storage().delete(&hex_hash).await
+    }
 
-        let response = bucket.delete_object(&hex_hash).await?;
-        match response.status_code() {
-            204 => Ok(()),
-            _ => s3_error(&response, "hard-deleting S3 blob"),
+    /// Hard deletes a blob, unless something still refers to it.
+    ///
+    /// Blobs are shared by hash, so one which is no longer needed in one
+    /// place may still be a file revision, avatar, thumbnail, or file
+    /// archive elsewhere.
+    ///
+    /// # Returns
+    /// Whether the blob was deleted.
+    pub async fn hard_delete_unused(
+        ctx: &ServiceContext<'_>,
+        hash: &[u8],
+    ) -> Result<bool> {
+        if Self::is_referenced(ctx, hash).await? {
+            debug!(
+                "Blob {} is still referenced, not deleting",
+                blob_hash_to_hex(hash),
+            );
+            return Ok(false);
         }
+
+        Self::hard_delete(ctx, hash).await?;
+        Ok(true)
     }
-}
 
-/// Helper method to parse out an S3 error response and print the message (if any).
-fn s3_error<T>(response: &ResponseData, action: &str) -> Result<T> {
-    let error_message = match str::from_utf8(response.bytes()) {
-        Ok("") => "(no content)",
-        Ok(m) => m,
-        Err(_) => "(invalid UTF-8)",
-    };
-
-    error!(
-        "Error while {} (HTTP {}): {}",
-        action,
-        response.status_code(),
-        error_message,
-    );
+    async fn is_referenced(ctx: &ServiceContext<'_>, hash: &[u8]) -> Result<bool> {
+        let txn = ctx.transaction();
+
+        let file_revisions = FileRevision::find()
+            .filter(file_revision::Column::S3Hash.eq(hash))
+            .count(txn)
+            .await?;
 
-    // TODO replace with S3 backend-specific error
-    Err(Error::S3Response)
+        let users = User::find()
+            .filter(user::Column::AvatarS3Hash.eq(hash))
+            .count(txn)
+            .await?;
+
+        let avatars = UserAvatar::find()
+            .filter(user_avatar::Column::S3Hash.eq(hash))
+            .count(txn)
+            .await?;
+
+        let thumbnails = BlobThumbnail::find()
+            .filter(blob_thumbnail::Column::S3Hash.eq(hash))
+            .count(txn)
+            .await?;
+
+        let file_archives = FileArchive::find()
+            .filter(file_archive::Column::ArchiveHash.eq(hash))
+            .count(txn)
+            .await?;
+
+        Ok(file_revisions + users + avatars + thumbnails + file_archives > 0)
+    }
 }
 
 #[derive(Debug)]
 enum HardDeleteInner {
-    Commit { s3_hash: BlobHash, user_id: i64 },
-    DryRun { s3_hash: BlobHash },
+    Commit {
+        s3_hash: BlobHash,
+        user_id: i64,
+        near_duplicates: bool,
+    },
+    DryRun {
+        s3_hash: BlobHash,
+        near_duplicates: bool,
+    },
 }
 
 #[derive(Debug)]
@@ -974,6 +1965,22 @@ struct PendingBlob {
     s3_path: String,
     expected_length: i64,
     moved_hash: Option<Vec<u8>>,
+    multipart_upload_id: Option<String>,
+}
+
+#[derive(Debug)]
+struct MultipartBlob {
+    s3_path: String,
+    upload_id: String,
+    etags: Vec<String>,
+}
+
+/// Determines how many parts a multipart upload of this size has.
+///
+/// There is always at least one part, even for empty blobs.
+fn multipart_part_count(blob_size: i64, part_size: i64) -> usize {
+    let count = (blob_size + part_size - 1) / part_size;
+    usize::try_from(count.max(1)).expect("Part count exceeds usize")
 }
 
 /// Helper struct to produce a count of items and a sample list.
@@ -1009,3 +2016,68 @@ impl<T: Hash + Ord + Eq> SamplerCounter<T> {
         (count, samples)
     }
 }
+
+/// Builds an inline `Content-Disposition` value with the given file name.
+///
+/// The plain `filename` parameter is an ASCII-only fallback, while
+/// `filename*` carries the full UTF-8 name, per RFC 6266.
+fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect();
+
+    let mut encoded = String::new();
+    for byte in filename.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'!'
+            | b'#'
+            | b'$'
+            | b'&'
+            | b'+'
+            | b'-'
+            | b'.'
+            | b'^'
+            | b'_'
+            | b'`'
+            | b'|'
+            | b'~' => encoded.push(char::from(byte)),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    format!("inline; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
+
+#[test]
+fn multipart_parts() {
+    const MIB: i64 = 1024 * 1024;
+
+    assert_eq!(multipart_part_count(0, 16 * MIB), 1);
+    assert_eq!(multipart_part_count(1, 16 * MIB), 1);
+    assert_eq!(multipart_part_count(16 * MIB, 16 * MIB), 1);
+    assert_eq!(multipart_part_count(16 * MIB + 1, 16 * MIB), 2);
+    assert_eq!(multipart_part_count(1024 * MIB, 16 * MIB), 64);
+}
+
+#[test]
+fn content_disposition_header() {
+    assert_eq!(
+        content_disposition("image.png"),
+        "inline; filename=\"image.png\"; filename*=UTF-8''image.png",
+    );
+    assert_eq!(
+        content_disposition("my \"file\".txt"),
+        "inline; filename=\"my _file_.txt\"; filename*=UTF-8''my%20%22file%22.txt",
+    );
+    assert_eq!(
+        content_disposition("日本.jpg"),
+        "inline; filename=\"__.jpg\"; filename*=UTF-8''%E6%97%A5%E6%9C%AC.jpg",
+    );
+}
@@ -0,0 +1,671 @@
+/*
+ * services/blob/storage/local.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Local filesystem storage for blobs.
+//!
+//! Objects are kept as regular files under the storage root, with their
+//! MIME type in a hidden file alongside them. Since the filesystem cannot
+//! serve presigned URLs itself, they instead point to the `/-/blob` route
+//! of the files domain, which Framerail passes back here to be verified
+//! and served.
+//!
+//! Because these requests go through JSON-RPC, objects are read and
+//! written in chunks of at most `LOCAL_CHUNK_SIZE` bytes.
+
+use super::prelude::*;
+use crate::types::Bytes;
+use crate::utils::assert_is_csprng;
+use rand::distributions::{Alphanumeric, DistString};
+use rand::{thread_rng, RngCore};
+use reqwest::Url;
+use ring::hmac;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// The largest chunk of an object which is read or written in one request.
+pub const LOCAL_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// The file in the storage root with the key used to sign URLs.
+const SIGNING_KEY_FILE: &str = ".signing-key";
+
+/// The directory in the storage root where multipart uploads are kept.
+const MULTIPART_DIRECTORY: &str = ".multipart";
+
+/// The MIME type of objects uploaded through presigned URLs.
+const UPLOAD_MIME: &str = "application/octet-stream";
+
+const EXPIRES_PARAM: &str = "expires";
+const SIGNATURE_PARAM: &str = "signature";
+const UPLOAD_ID_PARAM: &str = "upload-id";
+const PART_NUMBER_PARAM: &str = "part-number";
+const CONTENT_TYPE_PARAM: &str = "response-content-type";
+const CACHE_CONTROL_PARAM: &str = "response-cache-control";
+const CONTENT_DISPOSITION_PARAM: &str = "response-content-disposition";
+
+#[derive(Debug)]
+pub struct LocalStorage {
+    root: PathBuf,
+    base_url: Url,
+    key: hmac::Key,
+}
+
+impl LocalStorage {
+    pub async fn open(root: &Path, files_domain: &str) -> anyhow::Result<Self> {
+        fs::create_dir_all(root.join(MULTIPART_DIRECTORY)).await?;
+
+        let base_url = Url::parse(&format!("https://{files_domain}/-/blob/"))?;
+        let key = Self::load_key(&root.join(SIGNING_KEY_FILE)).await?;
+
+        Ok(LocalStorage {
+            root: root.to_path_buf(),
+            base_url,
+            key: hmac::Key::new(hmac::HMAC_SHA256, &key),
+        })
+    }
+
+    /// Reads the key used to sign URLs, creating it for a new storage root.
+    ///
+    /// The key is kept with the blobs instead of being generated on startup,
+    /// so that URLs stay valid across restarts, and between instances which
+    /// share the same storage root.
+    async fn load_key(path: &Path) -> io::Result<Vec<u8>> {
+        if let Some(key) = optional(fs::read(path).await)? {
+            return Ok(key);
+        }
+
+        info!("Generating new signing key for local storage");
+        let mut key = vec![0; 32];
+        let mut rng = thread_rng();
+        assert_is_csprng(&rng);
+        rng.fill_bytes(&mut key);
+        fs::write(path, &key).await?;
+        Ok(key)
+    }
+
+    // Paths
+
+    /// Gets the filesystem path of an object.
+    ///
+    /// Only paths of plain file names are permitted, so that they cannot
+    /// escape the storage root or refer to the hidden files used internally.
+    fn object_path(&self, path: &str) -> Result<PathBuf> {
+        let valid = path.split('/').all(|part| {
+            !part.is_empty()
+                && !part.starts_with('.')
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        });
+
+        if !valid {
+            error!("Invalid path for local storage: {path}");
+            return Err(Error::BadRequest);
+        }
+
+        Ok(self.root.join(path))
+    }
+
+    /// Gets the directory where the parts of a multipart upload are kept.
+    fn multipart_path(&self, upload_id: &str) -> Result<PathBuf> {
+        if upload_id.is_empty() || !upload_id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            error!("Invalid multipart upload ID for local storage: {upload_id}");
+            return Err(Error::BadRequest);
+        }
+
+        Ok(self.root.join(MULTIPART_DIRECTORY).join(upload_id))
+    }
+
+    /// Gets the path of a hidden file alongside an object.
+    fn hidden_path(path: &Path, suffix: &str) -> PathBuf {
+        let name = path
+            .file_name()
+            .expect("Object path has no file name")
+            .to_string_lossy();
+
+        path.with_file_name(format!(".{name}.{suffix}"))
+    }
+
+    /// Gets a unique path alongside an object to write it to before moving it into place.
+    fn temp_path(path: &Path) -> PathBuf {
+        let mut rng = thread_rng();
+        let suffix = Alphanumeric.sample_string(&mut rng, 16);
+        Self::hidden_path(path, &format!("{suffix}.tmp"))
+    }
+
+    /// Moves a completely written file into place as an object.
+    async fn commit(temp_path: &Path, path: &Path, mime: &str) -> Result<()> {
+        let mime_temp_path = Self::temp_path(path);
+        fs::write(&mime_temp_path, mime).await?;
+        fs::rename(&mime_temp_path, Self::hidden_path(path, "mime")).await?;
+        fs::rename(temp_path, path).await?;
+        Ok(())
+    }
+
+    async fn create_parent(path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        Ok(())
+    }
+
+    // Signing
+
+    /// Creates a signed URL for a request to the `/-/blob` route.
+    fn sign_url(
+        &self,
+        method: &str,
+        path: &str,
+        expiry_secs: u32,
+        mut params: BTreeMap<&str, String>,
+    ) -> Result<String> {
+        self.object_path(path)?;
+
+        let expires = now().unix_timestamp() + i64::from(expiry_secs);
+        params.insert(EXPIRES_PARAM, expires.to_string());
+
+        let tag = hmac::sign(&self.key, &signature_message(method, path, &params));
+        params.insert(SIGNATURE_PARAM, hex::encode(tag));
+
+        let mut url = self.base_url.join(path).map_err(|_| Error::BadRequest)?;
+        url.query_pairs_mut().extend_pairs(&params);
+        Ok(url.into())
+    }
+
+    /// Checks that a request to the `/-/blob` route was signed here, and has not expired.
+    fn verify(
+        &self,
+        method: &str,
+        path: &str,
+        query: &HashMap<String, String>,
+    ) -> Result<()> {
+        let signature = query
+            .get(SIGNATURE_PARAM)
+            .and_then(|signature| hex::decode(signature).ok())
+            .ok_or(Error::InvalidAuthentication)?;
+
+        let params = query
+            .iter()
+            .filter(|(key, _)| key.as_str() != SIGNATURE_PARAM)
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect::<BTreeMap<_, _>>();
+
+        if hmac::verify(
+            &self.key,
+            &signature_message(method, path, &params),
+            &signature,
+        )
+        .is_err()
+        {
+            warn!("Invalid signature for local storage {method} request to {path}");
+            return Err(Error::InvalidAuthentication);
+        }
+
+        let expires = params
+            .get(EXPIRES_PARAM)
+            .and_then(|expires| expires.parse::<i64>().ok())
+            .ok_or(Error::InvalidAuthentication)?;
+
+        if expires < now().unix_timestamp() {
+            warn!("Expired local storage {method} request to {path}");
+            return Err(Error::InvalidAuthentication);
+        }
+
+        Ok(())
+    }
+
+    // Serving presigned URLs
+
+    /// Reads a chunk of an object through a presigned download URL.
+    pub async fn serve_get(
+        &self,
+        GetLocalBlob {
+            path,
+            query,
+            offset,
+        }: GetLocalBlob,
+    ) -> Result<GetLocalBlobOutput> {
+        self.verify("GET", &path, &query)?;
+
+        let object_path = self.object_path(&path)?;
+        let mut file = match optional(File::open(&object_path).await)? {
+            Some(file) => file,
+            None => return Err(Error::BlobNotFound),
+        };
+
+        let total_size = file.metadata().await?.len();
+        let mut data = Vec::new();
+        file.seek(SeekFrom::Start(offset)).await?;
+        file.take(LOCAL_CHUNK_SIZE as u64)
+            .read_to_end(&mut data)
+            .await?;
+
+        let mut query = query;
+        Ok(GetLocalBlobOutput {
+            data: Bytes::from(data),
+            total_size,
+            content_type: query
+                .remove(CONTENT_TYPE_PARAM)
+                .unwrap_or_else(|| str!(UPLOAD_MIME)),
+            cache_control: query.remove(CACHE_CONTROL_PARAM),
+            content_disposition: query.remove(CONTENT_DISPOSITION_PARAM),
+        })
+    }
+
+    /// Writes a chunk of an object through a presigned upload URL.
+    ///
+    /// Chunks must be written in order, and the object is only moved into
+    /// place after the last one. For parts of a multipart upload, the ETag
+    /// needed to complete the upload is returned.
+    pub async fn serve_put(
+        &self,
+        PutLocalBlob {
+            path,
+            query,
+            offset,
+            data,
+            last,
+        }: PutLocalBlob,
+    ) -> Result<PutLocalBlobOutput> {
+        self.verify("PUT", &path, &query)?;
+
+        if data.len() > LOCAL_CHUNK_SIZE {
+            error!(
+                "Local storage upload chunk is too large ({} bytes)",
+                data.len()
+            );
+            return Err(Error::BadRequest);
+        }
+
+        let object_path = self.object_path(&path)?;
+        let (path, is_part) =
+            match (query.get(UPLOAD_ID_PARAM), query.get(PART_NUMBER_PARAM)) {
+                (Some(upload_id), Some(part_number)) => {
+                    let part_number: u32 =
+                        part_number.parse().map_err(|_| Error::BadRequest)?;
+
+                    let directory = self.multipart_path(upload_id)?;
+                    if !fs::try_exists(&directory).await? {
+                        error!(
+                            "No multipart upload with ID {upload_id} in local storage"
+                        );
+                        return Err(Error::BlobUploadExpired);
+                    }
+
+                    (directory.join(part_number.to_string()), true)
+                }
+                _ => {
+                    Self::create_parent(&object_path).await?;
+                    (object_path, false)
+                }
+            };
+
+        // Chunks are written to a hidden file until the upload is done
+        let upload_path = Self::hidden_path(&path, "upload");
+        let mut file = if offset == 0 {
+            File::create(&upload_path).await?
+        } else {
+            let file = OpenOptions::new().append(true).open(&upload_path).await;
+            let file = optional(file)?.ok_or(Error::BadRequest)?;
+            let length = file.metadata().await?.len();
+            if length != offset {
+                error!("Local storage upload chunk at offset {offset}, but {length} bytes were written");
+                return Err(Error::BadRequest);
+            }
+
+            file
+        };
+
+        file.write_all(data.as_ref()).await?;
+        file.flush().await?;
+
+        if !last {
+            return Ok(PutLocalBlobOutput { etag: None });
+        }
+
+        if is_part {
+            fs::rename(&upload_path, &path).await?;
+            let etag = part_etag(&fs::read(&path).await?);
+            Ok(PutLocalBlobOutput { etag: Some(etag) })
+        } else {
+            Self::commit(&upload_path, &path, UPLOAD_MIME).await?;
+            Ok(PutLocalBlobOutput { etag: None })
+        }
+    }
+}
+
+#[async_trait]
+impl BlobStorage for LocalStorage {
+    async fn head(&self, path: &str) -> Result<Option<StorageMetadata>> {
+        let path = self.object_path(path)?;
+        let metadata = match optional(fs::metadata(&path).await)? {
+            Some(metadata) => metadata,
+            None => return Ok(None),
+        };
+
+        let mime = optional(fs::read_to_string(Self::hidden_path(&path, "mime")).await)?
+            .unwrap_or_else(|| str!(UPLOAD_MIME));
+
+        Ok(Some(StorageMetadata {
+            size: i64::try_from(metadata.len()).map_err(|_| Error::StorageResponse)?,
+            mime,
+            created_at: OffsetDateTime::from(metadata.modified()?),
+        }))
+    }
+
+    async fn get(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.object_path(path)?;
+        let data = optional(fs::read(&path).await)?;
+        Ok(data)
+    }
+
+    async fn put(&self, path: &str, data: &[u8], mime: &str) -> Result<()> {
+        let path = self.object_path(path)?;
+        let temp_path = Self::temp_path(&path);
+        Self::create_parent(&path).await?;
+        fs::write(&temp_path, data).await?;
+        Self::commit(&temp_path, &path, mime).await
+    }
+
+    async fn put_file(&self, path: &str, file: &Path, mime: &str) -> Result<()> {
+        let path = self.object_path(path)?;
+        let temp_path = Self::temp_path(&path);
+        Self::create_parent(&path).await?;
+        fs::copy(file, &temp_path).await?;
+        Self::commit(&temp_path, &path, mime).await
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        let path = self.object_path(path)?;
+        optional(fs::remove_file(&path).await)?;
+        optional(fs::remove_file(Self::hidden_path(&path, "mime")).await)?;
+        Ok(())
+    }
+
+    async fn presign_put(&self, path: &str, expiry_secs: u32) -> Result<String> {
+        self.sign_url("PUT", path, expiry_secs, BTreeMap::new())
+    }
+
+    async fn presign_get(
+        &self,
+        path: &str,
+        expiry_secs: u32,
+        headers: ResponseHeaders<'_>,
+    ) -> Result<String> {
+        let mut params = BTreeMap::new();
+        params.insert(CONTENT_TYPE_PARAM, str!(headers.content_type));
+        params.insert(CACHE_CONTROL_PARAM, str!(headers.cache_control));
+
+        if let Some(content_disposition) = headers.content_disposition {
+            params.insert(CONTENT_DISPOSITION_PARAM, str!(content_disposition));
+        }
+
+        self.sign_url("GET", path, expiry_secs, params)
+    }
+
+    async fn start_multipart(&self, path: &str) -> Result<String> {
+        self.object_path(path)?;
+
+        let upload_id = Alphanumeric.sample_string(&mut thread_rng(), 32);
+        fs::create_dir_all(self.multipart_path(&upload_id)?).await?;
+        Ok(upload_id)
+    }
+
+    async fn presign_part(
+        &self,
+        path: &str,
+        upload_id: &str,
+        part_number: u32,
+        expiry_secs: u32,
+    ) -> Result<String> {
+        let mut params = BTreeMap::new();
+        params.insert(UPLOAD_ID_PARAM, str!(upload_id));
+        params.insert(PART_NUMBER_PARAM, part_number.to_string());
+        self.sign_url("PUT", path, expiry_secs, params)
+    }
+
+    async fn complete_multipart(
+        &self,
+        path: &str,
+        upload_id: &str,
+        etags: Vec<String>,
+    ) -> Result<()> {
+        let path = self.object_path(path)?;
+        let directory = self.multipart_path(upload_id)?;
+        let temp_path = Self::temp_path(&path);
+        Self::create_parent(&path).await?;
+
+        let mut file = File::create(&temp_path).await?;
+        for (index, etag) in etags.iter().enumerate() {
+            let part_number = index + 1;
+            let part = optional(fs::read(directory.join(part_number.to_string())).await)?;
+            let part = match part {
+                Some(part) if part_etag(&part) == *etag => part,
+                _ => {
+                    error!("Part {part_number} of multipart upload is missing or has the wrong ETag");
+                    drop(file);
+                    fs::remove_file(&temp_path).await?;
+                    return Err(Error::BlobPartInvalid);
+                }
+            };
+
+            file.write_all(&part).await?;
+        }
+
+        file.flush().await?;
+        Self::commit(&temp_path, &path, UPLOAD_MIME).await?;
+        fs::remove_dir_all(&directory).await?;
+        Ok(())
+    }
+
+    async fn abort_multipart(&self, _path: &str, upload_id: &str) -> Result<()> {
+        let directory = self.multipart_path(upload_id)?;
+        optional(fs::remove_dir_all(&directory).await)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn as_local(&self) -> Option<&LocalStorage> {
+        Some(self)
+    }
+}
+
+/// Converts an I/O error for a file which does not exist into `None`.
+fn optional<T>(result: io::Result<T>) -> io::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Produces the message which is signed for a request.
+///
+/// It is serialized as JSON so that the request method,
+/// path, and each parameter are unambiguously separated.
+fn signature_message<V: AsRef<str>>(
+    method: &str,
+    path: &str,
+    params: &BTreeMap<&str, V>,
+) -> Vec<u8> {
+    let params = params
+        .iter()
+        .map(|(key, value)| (*key, value.as_ref()))
+        .collect::<Vec<_>>();
+
+    serde_json::to_vec(&(method, path, params))
+        .expect("Unable to serialize signature message")
+}
+
+/// Gets the ETag of an uploaded part, which is used to check it when the upload is completed.
+fn part_etag(data: &[u8]) -> String {
+    format!("\"{}\"", hex::encode(&sha512_hash(data)[..16]))
+}
+
+#[tokio::test]
+async fn local_objects() {
+    let directory = tempfile::tempdir().expect("Unable to create temporary directory");
+    let storage = LocalStorage::open(directory.path(), "files.example.com")
+        .await
+        .expect("Unable to open local storage");
+
+    assert!(storage.get("abc").await.unwrap().is_none());
+    assert!(storage.head("abc").await.unwrap().is_none());
+
+    storage.put("abc", b"apple", "text/plain").await.unwrap();
+    let metadata = storage
+        .head("abc")
+        .await
+        .unwrap()
+        .expect("Object not found");
+    assert_eq!(metadata.size, 5);
+    assert_eq!(metadata.mime, "text/plain");
+    assert_eq!(storage.get("abc").await.unwrap().unwrap(), b"apple");
+
+    storage.delete("abc").await.unwrap();
+    storage.delete("abc").await.unwrap();
+    assert!(storage.get("abc").await.unwrap().is_none());
+
+    assert!(storage.get("../abc").await.is_err());
+    assert!(storage.get(".signing-key").await.is_err());
+    assert!(storage.get("uploads//abc").await.is_err());
+}
+
+#[tokio::test]
+async fn local_presigned_urls() {
+    let directory = tempfile::tempdir().expect("Unable to create temporary directory");
+    let storage = LocalStorage::open(directory.path(), "files.example.com")
+        .await
+        .expect("Unable to open local storage");
+
+    // Split a URL into the path and query passed from the route
+    fn request(url: &str) -> (String, HashMap<String, String>) {
+        let url = Url::parse(url).expect("Invalid presigned URL");
+        let path = url
+            .path()
+            .strip_prefix("/-/blob/")
+            .expect("Presigned URL not for blob route");
+
+        (str!(path), url.query_pairs().into_owned().collect())
+    }
+
+    macro_rules! put {
+        ($url:expr, $offset:expr, $data:expr, $last:expr $(,)?) => {{
+            let (path, query) = request(&$url);
+            storage
+                .serve_put(PutLocalBlob {
+                    path,
+                    query,
+                    offset: $offset,
+                    data: Bytes::from($data.to_vec()),
+                    last: $last,
+                })
+                .await
+        }};
+    }
+
+    // Upload in chunks
+    let url = storage.presign_put("uploads/xyz", 60).await.unwrap();
+    put!(url, 0, b"apple ", false).unwrap();
+    assert!(storage.get("uploads/xyz").await.unwrap().is_none());
+    assert!(put!(url, 3, b"banana", true).is_err());
+    put!(url, 6, b"banana", true).unwrap();
+    assert_eq!(
+        storage.get("uploads/xyz").await.unwrap().unwrap(),
+        b"apple banana"
+    );
+
+    // Tampered request
+    let (_, query) = request(&url);
+    let result = storage
+        .serve_put(PutLocalBlob {
+            path: str!("uploads/other"),
+            query,
+            offset: 0,
+            data: Bytes::from(b"cherry".to_vec()),
+            last: true,
+        })
+        .await;
+    assert!(matches!(result, Err(Error::InvalidAuthentication)));
+
+    // Download
+    let headers = ResponseHeaders {
+        content_type: "text/plain",
+        cache_control: "private",
+        content_disposition: Some("inline"),
+    };
+    let url = storage
+        .presign_get("uploads/xyz", 60, headers)
+        .await
+        .unwrap();
+    let (path, mut query) = request(&url);
+    let output = storage
+        .serve_get(GetLocalBlob {
+            path: path.clone(),
+            query: query.clone(),
+            offset: 6,
+        })
+        .await
+        .unwrap();
+    assert_eq!(output.data.as_ref(), b"banana");
+    assert_eq!(output.total_size, 12);
+    assert_eq!(output.content_type, "text/plain");
+    assert_eq!(output.content_disposition.as_deref(), Some("inline"));
+
+    query.insert(str!(CONTENT_TYPE_PARAM), str!("text/html"));
+    let result = storage
+        .serve_get(GetLocalBlob {
+            path,
+            query,
+            offset: 0,
+        })
+        .await;
+    assert!(matches!(result, Err(Error::InvalidAuthentication)));
+
+    // Multipart upload
+    let upload_id = storage.start_multipart("uploads/parts").await.unwrap();
+    let mut etags = Vec::new();
+    for (part_number, data) in [(1, b"first "), (2, b"second")] {
+        let url = storage
+            .presign_part("uploads/parts", &upload_id, part_number, 60)
+            .await
+            .unwrap();
+        let output = put!(url, 0, data, true).unwrap();
+        etags.push(output.etag.expect("No ETag for part"));
+    }
+
+    let result = storage
+        .complete_multipart("uploads/parts", &upload_id, vec![str!("\"x\""); 2])
+        .await;
+    assert!(matches!(result, Err(Error::BlobPartInvalid)));
+
+    storage
+        .complete_multipart("uploads/parts", &upload_id, etags)
+        .await
+        .unwrap();
+    assert_eq!(
+        storage.get("uploads/parts").await.unwrap().unwrap(),
+        b"first second"
+    );
+}
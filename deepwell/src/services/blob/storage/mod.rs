@@ -0,0 +1,124 @@
+/*
+ * services/blob/storage/mod.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Backends for where blobs are actually stored.
+//!
+//! Paths given to a backend are relative to its root, and are either
+//! the hexadecimal hash of a blob, a pending upload in `PRESIGN_DIRECTORY`,
+//! an infected upload in `QUARANTINE_DIRECTORY`, or an export archive.
+//!
+//! Uploads and downloads do not go through DEEPWELL, instead the client is
+//! given a presigned URL which it uses directly. For S3 this is the usual
+//! presigned request, and for local storage it is a signed URL to the
+//! `/-/blob` route of the files domain.
+
+use super::prelude;
+
+mod local;
+mod s3;
+
+pub use self::local::{LocalStorage, LOCAL_CHUNK_SIZE};
+pub use self::s3::S3Storage;
+
+use self::prelude::*;
+use std::fmt::Debug;
+use std::path::Path;
+use time::OffsetDateTime;
+
+/// Metadata about a stored object.
+#[derive(Debug, Clone)]
+pub struct StorageMetadata {
+    pub size: i64,
+    pub mime: String,
+    pub created_at: OffsetDateTime,
+}
+
+/// Headers to override in the response to a presigned download.
+#[derive(Debug, Clone)]
+pub struct ResponseHeaders<'a> {
+    pub content_type: &'a str,
+    pub cache_control: &'a str,
+    pub content_disposition: Option<&'a str>,
+}
+
+#[async_trait]
+pub trait BlobStorage: Debug + Send + Sync {
+    /// Gets the metadata of an object, if it exists.
+    async fn head(&self, path: &str) -> Result<Option<StorageMetadata>>;
+
+    /// Gets the contents of an object, if it exists.
+    async fn get(&self, path: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Creates or replaces an object.
+    async fn put(&self, path: &str, data: &[u8], mime: &str) -> Result<()>;
+
+    /// Creates or replaces an object with the contents of a file.
+    ///
+    /// This is meant for large objects, which should not be read into memory.
+    async fn put_file(&self, path: &str, file: &Path, mime: &str) -> Result<()>;
+
+    /// Deletes an object. Deleting an object which does not exist is not an error.
+    async fn delete(&self, path: &str) -> Result<()>;
+
+    /// Creates a URL which the object can be uploaded to, until it expires.
+    async fn presign_put(&self, path: &str, expiry_secs: u32) -> Result<String>;
+
+    /// Creates a URL which the object can be downloaded from, until it expires.
+    async fn presign_get(
+        &self,
+        path: &str,
+        expiry_secs: u32,
+        headers: ResponseHeaders<'_>,
+    ) -> Result<String>;
+
+    /// Starts a multipart upload for an object, returning its upload ID.
+    async fn start_multipart(&self, path: &str) -> Result<String>;
+
+    /// Creates a URL which one part of a multipart upload can be uploaded to.
+    ///
+    /// The response to the upload has an `ETag` header, which is needed
+    /// to complete the upload.
+    async fn presign_part(
+        &self,
+        path: &str,
+        upload_id: &str,
+        part_number: u32,
+        expiry_secs: u32,
+    ) -> Result<String>;
+
+    /// Assembles the parts of a multipart upload into the object.
+    ///
+    /// The ETags are in order of part number, starting at 1.
+    async fn complete_multipart(
+        &self,
+        path: &str,
+        upload_id: &str,
+        etags: Vec<String>,
+    ) -> Result<()>;
+
+    /// Discards a multipart upload and any parts uploaded so far.
+    async fn abort_multipart(&self, path: &str, upload_id: &str) -> Result<()>;
+
+    /// Gets this backend as local storage, which serves its own presigned URLs.
+    #[inline]
+    fn as_local(&self) -> Option<&LocalStorage> {
+        None
+    }
+}
@@ -0,0 +1,263 @@
+/*
+ * services/blob/storage/s3.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use crate::config::S3Secrets;
+use bytes::Bytes;
+use s3::bucket::Bucket;
+use s3::error::S3Error;
+use s3::region::Region;
+use s3::request::request_trait::ResponseData;
+use s3::serde_types::Part;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str;
+use std::time::Duration;
+use time::format_description::well_known::Rfc2822;
+use time::OffsetDateTime;
+
+#[derive(Debug)]
+pub struct S3Storage {
+    bucket: Box<Bucket>,
+    files_bucket: Box<Bucket>,
+}
+
+impl S3Storage {
+    pub fn new(secrets: &S3Secrets, files_domain: &str) -> StdResult<Self, S3Error> {
+        let bucket = {
+            let mut bucket = Bucket::new(
+                &secrets.bucket,
+                secrets.region.clone(),
+                secrets.credentials.clone(),
+            )?;
+
+            if secrets.path_style {
+                bucket = bucket.with_path_style();
+            }
+
+            bucket.request_timeout = Some(Duration::from_millis(500));
+            bucket
+        };
+
+        // Presigned downloads are served from the files domain, so they are signed
+        // with it as the host. The files domain proxies requests to the S3 endpoint.
+        let files_bucket = {
+            let region = Region::Custom {
+                region: secrets.region.to_string(),
+                endpoint: format!("https://{files_domain}"),
+            };
+
+            Bucket::new(&secrets.bucket, region, secrets.credentials.clone())?
+                .with_path_style()
+        };
+
+        Ok(S3Storage {
+            bucket,
+            files_bucket,
+        })
+    }
+}
+
+#[async_trait]
+impl BlobStorage for S3Storage {
+    async fn head(&self, path: &str) -> Result<Option<StorageMetadata>> {
+        let (result, status) = self.bucket.head_object(path).await?;
+
+        match status {
+            200 | 204 => {
+                // Headers should be passed in
+                let size = result.content_length.ok_or(Error::StorageResponse)?;
+                let mime = result.content_type.ok_or(Error::StorageResponse)?;
+                let created_at = {
+                    let timestamp = result.last_modified.ok_or(Error::StorageResponse)?;
+
+                    OffsetDateTime::parse(&timestamp, &Rfc2822)
+                        .map_err(|_| Error::StorageResponse)?
+                };
+
+                Ok(Some(StorageMetadata {
+                    size,
+                    mime,
+                    created_at,
+                }))
+            }
+            404 => Ok(None),
+            _ => {
+                let response = ResponseData::new(Bytes::new(), status, HashMap::new());
+                s3_error(&response, "heading S3 object")
+            }
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        let response = self.bucket.get_object(path).await?;
+        match response.status_code() {
+            200 => Ok(Some(response.into())),
+            404 => Ok(None),
+            _ => s3_error(&response, "fetching S3 object"),
+        }
+    }
+
+    async fn put(&self, path: &str, data: &[u8], mime: &str) -> Result<()> {
+        let response = self
+            .bucket
+            .put_object_with_content_type(path, data, mime)
+            .await?;
+
+        // We assume all unexpected statuses are errors, even if 1XX or 2XX
+        match response.status_code() {
+            200 => Ok(()),
+            _ => s3_error(&response, "creating S3 object"),
+        }
+    }
+
+    async fn put_file(&self, path: &str, file: &Path, mime: &str) -> Result<()> {
+        // The bucket's usual timeout is meant for small requests,
+        // and isn't long enough for large files like site archives.
+        let mut bucket = self.bucket.clone();
+        bucket.request_timeout = None;
+
+        let mut file = tokio::fs::File::open(file).await?;
+        bucket
+            .put_object_stream_with_content_type(&mut file, path, mime)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        let response = self.bucket.delete_object(path).await?;
+        match response.status_code() {
+            204 => Ok(()),
+            _ => s3_error(&response, "deleting S3 object"),
+        }
+    }
+
+    async fn presign_put(&self, path: &str, expiry_secs: u32) -> Result<String> {
+        let url = self
+            .bucket
+            .presign_put(path, expiry_secs, None, None)
+            .await?;
+
+        Ok(url)
+    }
+
+    async fn presign_get(
+        &self,
+        path: &str,
+        expiry_secs: u32,
+        headers: ResponseHeaders<'_>,
+    ) -> Result<String> {
+        let mut queries = HashMap::new();
+        queries.insert(str!("response-content-type"), str!(headers.content_type));
+        queries.insert(str!("response-cache-control"), str!(headers.cache_control));
+
+        if let Some(content_disposition) = headers.content_disposition {
+            queries.insert(
+                str!("response-content-disposition"),
+                str!(content_disposition),
+            );
+        }
+
+        let url = self
+            .files_bucket
+            .presign_get(path, expiry_secs, Some(queries))
+            .await?;
+
+        Ok(url)
+    }
+
+    async fn start_multipart(&self, path: &str) -> Result<String> {
+        let response = self
+            .bucket
+            .initiate_multipart_upload(path, "application/octet-stream")
+            .await?;
+
+        Ok(response.upload_id)
+    }
+
+    async fn presign_part(
+        &self,
+        path: &str,
+        upload_id: &str,
+        part_number: u32,
+        expiry_secs: u32,
+    ) -> Result<String> {
+        let mut queries = HashMap::new();
+        queries.insert(str!("partNumber"), part_number.to_string());
+        queries.insert(str!("uploadId"), str!(upload_id));
+
+        let url = self
+            .bucket
+            .presign_put(path, expiry_secs, None, Some(queries))
+            .await?;
+
+        Ok(url)
+    }
+
+    async fn complete_multipart(
+        &self,
+        path: &str,
+        upload_id: &str,
+        etags: Vec<String>,
+    ) -> Result<()> {
+        let parts = etags
+            .into_iter()
+            .enumerate()
+            .map(|(index, etag)| Part {
+                part_number: u32::try_from(index + 1).expect("Part number exceeds u32"),
+                etag,
+            })
+            .collect();
+
+        let response = self
+            .bucket
+            .complete_multipart_upload(path, upload_id, parts)
+            .await?;
+
+        match response.status_code() {
+            200 => Ok(()),
+            _ => s3_error(&response, "completing multipart upload"),
+        }
+    }
+
+    async fn abort_multipart(&self, path: &str, upload_id: &str) -> Result<()> {
+        self.bucket.abort_upload(path, upload_id).await?;
+        Ok(())
+    }
+}
+
+/// Helper method to parse out an S3 error response and print the message (if any).
+fn s3_error<T>(response: &ResponseData, action: &str) -> Result<T> {
+    let error_message = match str::from_utf8(response.bytes()) {
+        Ok("") => "(no content)",
+        Ok(m) => m,
+        Err(_) => "(invalid UTF-8)",
+    };
+
+    error!(
+        "Error while {} (HTTP {}): {}",
+        action,
+        response.status_code(),
+        error_message,
+    );
+
+    Err(Error::StorageResponse)
+}
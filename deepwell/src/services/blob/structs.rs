@@ -21,6 +21,7 @@
 use super::prelude::*;
 use crate::hash::BlobHash;
 use crate::types::Bytes;
+use std::collections::HashMap;
 use time::OffsetDateTime;
 
 #[derive(Deserialize, Debug, Clone)]
@@ -38,12 +39,47 @@ pub struct StartBlobUploadOutput {
     pub expires_at: OffsetDateTime,
 }
 
+#[derive(Serialize, Debug, Clone)]
+pub struct StartMultipartBlobUploadOutput {
+    pub pending_blob_id: String,
+    pub part_size: i64,
+    pub part_count: usize,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetBlobUploadPart {
+    pub user_id: i64,
+    pub pending_blob_id: String,
+    pub part_number: u32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GetBlobUploadPartOutput {
+    pub presign_url: String,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FinishBlobUploadPart {
+    pub user_id: i64,
+    pub pending_blob_id: String,
+    pub part_number: u32,
+    pub etag: String,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct CancelBlobUpload {
     pub user_id: i64,
     pub pending_blob_id: String,
 }
 
+pub type CompleteMultipartBlobUpload = CancelBlobUpload;
+
 #[derive(Debug)]
 pub struct FinalizeBlobUploadOutput {
     pub s3_hash: BlobHash,
@@ -56,10 +92,15 @@ pub struct FinalizeBlobUploadOutput {
 pub struct HardDelete {
     pub s3_hash: Bytes<'static>,
     pub user_id: i64,
+
+    /// Whether to also delete and blacklist perceptually similar images.
+    #[serde(default)]
+    pub near_duplicates: bool,
 }
 
 #[derive(Serialize, Debug, Clone)]
 pub struct HardDeleteOutput {
+    pub s3_hashes: Vec<String>,
     pub total_revisions: usize,
     pub total_files: usize,
     pub total_files_deleted: usize,
@@ -82,10 +123,44 @@ pub struct BlobMetadata {
 
 #[derive(Serialize, Debug, Clone)]
 pub struct GetBlobOutput {
-    pub data: Vec<u8>,
+    pub url: String,
     pub mime: String,
     pub size: i64,
 
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
 }
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetLocalBlob {
+    pub path: String,
+    pub query: HashMap<String, String>,
+
+    #[serde(default)]
+    pub offset: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GetLocalBlobOutput {
+    pub data: Bytes<'static>,
+    pub total_size: u64,
+    pub content_type: String,
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PutLocalBlob {
+    pub path: String,
+    pub query: HashMap<String, String>,
+
+    #[serde(default)]
+    pub offset: u64,
+    pub data: Bytes<'static>,
+    pub last: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PutLocalBlobOutput {
+    pub etag: Option<String>,
+}
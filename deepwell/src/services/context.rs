@@ -21,11 +21,11 @@
 use crate::api::ServerState;
 use crate::config::Config;
 use crate::locales::Localizations;
-use crate::services::blob::MimeAnalyzer;
+use crate::services::blob::{BlobScanner, BlobStorage, MimeAnalyzer};
 use crate::services::error::Result;
+use crate::services::oidc::OidcClient;
 use redis::aio::MultiplexedConnection as RedisMultiplexedConnection;
 use rsmq_async::PooledRsmq;
-use s3::bucket::Bucket;
 use sea_orm::DatabaseTransaction;
 use std::sync::Arc;
 
@@ -88,8 +88,18 @@ impl<'txn> ServiceContext<'txn> {
     }
 
     #[inline]
-    pub fn s3_bucket(&self) -> &Bucket {
-        &self.state.s3_bucket
+    pub fn oidc(&self) -> &OidcClient {
+        &self.state.oidc
+    }
+
+    #[inline]
+    pub fn scanner(&self) -> &BlobScanner {
+        &self.state.scanner
+    }
+
+    #[inline]
+    pub fn storage(&self) -> &dyn BlobStorage {
+        self.state.storage.as_ref()
     }
 
     #[inline]
@@ -0,0 +1,259 @@
+/*
+ * services/contribution/feed.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Rendering of revision listings as syndication feeds.
+//!
+//! Both Atom and RSS 2.0 are supported, so that watchers and bots
+//! can subscribe to changes on a site.
+
+use crate::services::{Result, StdResult};
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::Writer;
+use time::format_description::well_known::{Rfc2822, Rfc3339};
+use time::OffsetDateTime;
+
+/// The most entries a feed may contain, regardless of the requested limit.
+pub const MAXIMUM_FEED_ENTRIES: u64 = 200;
+
+/// Which syndication format to produce.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FeedFormat {
+    Atom,
+    Rss,
+}
+
+impl FeedFormat {
+    pub fn mime(self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "application/atom+xml",
+            FeedFormat::Rss => "application/rss+xml",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedInfo {
+    pub title: String,
+    pub subtitle: String,
+    pub id: String,
+    pub link: String,
+    pub updated: OffsetDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedEntry {
+    pub id: String,
+    pub title: String,
+    pub link: String,
+    pub author: Option<String>,
+    pub summary: Option<String>,
+    pub updated: OffsetDateTime,
+}
+
+pub fn render_feed(
+    format: FeedFormat,
+    info: &FeedInfo,
+    entries: &[FeedEntry],
+) -> Result<String> {
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
+
+    match format {
+        FeedFormat::Atom => render_atom(&mut writer, info, entries)?,
+        FeedFormat::Rss => render_rss(&mut writer, info, entries)?,
+    }
+
+    let body = String::from_utf8(writer.into_inner())
+        .expect("Feed XML built from strings is not valid UTF-8");
+
+    Ok(body)
+}
+
+fn render_atom(
+    writer: &mut Writer<Vec<u8>>,
+    info: &FeedInfo,
+    entries: &[FeedEntry],
+) -> Result<()> {
+    writer
+        .create_element("feed")
+        .with_attribute(("xmlns", "http://www.w3.org/2005/Atom"))
+        .write_inner_content::<_, quick_xml::Error>(|writer| {
+            text_element(writer, "title", &info.title)?;
+            text_element(writer, "subtitle", &info.subtitle)?;
+            text_element(writer, "id", &info.id)?;
+            writer
+                .create_element("link")
+                .with_attribute(("href", info.link.as_str()))
+                .write_empty()?;
+            text_element(writer, "updated", &format_rfc3339(info.updated))?;
+
+            for entry in entries {
+                writer
+                    .create_element("entry")
+                    .write_inner_content::<_, quick_xml::Error>(|writer| {
+                        text_element(writer, "title", &entry.title)?;
+                        text_element(writer, "id", &entry.id)?;
+                        writer
+                            .create_element("link")
+                            .with_attribute(("href", entry.link.as_str()))
+                            .write_empty()?;
+                        text_element(writer, "updated", &format_rfc3339(entry.updated))?;
+
+                        if let Some(ref author) = entry.author {
+                            writer
+                                .create_element("author")
+                                .write_inner_content::<_, quick_xml::Error>(|writer| {
+                                    text_element(writer, "name", author)
+                                })?;
+                        }
+
+                        if let Some(ref summary) = entry.summary {
+                            text_element(writer, "summary", summary)?;
+                        }
+
+                        Ok(())
+                    })?;
+            }
+
+            Ok(())
+        })?;
+
+    Ok(())
+}
+
+fn render_rss(
+    writer: &mut Writer<Vec<u8>>,
+    info: &FeedInfo,
+    entries: &[FeedEntry],
+) -> Result<()> {
+    writer
+        .create_element("rss")
+        .with_attribute(("version", "2.0"))
+        .with_attribute(("xmlns:dc", "http://purl.org/dc/elements/1.1/"))
+        .write_inner_content::<_, quick_xml::Error>(|writer| {
+            writer
+                .create_element("channel")
+                .write_inner_content::<_, quick_xml::Error>(|writer| {
+                    text_element(writer, "title", &info.title)?;
+                    text_element(writer, "link", &info.link)?;
+                    text_element(writer, "description", &info.subtitle)?;
+                    text_element(writer, "lastBuildDate", &format_rfc2822(info.updated))?;
+
+                    for entry in entries {
+                        writer
+                            .create_element("item")
+                            .write_inner_content::<_, quick_xml::Error>(|writer| {
+                                text_element(writer, "title", &entry.title)?;
+                                text_element(writer, "link", &entry.link)?;
+                                writer
+                                    .create_element("guid")
+                                    .with_attribute(("isPermaLink", "false"))
+                                    .write_text_content(BytesText::new(&entry.id))?;
+                                text_element(
+                                    writer,
+                                    "pubDate",
+                                    &format_rfc2822(entry.updated),
+                                )?;
+
+                                if let Some(ref author) = entry.author {
+                                    text_element(writer, "dc:creator", author)?;
+                                }
+
+                                if let Some(ref summary) = entry.summary {
+                                    text_element(writer, "description", summary)?;
+                                }
+
+                                Ok(())
+                            })?;
+                    }
+
+                    Ok(())
+                })?;
+
+            Ok(())
+        })?;
+
+    Ok(())
+}
+
+fn text_element(
+    writer: &mut Writer<Vec<u8>>,
+    name: &str,
+    text: &str,
+) -> StdResult<(), quick_xml::Error> {
+    writer
+        .create_element(name)
+        .write_text_content(BytesText::new(text))?;
+
+    Ok(())
+}
+
+fn format_rfc3339(timestamp: OffsetDateTime) -> String {
+    timestamp
+        .format(&Rfc3339)
+        .expect("Database timestamp cannot be formatted as RFC 3339")
+}
+
+fn format_rfc2822(timestamp: OffsetDateTime) -> String {
+    timestamp
+        .format(&Rfc2822)
+        .expect("Database timestamp cannot be formatted as RFC 2822")
+}
+
+#[test]
+fn feeds() {
+    let timestamp =
+        OffsetDateTime::from_unix_timestamp(1704164645).expect("Invalid timestamp");
+
+    let info = FeedInfo {
+        title: str!("Test Wiki - Recent changes"),
+        subtitle: str!("A wiki for <testing>"),
+        id: str!("https://test.wikijump.com/"),
+        link: str!("https://test.wikijump.com/"),
+        updated: timestamp,
+    };
+
+    let entries = [FeedEntry {
+        id: str!("https://test.wikijump.com/#page-revision-10"),
+        title: str!("Start & Finish (edited)"),
+        link: str!("https://test.wikijump.com/start"),
+        author: Some(str!("Alice")),
+        summary: None,
+        updated: timestamp,
+    }];
+
+    let atom = render_feed(FeedFormat::Atom, &info, &entries).expect("Unable to render");
+    assert!(atom.starts_with(r#"<?xml version="1.0" encoding="utf-8"?>"#));
+    assert!(atom.contains(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#));
+    assert!(atom.contains("<subtitle>A wiki for &lt;testing&gt;</subtitle>"));
+    assert!(atom.contains("<title>Start &amp; Finish (edited)</title>"));
+    assert!(atom.contains("<updated>2024-01-02T03:04:05Z</updated>"));
+    assert!(atom.contains("<name>Alice</name>"));
+    assert!(!atom.contains("<summary>"));
+
+    let rss = render_feed(FeedFormat::Rss, &info, &entries).expect("Unable to render");
+    assert!(rss.contains(r#"<rss version="2.0""#));
+    assert!(rss.contains("<pubDate>Tue, 02 Jan 2024 03:04:05 +0000</pubDate>"));
+    assert!(rss.contains(
+        r#"<guid isPermaLink="false">https://test.wikijump.com/#page-revision-10</guid>"#,
+    ));
+    assert!(rss.contains("<dc:creator>Alice</dc:creator>"));
+}
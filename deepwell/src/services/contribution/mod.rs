@@ -0,0 +1,39 @@
+/*
+ * services/contribution/mod.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The contribution service, for listing page and file revisions together.
+//!
+//! This pages through revisions newest first, either for a particular
+//! user (for profiles and moderation tools) or for a whole site (the
+//! "recent changes" listing, which can also be rendered as a feed).
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use super::super::prelude::*;
+    pub use super::structs::*;
+}
+
+mod feed;
+mod service;
+mod structs;
+
+pub use self::feed::FeedFormat;
+pub use self::service::ContributionService;
+pub use self::structs::*;
@@ -0,0 +1,523 @@
+/*
+ * services/contribution/service.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::feed::{render_feed, FeedEntry, FeedInfo, MAXIMUM_FEED_ENTRIES};
+use super::prelude::*;
+use crate::models::file_revision::{self, Entity as FileRevision};
+use crate::models::page::{self, Entity as Page};
+use crate::models::page_revision::{self, Entity as PageRevision};
+use crate::models::sea_orm_active_enums::{FileRevisionType, PageRevisionType};
+use crate::models::user::{self, Entity as User};
+use crate::services::{
+    CategoryService, DomainService, FileRevisionService, PageRevisionService,
+    PageService, RelationService, SiteService, UserService,
+};
+use crate::types::{FileHiddenField, PageDetails, PageHiddenField};
+use sea_orm::sea_query::{Expr, Query};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use time::OffsetDateTime;
+
+#[derive(Debug)]
+pub struct ContributionService;
+
+impl ContributionService {
+    /// Lists a user's page and file revisions, newest first.
+    ///
+    /// Revisions where the user has been hidden are excluded unless the
+    /// viewer is staff of the site being listed, and all other hidden
+    /// fields are redacted as usual.
+    pub async fn get_user(
+        ctx: &ServiceContext<'_>,
+        GetUserContributions {
+            user,
+            site_id,
+            page_revision_types,
+            file_revision_types,
+            created_after,
+            created_before,
+            cursor,
+            limit,
+            viewer_user_id,
+        }: GetUserContributions<'_>,
+    ) -> Result<ContributionsOutput> {
+        let user_id = UserService::get_id(ctx, user).await?;
+        let bypass_hidden = match site_id {
+            Some(site_id) => Self::is_staff(ctx, site_id, viewer_user_id).await?,
+            None => false,
+        };
+
+        Self::list(
+            ctx,
+            ContributionQuery {
+                user_id: Some(user_id),
+                site_id,
+                category_id: None,
+                page_revision_types,
+                file_revision_types,
+                created_after,
+                created_before,
+                cursor,
+                limit,
+                bypass_hidden,
+            },
+        )
+        .await
+    }
+
+    /// Lists the most recent page and file revisions on a site, newest first.
+    pub async fn get_site_recent(
+        ctx: &ServiceContext<'_>,
+        GetSiteRecentChanges {
+            site_id,
+            category,
+            user,
+            page_revision_types,
+            file_revision_types,
+            created_after,
+            created_before,
+            cursor,
+            limit,
+            viewer_user_id,
+        }: GetSiteRecentChanges<'_>,
+    ) -> Result<ContributionsOutput> {
+        let bypass_hidden = Self::is_staff(ctx, site_id, viewer_user_id).await?;
+
+        let category_id = match category {
+            None => None,
+            Some(reference) => {
+                let category = CategoryService::get(ctx, site_id, reference).await?;
+                Some(category.category_id)
+            }
+        };
+
+        let user_id = match user {
+            None => None,
+            Some(reference) => Some(UserService::get_id(ctx, reference).await?),
+        };
+
+        Self::list(
+            ctx,
+            ContributionQuery {
+                user_id,
+                site_id: Some(site_id),
+                category_id,
+                page_revision_types,
+                file_revision_types,
+                created_after,
+                created_before,
+                cursor,
+                limit,
+                bypass_hidden,
+            },
+        )
+        .await
+    }
+
+    /// Renders the most recent changes on a site as an Atom or RSS feed.
+    ///
+    /// Feeds are public, so hidden users are always excluded,
+    /// and at most `MAXIMUM_FEED_ENTRIES` entries are included.
+    pub async fn get_site_recent_feed(
+        ctx: &ServiceContext<'_>,
+        GetSiteRecentChangesFeed { mut input, format }: GetSiteRecentChangesFeed<'_>,
+    ) -> Result<SiteRecentChangesFeedOutput> {
+        input.limit = input.limit.clamp(1, MAXIMUM_FEED_ENTRIES);
+        input.viewer_user_id = None;
+
+        let site = SiteService::get(ctx, Reference::Id(input.site_id)).await?;
+        let domain = DomainService::domain_for_site(ctx.config(), &site);
+        let site_url = format!("https://{domain}/");
+        let ContributionsOutput { contributions, .. } =
+            Self::get_site_recent(ctx, input).await?;
+
+        // Look up page slugs and author names for all entries at once.
+        // Files use their page's current slug, as do pages with a hidden slug.
+        let txn = ctx.transaction();
+        let mut page_ids = Vec::new();
+        let mut user_ids = Vec::new();
+        for contribution in &contributions {
+            let (page_id, slug, user_id) = match contribution {
+                Contribution::Page(revision) => {
+                    (revision.page_id, revision.slug.as_ref(), revision.user_id)
+                }
+                Contribution::File(revision) => {
+                    (revision.page_id, None, revision.user_id)
+                }
+            };
+
+            if slug.is_none() {
+                page_ids.push(page_id);
+            }
+
+            user_ids.extend(user_id);
+        }
+
+        let slugs: HashMap<i64, String> = Page::find()
+            .select_only()
+            .column(page::Column::PageId)
+            .column(page::Column::Slug)
+            .filter(page::Column::PageId.is_in(page_ids))
+            .into_tuple()
+            .all(txn)
+            .await?
+            .into_iter()
+            .collect();
+
+        let names: HashMap<i64, String> = User::find()
+            .select_only()
+            .column(user::Column::UserId)
+            .column(user::Column::Name)
+            .filter(user::Column::UserId.is_in(user_ids))
+            .into_tuple()
+            .all(txn)
+            .await?
+            .into_iter()
+            .collect();
+
+        let entries = contributions
+            .into_iter()
+            .map(|contribution| {
+                Self::build_feed_entry(&site_url, &slugs, &names, contribution)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let info = FeedInfo {
+            title: format!("{} - Recent changes", site.name),
+            subtitle: site.tagline,
+            id: site_url.clone(),
+            link: site_url,
+            updated: entries
+                .first()
+                .map(|entry| entry.updated)
+                .unwrap_or_else(now),
+        };
+
+        Ok(SiteRecentChangesFeedOutput {
+            mime: format.mime(),
+            body: render_feed(format, &info, &entries)?,
+        })
+    }
+
+    fn build_feed_entry(
+        site_url: &str,
+        slugs: &HashMap<i64, String>,
+        names: &HashMap<i64, String>,
+        contribution: Contribution,
+    ) -> Result<FeedEntry> {
+        let (id, title, page_id, slug, user_id, comments, updated) = match contribution {
+            Contribution::Page(revision) => (
+                format!("{site_url}#page-revision-{}", revision.revision_id),
+                format!(
+                    "{} ({})",
+                    revision.title.as_deref().unwrap_or("[hidden]"),
+                    page_revision_type_name(revision.revision_type),
+                ),
+                revision.page_id,
+                revision.slug,
+                revision.user_id,
+                revision.comments,
+                revision.created_at,
+            ),
+            Contribution::File(revision) => (
+                format!("{site_url}#file-revision-{}", revision.revision_id),
+                format!(
+                    "File {} ({})",
+                    revision.name.as_deref().unwrap_or("[hidden]"),
+                    file_revision_type_name(revision.revision_type),
+                ),
+                revision.page_id,
+                None,
+                revision.user_id,
+                revision.comments,
+                revision.created_at,
+            ),
+        };
+
+        let slug = match slug {
+            Some(slug) => slug,
+            None => slugs.get(&page_id).ok_or(Error::PageNotFound)?.clone(),
+        };
+
+        let author = match user_id {
+            Some(user_id) => {
+                Some(names.get(&user_id).ok_or(Error::UserNotFound)?.clone())
+            }
+            None => None,
+        };
+
+        Ok(FeedEntry {
+            id,
+            title,
+            link: format!("{site_url}{slug}"),
+            author,
+            summary: comments,
+            updated,
+        })
+    }
+
+    async fn is_staff(
+        ctx: &ServiceContext<'_>,
+        site_id: i64,
+        viewer_user_id: Option<i64>,
+    ) -> Result<bool> {
+        match viewer_user_id {
+            Some(user_id) => RelationService::is_site_staff(ctx, site_id, user_id).await,
+            None => Ok(false),
+        }
+    }
+
+    /// Lists page and file revisions matching the query, newest first.
+    async fn list(
+        ctx: &ServiceContext<'_>,
+        ContributionQuery {
+            user_id,
+            site_id,
+            category_id,
+            page_revision_types,
+            file_revision_types,
+            created_after,
+            created_before,
+            cursor,
+            limit,
+            bypass_hidden,
+        }: ContributionQuery,
+    ) -> Result<ContributionsOutput> {
+        let txn = ctx.transaction();
+
+        // Fetch one more than needed from each table,
+        // so we can tell if there are more contributions after this batch.
+        let fetch_limit = limit.saturating_add(1);
+
+        // Only exclude revisions with a hidden user when listing by user,
+        // otherwise they are included with the user redacted.
+        let exclude_hidden_user = user_id.is_some() && !bypass_hidden;
+
+        // Pages in the given category, for filtering by category
+        let category_pages = |category_id| {
+            Query::select()
+                .column(page::Column::PageId)
+                .from(Page)
+                .and_where(page::Column::PageCategoryId.eq(category_id))
+                .to_owned()
+        };
+
+        let page_revisions = match page_revision_types {
+            Some(ref types) if types.is_empty() => vec![],
+            _ => {
+                PageRevision::find()
+                    .filter(
+                        Condition::all()
+                            .add_option(
+                                user_id.map(|id| page_revision::Column::UserId.eq(id)),
+                            )
+                            .add_option(
+                                site_id.map(|id| page_revision::Column::SiteId.eq(id)),
+                            )
+                            .add_option(category_id.map(|id| {
+                                page_revision::Column::PageId
+                                    .in_subquery(category_pages(id))
+                            }))
+                            .add_option(page_revision_types.map(|types| {
+                                page_revision::Column::RevisionType.is_in(types)
+                            }))
+                            .add_option(
+                                created_after
+                                    .map(|at| page_revision::Column::CreatedAt.gt(at)),
+                            )
+                            .add_option(
+                                created_before
+                                    .map(|at| page_revision::Column::CreatedAt.lt(at)),
+                            )
+                            .add_option(cursor.map(|cursor| {
+                                cursor_condition(
+                                    page_revision::Column::CreatedAt,
+                                    page_revision::Column::RevisionId,
+                                    ContributionKind::Page,
+                                    cursor,
+                                )
+                            }))
+                            .add_option(exclude_hidden_user.then(|| {
+                                Expr::cust_with_values(
+                                    "NOT ($1 = ANY(hidden))",
+                                    [PageHiddenField::User.name()],
+                                )
+                            })),
+                    )
+                    .order_by_desc(page_revision::Column::CreatedAt)
+                    .order_by_desc(page_revision::Column::RevisionId)
+                    .limit(fetch_limit)
+                    .all(txn)
+                    .await?
+            }
+        };
+
+        let file_revisions = match file_revision_types {
+            Some(ref types) if types.is_empty() => vec![],
+            _ => {
+                FileRevision::find()
+                    .filter(
+                        Condition::all()
+                            .add_option(
+                                user_id.map(|id| file_revision::Column::UserId.eq(id)),
+                            )
+                            .add_option(
+                                site_id.map(|id| file_revision::Column::SiteId.eq(id)),
+                            )
+                            .add_option(category_id.map(|id| {
+                                file_revision::Column::PageId
+                                    .in_subquery(category_pages(id))
+                            }))
+                            .add_option(file_revision_types.map(|types| {
+                                file_revision::Column::RevisionType.is_in(types)
+                            }))
+                            .add_option(
+                                created_after
+                                    .map(|at| file_revision::Column::CreatedAt.gt(at)),
+                            )
+                            .add_option(
+                                created_before
+                                    .map(|at| file_revision::Column::CreatedAt.lt(at)),
+                            )
+                            .add_option(cursor.map(|cursor| {
+                                cursor_condition(
+                                    file_revision::Column::CreatedAt,
+                                    file_revision::Column::RevisionId,
+                                    ContributionKind::File,
+                                    cursor,
+                                )
+                            }))
+                            .add_option(exclude_hidden_user.then(|| {
+                                Expr::cust_with_values(
+                                    "NOT ($1 = ANY(hidden))",
+                                    [FileHiddenField::User.name()],
+                                )
+                            })),
+                    )
+                    .order_by_desc(file_revision::Column::CreatedAt)
+                    .order_by_desc(file_revision::Column::RevisionId)
+                    .limit(fetch_limit)
+                    .all(txn)
+                    .await?
+            }
+        };
+
+        // Merge both lists, then cut down to the requested size
+        let mut contributions = Vec::new();
+
+        for revision in PageRevisionService::filter_and_populate_all(
+            ctx,
+            page_revisions,
+            PageDetails::default(),
+        )
+        .await?
+        {
+            contributions.push(Contribution::Page(revision));
+        }
+
+        for revision in FileRevisionService::filter_all(file_revisions)? {
+            contributions.push(Contribution::File(revision));
+        }
+
+        contributions
+            .sort_by_key(|contribution| std::cmp::Reverse(contribution.cursor()));
+
+        let next_cursor = if contributions.len() as u64 > limit {
+            contributions.truncate(limit as usize);
+            contributions.last().map(Contribution::cursor)
+        } else {
+            None
+        };
+
+        Ok(ContributionsOutput {
+            contributions,
+            next_cursor,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct ContributionQuery {
+    user_id: Option<i64>,
+    site_id: Option<i64>,
+    category_id: Option<i64>,
+    page_revision_types: Option<Vec<PageRevisionType>>,
+    file_revision_types: Option<Vec<FileRevisionType>>,
+    created_after: Option<OffsetDateTime>,
+    created_before: Option<OffsetDateTime>,
+    cursor: Option<ContributionCursor>,
+    limit: u64,
+    bypass_hidden: bool,
+}
+
+/// Builds the condition selecting revisions which come after the cursor.
+///
+/// Since the listing is newest first, this means revisions which
+/// are "less than" the cursor, see `ContributionCursor`.
+fn cursor_condition<C: ColumnTrait>(
+    created_at_column: C,
+    revision_id_column: C,
+    kind: ContributionKind,
+    cursor: ContributionCursor,
+) -> Condition {
+    let ContributionCursor {
+        created_at,
+        kind: cursor_kind,
+        revision_id,
+    } = cursor;
+
+    match kind.cmp(&cursor_kind) {
+        // All revisions of this kind at the cursor's timestamp come after it
+        Ordering::Less => Condition::all().add(created_at_column.lte(created_at)),
+
+        // Same kind, so compare IDs at the cursor's timestamp
+        Ordering::Equal => Condition::any().add(created_at_column.lt(created_at)).add(
+            Condition::all()
+                .add(created_at_column.eq(created_at))
+                .add(revision_id_column.lt(revision_id)),
+        ),
+
+        // All revisions of this kind at the cursor's timestamp come before it
+        Ordering::Greater => Condition::all().add(created_at_column.lt(created_at)),
+    }
+}
+
+fn page_revision_type_name(revision_type: PageRevisionType) -> &'static str {
+    match revision_type {
+        PageRevisionType::Create => "created",
+        PageRevisionType::Delete => "deleted",
+        PageRevisionType::Move => "moved",
+        PageRevisionType::Regular => "edited",
+        PageRevisionType::Rollback => "rolled back",
+        PageRevisionType::Undelete => "restored",
+        PageRevisionType::Undo => "undone",
+    }
+}
+
+fn file_revision_type_name(revision_type: FileRevisionType) -> &'static str {
+    match revision_type {
+        FileRevisionType::Create => "uploaded",
+        FileRevisionType::Delete => "deleted",
+        FileRevisionType::Move => "moved",
+        FileRevisionType::Regular => "edited",
+        FileRevisionType::Rollback => "rolled back",
+        FileRevisionType::Undelete => "restored",
+    }
+}
@@ -0,0 +1,175 @@
+/*
+ * services/contribution/structs.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::feed::FeedFormat;
+use super::prelude::*;
+use crate::models::sea_orm_active_enums::{FileRevisionType, PageRevisionType};
+use crate::services::file_revision::FileRevisionModelFiltered;
+use crate::services::page_revision::PageRevisionModelFiltered;
+use crate::types::deserialize_limit;
+use time::OffsetDateTime;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetSiteRecentChanges<'a> {
+    pub site_id: i64,
+
+    #[serde(default)]
+    pub category: Option<Reference<'a>>,
+
+    #[serde(default)]
+    pub user: Option<Reference<'a>>,
+
+    /// Which page revision types to list.
+    /// If `None`, then all are listed. If empty, then no page revisions are listed.
+    #[serde(default)]
+    pub page_revision_types: Option<Vec<PageRevisionType>>,
+
+    /// Which file revision types to list.
+    /// If `None`, then all are listed. If empty, then no file revisions are listed.
+    #[serde(default)]
+    pub file_revision_types: Option<Vec<FileRevisionType>>,
+
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub created_after: Option<OffsetDateTime>,
+
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub created_before: Option<OffsetDateTime>,
+
+    #[serde(default)]
+    pub cursor: Option<ContributionCursor>,
+
+    #[serde(deserialize_with = "deserialize_limit")]
+    pub limit: u64,
+
+    /// The user viewing the listing, if any.
+    /// Site staff can see revisions where the user has been hidden,
+    /// when filtering by user.
+    #[serde(default)]
+    pub viewer_user_id: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetSiteRecentChangesFeed<'a> {
+    #[serde(flatten)]
+    pub input: GetSiteRecentChanges<'a>,
+    pub format: FeedFormat,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SiteRecentChangesFeedOutput {
+    pub mime: &'static str,
+    pub body: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetUserContributions<'a> {
+    pub user: Reference<'a>,
+
+    /// Only list contributions on this site.
+    /// If `None`, then contributions from all sites are listed.
+    #[serde(default)]
+    pub site_id: Option<i64>,
+
+    /// Which page revision types to list.
+    /// If `None`, then all are listed. If empty, then no page revisions are listed.
+    #[serde(default)]
+    pub page_revision_types: Option<Vec<PageRevisionType>>,
+
+    /// Which file revision types to list.
+    /// If `None`, then all are listed. If empty, then no file revisions are listed.
+    #[serde(default)]
+    pub file_revision_types: Option<Vec<FileRevisionType>>,
+
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub created_after: Option<OffsetDateTime>,
+
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub created_before: Option<OffsetDateTime>,
+
+    #[serde(default)]
+    pub cursor: Option<ContributionCursor>,
+
+    #[serde(deserialize_with = "deserialize_limit")]
+    pub limit: u64,
+
+    /// The user viewing the listing, if any.
+    /// When listing one site, its staff can see revisions
+    /// where the user has been hidden.
+    #[serde(default)]
+    pub viewer_user_id: Option<i64>,
+}
+
+/// Which kind of object a contribution was made to.
+///
+/// The variant order is significant, it is used to break ties
+/// between revisions with the same timestamp. See `ContributionCursor`.
+#[derive(
+    Serialize, Deserialize, Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContributionKind {
+    File,
+    Page,
+}
+
+/// The position of a contribution in the listing.
+///
+/// Contributions are listed in descending order of this struct,
+/// that is, newest first. Since page and file revisions have separate
+/// ID sequences, the kind is needed to keep the ordering total.
+#[derive(
+    Serialize, Deserialize, Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub struct ContributionCursor {
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    pub kind: ContributionKind,
+    pub revision_id: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", tag = "kind", content = "revision")]
+pub enum Contribution {
+    Page(PageRevisionModelFiltered),
+    File(FileRevisionModelFiltered),
+}
+
+impl Contribution {
+    pub fn cursor(&self) -> ContributionCursor {
+        match self {
+            Contribution::Page(revision) => ContributionCursor {
+                created_at: revision.created_at,
+                kind: ContributionKind::Page,
+                revision_id: revision.revision_id,
+            },
+            Contribution::File(revision) => ContributionCursor {
+                created_at: revision.created_at,
+                kind: ContributionKind::File,
+                revision_id: revision.revision_id,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ContributionsOutput {
+    pub contributions: Vec<Contribution>,
+    pub next_cursor: Option<ContributionCursor>,
+}
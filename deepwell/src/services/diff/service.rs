@@ -0,0 +1,189 @@
+/*
+ * services/diff/service.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use similar::{Algorithm, ChangeTag, DiffOp, TextDiff as SimilarTextDiff};
+
+#[derive(Debug)]
+pub struct DiffService;
+
+impl DiffService {
+    /// Produces a line diff of two texts, with word-level changes within each line.
+    ///
+    /// If `context_lines` is set, then only changed lines, plus that many lines
+    /// of unchanged context around them, are included. Otherwise every line
+    /// of both texts is present in the output.
+    pub fn text(old: &str, new: &str, context_lines: Option<usize>) -> TextDiff {
+        let diff = SimilarTextDiff::configure()
+            .algorithm(Algorithm::Patience)
+            .diff_lines(old, new);
+
+        let ops: Vec<DiffOp> = match context_lines {
+            None => diff.ops().to_vec(),
+            Some(context) => diff.grouped_ops(context).into_iter().flatten().collect(),
+        };
+
+        let mut lines = Vec::new();
+        let mut lines_added = 0;
+        let mut lines_removed = 0;
+
+        for op in &ops {
+            for change in diff.iter_inline_changes(op) {
+                let change_type = match change.tag() {
+                    ChangeTag::Equal => DiffChange::Equal,
+                    ChangeTag::Insert => {
+                        lines_added += 1;
+                        DiffChange::Insert
+                    }
+                    ChangeTag::Delete => {
+                        lines_removed += 1;
+                        DiffChange::Delete
+                    }
+                };
+
+                let segments = change
+                    .iter_strings_lossy()
+                    .map(|(changed, text)| TextDiffSegment {
+                        text: text.into_owned(),
+                        changed,
+                    })
+                    .collect();
+
+                lines.push(TextDiffLine {
+                    change: change_type,
+                    old_index: change.old_index(),
+                    new_index: change.new_index(),
+                    segments,
+                });
+            }
+        }
+
+        TextDiff {
+            lines,
+            lines_added,
+            lines_removed,
+        }
+    }
+
+    /// Compares a single field value between two revisions.
+    pub fn value<T: PartialEq>(
+        old: T,
+        new: T,
+        hidden: bool,
+    ) -> FieldDiff<ValueChange<T>> {
+        if hidden {
+            FieldDiff::Hidden
+        } else if old == new {
+            FieldDiff::Unchanged
+        } else {
+            FieldDiff::Changed(ValueChange { old, new })
+        }
+    }
+
+    /// Compares a list field between two revisions, disregarding order.
+    pub fn list<T: PartialEq + Clone>(
+        old: &[T],
+        new: &[T],
+        hidden: bool,
+    ) -> FieldDiff<ListChange<T>> {
+        if hidden {
+            return FieldDiff::Hidden;
+        }
+
+        let added: Vec<T> = new
+            .iter()
+            .filter(|item| !old.contains(item))
+            .cloned()
+            .collect();
+
+        let removed: Vec<T> = old
+            .iter()
+            .filter(|item| !new.contains(item))
+            .cloned()
+            .collect();
+
+        if added.is_empty() && removed.is_empty() {
+            FieldDiff::Unchanged
+        } else {
+            FieldDiff::Changed(ListChange { added, removed })
+        }
+    }
+}
+
+#[test]
+fn text_diff() {
+    let old = "apple\nbanana split\ncherry\n";
+    let new = "apple\nbanana boat\ncherry\ndurian\n";
+    let diff = DiffService::text(old, new, None);
+
+    assert_eq!(diff.lines_added, 2, "Incorrect number of added lines");
+    assert_eq!(diff.lines_removed, 1, "Incorrect number of removed lines");
+    assert_eq!(diff.lines.len(), 5, "Incorrect number of diff lines");
+
+    let removed = diff
+        .lines
+        .iter()
+        .find(|line| line.change == DiffChange::Delete)
+        .expect("No removed line in diff");
+
+    assert_eq!(removed.old_index, Some(1));
+    assert_eq!(removed.new_index, None);
+
+    let changed_words: Vec<&str> = removed
+        .segments
+        .iter()
+        .filter(|segment| segment.changed)
+        .map(|segment| segment.text.as_str())
+        .collect();
+
+    assert_eq!(changed_words, ["split"], "Word-level change not detected");
+}
+
+#[test]
+fn text_diff_context() {
+    let old = "a\nb\nc\nd\ne\nf\ng\n";
+    let new = "a\nb\nc\nD\ne\nf\ng\n";
+    let diff = DiffService::text(old, new, Some(1));
+
+    let indices: Vec<_> = diff.lines.iter().map(|line| line.old_index).collect();
+    assert_eq!(indices, [Some(2), Some(3), None, Some(4)]);
+}
+
+#[test]
+fn field_diff() {
+    assert_eq!(DiffService::value("a", "a", false), FieldDiff::Unchanged);
+    assert_eq!(DiffService::value("a", "b", true), FieldDiff::Hidden);
+    assert_eq!(
+        DiffService::value("a", "b", false),
+        FieldDiff::Changed(ValueChange { old: "a", new: "b" }),
+    );
+
+    assert_eq!(
+        DiffService::list(&["x", "y"], &["y", "z"], false),
+        FieldDiff::Changed(ListChange {
+            added: vec!["z"],
+            removed: vec!["x"],
+        }),
+    );
+    assert_eq!(
+        DiffService::list(&["x", "y"], &["y", "x"], false),
+        FieldDiff::Unchanged
+    );
+}
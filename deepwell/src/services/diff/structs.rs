@@ -0,0 +1,88 @@
+/*
+ * services/diff/structs.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+
+/// What kind of change a line or segment in a text diff represents.
+#[derive(Serialize, Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiffChange {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// A line- and word-level diff between two texts.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TextDiff {
+    pub lines: Vec<TextDiffLine>,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+/// A single line in a text diff.
+///
+/// The indices are zero-based line numbers in the old and new
+/// texts respectively, and are `None` if the line is not present
+/// on that side (e.g. an inserted line has no `old_index`).
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TextDiffLine {
+    pub change: DiffChange,
+    pub old_index: Option<usize>,
+    pub new_index: Option<usize>,
+    pub segments: Vec<TextDiffSegment>,
+}
+
+/// A run of text within a diff line.
+///
+/// If `changed` is true, then this particular span of words is what
+/// differs between the two versions of the line. Otherwise it is
+/// context shared by both.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TextDiffSegment {
+    pub text: String,
+    pub changed: bool,
+}
+
+/// The result of comparing one field across two revisions.
+///
+/// If either revision has the field hidden, then no information
+/// about its value (including whether it changed) is exposed.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", tag = "status", content = "diff")]
+pub enum FieldDiff<T> {
+    Unchanged,
+    Changed(T),
+    Hidden,
+}
+
+/// The previous and new values of a field which was changed.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ValueChange<T> {
+    pub old: T,
+    pub new: T,
+}
+
+/// The items added and removed from a list field, such as tags.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ListChange<T> {
+    pub added: Vec<T>,
+    pub removed: Vec<T>,
+}
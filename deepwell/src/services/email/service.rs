@@ -19,13 +19,28 @@
  */
 
 use super::prelude::*;
+use crate::services::rate_limit::{RateLimitAction, RateLimitScope};
+use crate::services::RateLimitService;
 
 #[derive(Debug)]
 pub struct EmailService;
 
 impl EmailService {
     /// Validates an email through the MailCheck API.
-    pub async fn validate(email: &str) -> Result<EmailValidationOutput> {
+    ///
+    /// Requests to the API are rate limited globally, to stay within its quota.
+    pub async fn validate(
+        ctx: &ServiceContext<'_>,
+        email: &str,
+    ) -> Result<EmailValidationOutput> {
+        RateLimitService::check(
+            ctx,
+            RateLimitAction::EmailValidate,
+            RateLimitScope::Global,
+            ctx.config().rate_limit_email_validate,
+        )
+        .await?;
+
         // Sends a GET request to the MailCheck API and deserializes the response.
         let mailcheck = reqwest::get(format!("https://api.mailcheck.ai/email/{email}"))
             .await?
@@ -26,6 +26,7 @@ use s3::error::S3Error;
 use sea_orm::{error::DbErr, TransactionError};
 use thiserror::Error as ThisError;
 use unic_langid::LanguageIdentifierError;
+use webauthn_rs::prelude::WebauthnError;
 
 pub use std::error::Error as StdError;
 
@@ -78,11 +79,20 @@ pub enum Error {
     #[error("Serialization error: {0}")]
     Serde(#[from] serde_json::Error),
 
-    #[error("S3 service returned error: {0}")]
-    S3Service(#[from] S3Error),
+    #[error("XML serialization error: {0}")]
+    Xml(#[from] quick_xml::Error),
 
-    #[error("S3 service failed to respond properly")]
-    S3Response,
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Blob storage backend returned error: {0}")]
+    StorageBackend(String),
+
+    #[error("Blob storage backend failed to respond properly")]
+    StorageResponse,
+
+    #[error("Blob content scanner returned error: {0}")]
+    ScannerBackend(String),
 
     #[error("Email verification error: {}", .0.as_ref().unwrap_or(&str!("<unspecified>")))]
     EmailVerification(Option<String>),
@@ -114,6 +124,33 @@ pub enum Error {
         session_user_id: i64,
     },
 
+    #[error("Session is being used from a different network or device")]
+    SessionBindingMismatch,
+
+    #[error("Account is temporarily locked after too many failed login attempts")]
+    AccountLocked,
+
+    #[error("User is not staff of this site")]
+    NotSiteStaff,
+
+    #[error("User is not a member of this site")]
+    NotSiteMember,
+
+    #[error("User is not an administrator of the platform")]
+    NotPlatformAdmin,
+
+    #[error("Only an owner of this bot user may perform this action")]
+    NotBotOwner,
+
+    #[error("Token does not grant the scope required for this action")]
+    ScopeNotGranted,
+
+    #[error("OAuth client authentication failed")]
+    OauthInvalidClient,
+
+    #[error("OAuth grant is invalid, expired, or revoked")]
+    OauthInvalidGrant,
+
     #[error("A password is required")]
     EmptyPassword,
 
@@ -132,6 +169,32 @@ pub enum Error {
     #[error("Cannot hide the wikitext for the latest page revision")]
     CannotHideLatestRevision,
 
+    #[error(
+        "Cannot roll back to a revision with hidden fields without staff permission"
+    )]
+    CannotRollbackHiddenRevision,
+
+    #[error("Invalid WebAuthn response: {0}")]
+    WebauthnInvalid(#[from] WebauthnError),
+
+    #[error("Invalid OpenID Connect response: {0}")]
+    OidcInvalid(&'static str),
+
+    #[error("Cannot remove the only way this account can sign in")]
+    ExternalIdentityRequired,
+
+    #[error("Alias cannot be released until its cooldown has passed")]
+    AliasCooldown,
+
+    #[error("Uploaded avatar is not a valid image: {0}")]
+    AvatarInvalid(&'static str),
+
+    #[error("Invalid OAuth request: {0}")]
+    OauthInvalidRequest(&'static str),
+
+    #[error("Requested OAuth scope is invalid or not permitted for this client")]
+    OauthInvalidScope,
+
     #[error("Revision ID passed for this operation is not the latest")]
     NotLatestRevisionId,
 
@@ -249,6 +312,18 @@ pub enum Error {
     #[error("Cannot use blob uploaded by different user")]
     BlobWrongUser,
 
+    #[error("Blob upload has expired")]
+    BlobUploadExpired,
+
+    #[error("Blob upload is not a multipart upload")]
+    BlobNotMultipart,
+
+    #[error("Blob upload part number is out of range")]
+    BlobPartInvalid,
+
+    #[error("Storage quota would be exceeded by this upload")]
+    StorageQuotaExceeded { usage: i64, size: i64, maximum: i64 },
+
     #[error("Uploaded blob is too big for this operation")]
     BlobTooBig,
 
@@ -258,6 +333,21 @@ pub enum Error {
     #[error("Uploaded blob content is blacklisted")]
     BlobBlacklisted(BlobHash),
 
+    #[error("Uploaded blob content was detected as malware")]
+    BlobInfected(String),
+
+    #[error("Blob is not an image, so its near-duplicates cannot be found")]
+    BlobNotImage,
+
+    #[error("File licensing is invalid: {0}")]
+    FileLicensingInvalid(&'static str),
+
+    #[error("Uploaded file archive is invalid: {0}")]
+    FileArchiveInvalid(&'static str),
+
+    #[error("Files are too large to archive together")]
+    FileArchiveTooBig,
+
     #[error(
         "Cannot blacklist a blob which is already in use, you must do a hard deletion"
     )]
@@ -266,6 +356,30 @@ pub enum Error {
     #[error("Text item does not exist")]
     TextNotFound,
 
+    #[error("Export does not exist")]
+    ExportNotFound,
+
+    #[error("WebAuthn credential does not exist")]
+    WebauthnCredentialNotFound,
+
+    #[error("API token does not exist")]
+    ApiTokenNotFound,
+
+    #[error("OpenID Connect provider does not exist")]
+    OidcProviderNotFound,
+
+    #[error("External identity is not linked to any account")]
+    ExternalIdentityNotFound,
+
+    #[error("OAuth client does not exist")]
+    OauthClientNotFound,
+
+    #[error("No account deletion is pending for this user")]
+    UserDeletionNotFound,
+
+    #[error("File archive does not exist")]
+    FileArchiveNotFound,
+
     #[error("Cannot perform, user already exists")]
     UserExists,
 
@@ -293,13 +407,22 @@ pub enum Error {
     #[error("Cannot perform, custom domain already exists")]
     CustomDomainExists,
 
+    #[error("Cannot perform, WebAuthn credential already exists")]
+    WebauthnCredentialExists,
+
+    #[error("Cannot perform, external identity is already linked to an account")]
+    ExternalIdentityExists,
+
+    #[error("Cannot perform, account deletion is already pending")]
+    UserDeletionExists,
+
     #[error("Cannot perform this action because you are blocked by the user")]
     UserBlockedUser,
 
     #[error("Cannot perform this action because you are blocked by the site")]
     SiteBlockedUser,
 
-    #[error("The rate limit for an external API has been reached")]
+    #[error("The rate limit for this action has been reached")]
     RateLimited,
 }
 
@@ -342,6 +465,14 @@ impl Error {
             Error::MessageDraftNotFound => 2015,
             Error::BlobNotFound => 2016,
             Error::TextNotFound => 2017,
+            Error::ExportNotFound => 2018,
+            Error::WebauthnCredentialNotFound => 2019,
+            Error::ApiTokenNotFound => 2020,
+            Error::OidcProviderNotFound => 2021,
+            Error::ExternalIdentityNotFound => 2022,
+            Error::OauthClientNotFound => 2023,
+            Error::UserDeletionNotFound => 2024,
+            Error::FileArchiveNotFound => 2025,
 
             // 2100 -- Existing data
             Error::UserExists => 2100,
@@ -353,6 +484,9 @@ impl Error {
             Error::FileExists => 2106,
             Error::FilterExists => 2107,
             Error::CustomDomainExists => 2108,
+            Error::WebauthnCredentialExists => 2109,
+            Error::ExternalIdentityExists => 2110,
+            Error::UserDeletionExists => 2111,
 
             // 3000 - Server errors, unexpected
             Error::RateLimited => 3000,
@@ -362,17 +496,20 @@ impl Error {
             // 3100 -- Remote services
             Error::RenderTimeout => 3100,
             Error::EmailVerification(_) => 3101,
-            Error::S3Service(_) => 3102,
-            Error::S3Response => 3103,
+            Error::StorageBackend(_) => 3102,
+            Error::StorageResponse => 3103,
+            Error::ScannerBackend(_) => 3104,
 
             // 3200 -- Backend issues
             Error::Serde(_) => 3200,
             Error::Database(_) => 3201,
             Error::Cryptography(_) => 3202,
+            Error::Xml(_) => 3203,
             Error::Magic(_) => 3204,
             Error::Otp(_) => 3205,
             Error::Redis(_) => 3206,
             Error::Rsmq(_) => 3207,
+            Error::Io(_) => 3208,
 
             // 4000 - Client, request errors
             //        BadRequest is pretty general, avoid it except for rare weird cases
@@ -407,6 +544,23 @@ impl Error {
             Error::BlobBlacklisted(_) => 4028,
             Error::BlobCannotBlacklistExisting => 4029,
             Error::NotLatestRevisionId => 4030,
+            Error::CannotRollbackHiddenRevision => 4031,
+            Error::WebauthnInvalid(_) => 4032,
+            Error::OidcInvalid(_) => 4033,
+            Error::ExternalIdentityRequired => 4034,
+            Error::OauthInvalidRequest(_) => 4035,
+            Error::OauthInvalidScope => 4036,
+            Error::AliasCooldown => 4037,
+            Error::AvatarInvalid(_) => 4038,
+            Error::BlobUploadExpired => 4039,
+            Error::BlobNotMultipart => 4040,
+            Error::BlobPartInvalid => 4041,
+            Error::StorageQuotaExceeded { .. } => 4042,
+            Error::BlobInfected(_) => 4043,
+            Error::BlobNotImage => 4044,
+            Error::FileLicensingInvalid(_) => 4045,
+            Error::FileArchiveInvalid(_) => 4046,
+            Error::FileArchiveTooBig => 4047,
 
             // 4100 -- Localization
             Error::LocaleInvalid(_) => 4100,
@@ -429,6 +583,15 @@ impl Error {
             Error::InvalidAuthentication => 5000,
             Error::InvalidSessionToken => 5001,
             Error::SessionUserId { .. } => 5002,
+            Error::SessionBindingMismatch => 5003,
+            Error::AccountLocked => 5004,
+            Error::NotBotOwner => 5005,
+            Error::OauthInvalidClient => 5006,
+            Error::OauthInvalidGrant => 5007,
+            Error::NotSiteStaff => 5008,
+            Error::ScopeNotGranted => 5009,
+            Error::NotSiteMember => 5010,
+            Error::NotPlatformAdmin => 5011,
             // TODO: permission errors (e.g. locked page, cannot apply bans)
         }
     }
@@ -464,9 +627,21 @@ impl Error {
                 "length": length,
                 "maximum": maximum,
             }),
+            Error::StorageQuotaExceeded {
+                usage,
+                size,
+                maximum,
+            } => json!({
+                "usage": usage,
+                "size": size,
+                "maximum": maximum,
+            }),
 
             // Emit as-is
             Error::EmailVerification(value) => json!(value),
+            Error::StorageBackend(value) => json!(value),
+            Error::ScannerBackend(value) => json!(value),
+            Error::BlobInfected(value) => json!(value),
 
             // Emit as a Debug string
             Error::Cryptography(value) => json!(format!("{value:?}")),
@@ -475,7 +650,6 @@ impl Error {
             Error::Magic(value) => json!(format!("{value:?}")),
             Error::Otp(value) => json!(format!("{value:?}")),
             Error::Serde(value) => json!(format!("{value:?}")),
-            Error::S3Service(value) => json!(format!("{value:?}")),
             Error::WebRequest(value) => json!(format!("{value:?}")),
             Error::FilterRegexInvalid(value) => json!(format!("{value:?}")),
 
@@ -506,6 +680,13 @@ impl From<argon2::password_hash::Error> for Error {
     }
 }
 
+impl From<S3Error> for Error {
+    #[inline]
+    fn from(error: S3Error) -> Error {
+        Error::StorageBackend(format!("{error:?}"))
+    }
+}
+
 impl From<DbErr> for Error {
     fn from(error: DbErr) -> Error {
         match error {
@@ -0,0 +1,288 @@
+/*
+ * services/export/archive.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Writing of export archives, along with their manifest.
+
+use crate::hash::{blob_hash_to_hex, sha512_hash, slice_to_blob_hash, BlobHash};
+use crate::services::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use sha2::{Digest, Sha512};
+use std::io::{self, Write};
+use std::panic;
+use tar::{Builder, EntryType, Header};
+use time::OffsetDateTime;
+use tokio::sync::mpsc;
+use tokio::task::{self, JoinHandle};
+
+/// The name of the archive format, as recorded in the manifest.
+pub const ARCHIVE_FORMAT: &str = "wikijump-export";
+
+/// The version of the archive format, as recorded in the manifest.
+///
+/// This should be bumped whenever the layout of the archive changes.
+pub const ARCHIVE_VERSION: u32 = 2;
+
+/// The path of the manifest within the archive.
+///
+/// This is always the last entry, since it needs the checksums of every other.
+pub const MANIFEST_PATH: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExportManifest {
+    pub format: String,
+    pub version: u32,
+    pub export_id: String,
+
+    /// The site exported, or `None` for account exports.
+    pub site_id: Option<i64>,
+
+    /// The user whose data was exported, or `None` for site exports.
+    pub user_id: Option<i64>,
+    pub page_ids: Option<Vec<i64>>,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha512: String,
+}
+
+/// Writes entries to a gzipped tarball, tracking them for the manifest.
+///
+/// The output is also hashed as it is written, so that the checksum
+/// of the whole archive is available once it is finished.
+pub struct ArchiveWriter<W: Write> {
+    builder: Builder<GzEncoder<HashWriter<W>>>,
+    manifest: ExportManifest,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    pub fn new(
+        inner: W,
+        export_id: String,
+        site_id: Option<i64>,
+        user_id: Option<i64>,
+        page_ids: Option<Vec<i64>>,
+        created_at: OffsetDateTime,
+    ) -> Self {
+        let writer = HashWriter {
+            inner,
+            hasher: Sha512::new(),
+            length: 0,
+        };
+
+        ArchiveWriter {
+            builder: Builder::new(GzEncoder::new(writer, Compression::default())),
+            manifest: ExportManifest {
+                format: str!(ARCHIVE_FORMAT),
+                version: ARCHIVE_VERSION,
+                export_id,
+                site_id,
+                user_id,
+                page_ids,
+                created_at,
+                entries: Vec::new(),
+            },
+        }
+    }
+
+    /// Adds a file with the given contents to the archive.
+    pub fn add_bytes(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        debug!(
+            "Adding entry to export archive: {path} ({} bytes)",
+            data.len()
+        );
+        self.append(path, data)?;
+        self.manifest.entries.push(ManifestEntry {
+            path: str!(path),
+            size: data.len() as u64,
+            sha512: blob_hash_to_hex(&sha512_hash(data)).to_string(),
+        });
+
+        Ok(())
+    }
+
+    /// Writes the manifest and closes the archive.
+    ///
+    /// # Returns
+    /// The underlying writer, the SHA-512 hash of the archive,
+    /// and its length in bytes.
+    pub fn finish(mut self) -> Result<(W, BlobHash, u64)> {
+        let manifest = serde_json::to_vec_pretty(&self.manifest)?;
+        self.append(MANIFEST_PATH, &manifest)?;
+
+        let HashWriter {
+            inner,
+            hasher,
+            length,
+        } = self.builder.into_inner()?.finish()?;
+
+        let hash = slice_to_blob_hash(&hasher.finalize());
+        Ok((inner, hash, length))
+    }
+
+    fn append(&mut self, path: &str, data: &[u8]) -> io::Result<()> {
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Regular);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(self.manifest.created_at.unix_timestamp().max(0) as u64);
+        self.builder.append_data(&mut header, path, data)
+    }
+}
+
+/// Runs an `ArchiveWriter` on a blocking thread.
+///
+/// Compressing and writing the archive is synchronous, so entries are
+/// sent to a dedicated thread instead of being written on the async runtime.
+pub struct ArchiveTask<W: Write> {
+    sender: Option<mpsc::Sender<(String, Vec<u8>)>>,
+    handle: JoinHandle<Result<(W, BlobHash, u64)>>,
+}
+
+impl<W: Write + Send + 'static> ArchiveTask<W> {
+    pub fn spawn(mut writer: ArchiveWriter<W>) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<(String, Vec<u8>)>(16);
+        let handle = task::spawn_blocking(move || {
+            while let Some((path, data)) = receiver.blocking_recv() {
+                writer.add_bytes(&path, &data)?;
+            }
+
+            writer.finish()
+        });
+
+        ArchiveTask {
+            sender: Some(sender),
+            handle,
+        }
+    }
+
+    /// Adds a file with the given contents to the archive.
+    pub async fn add_bytes(&mut self, path: &str, data: Vec<u8>) -> Result<()> {
+        let sender = self.sender.as_ref().expect("Archive task already finished");
+        if sender.send((str!(path), data)).await.is_err() {
+            // The writer stopped early, so get its error
+            self.sender = None;
+            return self.join().await.map(|_| ());
+        }
+
+        Ok(())
+    }
+
+    /// Adds a JSON file with the serialized value to the archive.
+    pub async fn add_json<T: Serialize>(&mut self, path: &str, value: &T) -> Result<()> {
+        let data = serde_json::to_vec_pretty(value)?;
+        self.add_bytes(path, data).await
+    }
+
+    /// Writes the manifest and closes the archive, see `ArchiveWriter::finish()`.
+    pub async fn finish(mut self) -> Result<(W, BlobHash, u64)> {
+        self.sender = None;
+        self.join().await
+    }
+
+    async fn join(&mut self) -> Result<(W, BlobHash, u64)> {
+        match (&mut self.handle).await {
+            Ok(result) => result,
+            Err(error) => panic::resume_unwind(error.into_panic()),
+        }
+    }
+}
+
+/// Wrapper which hashes and counts all data written through it.
+struct HashWriter<W: Write> {
+    inner: W,
+    hasher: Sha512,
+    length: u64,
+}
+
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buffer)?;
+        self.hasher.update(&buffer[..written]);
+        self.length += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[tokio::test]
+async fn archive() {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+    use tar::Archive;
+
+    let created_at = OffsetDateTime::from_unix_timestamp(1704164645).unwrap();
+    let writer = ArchiveWriter::new(
+        Vec::new(),
+        str!("test"),
+        Some(2),
+        None,
+        Some(vec![3]),
+        created_at,
+    );
+    let mut task = ArchiveTask::spawn(writer);
+    task.add_bytes("blobs/test", b"apple".to_vec())
+        .await
+        .unwrap();
+    task.add_json("site.json", &[1, 2, 3]).await.unwrap();
+    let (buffer, hash, length) = task.finish().await.unwrap();
+
+    assert_eq!(length, buffer.len() as u64, "Archive length doesn't match");
+    assert_eq!(hash, sha512_hash(&buffer), "Archive hash doesn't match");
+
+    // Read it back and check the manifest
+    let mut archive = Archive::new(GzDecoder::new(buffer.as_slice()));
+    let mut files = Vec::new();
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let path = entry.path().unwrap().to_string_lossy().into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        files.push((path, data));
+    }
+
+    let paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+    assert_eq!(paths, ["blobs/test", "site.json", MANIFEST_PATH]);
+
+    let manifest: ExportManifest = serde_json::from_slice(&files[2].1).unwrap();
+    assert_eq!(manifest.format, ARCHIVE_FORMAT);
+    assert_eq!(manifest.export_id, "test");
+    assert_eq!(manifest.site_id, Some(2));
+    assert_eq!(manifest.user_id, None);
+    assert_eq!(manifest.page_ids, Some(vec![3]));
+    assert_eq!(manifest.entries.len(), 2);
+
+    for (entry, (path, data)) in manifest.entries.iter().zip(&files) {
+        assert_eq!(&entry.path, path);
+        assert_eq!(entry.size, data.len() as u64);
+        assert_eq!(entry.sha512, blob_hash_to_hex(&sha512_hash(data)).as_str());
+    }
+}
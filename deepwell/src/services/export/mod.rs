@@ -0,0 +1,40 @@
+/*
+ * services/export/mod.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The export service, for writing sites and pages out to a portable archive.
+//!
+//! Exports are requested through the API, and then written by a background
+//! job to either S3 or a local directory. The archive is a gzipped tarball
+//! containing JSON files for each object, the blobs for any files, and a
+//! `manifest.json` listing every entry with its SHA-512 checksum.
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use super::super::prelude::*;
+    pub use super::structs::*;
+}
+
+mod archive;
+mod service;
+mod structs;
+
+pub use self::archive::{ArchiveWriter, ExportManifest, ManifestEntry};
+pub use self::service::ExportService;
+pub use self::structs::*;
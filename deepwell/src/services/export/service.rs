@@ -0,0 +1,694 @@
+/*
+ * services/export/service.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::archive::{ArchiveTask, ArchiveWriter};
+use super::prelude::*;
+use crate::hash::blob_hash_to_hex;
+use crate::models::alias::{self, Entity as Alias};
+use crate::models::export::{self, Entity as Export, Model as ExportModel};
+use crate::models::file::Model as FileModel;
+use crate::models::file_revision::{self, Entity as FileRevision};
+use crate::models::message::{self, Entity as Message};
+use crate::models::message_draft::{self, Entity as MessageDraft};
+use crate::models::message_record::Entity as MessageRecord;
+use crate::models::page::Model as PageModel;
+use crate::models::page_attribution::{self, Entity as PageAttribution};
+use crate::models::page_revision::{self, Entity as PageRevision};
+use crate::models::page_vote::{self, Entity as PageVote};
+use crate::models::relation::{self, Entity as Relation};
+use crate::models::sea_orm_active_enums::{
+    AliasType, ExportDestination, RelationObjectType,
+};
+use crate::models::session::{self, Entity as Session};
+use crate::services::job::Job;
+use crate::services::{
+    BlobService, CategoryService, FileRevisionService, FileService, JobService,
+    PageRevisionService, PageService, ParentService, RelationService, SiteService,
+    TextService, UserService,
+};
+use crate::types::{FileOrder, PageDetails, PageOrder, Pagination};
+use crate::utils::run_blocking;
+use cuid2::cuid;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use tempfile::NamedTempFile;
+
+type Archive = ArchiveTask<File>;
+
+/// Columns of the user table which are left out of account exports.
+const SENSITIVE_USER_FIELDS: &[&str] = &[
+    "password",
+    "multi_factor_secret",
+    "multi_factor_recovery_codes",
+];
+
+#[derive(Debug)]
+pub struct ExportService;
+
+impl ExportService {
+    /// Requests an export of a site, or some of its pages.
+    ///
+    /// The archive itself is written later by a job, the export
+    /// row can be checked to see when it has completed.
+    pub async fn create(
+        ctx: &ServiceContext<'_>,
+        CreateExport {
+            site_id,
+            user_id,
+            page_ids,
+            destination,
+        }: CreateExport,
+    ) -> Result<CreateExportOutput> {
+        let txn = ctx.transaction();
+        info!("Creating export for site ID {site_id} (pages {page_ids:?})");
+
+        if matches!(page_ids, Some(ref page_ids) if page_ids.is_empty()) {
+            error!("Export requested with an empty list of pages");
+            return Err(Error::BadRequest);
+        }
+
+        // Ensure the site exists, and that the user may export it
+        SiteService::get(ctx, Reference::Id(site_id)).await?;
+        Self::check_staff(ctx, site_id, user_id).await?;
+
+        let export_id = cuid();
+        let model = export::ActiveModel {
+            export_id: Set(export_id.clone()),
+            created_by: Set(user_id),
+            site_id: Set(Some(site_id)),
+            page_ids: Set(page_ids),
+            destination: Set(destination),
+            ..Default::default()
+        };
+
+        model.insert(txn).await?;
+        JobService::queue_job(
+            ctx,
+            &Job::Export {
+                export_id: export_id.clone(),
+            },
+            None,
+        )
+        .await?;
+
+        Ok(CreateExportOutput { export_id })
+    }
+
+    /// Requests an export of all of a user's account data.
+    ///
+    /// This does not verify the session, the caller is responsible for that.
+    pub async fn create_account(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+    ) -> Result<CreateExportOutput> {
+        let txn = ctx.transaction();
+        info!("Creating account export for user ID {user_id}");
+
+        let export_id = cuid();
+        let model = export::ActiveModel {
+            export_id: Set(export_id.clone()),
+            created_by: Set(user_id),
+            site_id: Set(None),
+            destination: Set(ExportDestination::S3),
+            ..Default::default()
+        };
+
+        model.insert(txn).await?;
+        JobService::queue_job(
+            ctx,
+            &Job::Export {
+                export_id: export_id.clone(),
+            },
+            None,
+        )
+        .await?;
+
+        Ok(CreateExportOutput { export_id })
+    }
+
+    /// Gets all account exports a user has requested, newest first.
+    pub async fn get_account_exports(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+    ) -> Result<Vec<ExportModel>> {
+        let txn = ctx.transaction();
+        let exports = Export::find()
+            .filter(
+                Condition::all()
+                    .add(export::Column::CreatedBy.eq(user_id))
+                    .add(export::Column::SiteId.is_null()),
+            )
+            .order_by_desc(export::Column::CreatedAt)
+            .all(txn)
+            .await?;
+
+        Ok(exports)
+    }
+
+    pub async fn get_optional(
+        ctx: &ServiceContext<'_>,
+        export_id: &str,
+    ) -> Result<Option<ExportModel>> {
+        let txn = ctx.transaction();
+        let export = Export::find_by_id(export_id).one(txn).await?;
+        Ok(export)
+    }
+
+    #[inline]
+    pub async fn get(ctx: &ServiceContext<'_>, export_id: &str) -> Result<ExportModel> {
+        find_or_error!(Self::get_optional(ctx, export_id), Export)
+    }
+
+    /// Gets an export, if the user is allowed to download it.
+    ///
+    /// Account exports may only be downloaded by their user,
+    /// and site exports by the staff of that site.
+    pub async fn get_for_user(
+        ctx: &ServiceContext<'_>,
+        export_id: &str,
+        user_id: i64,
+    ) -> Result<Option<ExportModel>> {
+        let export = match Self::get_optional(ctx, export_id).await? {
+            Some(export) => export,
+            None => return Ok(None),
+        };
+
+        match export.site_id {
+            Some(site_id) => Self::check_staff(ctx, site_id, user_id).await?,
+            None if export.created_by == user_id => (),
+            None => {
+                error!("User ID {user_id} cannot access account export ID {export_id}");
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(export))
+    }
+
+    /// Creates a short-lived download URL for a completed export.
+    ///
+    /// Only exports stored in S3 can be downloaded, local
+    /// exports are for the server operator to retrieve.
+    pub async fn get_download_url(
+        ctx: &ServiceContext<'_>,
+        export: &ExportModel,
+    ) -> Result<Option<String>> {
+        if export.completed_at.is_none() || export.destination != ExportDestination::S3 {
+            return Ok(None);
+        }
+
+        let location = Self::location(ctx, export);
+        let filename = format!("export-{}.tar.gz", export.export_id);
+        let url = BlobService::presign_download(
+            ctx,
+            &location,
+            "application/gzip",
+            Some(&filename),
+        )
+        .await?;
+
+        Ok(Some(url))
+    }
+
+    /// Deletes all of a user's account exports, including their archives.
+    pub async fn delete_account_exports(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+    ) -> Result<()> {
+        info!("Deleting all account exports for user ID {user_id}");
+
+        let txn = ctx.transaction();
+        for export in Self::get_account_exports(ctx, user_id).await? {
+            if export.completed_at.is_some() {
+                let location = Self::location(ctx, &export);
+                match export.destination {
+                    ExportDestination::S3 => ctx.storage().delete(&location).await?,
+                    ExportDestination::Local => {
+                        run_blocking(move || fs::remove_file(location)).await?
+                    }
+                }
+            }
+
+            Export::delete_by_id(export.export_id).exec(txn).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn check_staff(
+        ctx: &ServiceContext<'_>,
+        site_id: i64,
+        user_id: i64,
+    ) -> Result<()> {
+        if !RelationService::is_site_staff(ctx, site_id, user_id).await? {
+            error!("User ID {user_id} is not staff of site ID {site_id}, cannot export");
+            return Err(Error::NotSiteStaff);
+        }
+
+        Ok(())
+    }
+
+    /// Gets where an export's archive is written to.
+    ///
+    /// For S3 this is the path within blob storage, and for local
+    /// destinations it is the path on the filesystem.
+    pub fn location(ctx: &ServiceContext<'_>, export: &ExportModel) -> String {
+        let filename = format!("{}.tar.gz", export.export_id);
+
+        match export.destination {
+            ExportDestination::S3 => format!("exports/{filename}"),
+            ExportDestination::Local => ctx
+                .config()
+                .export_path
+                .join(filename)
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
+
+    /// Writes the archive for an export, then marks it as completed.
+    ///
+    /// The archive is first written to a temporary file, and only
+    /// moved to its destination once it is complete.
+    pub async fn run(ctx: &ServiceContext<'_>, export_id: &str) -> Result<()> {
+        let export = Self::get(ctx, export_id).await?;
+        if export.completed_at.is_some() {
+            warn!("Export ID {export_id} was already completed, skipping");
+            return Ok(());
+        }
+
+        info!("Writing archive for export ID {export_id}");
+        let temp_file = match export.destination {
+            // Create in the same directory, so it can be moved into place
+            ExportDestination::Local => {
+                let export_path = ctx.config().export_path.clone();
+                run_blocking(move || NamedTempFile::new_in(export_path)).await?
+            }
+            ExportDestination::S3 => run_blocking(NamedTempFile::new).await?,
+        };
+
+        let writer = ArchiveWriter::new(
+            temp_file.as_file().try_clone()?,
+            str!(export_id),
+            export.site_id,
+            export.site_id.is_none().then_some(export.created_by),
+            export.page_ids.clone(),
+            export.created_at,
+        );
+
+        // Compression and file writes happen on a separate thread
+        let mut archive = ArchiveTask::spawn(writer);
+
+        Self::write_contents(ctx, &mut archive, &export).await?;
+        let (_, archive_hash, archive_size) = archive.finish().await?;
+
+        let location = Self::location(ctx, &export);
+        match export.destination {
+            ExportDestination::Local => {
+                debug!("Moving export archive to {location}");
+                let path = PathBuf::from(&location);
+                run_blocking(move || temp_file.persist(path))
+                    .await
+                    .map_err(|e| e.error)?;
+            }
+            ExportDestination::S3 => {
+                debug!("Uploading export archive to blob storage at {location}");
+
+                ctx.storage()
+                    .put_file(&location, temp_file.path(), "application/gzip")
+                    .await?;
+            }
+        }
+
+        let txn = ctx.transaction();
+        let model = export::ActiveModel {
+            export_id: Set(str!(export_id)),
+            completed_at: Set(Some(now())),
+            archive_hash: Set(Some(archive_hash.to_vec())),
+            archive_size: Set(Some(archive_size as i64)),
+            ..Default::default()
+        };
+        model.update(txn).await?;
+
+        info!("Finished export ID {export_id} ({archive_size} bytes)");
+        Ok(())
+    }
+
+    /// Records that an export has failed, and will not be retried.
+    pub async fn set_error(
+        ctx: &ServiceContext<'_>,
+        export_id: &str,
+        error: String,
+    ) -> Result<()> {
+        let txn = ctx.transaction();
+        warn!("Export ID {export_id} failed: {error}");
+
+        let model = export::ActiveModel {
+            export_id: Set(str!(export_id)),
+            error: Set(Some(error)),
+            ..Default::default()
+        };
+        model.update(txn).await?;
+        Ok(())
+    }
+
+    async fn write_contents(
+        ctx: &ServiceContext<'_>,
+        archive: &mut Archive,
+        export: &ExportModel,
+    ) -> Result<()> {
+        let site_id = match export.site_id {
+            Some(site_id) => site_id,
+            None => return Self::write_account(ctx, archive, export.created_by).await,
+        };
+
+        let site = SiteService::get(ctx, Reference::Id(site_id)).await?;
+        archive.add_json("site.json", &site).await?;
+
+        // Categories include their layouts, so they are exported in full
+        for category in CategoryService::get_all(ctx, site_id).await? {
+            let path = format!("categories/{}.json", category.category_id);
+            archive.add_json(&path, &category).await?;
+        }
+
+        let pages = match export.page_ids {
+            None => {
+                PageService::get_all(
+                    ctx,
+                    site_id,
+                    None,
+                    None,
+                    PageOrder::default(),
+                    Pagination::default(),
+                )
+                .await?
+            }
+            Some(ref page_ids) => {
+                let mut pages = Vec::with_capacity(page_ids.len());
+                for &page_id in page_ids {
+                    let page = PageService::get_direct(ctx, page_id, true).await?;
+                    if page.site_id != site_id {
+                        error!("Page ID {page_id} is not in site ID {site_id}");
+                        return Err(Error::PageNotFound);
+                    }
+
+                    pages.push(page);
+                }
+                pages
+            }
+        };
+
+        // Blobs can be shared between files, so only write each once
+        let mut blobs = HashSet::new();
+        for page in pages {
+            Self::write_page(ctx, archive, &mut blobs, page).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn write_account(
+        ctx: &ServiceContext<'_>,
+        archive: &mut Archive,
+        user_id: i64,
+    ) -> Result<()> {
+        let txn = ctx.transaction();
+        debug!("Exporting account data for user ID {user_id}");
+
+        // Profile, without credentials
+        let user = UserService::get(ctx, Reference::Id(user_id)).await?;
+        let mut user = serde_json::to_value(user)?;
+        if let Some(fields) = user.as_object_mut() {
+            for field in SENSITIVE_USER_FIELDS {
+                fields.remove(*field);
+            }
+        }
+        archive.add_json("user.json", &user).await?;
+
+        let aliases = Alias::find()
+            .filter(
+                Condition::all()
+                    .add(alias::Column::AliasType.eq(AliasType::User))
+                    .add(alias::Column::TargetId.eq(user_id)),
+            )
+            .order_by_asc(alias::Column::CreatedAt)
+            .all(txn)
+            .await?;
+        archive.add_json("aliases.json", &aliases).await?;
+
+        // Sessions, without their tokens
+        let sessions: Vec<ExportedSession> = Session::find()
+            .filter(session::Column::UserId.eq(user_id))
+            .order_by_asc(session::Column::CreatedAt)
+            .all(txn)
+            .await?
+            .into_iter()
+            .map(ExportedSession::from)
+            .collect();
+        archive.add_json("sessions.json", &sessions).await?;
+
+        // Messages, both the user's copies and drafts
+        let messages = Message::find()
+            .find_also_related(MessageRecord)
+            .filter(message::Column::UserId.eq(user_id))
+            .order_by_asc(message::Column::InternalId)
+            .all(txn)
+            .await?;
+
+        for (message, record) in messages {
+            let record = match record {
+                Some(record) => record,
+                None => continue,
+            };
+
+            let wikitext = TextService::get(ctx, &record.wikitext_hash).await?;
+            let path = format!("messages/{}.json", message.internal_id);
+            archive
+                .add_json(
+                    &path,
+                    &ExportedMessage {
+                        message,
+                        record,
+                        wikitext,
+                    },
+                )
+                .await?;
+        }
+
+        let drafts = MessageDraft::find()
+            .filter(message_draft::Column::UserId.eq(user_id))
+            .order_by_asc(message_draft::Column::CreatedAt)
+            .all(txn)
+            .await?;
+
+        for draft in drafts {
+            let wikitext = TextService::get(ctx, &draft.wikitext_hash).await?;
+            let path = format!("message-drafts/{}.json", draft.external_id);
+            archive
+                .add_json(&path, &ExportedMessageDraft { draft, wikitext })
+                .await?;
+        }
+
+        // Votes, including removed ones
+        let votes = PageVote::find()
+            .filter(page_vote::Column::UserId.eq(user_id))
+            .order_by_asc(page_vote::Column::PageVoteId)
+            .all(txn)
+            .await?;
+        archive.add_json("votes.json", &votes).await?;
+
+        // Revisions authored, subject to any hidden fields
+        let page_revisions = PageRevision::find()
+            .filter(page_revision::Column::UserId.eq(user_id))
+            .order_by_asc(page_revision::Column::RevisionId)
+            .all(txn)
+            .await?;
+
+        let details = PageDetails {
+            wikitext: true,
+            compiled_html: false,
+        };
+
+        for revision in page_revisions {
+            let revision =
+                PageRevisionService::filter_and_populate(ctx, revision, details).await?;
+
+            let path = format!("revisions/pages/{}.json", revision.revision_id);
+            archive.add_json(&path, &revision).await?;
+        }
+
+        let file_revisions = FileRevision::find()
+            .filter(file_revision::Column::UserId.eq(user_id))
+            .order_by_asc(file_revision::Column::RevisionId)
+            .all(txn)
+            .await?;
+
+        for revision in file_revisions {
+            let revision = FileRevisionService::filter(revision)?;
+            let path = format!("revisions/files/{}.json", revision.revision_id);
+            archive.add_json(&path, &revision).await?;
+        }
+
+        // Relations to or from the user
+        let relations = Relation::find()
+            .filter(
+                Condition::any()
+                    .add(
+                        Condition::all()
+                            .add(relation::Column::FromType.eq(RelationObjectType::User))
+                            .add(relation::Column::FromId.eq(user_id)),
+                    )
+                    .add(
+                        Condition::all()
+                            .add(relation::Column::DestType.eq(RelationObjectType::User))
+                            .add(relation::Column::DestId.eq(user_id)),
+                    ),
+            )
+            .order_by_asc(relation::Column::RelationId)
+            .all(txn)
+            .await?;
+        archive.add_json("relations.json", &relations).await?;
+
+        Ok(())
+    }
+
+    async fn write_page(
+        ctx: &ServiceContext<'_>,
+        archive: &mut Archive,
+        blobs: &mut HashSet<Vec<u8>>,
+        page: PageModel,
+    ) -> Result<()> {
+        let txn = ctx.transaction();
+        let PageModel {
+            page_id, site_id, ..
+        } = page;
+
+        debug!("Exporting page ID {page_id}");
+
+        let (parents, attributions) = try_join!(
+            ParentService::get_parents(ctx, site_id, Reference::Id(page_id)),
+            async {
+                let attributions = PageAttribution::find()
+                    .filter(page_attribution::Column::PageId.eq(page_id))
+                    .order_by_asc(page_attribution::Column::CreatedAt)
+                    .all(txn)
+                    .await?;
+
+                Ok(attributions)
+            },
+        )?;
+
+        archive
+            .add_json(
+                &format!("pages/{page_id}/page.json"),
+                &ExportedPage {
+                    page,
+                    parents,
+                    attributions,
+                },
+            )
+            .await?;
+
+        // Full revision history, with wikitext
+        let revisions = PageRevision::find()
+            .filter(page_revision::Column::PageId.eq(page_id))
+            .order_by_asc(page_revision::Column::RevisionNumber)
+            .all(txn)
+            .await?;
+
+        let details = PageDetails {
+            wikitext: true,
+            compiled_html: false,
+        };
+
+        for revision in revisions {
+            let revision =
+                PageRevisionService::filter_and_populate(ctx, revision, details).await?;
+
+            let path = format!(
+                "pages/{page_id}/revisions/{}.json",
+                revision.revision_number,
+            );
+            archive.add_json(&path, &revision).await?;
+        }
+
+        // Files, including deleted ones
+        let files = FileService::get_all(
+            ctx,
+            site_id,
+            page_id,
+            None,
+            FileOrder::default(),
+            Pagination::default(),
+        )
+        .await?;
+
+        for file in files {
+            Self::write_file(ctx, archive, blobs, file).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn write_file(
+        ctx: &ServiceContext<'_>,
+        archive: &mut Archive,
+        blobs: &mut HashSet<Vec<u8>>,
+        file: FileModel,
+    ) -> Result<()> {
+        let txn = ctx.transaction();
+        let FileModel {
+            file_id, page_id, ..
+        } = file;
+
+        debug!("Exporting file ID {file_id}");
+        let prefix = format!("pages/{page_id}/files/{file_id}");
+        archive
+            .add_json(&format!("{prefix}/file.json"), &file)
+            .await?;
+
+        let revisions = FileRevision::find()
+            .filter(file_revision::Column::FileId.eq(file_id))
+            .order_by_asc(file_revision::Column::RevisionNumber)
+            .all(txn)
+            .await?;
+
+        for revision in revisions {
+            let revision = FileRevisionService::filter(revision)?;
+            let path = format!("{prefix}/revisions/{}.json", revision.revision_number);
+            archive.add_json(&path, &revision).await?;
+
+            // Hidden blobs (e.g. hard deleted ones) are not included
+            if let Some(s3_hash) = revision.s3_hash {
+                if blobs.contains(&s3_hash) {
+                    continue;
+                }
+
+                let data = BlobService::get(ctx, &s3_hash).await?;
+                let path = format!("blobs/{}", blob_hash_to_hex(&s3_hash));
+                archive.add_bytes(&path, data).await?;
+                blobs.insert(s3_hash);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+/*
+ * services/export/structs.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::models::export::Model as ExportModel;
+use crate::models::message::Model as MessageModel;
+use crate::models::message_draft::Model as MessageDraftModel;
+use crate::models::message_record::Model as MessageRecordModel;
+use crate::models::page::Model as PageModel;
+use crate::models::page_attribution::Model as PageAttributionModel;
+use crate::models::page_parent::Model as PageParentModel;
+use crate::models::sea_orm_active_enums::ExportDestination;
+use crate::models::session::Model as SessionModel;
+use time::OffsetDateTime;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreateExport {
+    pub site_id: i64,
+    pub user_id: i64,
+
+    /// Which pages to export, or all pages on the site if `None`.
+    #[serde(default)]
+    pub page_ids: Option<Vec<i64>>,
+    pub destination: ExportDestination,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreateAccountExport {
+    pub user_id: i64,
+    pub session_token: String,
+}
+
+pub type GetAccountExports = CreateAccountExport;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CreateExportOutput {
+    pub export_id: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetExport {
+    pub export_id: String,
+    pub user_id: i64,
+    pub session_token: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GetExportOutput {
+    #[serde(flatten)]
+    pub export: ExportModel,
+
+    /// A short-lived download URL, once the export has completed.
+    pub url: Option<String>,
+}
+
+/// The contents of each `page.json` file in an export archive.
+#[derive(Serialize, Debug, Clone)]
+pub struct ExportedPage {
+    pub page: PageModel,
+    pub parents: Vec<PageParentModel>,
+    pub attributions: Vec<PageAttributionModel>,
+}
+
+/// A session as included in account exports, without its token.
+#[derive(Serialize, Debug, Clone)]
+pub struct ExportedSession {
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_used_at: OffsetDateTime,
+
+    pub ip_address: String,
+    pub user_agent: String,
+}
+
+impl From<SessionModel> for ExportedSession {
+    fn from(session: SessionModel) -> Self {
+        ExportedSession {
+            created_at: session.created_at,
+            expires_at: session.expires_at,
+            last_used_at: session.last_used_at,
+            ip_address: session.ip_address,
+            user_agent: session.user_agent,
+        }
+    }
+}
+
+/// The contents of each message file in an account export archive.
+#[derive(Serialize, Debug, Clone)]
+pub struct ExportedMessage {
+    pub message: MessageModel,
+    pub record: MessageRecordModel,
+    pub wikitext: String,
+}
+
+/// The contents of each message draft file in an account export archive.
+#[derive(Serialize, Debug, Clone)]
+pub struct ExportedMessageDraft {
+    pub draft: MessageDraftModel,
+    pub wikitext: String,
+}
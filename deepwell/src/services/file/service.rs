@@ -32,12 +32,22 @@ use crate::services::file_revision::{
     GetFileRevision,
 };
 use crate::services::filter::{FilterClass, FilterType};
-use crate::services::{BlobService, FileRevisionService, FilterService, PageService};
-use crate::types::FileOrder;
+use crate::services::relation::{GetSiteBan, GetSiteMember};
+use crate::services::{
+    BlobService, FileRevisionService, FilterService, PageService, QuotaService,
+    RelationService,
+};
+use crate::types::{
+    cursor_condition, parse_hidden_fields, FileHiddenField, FileLicensing, FileOrder,
+    Pagination,
+};
 use crate::utils::regex_replace_in_place;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use sea_orm::ActiveValue;
+use sea_orm::{ActiveValue, DatabaseBackend, FromQueryResult, Statement, Value};
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, HashSet};
+use std::slice;
 
 pub const MAXIMUM_FILE_NAME_LENGTH: usize = 256;
 
@@ -83,25 +93,28 @@ impl FileService {
         }
 
         // Finish blob upload
-        let FinalizeBlobUploadOutput {
-            s3_hash,
-            mime,
-            size,
-            created: blob_created,
-        } = match direct_upload {
+        let blob = match direct_upload {
             None => {
                 // Normal path, finish upload of blob from user
                 BlobService::finish_upload(ctx, user_id, &uploaded_blob_id).await?
             }
             Some(data) => {
                 // Special path, used only internally to directly upload a blob,
-                // for instance in the seeder
+                // for instance in the seeder or when importing an archive
                 //
                 // This should always be None when called from API users
-                BlobService::direct_upload(ctx, data).await?
+                BlobService::checked_upload(ctx, data, user_id).await?
             }
         };
 
+        Self::check_upload_quota(ctx, site_id, user_id, &blob).await?;
+        let FinalizeBlobUploadOutput {
+            s3_hash,
+            mime,
+            size,
+            created: blob_created,
+        } = blob;
+
         // Add new file
         let model = file::ActiveModel {
             name: Set(name.clone()),
@@ -182,12 +195,7 @@ impl FileService {
         let blob = match uploaded_blob_id {
             Maybe::Unset => Maybe::Unset,
             Maybe::Set(ref id) => {
-                let FinalizeBlobUploadOutput {
-                    s3_hash,
-                    mime,
-                    size,
-                    created: blob_created,
-                } = match direct_upload {
+                let blob = match direct_upload {
                     Maybe::Unset => {
                         // Normal path, finish upload of blob from user
                         BlobService::finish_upload(ctx, user_id, id).await?
@@ -195,10 +203,18 @@ impl FileService {
                     Maybe::Set(data) => {
                         // Special path, used only internally to directly upload a blob
                         // See FileService::create()
-                        BlobService::direct_upload(ctx, data).await?
+                        BlobService::checked_upload(ctx, data, user_id).await?
                     }
                 };
 
+                Self::check_upload_quota(ctx, site_id, user_id, &blob).await?;
+                let FinalizeBlobUploadOutput {
+                    s3_hash,
+                    mime,
+                    size,
+                    created: blob_created,
+                } = blob;
+
                 Maybe::Set(FileBlob {
                     s3_hash,
                     mime,
@@ -312,6 +328,181 @@ impl FileService {
         Ok(revision_output)
     }
 
+    /// Copies a file to another page, possibly on another site.
+    ///
+    /// The copy is a new file with its own revision history, using the
+    /// same blob and licensing as the latest revision of the original.
+    /// Filters and storage quotas of the destination site apply, and
+    /// copying to another site requires being a member of it.
+    pub async fn copy(
+        ctx: &ServiceContext<'_>,
+        CopyFile {
+            site_id,
+            page_id,
+            file: reference,
+            destination_site_id,
+            destination_page,
+            name,
+            user_id,
+            revision_comments,
+            bypass_filter,
+        }: CopyFile<'_>,
+    ) -> Result<CopyFileOutput> {
+        let txn = ctx.transaction();
+        let source = Self::get(
+            ctx,
+            GetFile {
+                site_id,
+                page_id,
+                file: reference,
+            },
+        )
+        .await?;
+
+        let source_revision =
+            FileRevisionService::get_latest(ctx, site_id, page_id, source.file_id)
+                .await?;
+
+        // Hard deleted blobs cannot be copied
+        let hidden: Vec<FileHiddenField> = parse_hidden_fields(&source_revision.hidden)?;
+        if hidden.contains(&FileHiddenField::S3Hash) {
+            warn!("Latest revision of file has its blob hidden, cannot copy");
+            return Err(Error::BlobNotFound);
+        }
+
+        // Get destination page id
+        let destination_page_id =
+            PageService::get_id(ctx, destination_site_id, destination_page).await?;
+
+        // Check the user can read the source and add files to the destination
+        Self::check_copy_access(
+            ctx,
+            (site_id, page_id),
+            (destination_site_id, destination_page_id),
+            user_id,
+        )
+        .await?;
+
+        // Get destination filename
+        let mut name = name.unwrap_or_else(|| source_revision.name.clone());
+
+        info!(
+            "Copying file with ID {} from page ID {} in site ID {} to page ID {} in site ID {}",
+            source.file_id, page_id, site_id, destination_page_id, destination_site_id,
+        );
+
+        // Verify filename is valid
+        check_file_name(&mut name)?;
+
+        // Ensure there isn't a file with this name on the destination page
+        Self::check_conflicts(ctx, destination_page_id, &name, "copy").await?;
+
+        // Perform filter validation
+        if !bypass_filter {
+            Self::run_filter(ctx, destination_site_id, Some(&name)).await?;
+        }
+
+        // The blob already exists, but it may be new to this site or user
+        QuotaService::check(
+            ctx,
+            destination_site_id,
+            user_id,
+            &source_revision.s3_hash,
+            source_revision.size,
+        )
+        .await?;
+
+        // Add new file
+        let model = file::ActiveModel {
+            name: Set(name.clone()),
+            site_id: Set(destination_site_id),
+            page_id: Set(destination_page_id),
+            copied_from_revision_id: Set(Some(source_revision.revision_id)),
+            ..Default::default()
+        };
+        let file = model.insert(txn).await?;
+
+        FileRevisionService::create_first(
+            ctx,
+            CreateFirstFileRevision {
+                page_id: destination_page_id,
+                site_id: destination_site_id,
+                file_id: file.file_id,
+                user_id,
+                name,
+                s3_hash: slice_to_blob_hash(&source_revision.s3_hash),
+                size: source_revision.size,
+                mime: source_revision.mime,
+                blob_created: false,
+                licensing: FileLicensing::from_json(&source_revision.licensing),
+                revision_comments,
+            },
+        )
+        .await
+    }
+
+    /// Gets where a file was copied from, if it is a copy.
+    pub async fn get_copy_source(
+        ctx: &ServiceContext<'_>,
+        file: &FileModel,
+    ) -> Result<Option<FileCopySource>> {
+        let revision_id = match file.copied_from_revision_id {
+            Some(revision_id) => revision_id,
+            None => return Ok(None),
+        };
+
+        let mut sources = Self::get_copy_sources(ctx, slice::from_ref(file)).await?;
+        Ok(sources.remove(&revision_id))
+    }
+
+    /// Gets where each of several files were copied from, in one query.
+    ///
+    /// # Returns
+    /// A map of each file's `copied_from_revision_id` to its source.
+    pub async fn get_copy_sources(
+        ctx: &ServiceContext<'_>,
+        files: &[FileModel],
+    ) -> Result<HashMap<i64, FileCopySource>> {
+        let revision_ids = files
+            .iter()
+            .filter_map(|file| file.copied_from_revision_id)
+            .collect::<HashSet<_>>();
+
+        if revision_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let txn = ctx.transaction();
+        let sources = FileRevision::find()
+            .filter(file_revision::Column::RevisionId.is_in(revision_ids))
+            .all(txn)
+            .await?
+            .into_iter()
+            .map(
+                |FileRevisionModel {
+                     site_id,
+                     page_id,
+                     file_id,
+                     revision_id,
+                     revision_number,
+                     ..
+                 }| {
+                    let source = FileCopySource {
+                        site_id,
+                        page_id,
+                        file_id,
+                        revision_id,
+                        revision_number,
+                    };
+
+                    (revision_id, source)
+                },
+            )
+            .collect();
+
+        Ok(sources)
+    }
+
     /// Deletes this file.
     ///
     /// Like other deletions throughout Wikijump, this is a soft deletion.
@@ -518,8 +709,17 @@ impl FileService {
             FileRevisionService::get_latest(ctx, site_id, page_id, file_id),
         )?;
 
-        // TODO Handle hidden fields, see https://scuttle.atlassian.net/browse/WJ-1285
-        let _ = target_revision.hidden;
+        // Rolling back copies the target revision's contents into a new,
+        // visible revision. So if any of them are hidden, only staff may do this.
+        //
+        // See PageService::rollback().
+        let hidden: Vec<FileHiddenField> = parse_hidden_fields(&target_revision.hidden)?;
+        if hidden.iter().any(|field| field.is_content())
+            && !RelationService::is_site_staff(ctx, site_id, user_id).await?
+        {
+            warn!("Target revision has hidden fields, denying rollback");
+            return Err(Error::CannotRollbackHiddenRevision);
+        }
 
         // Check last revision ID
         check_last_revision(&last_revision, last_revision_id)?;
@@ -568,7 +768,7 @@ impl FileService {
             body: CreateFileRevisionBody {
                 name: Maybe::Set(name),
                 blob: Maybe::Set(blob),
-                licensing: Maybe::Set(licensing),
+                licensing: Maybe::Set(FileLicensing::from_json(&licensing)),
                 page_id: Maybe::Unset, // rollbacks should never move files
             },
         };
@@ -630,13 +830,16 @@ impl FileService {
     /// * If it is `Some(true)`, then it only returns pages which have been deleted.
     /// * If it is `Some(false)`, then it only returns pages which are extant.
     /// * If it is `None`, then it returns all pages regardless of deletion status.
-    // TODO add pagination
+    ///
+    /// For the `pagination` argument, see documentation on `Pagination`.
+    /// The cursor is a file ID.
     pub async fn get_all(
         ctx: &ServiceContext<'_>,
         site_id: i64,
         page_id: i64,
         deleted: Option<bool>,
         order: FileOrder,
+        pagination: Pagination,
     ) -> Result<Vec<FileModel>> {
         let txn = ctx.transaction();
         let deleted_condition = match deleted {
@@ -645,20 +848,130 @@ impl FileService {
             None => None,
         };
 
+        let order_column = order.column.into_column();
+        let cursor_condition = match pagination.cursor {
+            None => None,
+            Some(file_id) => {
+                let cursor = Self::get_direct(ctx, file_id, true).await?;
+                Some(cursor_condition(
+                    order_column,
+                    file::Column::FileId,
+                    &order.direction,
+                    cursor.get(order_column),
+                    file_id,
+                ))
+            }
+        };
+
         let files = File::find()
             .filter(
                 Condition::all()
                     .add(file::Column::SiteId.eq(site_id))
                     .add(file::Column::PageId.eq(page_id))
-                    .add_option(deleted_condition),
+                    .add_option(deleted_condition)
+                    .add_option(cursor_condition),
             )
-            .order_by(order.column.into_column(), order.direction)
+            .order_by(order_column, order.direction.clone())
+            .order_by(file::Column::FileId, order.direction)
+            .limit(pagination.limit)
             .all(txn)
             .await?;
 
         Ok(files)
     }
 
+    /// Gets all files in a site whose latest revision has the given license.
+    ///
+    /// If no license is given, then files with an unknown license are
+    /// returned instead. Deleted files are not included.
+    pub async fn get_by_license(
+        ctx: &ServiceContext<'_>,
+        GetSiteFilesByLicense {
+            site_id,
+            license,
+            pagination,
+        }: GetSiteFilesByLicense,
+    ) -> Result<Vec<SiteFileLicensing>> {
+        info!("Getting files in site ID {site_id} with license {license:?}");
+
+        #[derive(Debug, FromQueryResult)]
+        struct Row {
+            file_id: i64,
+            page_id: i64,
+            name: String,
+            revision_id: i64,
+            licensing: JsonValue,
+        }
+
+        // Revisions from before licensing was structured have
+        // other values (like null), which count as unknown.
+        let query = Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            str!(
+                "
+                SELECT
+                    f.file_id AS file_id,
+                    f.page_id AS page_id,
+                    f.name AS name,
+                    r.revision_id AS revision_id,
+                    r.licensing AS licensing
+                FROM file AS f
+                JOIN LATERAL (
+                    SELECT revision_id, licensing
+                    FROM file_revision
+                    WHERE file_id = f.file_id
+                    ORDER BY revision_number DESC
+                    LIMIT 1
+                ) AS r ON TRUE
+                WHERE f.site_id = $1
+                AND f.deleted_at IS NULL
+                AND ($2::bigint IS NULL OR f.file_id > $2)
+                AND CASE
+                    WHEN $3::text IS NULL THEN
+                        json_typeof(r.licensing) <> 'object'
+                        OR (r.licensing ->> 'unknown')::boolean IS TRUE
+                    ELSE lower(r.licensing ->> 'license') = lower($3)
+                END
+                ORDER BY f.file_id
+                LIMIT $4
+            "
+            ),
+            [
+                Value::from(site_id),
+                Value::from(pagination.cursor),
+                Value::from(license),
+                Value::from(pagination.limit.map(|limit| limit as i64)),
+            ],
+        );
+
+        let rows = File::find()
+            .from_raw_sql(query)
+            .into_model::<Row>()
+            .all(ctx.transaction())
+            .await?;
+
+        let files = rows
+            .into_iter()
+            .map(
+                |Row {
+                     file_id,
+                     page_id,
+                     name,
+                     revision_id,
+                     licensing,
+                 }| SiteFileLicensing {
+                    file_id,
+                    page_id,
+                    name,
+                    revision_id,
+                    licensing: FileLicensing::from_json(&licensing),
+                },
+            )
+            .collect();
+
+        Ok(files)
+    }
+
     /// Gets the file ID from a reference, looking up if necessary.
     ///
     /// Convenience method since this is much more common than the optional
@@ -758,6 +1071,93 @@ impl FileService {
         }
     }
 
+    /// Checks that a user may copy a file from one page to another.
+    ///
+    /// Both pages must exist on their given sites and not be deleted,
+    /// and the user must not be banned from either site. Copying to
+    /// another site also requires being a member of it.
+    async fn check_copy_access(
+        ctx: &ServiceContext<'_>,
+        (site_id, page_id): (i64, i64),
+        (destination_site_id, destination_page_id): (i64, i64),
+        user_id: i64,
+    ) -> Result<()> {
+        Self::check_page_site(ctx, site_id, page_id).await?;
+        Self::check_page_site(ctx, destination_site_id, destination_page_id).await?;
+
+        RelationService::check_site_ban(
+            ctx,
+            GetSiteBan { site_id, user_id },
+            "copy from",
+        )
+        .await?;
+
+        if destination_site_id != site_id {
+            RelationService::check_site_ban(
+                ctx,
+                GetSiteBan {
+                    site_id: destination_site_id,
+                    user_id,
+                },
+                "copy to",
+            )
+            .await?;
+
+            let member = GetSiteMember {
+                site_id: destination_site_id,
+                user_id,
+            };
+
+            if !RelationService::site_member_exists(ctx, member).await? {
+                error!(
+                    "User ID {user_id} is not a member of destination site ID {destination_site_id}",
+                );
+                return Err(Error::NotSiteMember);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that a page exists on the given site, and has not been deleted.
+    async fn check_page_site(
+        ctx: &ServiceContext<'_>,
+        site_id: i64,
+        page_id: i64,
+    ) -> Result<()> {
+        let page = PageService::get_direct(ctx, page_id, false).await?;
+        if page.site_id != site_id {
+            error!("Page ID {page_id} is not in site ID {site_id}");
+            return Err(Error::PageNotFound);
+        }
+
+        Ok(())
+    }
+
+    /// Checks storage quotas for a newly-uploaded blob.
+    ///
+    /// The size of an upload is only known for certain once it has been
+    /// finalized, so if the quota is exceeded, a blob which was created
+    /// by this upload is deleted rather than left orphaned in storage.
+    async fn check_upload_quota(
+        ctx: &ServiceContext<'_>,
+        site_id: i64,
+        user_id: i64,
+        blob: &FinalizeBlobUploadOutput,
+    ) -> Result<()> {
+        let result =
+            QuotaService::check(ctx, site_id, user_id, &blob.s3_hash, blob.size).await;
+
+        if let Err(Error::StorageQuotaExceeded { .. }) = result {
+            if blob.created {
+                warn!("Deleting newly-uploaded blob which exceeded storage quota");
+                BlobService::hard_delete_unused(ctx, &blob.s3_hash).await?;
+            }
+        }
+
+        result
+    }
+
     /// This runs the regular expression-based text filters against a file's name.
     ///
     /// It does not check the file's contents, as that is a binary blob.
@@ -22,7 +22,10 @@ use crate::models::sea_orm_active_enums::FileRevisionType;
 use crate::services::file_revision::{
     CreateFileRevisionOutput, CreateFirstFileRevisionOutput,
 };
-use crate::types::{Bytes, FileDetails, Maybe, Reference};
+use crate::services::thumbnail::FileMedia;
+use crate::types::{
+    Bytes, FileDetails, FileHiddenField, FileLicensing, Maybe, Pagination, Reference,
+};
 use serde_json::Value as JsonValue;
 use time::OffsetDateTime;
 
@@ -34,7 +37,7 @@ pub struct CreateFile {
     pub uploaded_blob_id: String,
     pub revision_comments: String,
     pub user_id: i64,
-    pub licensing: JsonValue, // TODO
+    pub licensing: FileLicensing,
 
     #[serde(default)]
     pub bypass_filter: bool,
@@ -84,14 +87,16 @@ pub struct GetFileOutput {
     #[serde(with = "time::serde::rfc3339")]
     pub revision_created_at: OffsetDateTime,
     pub revision_number: i32,
-    pub revision_user_id: i64,
+    pub revision_user_id: Option<i64>,
     pub name: String,
-    pub data: Option<Bytes<'static>>,
+    pub url: Option<String>,
     pub mime: String,
     pub size: i64,
-    pub licensing: JsonValue,
-    pub revision_comments: String,
-    pub hidden_fields: Vec<String>,
+    pub licensing: FileLicensing,
+    pub revision_comments: Option<String>,
+    pub hidden_fields: Vec<FileHiddenField>,
+    pub copied_from: Option<FileCopySource>,
+    pub media: Option<FileMedia>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -99,6 +104,31 @@ pub struct GetPageFiles {
     pub site_id: i64,
     pub page_id: i64,
     pub deleted: Maybe<bool>,
+
+    #[serde(flatten)]
+    pub pagination: Pagination,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetSiteFilesByLicense {
+    pub site_id: i64,
+
+    /// The SPDX identifier to search for, case-insensitively.
+    /// If `None`, then files with an unknown license are found.
+    #[serde(default)]
+    pub license: Option<String>,
+
+    #[serde(flatten)]
+    pub pagination: Pagination,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SiteFileLicensing {
+    pub file_id: i64,
+    pub page_id: i64,
+    pub name: String,
+    pub revision_id: i64,
+    pub licensing: FileLicensing,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -121,7 +151,7 @@ pub struct EditFile {
 #[serde(default)]
 pub struct EditFileBody {
     pub name: Maybe<String>,
-    pub licensing: Maybe<serde_json::Value>,
+    pub licensing: Maybe<FileLicensing>,
     pub uploaded_blob_id: Maybe<String>,
 
     /// Allows internal users to upload directly.
@@ -149,6 +179,33 @@ pub struct MoveFile<'a> {
 
 pub type MoveFileOutput = CreateFileRevisionOutput;
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct CopyFile<'a> {
+    pub site_id: i64,
+    pub page_id: i64,
+    pub file: Reference<'a>,
+    pub destination_site_id: i64,
+    pub destination_page: Reference<'a>,
+    pub name: Option<String>,
+    pub user_id: i64,
+    pub revision_comments: String,
+
+    #[serde(default)]
+    pub bypass_filter: bool,
+}
+
+pub type CopyFileOutput = CreateFirstFileRevisionOutput;
+
+/// Where a file was copied from, as of the time of copying.
+#[derive(Serialize, Debug, Clone)]
+pub struct FileCopySource {
+    pub site_id: i64,
+    pub page_id: i64,
+    pub file_id: i64,
+    pub revision_id: i64,
+    pub revision_number: i32,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct DeleteFile<'a> {
     pub last_revision_id: i64,
@@ -0,0 +1,294 @@
+/*
+ * services/file_archive/archive.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Reading and writing of file archives.
+//!
+//! Archive entries are flattened, keeping only the last component of their
+//! path as the filename. Entries which aren't regular files, dotfiles (like
+//! `.DS_Store`), and metadata added by macOS archivers are skipped.
+
+use crate::services::{Error, Result};
+use flate2::read::GzDecoder;
+use std::io::{self, Cursor, Read, Write};
+use tar::Archive as TarArchive;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// A file extracted from an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Extracts the files from a zip, tar, or gzipped tar archive.
+///
+/// At most `maximum_entries` files may be extracted, and their total size
+/// cannot exceed `maximum_size` bytes, so that highly compressed archives
+/// cannot exhaust memory.
+pub fn read_archive(
+    data: &[u8],
+    maximum_entries: usize,
+    maximum_size: u64,
+) -> Result<Vec<ArchiveEntry>> {
+    let mut reader = EntryReader {
+        entries: Vec::new(),
+        maximum_entries,
+        remaining_size: maximum_size,
+    };
+
+    if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+        debug!("Reading zip archive ({} bytes)", data.len());
+        reader.read_zip(data)?;
+    } else if data.starts_with(&[0x1f, 0x8b]) {
+        debug!("Reading gzipped tar archive ({} bytes)", data.len());
+        reader.read_tar(GzDecoder::new(data))?;
+    } else if data.get(257..262) == Some(&b"ustar"[..]) {
+        debug!("Reading tar archive ({} bytes)", data.len());
+        reader.read_tar(data)?;
+    } else {
+        return Err(Error::FileArchiveInvalid("unsupported archive format"));
+    }
+
+    Ok(reader.entries)
+}
+
+/// Builds a zip archive in memory, one file at a time.
+///
+/// Compression is blocking, so entries should be added using `run_blocking()`.
+#[derive(Debug)]
+pub struct ZipBuilder {
+    writer: ZipWriter<Cursor<Vec<u8>>>,
+    remaining_size: u64,
+}
+
+impl ZipBuilder {
+    /// Creates a new archive, whose files cannot exceed `maximum_size` bytes in total.
+    pub fn new(maximum_size: u64) -> Self {
+        ZipBuilder {
+            writer: ZipWriter::new(Cursor::new(Vec::new())),
+            remaining_size: maximum_size,
+        }
+    }
+
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let size = data.len() as u64;
+        if size > self.remaining_size {
+            return Err(Error::FileArchiveTooBig);
+        }
+
+        let options =
+            SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        self.writer
+            .start_file(name, options)
+            .map_err(io::Error::from)?;
+        self.writer.write_all(data)?;
+        self.remaining_size -= size;
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let buffer = self.writer.finish().map_err(io::Error::from)?;
+        Ok(buffer.into_inner())
+    }
+}
+
+struct EntryReader {
+    entries: Vec<ArchiveEntry>,
+    maximum_entries: usize,
+    remaining_size: u64,
+}
+
+impl EntryReader {
+    fn read_zip(&mut self, data: &[u8]) -> Result<()> {
+        let mut archive = ZipArchive::new(Cursor::new(data))
+            .map_err(|_| Error::FileArchiveInvalid("unable to read zip archive"))?;
+
+        for index in 0..archive.len() {
+            let entry = archive
+                .by_index(index)
+                .map_err(|_| Error::FileArchiveInvalid("unable to read zip archive"))?;
+
+            if !entry.is_file() {
+                continue;
+            }
+
+            let path = str!(entry.name());
+            self.add(&path, entry)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_tar<R: Read>(&mut self, data: R) -> Result<()> {
+        let mut archive = TarArchive::new(data);
+        let entries = archive
+            .entries()
+            .map_err(|_| Error::FileArchiveInvalid("unable to read tar archive"))?;
+
+        for entry in entries {
+            let entry = entry
+                .map_err(|_| Error::FileArchiveInvalid("unable to read tar archive"))?;
+
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let path = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+            self.add(&path, entry)?;
+        }
+
+        Ok(())
+    }
+
+    fn add<R: Read>(&mut self, path: &str, reader: R) -> Result<()> {
+        let name = match entry_name(path) {
+            Some(name) => name,
+            None => {
+                debug!("Skipping archive entry '{path}'");
+                return Ok(());
+            }
+        };
+
+        if self.entries.len() >= self.maximum_entries {
+            return Err(Error::FileArchiveInvalid("archive has too many files"));
+        }
+
+        // Read one byte past the limit, to tell if it was exceeded
+        let mut data = Vec::new();
+        reader
+            .take(self.remaining_size + 1)
+            .read_to_end(&mut data)
+            .map_err(|_| Error::FileArchiveInvalid("unable to read archive entry"))?;
+
+        let size = data.len() as u64;
+        if size > self.remaining_size {
+            return Err(Error::FileArchiveInvalid("archive contents are too large"));
+        }
+
+        self.remaining_size -= size;
+        self.entries.push(ArchiveEntry { name, data });
+        Ok(())
+    }
+}
+
+/// Gets the filename for an archive entry, or `None` if it should be skipped.
+fn entry_name(path: &str) -> Option<String> {
+    if path.split(['/', '\\']).any(|part| part == "__MACOSX") {
+        return None;
+    }
+
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    if name.is_empty() || name.starts_with('.') {
+        return None;
+    }
+
+    Some(str!(name))
+}
+
+#[test]
+fn archive_entry_name() {
+    assert_eq!(entry_name("apple.png"), Some(str!("apple.png")));
+    assert_eq!(entry_name("images/apple.png"), Some(str!("apple.png")));
+    assert_eq!(entry_name("images\\apple.png"), Some(str!("apple.png")));
+    assert_eq!(entry_name("../../etc/passwd"), Some(str!("passwd")));
+    assert_eq!(entry_name("images/"), None);
+    assert_eq!(entry_name(".DS_Store"), None);
+    assert_eq!(entry_name("images/.hidden"), None);
+    assert_eq!(entry_name("__MACOSX/images/._apple.png"), None);
+}
+
+#[test]
+fn archive_zip() {
+    let entries: [(&str, &[u8]); 3] = [
+        ("apple.txt", b"apple"),
+        ("fruit/banana.txt", b"banana"),
+        (".DS_Store", b"junk"),
+    ];
+
+    let mut builder = ZipBuilder::new(1024);
+    for (name, data) in entries {
+        builder.add(name, data).expect("Unable to add zip entry");
+    }
+    let data = builder.finish().expect("Unable to write zip");
+    let read = read_archive(&data, 10, 1024).expect("Unable to read zip");
+    assert_eq!(
+        read,
+        [
+            ArchiveEntry {
+                name: str!("apple.txt"),
+                data: b"apple".to_vec(),
+            },
+            ArchiveEntry {
+                name: str!("banana.txt"),
+                data: b"banana".to_vec(),
+            },
+        ],
+    );
+
+    // Limits
+    assert!(matches!(
+        read_archive(&data, 1, 1024),
+        Err(Error::FileArchiveInvalid(_)),
+    ));
+    assert!(matches!(
+        read_archive(&data, 10, 8),
+        Err(Error::FileArchiveInvalid(_)),
+    ));
+    assert!(matches!(
+        ZipBuilder::new(8).add("apple.txt", b"apple pie"),
+        Err(Error::FileArchiveTooBig),
+    ));
+}
+
+#[test]
+fn archive_tar() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tar::{Builder, EntryType, Header};
+
+    let mut builder = Builder::new(Vec::new());
+    for (path, data) in [("cherry.txt", b"cherry"), ("fruit/durian.txt", b"durian")] {
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Regular);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, path, &data[..])
+            .expect("Unable to append tar entry");
+    }
+    let tar = builder.into_inner().expect("Unable to write tar");
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&tar).expect("Unable to compress tar");
+    let tar_gz = encoder.finish().expect("Unable to compress tar");
+
+    for data in [tar, tar_gz] {
+        let read = read_archive(&data, 10, 1024).expect("Unable to read tar");
+        let names: Vec<_> = read.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["cherry.txt", "durian.txt"]);
+    }
+
+    assert!(matches!(
+        read_archive(b"not an archive", 10, 1024),
+        Err(Error::FileArchiveInvalid(_)),
+    ));
+}
@@ -0,0 +1,39 @@
+/*
+ * services/file_archive/mod.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The file archive service, for working with many files of a page at once.
+//!
+//! Users can upload a zip or tar archive, which is extracted into files on
+//! a page, each going through the same checks as an ordinary upload. In the
+//! other direction, a background job bundles all current files of a page
+//! into a zip archive, stored as a blob which can then be downloaded.
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use super::super::prelude::*;
+    pub use super::structs::*;
+}
+
+mod archive;
+mod service;
+mod structs;
+
+pub use self::service::FileArchiveService;
+pub use self::structs::*;
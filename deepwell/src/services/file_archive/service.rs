@@ -0,0 +1,290 @@
+/*
+ * services/file_archive/service.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::archive::{read_archive, ArchiveEntry, ZipBuilder};
+use super::prelude::*;
+use crate::models::file_archive::{
+    self, Entity as FileArchive, Model as FileArchiveModel,
+};
+use crate::services::blob::FinalizeBlobUploadOutput;
+use crate::services::file::{CreateFile, CreateFileOutput};
+use crate::services::job::Job;
+use crate::services::{
+    BlobService, FileRevisionService, FileService, JobService, PageService,
+};
+use crate::types::{FileOrder, Pagination};
+use crate::utils::run_blocking;
+
+#[derive(Debug)]
+pub struct FileArchiveService;
+
+impl FileArchiveService {
+    /// Creates files on a page from each file in an uploaded archive.
+    ///
+    /// Each file is created as though it were uploaded individually,
+    /// so the same name, filter, quota, and blob checks apply. If any
+    /// file fails, then none of them are created.
+    pub async fn import(
+        ctx: &ServiceContext<'_>,
+        ImportFileArchive {
+            site_id,
+            page_id,
+            user_id,
+            uploaded_blob_id,
+            revision_comments,
+            licensing,
+            bypass_filter,
+        }: ImportFileArchive,
+    ) -> Result<ImportFileArchiveOutput> {
+        info!("Importing file archive to page ID {page_id} in site ID {site_id}");
+        Self::check_page(ctx, site_id, page_id).await?;
+
+        let FinalizeBlobUploadOutput {
+            s3_hash,
+            created: blob_created,
+            ..
+        } = BlobService::finish_upload(ctx, user_id, &uploaded_blob_id).await?;
+
+        // Decompression is expensive, so do it off the runtime
+        let config = ctx.config();
+        let maximum_entries = config.maximum_archive_entries;
+        let maximum_size = config.maximum_archive_size;
+        let data = BlobService::get(ctx, &s3_hash).await?;
+        let entries =
+            run_blocking(move || read_archive(&data, maximum_entries, maximum_size))
+                .await?;
+
+        // Only the extracted files are kept, not the archive itself,
+        // unless the same blob was also uploaded as something else.
+        if blob_created {
+            BlobService::hard_delete_unused(ctx, &s3_hash).await?;
+        }
+
+        let mut files = Vec::with_capacity(entries.len());
+        for ArchiveEntry { name, data } in entries {
+            debug!("Creating file '{name}' from archive ({} bytes)", data.len());
+
+            let CreateFileOutput {
+                file_id,
+                file_revision_id,
+                ..
+            } = FileService::create(
+                ctx,
+                CreateFile {
+                    site_id,
+                    page_id,
+                    name: name.clone(),
+                    uploaded_blob_id: String::new(),
+                    revision_comments: revision_comments.clone(),
+                    user_id,
+                    licensing: licensing.clone(),
+                    bypass_filter,
+                    direct_upload: Some(data),
+                },
+            )
+            .await?;
+
+            files.push(ImportedFile {
+                name,
+                file_id,
+                file_revision_id,
+            });
+        }
+
+        info!("Imported {} files from archive", files.len());
+        Ok(ImportFileArchiveOutput { files })
+    }
+
+    /// Requests a zip archive of all the current files on a page.
+    ///
+    /// The archive itself is written later by a job, the row
+    /// can be checked to see when it has completed.
+    pub async fn create(
+        ctx: &ServiceContext<'_>,
+        CreateFileArchive {
+            site_id,
+            page_id,
+            user_id,
+        }: CreateFileArchive,
+    ) -> Result<CreateFileArchiveOutput> {
+        let txn = ctx.transaction();
+        info!("Creating file archive for page ID {page_id} in site ID {site_id}");
+        Self::check_page(ctx, site_id, page_id).await?;
+
+        let model = file_archive::ActiveModel {
+            created_by: Set(user_id),
+            site_id: Set(site_id),
+            page_id: Set(page_id),
+            ..Default::default()
+        };
+
+        let FileArchiveModel {
+            file_archive_id, ..
+        } = model.insert(txn).await?;
+
+        JobService::queue_job(ctx, &Job::FileArchive { file_archive_id }, None).await?;
+        Ok(CreateFileArchiveOutput { file_archive_id })
+    }
+
+    pub async fn get_optional(
+        ctx: &ServiceContext<'_>,
+        file_archive_id: i64,
+    ) -> Result<Option<FileArchiveModel>> {
+        let txn = ctx.transaction();
+        let file_archive = FileArchive::find_by_id(file_archive_id).one(txn).await?;
+        Ok(file_archive)
+    }
+
+    #[inline]
+    pub async fn get(
+        ctx: &ServiceContext<'_>,
+        file_archive_id: i64,
+    ) -> Result<FileArchiveModel> {
+        find_or_error!(Self::get_optional(ctx, file_archive_id), FileArchive)
+    }
+
+    /// Gets a download URL for a completed archive.
+    pub async fn get_url(
+        ctx: &ServiceContext<'_>,
+        file_archive: &FileArchiveModel,
+    ) -> Result<Option<String>> {
+        let archive_hash = match file_archive.archive_hash {
+            Some(ref archive_hash) => archive_hash,
+            None => return Ok(None),
+        };
+
+        let filename =
+            match PageService::get_direct_optional(ctx, file_archive.page_id, true)
+                .await?
+            {
+                Some(page) => format!("{}-files.zip", page.slug),
+                None => format!("{}-files.zip", file_archive.page_id),
+            };
+
+        let url = BlobService::get_download_url(
+            ctx,
+            archive_hash,
+            "application/zip",
+            Some(&filename),
+        )
+        .await?;
+
+        Ok(Some(url))
+    }
+
+    /// Writes the archive of a page's files, then marks it as completed.
+    ///
+    /// Files whose blobs are hidden (e.g. hard deleted ones) are left out.
+    pub async fn run(ctx: &ServiceContext<'_>, file_archive_id: i64) -> Result<()> {
+        let file_archive = Self::get(ctx, file_archive_id).await?;
+        if file_archive.completed_at.is_some() {
+            warn!("File archive ID {file_archive_id} was already completed, skipping");
+            return Ok(());
+        }
+
+        let FileArchiveModel {
+            site_id, page_id, ..
+        } = file_archive;
+
+        info!("Writing archive of files on page ID {page_id} in site ID {site_id}");
+        let files = FileService::get_all(
+            ctx,
+            site_id,
+            page_id,
+            Some(false),
+            FileOrder::default(),
+            Pagination::default(),
+        )
+        .await?;
+
+        // Each blob is fetched and compressed in turn, so that
+        // only one is held in memory at a time, apart from the zip.
+        let mut builder = ZipBuilder::new(ctx.config().maximum_archive_size);
+        let mut count = 0;
+        for file in files {
+            let revision =
+                FileRevisionService::get_latest(ctx, site_id, page_id, file.file_id)
+                    .await?;
+            let revision = FileRevisionService::filter(revision)?;
+
+            match revision.s3_hash {
+                Some(s3_hash) => {
+                    let data = BlobService::get(ctx, &s3_hash).await?;
+                    builder = run_blocking(move || {
+                        builder.add(&file.name, &data)?;
+                        Ok::<_, Error>(builder)
+                    })
+                    .await?;
+                    count += 1;
+                }
+                None => debug!("Skipping file ID {}, blob is hidden", file.file_id),
+            }
+        }
+
+        let data = run_blocking(move || builder.finish()).await?;
+        let FinalizeBlobUploadOutput { s3_hash, size, .. } =
+            BlobService::direct_upload(ctx, data).await?;
+
+        let txn = ctx.transaction();
+        let model = file_archive::ActiveModel {
+            file_archive_id: Set(file_archive_id),
+            completed_at: Set(Some(now())),
+            archive_hash: Set(Some(s3_hash.to_vec())),
+            ..Default::default()
+        };
+        model.update(txn).await?;
+
+        info!("Finished file archive ID {file_archive_id} ({count} files, {size} bytes)",);
+        Ok(())
+    }
+
+    /// Records that a file archive has failed, and will not be retried.
+    pub async fn set_error(
+        ctx: &ServiceContext<'_>,
+        file_archive_id: i64,
+        error: String,
+    ) -> Result<()> {
+        let txn = ctx.transaction();
+        warn!("File archive ID {file_archive_id} failed: {error}");
+
+        let model = file_archive::ActiveModel {
+            file_archive_id: Set(file_archive_id),
+            error: Set(Some(error)),
+            ..Default::default()
+        };
+        model.update(txn).await?;
+        Ok(())
+    }
+
+    /// Ensures that the page exists and is within the given site.
+    async fn check_page(
+        ctx: &ServiceContext<'_>,
+        site_id: i64,
+        page_id: i64,
+    ) -> Result<()> {
+        let page = PageService::get_direct(ctx, page_id, false).await?;
+        if page.site_id != site_id {
+            error!("Page ID {page_id} is not in site ID {site_id}");
+            return Err(Error::PageNotFound);
+        }
+
+        Ok(())
+    }
+}
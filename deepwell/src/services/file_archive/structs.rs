@@ -0,0 +1,77 @@
+/*
+ * services/file_archive/structs.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::models::file_archive::Model as FileArchiveModel;
+use crate::types::FileLicensing;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ImportFileArchive {
+    pub site_id: i64,
+    pub page_id: i64,
+    pub user_id: i64,
+
+    /// The uploaded zip or tar archive.
+    pub uploaded_blob_id: String,
+    pub revision_comments: String,
+
+    /// Licensing applied to every file in the archive.
+    pub licensing: FileLicensing,
+
+    #[serde(default)]
+    pub bypass_filter: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ImportFileArchiveOutput {
+    pub files: Vec<ImportedFile>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ImportedFile {
+    pub name: String,
+    pub file_id: i64,
+    pub file_revision_id: i64,
+}
+
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub struct CreateFileArchive {
+    pub site_id: i64,
+    pub page_id: i64,
+    pub user_id: i64,
+}
+
+#[derive(Serialize, Debug, Copy, Clone)]
+pub struct CreateFileArchiveOutput {
+    pub file_archive_id: i64,
+}
+
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub struct GetFileArchive {
+    pub file_archive_id: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GetFileArchiveOutput {
+    #[serde(flatten)]
+    pub file_archive: FileArchiveModel,
+
+    /// Where the archive can be downloaded from, once it has completed.
+    pub url: Option<String>,
+}
@@ -25,8 +25,13 @@ use crate::models::file_revision::{
 };
 use crate::models::{file, page, site};
 use crate::services::blob::{FinalizeBlobUploadOutput, EMPTY_BLOB_HASH, EMPTY_BLOB_MIME};
-use crate::services::{BlobService, OutdateService, PageService};
-use crate::types::{Bytes, FetchDirection};
+use crate::services::{
+    BlobService, DiffService, OutdateService, PageService, ThumbnailService,
+};
+use crate::types::{
+    hidden_fields_column, parse_hidden_fields, Bytes, FetchDirection, FileHiddenField,
+    FileLicensing,
+};
 use once_cell::sync::Lazy;
 use sea_orm::{prelude::*, FromQueryResult};
 use std::num::NonZeroI32;
@@ -128,9 +133,12 @@ impl FileRevisionService {
         }
 
         if let Maybe::Set(new_licensing) = body.licensing {
-            if licensing != new_licensing {
+            if FileLicensing::from_json(&licensing) != new_licensing {
+                new_licensing
+                    .validate()
+                    .map_err(Error::FileLicensingInvalid)?;
                 changes.push(str!("licensing"));
-                licensing = new_licensing;
+                licensing = new_licensing.to_json();
             }
         }
 
@@ -149,12 +157,15 @@ impl FileRevisionService {
             return Err(Error::FileMimeEmpty);
         }
 
-        // TODO validate licensing field
-
         // Run outdater
         let page_slug = Self::get_page_slug(ctx, site_id, page_id).await?;
         OutdateService::process_page_edit(ctx, site_id, page_id, &page_slug, 0).await?;
 
+        // Process new images for thumbnails and metadata
+        if blob_created.is_set() {
+            ThumbnailService::queue(ctx, &s3_hash, &mime).await?;
+        }
+
         // Insert the new revision into the table
         let model = file_revision::ActiveModel {
             revision_type: Set(revision_type),
@@ -203,11 +214,17 @@ impl FileRevisionService {
     ) -> Result<CreateFirstFileRevisionOutput> {
         let txn = ctx.transaction();
 
+        // Validate inputs
+        licensing.validate().map_err(Error::FileLicensingInvalid)?;
+
         // Run outdater
         let page_slug = Self::get_page_slug(ctx, site_id, page_id).await?;
         OutdateService::process_page_displace(ctx, site_id, page_id, &page_slug, 0)
             .await?;
 
+        // Process images for thumbnails and metadata
+        ThumbnailService::queue(ctx, &s3_hash, &mime).await?;
+
         // Insert the first revision into the table
         let model = file_revision::ActiveModel {
             revision_type: Set(FileRevisionType::Create),
@@ -220,7 +237,7 @@ impl FileRevisionService {
             s3_hash: Set(s3_hash.to_vec()),
             mime: Set(mime),
             size: Set(size),
-            licensing: Set(licensing),
+            licensing: Set(licensing.to_json()),
             changes: Set(ALL_CHANGES.clone()),
             comments: Set(revision_comments),
             hidden: Set(vec![]),
@@ -274,7 +291,7 @@ impl FileRevisionService {
             s3_hash.copy_from_slice(&EMPTY_BLOB_HASH);
 
             // Also block the s3_hash column for this revision
-            hidden.push(str!("s3_hash"));
+            hidden.push(str!(FileHiddenField::S3Hash.name()));
         }
 
         // Run outdater
@@ -412,14 +429,18 @@ impl FileRevisionService {
             hidden,
         }: UpdateFileRevision,
     ) -> Result<FileRevisionModel> {
-        // The latest file revision cannot be hidden, because
+        // The contents of the latest file revision cannot be hidden, because
         // the file, its name, contents, etc are exposed.
         // It should be reverted first, and then it can be hidden.
+        //
+        // See PageRevisionService::update().
 
         let txn = ctx.transaction();
         let latest = Self::get_latest(ctx, site_id, page_id, file_id).await?;
-        if revision_id == latest.revision_id {
-            warn!("Attempting to edit latest revision, denying request");
+        if revision_id == latest.revision_id
+            && hidden.iter().any(|field| field.is_content())
+        {
+            warn!("Attempting to hide contents of latest revision, denying request");
             return Err(Error::CannotHideLatestRevision);
         }
 
@@ -430,7 +451,7 @@ impl FileRevisionService {
 
         let model = file_revision::ActiveModel {
             revision_id: Set(revision_id),
-            hidden: Set(hidden),
+            hidden: Set(hidden_fields_column(&hidden, FileHiddenField::name)),
             ..Default::default()
         };
 
@@ -583,6 +604,157 @@ impl FileRevisionService {
         Ok(revisions)
     }
 
+    /// Strips hidden fields from a file revision.
+    ///
+    /// See `PageRevisionService::filter_and_populate()`.
+    pub fn filter(model: FileRevisionModel) -> Result<FileRevisionModelFiltered> {
+        let FileRevisionModel {
+            revision_id,
+            revision_type,
+            created_at,
+            revision_number,
+            file_id,
+            page_id,
+            site_id,
+            user_id,
+            name,
+            s3_hash,
+            mime,
+            size,
+            licensing,
+            changes,
+            comments,
+            hidden,
+        } = model;
+
+        // Strip hidden fields
+        let hidden: Vec<FileHiddenField> = parse_hidden_fields(&hidden)?;
+        let mut user_id = Some(user_id);
+        let mut name = Some(name);
+        let mut s3_hash = Some(s3_hash);
+        let mut mime = Some(mime);
+        let mut size = Some(size);
+        let mut licensing = Some(FileLicensing::from_json(&licensing));
+        let mut comments = Some(comments);
+
+        for field in &hidden {
+            match field {
+                FileHiddenField::Comments => comments = None,
+                FileHiddenField::User => user_id = None,
+                FileHiddenField::Name => name = None,
+                FileHiddenField::S3Hash => s3_hash = None,
+                FileHiddenField::Mime => mime = None,
+                FileHiddenField::Size => size = None,
+                FileHiddenField::Licensing => licensing = None,
+            }
+        }
+
+        Ok(FileRevisionModelFiltered {
+            revision_id,
+            revision_type,
+            created_at,
+            revision_number,
+            file_id,
+            page_id,
+            site_id,
+            user_id,
+            name,
+            s3_hash,
+            mime,
+            size,
+            licensing,
+            changes,
+            comments,
+            hidden,
+        })
+    }
+
+    /// Strips hidden fields from several file revisions.
+    pub fn filter_all(
+        revisions: Vec<FileRevisionModel>,
+    ) -> Result<Vec<FileRevisionModelFiltered>> {
+        revisions.into_iter().map(Self::filter).collect()
+    }
+
+    /// Compares two revisions of a file.
+    ///
+    /// Since files can be moved between pages, revisions are looked up by
+    /// file ID alone, rather than by the page they were on at the time.
+    ///
+    /// See `PageRevisionService::diff()`.
+    pub async fn diff(
+        ctx: &ServiceContext<'_>,
+        GetFileRevisionDiff {
+            site_id,
+            file_id,
+            from_revision_number,
+            to_revision_number,
+        }: GetFileRevisionDiff,
+    ) -> Result<FileRevisionDiffOutput> {
+        let (from, to) = try_join!(
+            Self::get_by_number(ctx, site_id, file_id, from_revision_number),
+            Self::get_by_number(ctx, site_id, file_id, to_revision_number),
+        )?;
+
+        let hidden: Vec<FileHiddenField> = parse_hidden_fields(&from.hidden)?
+            .into_iter()
+            .chain(parse_hidden_fields(&to.hidden)?)
+            .collect();
+
+        let is_hidden = |field| hidden.contains(&field);
+
+        let s3_hash = DiffService::value(
+            blob_hash_to_hex(&from.s3_hash).to_string(),
+            blob_hash_to_hex(&to.s3_hash).to_string(),
+            is_hidden(FileHiddenField::S3Hash),
+        );
+
+        let name =
+            DiffService::value(from.name, to.name, is_hidden(FileHiddenField::Name));
+        let mime =
+            DiffService::value(from.mime, to.mime, is_hidden(FileHiddenField::Mime));
+        let size =
+            DiffService::value(from.size, to.size, is_hidden(FileHiddenField::Size));
+        let licensing = DiffService::value(
+            from.licensing,
+            to.licensing,
+            is_hidden(FileHiddenField::Licensing),
+        );
+
+        Ok(FileRevisionDiffOutput {
+            from_revision_id: from.revision_id,
+            from_revision_number,
+            to_revision_id: to.revision_id,
+            to_revision_number,
+            name,
+            mime,
+            size,
+            licensing,
+            s3_hash,
+        })
+    }
+
+    async fn get_by_number(
+        ctx: &ServiceContext<'_>,
+        site_id: i64,
+        file_id: i64,
+        revision_number: i32,
+    ) -> Result<FileRevisionModel> {
+        let txn = ctx.transaction();
+        let revision = FileRevision::find()
+            .filter(
+                Condition::all()
+                    .add(file_revision::Column::SiteId.eq(site_id))
+                    .add(file_revision::Column::FileId.eq(file_id))
+                    .add(file_revision::Column::RevisionNumber.eq(revision_number)),
+            )
+            .one(txn)
+            .await?
+            .ok_or(Error::FileRevisionNotFound)?;
+
+        Ok(revision)
+    }
+
     async fn get_page_slug(
         ctx: &ServiceContext<'_>,
         site_id: i64,
@@ -20,8 +20,11 @@
 
 use super::prelude::*;
 use crate::models::sea_orm_active_enums::FileRevisionType;
+use crate::services::diff::{FieldDiff, ValueChange};
 use crate::services::page_revision::PageRevisionCountOutput;
-use crate::types::FetchDirection;
+use crate::types::{FetchDirection, FileHiddenField, FileLicensing};
+use serde_json::Value as JsonValue;
+use time::OffsetDateTime;
 
 #[derive(Debug, Clone)]
 pub struct CreateFileRevision {
@@ -39,7 +42,7 @@ pub struct CreateFileRevisionBody {
     pub page_id: Maybe<i64>, // for changing the page this file is on
     pub name: Maybe<String>,
     pub blob: Maybe<FileBlob>,
-    pub licensing: Maybe<serde_json::Value>,
+    pub licensing: Maybe<FileLicensing>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -70,7 +73,7 @@ pub struct CreateFirstFileRevision {
     pub size: i64,
     pub mime: String,
     pub blob_created: bool,
-    pub licensing: serde_json::Value,
+    pub licensing: FileLicensing,
     pub revision_comments: String,
 }
 
@@ -117,7 +120,7 @@ pub struct UpdateFileRevision {
     pub file_id: i64,
     pub revision_id: i64,
     pub user_id: i64,
-    pub hidden: Vec<String>,
+    pub hidden: Vec<FileHiddenField>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -128,4 +131,47 @@ pub struct GetFileRevisionRange {
     pub limit: u64,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetFileRevisionDiff {
+    pub site_id: i64,
+    pub file_id: i64,
+    pub from_revision_number: i32,
+    pub to_revision_number: i32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FileRevisionDiffOutput {
+    pub from_revision_id: i64,
+    pub from_revision_number: i32,
+    pub to_revision_id: i64,
+    pub to_revision_number: i32,
+    pub name: FieldDiff<ValueChange<String>>,
+    pub mime: FieldDiff<ValueChange<String>>,
+    pub size: FieldDiff<ValueChange<i64>>,
+    pub licensing: FieldDiff<ValueChange<JsonValue>>,
+    pub s3_hash: FieldDiff<ValueChange<String>>,
+}
+
 pub type FileRevisionCountOutput = PageRevisionCountOutput;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FileRevisionModelFiltered {
+    pub revision_id: i64,
+    pub revision_type: FileRevisionType,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    pub revision_number: i32,
+    pub file_id: i64,
+    pub page_id: i64,
+    pub site_id: i64,
+    pub user_id: Option<i64>,
+    pub name: Option<String>,
+    pub s3_hash: Option<Vec<u8>>,
+    pub mime: Option<String>,
+    pub size: Option<i64>,
+    pub licensing: Option<FileLicensing>,
+    pub changes: Vec<String>,
+    pub comments: Option<String>,
+    pub hidden: Vec<FileHiddenField>,
+}
@@ -94,4 +94,23 @@ impl JobService {
         )
         .await
     }
+
+    /// Queues a rescan of all stored blobs for malware.
+    pub async fn queue_rescan_blobs(ctx: &ServiceContext<'_>) -> Result<()> {
+        debug!("Queuing rescan of all stored blobs");
+        Self::queue_job(ctx, &Job::RescanBlobs { last_hash: None }, None).await
+    }
+
+    /// Queues computing perceptual hashes for all stored images which lack one.
+    pub async fn queue_backfill_perceptual_hashes(
+        ctx: &ServiceContext<'_>,
+    ) -> Result<()> {
+        debug!("Queuing backfill of perceptual hashes for stored images");
+        Self::queue_job(
+            ctx,
+            &Job::BackfillPerceptualHashes { last_hash: None },
+            None,
+        )
+        .await
+    }
 }
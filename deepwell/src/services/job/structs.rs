@@ -28,7 +28,35 @@ pub enum Job {
     },
     PruneSessions,
     PruneText,
-    // TODO add job for pruning incomplete uploads (pending_blob table and corresponding columns for foreign keys)
+    PrunePendingBlobs,
     NameChangeRefill,
     LiftExpiredPunishments,
+    Export {
+        export_id: String,
+    },
+    FileArchive {
+        file_archive_id: i64,
+    },
+    NotifyAccountLocked {
+        user_id: i64,
+    },
+    RevokeOauthGrant {
+        oauth_client_id: i64,
+        user_id: i64,
+    },
+    DeleteUser {
+        user_deletion_id: i64,
+    },
+    RescanBlobs {
+        /// Hex hash of the last blob rescanned, or `None` to start from the beginning.
+        last_hash: Option<String>,
+    },
+    BackfillPerceptualHashes {
+        /// Hex hash of the last image processed, or `None` to start from the beginning.
+        last_hash: Option<String>,
+    },
+    GenerateThumbnails {
+        /// Hex hash of the image blob to process.
+        s3_hash: String,
+    },
 }
@@ -22,7 +22,12 @@
 
 use super::prelude::*;
 use crate::api::ServerState;
-use crate::services::{PageRevisionService, SessionService, TextService, UserService};
+use crate::hash::{blob_hash_to_hex, slice_to_blob_hash};
+use crate::services::{
+    AuthenticationService, BlobService, ExportService, FileArchiveService, OauthService,
+    PageRevisionService, SessionService, TextService, ThumbnailService,
+    UserDeletionService, UserService,
+};
 use crate::utils::debug_pointer;
 use rsmq_async::{PooledRsmq, RsmqConnection, RsmqMessage};
 use sea_orm::TransactionTrait;
@@ -189,6 +194,7 @@ impl JobWorker {
             Job::PruneSessions => {
                 debug!("Pruning all expired sesions from database");
                 SessionService::prune(ctx).await?;
+                OauthService::prune(ctx).await?;
                 NextJob::Next {
                     job: Job::PruneSessions,
                     delay: Some(self.state.config.job_prune_session),
@@ -202,6 +208,14 @@ impl JobWorker {
                     delay: Some(self.state.config.job_prune_text),
                 }
             }
+            Job::PrunePendingBlobs => {
+                debug!("Pruning all expired pending blobs from database");
+                BlobService::prune_pending(ctx).await?;
+                NextJob::Next {
+                    job: Job::PrunePendingBlobs,
+                    delay: Some(self.state.config.job_prune_pending_blob),
+                }
+            }
             Job::NameChangeRefill => {
                 debug!("Checking users for those who can get a name change token refill");
                 UserService::refresh_name_change_tokens(ctx).await?;
@@ -224,6 +238,142 @@ impl JobWorker {
                     delay: Some(self.state.config.job_lift_expired_punishments),
                 }
             }
+            Job::Export { export_id } => {
+                debug!("Writing archive for export ID {export_id}");
+
+                // Exports can take much longer than other jobs, so hide the
+                // message for longer, otherwise another worker would pick it up.
+                self.rsmq
+                    .change_message_visibility(
+                        JOB_QUEUE_NAME,
+                        &data.id,
+                        self.state.config.job_export_process,
+                    )
+                    .await?;
+
+                if let Err(error) = ExportService::run(ctx, &export_id).await {
+                    // The job's transaction is rolled back on failure,
+                    // so the error must be recorded in a separate one.
+                    if no_more_retries {
+                        let txn = self.state.database.begin().await?;
+                        let ctx = &ServiceContext::new(&self.state, &txn);
+                        ExportService::set_error(ctx, &export_id, error.to_string())
+                            .await?;
+                        txn.commit().await?;
+                    }
+
+                    return Err(error);
+                }
+
+                NextJob::Done
+            }
+            Job::FileArchive { file_archive_id } => {
+                debug!("Writing archive for file archive ID {file_archive_id}");
+
+                // Like exports, this fetches many blobs, so hide the message for longer
+                self.rsmq
+                    .change_message_visibility(
+                        JOB_QUEUE_NAME,
+                        &data.id,
+                        self.state.config.job_export_process,
+                    )
+                    .await?;
+
+                if let Err(error) = FileArchiveService::run(ctx, file_archive_id).await {
+                    // See Job::Export above
+                    if no_more_retries {
+                        let txn = self.state.database.begin().await?;
+                        let ctx = &ServiceContext::new(&self.state, &txn);
+                        FileArchiveService::set_error(
+                            ctx,
+                            file_archive_id,
+                            error.to_string(),
+                        )
+                        .await?;
+                        txn.commit().await?;
+                    }
+
+                    return Err(error);
+                }
+
+                NextJob::Done
+            }
+            Job::NotifyAccountLocked { user_id } => {
+                debug!("Notifying user ID {user_id} that their account was locked");
+                AuthenticationService::notify_locked(ctx, user_id).await?;
+                NextJob::Done
+            }
+            Job::RevokeOauthGrant {
+                oauth_client_id,
+                user_id,
+            } => {
+                debug!("Revoking OAuth grant for client ID {oauth_client_id} and user ID {user_id}");
+                OauthService::revoke_grant(ctx, oauth_client_id, user_id).await?;
+                NextJob::Done
+            }
+            Job::DeleteUser { user_deletion_id } => {
+                debug!("Running user deletion ID {user_deletion_id}");
+                match UserDeletionService::run(ctx, user_deletion_id).await? {
+                    None => NextJob::Done,
+                    Some(delay) => {
+                        debug!(
+                            "User deletion is not due yet, checking again in {delay:?}"
+                        );
+                        NextJob::Next {
+                            job: Job::DeleteUser { user_deletion_id },
+                            delay: Some(delay),
+                        }
+                    }
+                }
+            }
+            Job::RescanBlobs { last_hash } => {
+                debug!("Rescanning stored blobs for malware after {last_hash:?}");
+                let last_hash = match last_hash {
+                    Some(hex_hash) => {
+                        let bytes =
+                            hex::decode(hex_hash).map_err(|_| Error::BadRequest)?;
+                        Some(slice_to_blob_hash(&bytes))
+                    }
+                    None => None,
+                };
+
+                match BlobService::rescan_batch(ctx, last_hash).await? {
+                    None => NextJob::Done,
+                    Some(hash) => NextJob::Next {
+                        job: Job::RescanBlobs {
+                            last_hash: Some(blob_hash_to_hex(&hash).to_string()),
+                        },
+                        delay: None,
+                    },
+                }
+            }
+            Job::BackfillPerceptualHashes { last_hash } => {
+                debug!("Backfilling perceptual hashes for images after {last_hash:?}");
+                let last_hash = match last_hash {
+                    Some(hex_hash) => {
+                        let bytes =
+                            hex::decode(hex_hash).map_err(|_| Error::BadRequest)?;
+                        Some(slice_to_blob_hash(&bytes))
+                    }
+                    None => None,
+                };
+
+                match BlobService::backfill_perceptual_batch(ctx, last_hash).await? {
+                    None => NextJob::Done,
+                    Some(hash) => NextJob::Next {
+                        job: Job::BackfillPerceptualHashes {
+                            last_hash: Some(blob_hash_to_hex(&hash).to_string()),
+                        },
+                        delay: None,
+                    },
+                }
+            }
+            Job::GenerateThumbnails { s3_hash } => {
+                debug!("Generating thumbnails for blob {s3_hash}");
+                let bytes = hex::decode(s3_hash).map_err(|_| Error::BadRequest)?;
+                ThumbnailService::generate(ctx, slice_to_blob_hash(&bytes)).await?;
+                NextJob::Done
+            }
         };
 
         // Don't delete more than once
@@ -43,8 +43,10 @@ mod prelude {
 
 #[macro_use]
 mod macros;
+mod redirect;
 mod service;
 mod structs;
 
+pub use self::redirect::{parse_redirect, redirect_wikitext};
 pub use self::service::LinkService;
 pub use self::structs::*;
@@ -0,0 +1,120 @@
+/*
+ * services/link/redirect.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Detection of redirect modules in page wikitext.
+//!
+//! The parser does not handle `[[module Redirect]]` itself, so the
+//! destination is extracted here, and then stored as a page connection
+//! of type `redirect` alongside the page's other backlinks.
+//!
+//! Modules inside code blocks, raw text, and comments are not rendered,
+//! so they are skipped, otherwise a page documenting the redirect module
+//! would itself become a redirect.
+
+use ftml::data::PageRef;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Matches either a redirect module, or a span of wikitext which is not rendered.
+///
+/// Since matches are found from left to right, any redirect module within
+/// such a span is consumed as part of it, and only the first group is
+/// captured for modules outside of them.
+static REDIRECT_MODULE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r#"(?is)\[\[\s*module\s+redirect\s+destination\s*=\s*"([^"]+)"\s*\]\]"#,
+        r"|\[\[\s*code\b.*?\[\[/\s*code\s*\]\]",
+        r"|\[\[\s*raw\s*\]\].*?\[\[/\s*raw\s*\]\]",
+        r"|@@.*?@@",
+        r"|@<.*?>@",
+        r"|\[!--.*?--\]",
+    ))
+    .unwrap()
+});
+
+/// Builds the wikitext for a page which redirects to the given slug.
+pub fn redirect_wikitext(destination: &str) -> String {
+    format!(r#"[[module Redirect destination="{destination}"]]"#)
+}
+
+/// Finds the destination of the first redirect module in the wikitext, if any.
+pub fn parse_redirect(wikitext: &str) -> Option<PageRef<'_>> {
+    let destination = REDIRECT_MODULE
+        .captures_iter(wikitext)
+        .find_map(|captures| captures.get(1))?
+        .as_str();
+
+    PageRef::parse(destination).ok()
+}
+
+#[test]
+fn redirects() {
+    macro_rules! check {
+        ($wikitext:expr, $expected:expr $(,)?) => {{
+            let wikitext: &str = &$wikitext;
+            let actual = parse_redirect(wikitext);
+            let actual = actual.as_ref().map(PageRef::fields);
+            assert_eq!(actual, $expected, "Actual redirect doesn't match expected");
+        }};
+    }
+
+    check!("", None);
+    check!("**some** text", None);
+    check!("[[module Redirect]]", None);
+    check!(r#"[[module Redirect destination=""]]"#, None);
+    check!(
+        r#"[[module Redirect destination="scp-001"]]"#,
+        Some((None, "scp-001"))
+    );
+    check!(
+        r#"[[MODULE redirect   destination = "system:join" ]]"#,
+        Some((None, "system:join")),
+    );
+    check!(
+        r#"[[module Redirect destination=":scp-wiki:main"]]"#,
+        Some((Some("scp-wiki"), "main")),
+    );
+    check!(
+        "Text before\n[[module Redirect destination=\"a\"]]\n[[module Redirect destination=\"b\"]]",
+        Some((None, "a")),
+    );
+    check!(
+        redirect_wikitext("fragment:test"),
+        Some((None, "fragment:test")),
+    );
+
+    // Not rendered, so not redirects
+    check!(
+        "[[code]]\n[[module Redirect destination=\"a\"]]\n[[/code]]",
+        None,
+    );
+    check!(
+        "[[code type=\"html\"]][[module Redirect destination=\"a\"]][[/CODE]]",
+        None,
+    );
+    check!("@@[[module Redirect destination=\"a\"]]@@", None);
+    check!("@<[[module Redirect destination=\"a\"]]>@", None);
+    check!("[!-- [[module Redirect destination=\"a\"]] --]", None);
+    check!("[[raw]][[module Redirect destination=\"a\"]][[/raw]]", None,);
+    check!(
+        "[[code]]\n[[module Redirect destination=\"a\"]]\n[[/code]]\n[[module Redirect destination=\"b\"]]",
+        Some((None, "b")),
+    );
+}
@@ -110,6 +110,27 @@ impl LinkService {
         Ok(GetConnectionsFromOutput { present, absent })
     }
 
+    /// Gets the ID of the page this page redirects to, if any.
+    pub async fn get_redirect(
+        ctx: &ServiceContext<'_>,
+        page_id: i64,
+    ) -> Result<Option<i64>> {
+        let txn = ctx.transaction();
+        let connection = PageConnection::find()
+            .filter(
+                Condition::all()
+                    .add(page_connection::Column::FromPageId.eq(page_id))
+                    .add(
+                        page_connection::Column::ConnectionType
+                            .eq(ConnectionType::Redirect.name()),
+                    ),
+            )
+            .one(txn)
+            .await?;
+
+        Ok(connection.map(|connection| connection.to_page_id))
+    }
+
     pub async fn get_to(
         ctx: &ServiceContext<'_>,
         page_id: i64,
@@ -228,6 +249,7 @@ impl LinkService {
         site_id: i64,
         page_id: i64,
         backlinks: &Backlinks<'_>,
+        redirect: Option<&PageRef<'_>>,
     ) -> Result<()> {
         let mut connections = HashMap::new();
         let mut connections_missing = HashMap::new();
@@ -260,6 +282,19 @@ impl LinkService {
             .await?;
         }
 
+        // Get redirect, if the page has one
+        if let Some(redirect) = redirect {
+            count_connections(
+                ctx,
+                site_id,
+                redirect,
+                ConnectionType::Redirect,
+                &mut connections,
+                &mut connections_missing,
+            )
+            .await?;
+        }
+
         // Gather external URL link stats
         for url in &backlinks.external_links {
             let entry = external_links.entry(str!(url)).or_insert(0);
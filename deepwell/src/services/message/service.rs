@@ -19,6 +19,7 @@
  */
 
 use super::prelude::*;
+use crate::constants::SYSTEM_USER_ID;
 use crate::models::message::{self, Entity as Message, Model as MessageModel};
 use crate::models::message_draft::{
     self, Entity as MessageDraft, Model as MessageDraftModel,
@@ -28,8 +29,9 @@ use crate::models::message_record::{
     self, Entity as MessageRecord, Model as MessageRecordModel,
 };
 use crate::models::sea_orm_active_enums::{MessageRecipientType, UserType};
+use crate::services::rate_limit::{RateLimitAction, RateLimitScope};
 use crate::services::render::{RenderOutput, RenderService};
-use crate::services::{RelationService, TextService, UserService};
+use crate::services::{RateLimitService, RelationService, TextService, UserService};
 use crate::utils::validate_locale;
 use cuid2::cuid;
 use ftml::data::{PageInfo, ScoreValue};
@@ -266,6 +268,18 @@ impl MessageService {
             return Err(Error::MessageTooManyRecipients);
         }
 
+        // Limit how often users can send messages,
+        // except for notices sent by the system itself.
+        if draft.user_id != SYSTEM_USER_ID {
+            RateLimitService::check(
+                ctx,
+                RateLimitAction::MessageSend,
+                RateLimitScope::User(draft.user_id),
+                config.rate_limit_message_send,
+            )
+            .await?;
+        }
+
         let mut recipients_to_add = Vec::new();
         for recipient_user_id in recipients.iter() {
             // Ensure user is not blocked
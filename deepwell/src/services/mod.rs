@@ -57,13 +57,19 @@ mod context;
 mod error;
 
 pub mod alias;
+pub mod api_token;
+pub mod avatar;
 // TODO create audit
 pub mod authentication;
 pub mod blob;
 pub mod category;
+pub mod contribution;
+pub mod diff;
 pub mod domain;
 pub mod email;
+pub mod export;
 pub mod file;
+pub mod file_archive;
 pub mod file_revision;
 pub mod filter;
 pub mod import;
@@ -72,12 +78,16 @@ pub mod link;
 pub mod message;
 pub mod message_report;
 pub mod mfa;
+pub mod oauth;
+pub mod oidc;
 pub mod outdate;
 pub mod page;
 pub mod page_query;
 pub mod page_revision;
 pub mod parent;
 pub mod password;
+pub mod quota;
+pub mod rate_limit;
 pub mod relation;
 pub mod render;
 pub mod score;
@@ -86,19 +96,28 @@ pub mod settings;
 pub mod site;
 pub mod special_page;
 pub mod text;
+pub mod thumbnail;
 pub mod user;
 pub mod user_bot_owner;
+pub mod user_deletion;
 pub mod view;
 pub mod vote;
+pub mod webauthn;
 
 pub use self::alias::AliasService;
+pub use self::api_token::ApiTokenService;
 pub use self::authentication::AuthenticationService;
+pub use self::avatar::AvatarService;
 pub use self::blob::BlobService;
 pub use self::category::CategoryService;
 pub use self::context::ServiceContext;
+pub use self::contribution::ContributionService;
+pub use self::diff::DiffService;
 pub use self::domain::DomainService;
 pub use self::error::*;
+pub use self::export::ExportService;
 pub use self::file::FileService;
+pub use self::file_archive::FileArchiveService;
 pub use self::file_revision::FileRevisionService;
 pub use self::filter::FilterService;
 pub use self::job::JobService;
@@ -106,6 +125,8 @@ pub use self::link::LinkService;
 pub use self::message::MessageService;
 pub use self::message_report::MessageReportService;
 pub use self::mfa::MfaService;
+pub use self::oauth::OauthService;
+pub use self::oidc::OidcService;
 pub use self::outdate::OutdateService;
 pub use self::page::PageService;
 // TODO convert page attribution to a type of relation
@@ -113,6 +134,8 @@ pub use self::page_query::PageQueryService;
 pub use self::page_revision::PageRevisionService;
 pub use self::parent::ParentService;
 pub use self::password::PasswordService;
+pub use self::quota::QuotaService;
+pub use self::rate_limit::RateLimitService;
 pub use self::relation::RelationService;
 pub use self::render::RenderService;
 pub use self::score::ScoreService;
@@ -121,8 +144,11 @@ pub use self::settings::SettingsService;
 pub use self::site::SiteService;
 pub use self::special_page::SpecialPageService;
 pub use self::text::TextService;
+pub use self::thumbnail::ThumbnailService;
 pub use self::user::UserService;
 // TODO convert user-bot to a type of relation
 pub use self::user_bot_owner::UserBotOwnerService;
+pub use self::user_deletion::UserDeletionService;
 pub use self::view::ViewService;
 pub use self::vote::VoteService;
+pub use self::webauthn::WebauthnService;
@@ -0,0 +1,44 @@
+/*
+ * services/oauth/mod.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The OAuth service, for granting third-party applications access to accounts.
+//!
+//! DEEPWELL acts as an OAuth 2 authorization server. Users register client
+//! applications, which other users can then authorize using the authorization
+//! code flow. PKCE is required for all clients, and clients which can keep a
+//! secret must also authenticate with it.
+//!
+//! Access tokens are scoped tokens, using the same scopes as bot API tokens,
+//! and are accepted anywhere a session token is. They are short-lived, and
+//! are renewed with refresh tokens, which are rotated each time they are used.
+//! If a refresh token is used after being replaced, it may have been stolen,
+//! so the grant is revoked entirely.
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use super::super::prelude::*;
+    pub use super::structs::*;
+}
+
+mod service;
+mod structs;
+
+pub use self::service::OauthService;
+pub use self::structs::*;
@@ -0,0 +1,912 @@
+/*
+ * services/oauth/service.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use crate::models::oauth_client::{
+    self, Entity as OauthClient, Model as OauthClientModel,
+};
+use crate::models::oauth_token::{self, Entity as OauthToken, Model as OauthTokenModel};
+use crate::models::session::Model as SessionModel;
+use crate::services::api_token::ApiScope;
+use crate::services::job::{Job, JobService};
+use crate::services::session::ScopedSession;
+use crate::services::UserService;
+use crate::utils::{assert_is_csprng, pkce_challenge, LAST_USED_PRECISION};
+use rand::distributions::{Alphanumeric, DistString};
+use rand::thread_rng;
+use reqwest::Url;
+use sea_orm::UpdateResult;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use subtle::ConstantTimeEq;
+
+#[derive(Debug)]
+pub struct OauthService;
+
+impl OauthService {
+    // Client management
+
+    /// Registers a new client application.
+    ///
+    /// This does not verify the session, the caller is responsible for that.
+    ///
+    /// # Returns
+    /// The client's identifier, and its secret if it is confidential.
+    /// Only a hash of the secret is stored, so this is the only time it is available.
+    pub async fn create_client(
+        ctx: &ServiceContext<'_>,
+        CreateOauthClient {
+            user_id,
+            name,
+            redirect_uris,
+            scopes,
+            confidential,
+            ..
+        }: CreateOauthClient,
+    ) -> Result<CreateOauthClientOutput> {
+        info!("Creating OAuth client '{name}' by user ID {user_id}");
+
+        if name.is_empty() || redirect_uris.is_empty() || scopes.is_empty() {
+            error!(
+                "OAuth client must have a name, a redirect URI, and at least one scope"
+            );
+            return Err(Error::BadRequest);
+        }
+
+        if let Some(uri) = redirect_uris.iter().find(|uri| !valid_redirect_uri(uri)) {
+            error!("OAuth client redirect URI is not allowed: {uri}");
+            return Err(Error::BadRequest);
+        }
+
+        let (client_id, client_secret) = {
+            let mut rng = thread_rng();
+            assert_is_csprng(&rng);
+
+            let client_id = Alphanumeric.sample_string(&mut rng, 24);
+            let client_secret = confidential.then(|| {
+                Alphanumeric.sample_string(&mut rng, ctx.config().oauth_token_length)
+            });
+
+            (client_id, client_secret)
+        };
+
+        let txn = ctx.transaction();
+        let model = oauth_client::ActiveModel {
+            created_by: Set(user_id),
+            created_at: Set(now()),
+            name: Set(name),
+            client_id: Set(client_id.clone()),
+            client_secret_hash: Set(client_secret.as_deref().map(hash_token)),
+            redirect_uris: Set(redirect_uris),
+            scopes: Set(scopes.into_iter().map(String::from).collect()),
+            ..Default::default()
+        };
+
+        let OauthClientModel {
+            oauth_client_id, ..
+        } = model.insert(txn).await?;
+
+        Ok(CreateOauthClientOutput {
+            oauth_client_id,
+            client_id,
+            client_secret,
+        })
+    }
+
+    /// Gets all clients registered by a user.
+    pub async fn get_clients(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+    ) -> Result<Vec<OauthClientModel>> {
+        info!("Getting all OAuth clients for user ID {user_id}");
+
+        let txn = ctx.transaction();
+        let clients = OauthClient::find()
+            .filter(
+                Condition::all()
+                    .add(oauth_client::Column::CreatedBy.eq(user_id))
+                    .add(oauth_client::Column::DeletedAt.is_null()),
+            )
+            .order_by_asc(oauth_client::Column::OauthClientId)
+            .all(txn)
+            .await?;
+
+        Ok(clients)
+    }
+
+    /// Deletes a client, revoking all tokens issued to it.
+    ///
+    /// Only the user who registered the client may delete it.
+    /// This does not verify the session, the caller is responsible for that.
+    pub async fn delete_client(
+        ctx: &ServiceContext<'_>,
+        oauth_client_id: i64,
+        user_id: i64,
+    ) -> Result<()> {
+        info!("Deleting OAuth client ID {oauth_client_id} by user ID {user_id}");
+
+        let txn = ctx.transaction();
+        let client = OauthClient::find_by_id(oauth_client_id)
+            .filter(
+                Condition::all()
+                    .add(oauth_client::Column::CreatedBy.eq(user_id))
+                    .add(oauth_client::Column::DeletedAt.is_null()),
+            )
+            .one(txn)
+            .await?
+            .ok_or(Error::OauthClientNotFound)?;
+
+        let model = oauth_client::ActiveModel {
+            oauth_client_id: Set(client.oauth_client_id),
+            deleted_at: Set(Some(now())),
+            ..Default::default()
+        };
+        model.update(txn).await?;
+
+        let UpdateResult { rows_affected } = OauthToken::update_many()
+            .set(oauth_token::ActiveModel {
+                revoked_at: Set(Some(now())),
+                ..Default::default()
+            })
+            .filter(
+                Condition::all()
+                    .add(oauth_token::Column::OauthClientId.eq(oauth_client_id))
+                    .add(oauth_token::Column::RevokedAt.is_null()),
+            )
+            .exec(txn)
+            .await?;
+
+        debug!("Revoked {rows_affected} tokens for deleted OAuth client");
+        Ok(())
+    }
+
+    async fn get_client(
+        ctx: &ServiceContext<'_>,
+        client_id: &str,
+    ) -> Result<Option<OauthClientModel>> {
+        let txn = ctx.transaction();
+        let client = OauthClient::find()
+            .filter(
+                Condition::all()
+                    .add(oauth_client::Column::ClientId.eq(client_id))
+                    .add(oauth_client::Column::DeletedAt.is_null()),
+            )
+            .one(txn)
+            .await?;
+
+        Ok(client)
+    }
+
+    /// Looks up a client making a request to us, and verifies its secret.
+    ///
+    /// Public clients do not have a secret, and must not send one.
+    async fn authenticate_client(
+        ctx: &ServiceContext<'_>,
+        client_id: &str,
+        client_secret: Option<&str>,
+    ) -> Result<OauthClientModel> {
+        let client = match Self::get_client(ctx, client_id).await? {
+            Some(client) => client,
+            None => {
+                warn!("No OAuth client with client ID {client_id}");
+                return Err(Error::OauthInvalidClient);
+            }
+        };
+
+        let valid = match (&client.client_secret_hash, client_secret) {
+            (Some(secret_hash), Some(secret)) => {
+                secret_hash.ct_eq(&hash_token(secret)).into()
+            }
+            (None, None) => true,
+            _ => false,
+        };
+
+        if valid {
+            Ok(client)
+        } else {
+            warn!("Client authentication failed for OAuth client ID {client_id}");
+            Err(Error::OauthInvalidClient)
+        }
+    }
+
+    // Authorization
+
+    /// Validates an authorization request from a client.
+    ///
+    /// The client and redirect URI are checked first. If either is invalid
+    /// we must not send the user back, so those errors are distinct from
+    /// the `Oauth*` errors that can be reported to the client.
+    async fn check_authorization(
+        ctx: &ServiceContext<'_>,
+        request: &AuthorizationRequest,
+    ) -> Result<(OauthClientModel, Vec<ApiScope>)> {
+        let client = Self::get_client(ctx, &request.client_id)
+            .await?
+            .ok_or(Error::OauthClientNotFound)?;
+
+        if !client.redirect_uris.contains(&request.redirect_uri) {
+            error!(
+                "Redirect URI is not registered for OAuth client ID {}: {}",
+                client.oauth_client_id, request.redirect_uri,
+            );
+            return Err(Error::BadRequest);
+        }
+
+        if request.response_type != "code" {
+            return Err(Error::OauthInvalidRequest("unsupported response type"));
+        }
+
+        if request.code_challenge_method != "S256" || request.code_challenge.len() != 43 {
+            return Err(Error::OauthInvalidRequest("PKCE with S256 is required"));
+        }
+
+        let client_scopes = parse_scopes(&client.scopes.join(" "))?;
+        let scopes = parse_scopes(&request.scope)?;
+        if scopes.is_empty() {
+            return Ok((client, client_scopes));
+        }
+
+        if !scopes
+            .iter()
+            .all(|&scope| ApiScope::any_grants(&client_scopes, scope))
+        {
+            warn!("OAuth client requested scopes it was not registered with");
+            return Err(Error::OauthInvalidScope);
+        }
+
+        Ok((client, scopes))
+    }
+
+    /// Validates an authorization request, before asking the user for consent.
+    pub async fn start_authorization(
+        ctx: &ServiceContext<'_>,
+        request: &AuthorizationRequest,
+    ) -> Result<OauthConsentOutput> {
+        info!(
+            "Starting OAuth authorization for client ID {}",
+            request.client_id,
+        );
+
+        let (client, scopes) = Self::check_authorization(ctx, request).await?;
+        Ok(OauthConsentOutput {
+            client_name: client.name,
+            scopes,
+        })
+    }
+
+    /// Completes an authorization request after the user has responded.
+    ///
+    /// If they approved, an authorization code is issued, which the client
+    /// exchanges for tokens. Either way, the user is sent back to the client.
+    ///
+    /// This does not verify the session, the caller is responsible for that.
+    pub async fn finish_authorization(
+        ctx: &ServiceContext<'_>,
+        request: &AuthorizationRequest,
+        user_id: i64,
+        approve: bool,
+    ) -> Result<OauthRedirectOutput> {
+        info!(
+            "Finishing OAuth authorization for client ID {} by user ID {user_id} (approve {approve})",
+            request.client_id,
+        );
+
+        let (client, scopes) = Self::check_authorization(ctx, request).await?;
+        let mut redirect_url =
+            Url::parse(&request.redirect_uri).map_err(|_| Error::BadRequest)?;
+
+        let (key, value) = if approve {
+            let code = {
+                let mut rng = thread_rng();
+                assert_is_csprng(&rng);
+                Alphanumeric.sample_string(&mut rng, 32)
+            };
+
+            let stored = OauthCode {
+                oauth_client_id: client.oauth_client_id,
+                user_id,
+                redirect_uri: request.redirect_uri.clone(),
+                scopes,
+                code_challenge: request.code_challenge.clone(),
+            };
+
+            let mut redis = ctx.redis_connect().await?;
+            redis::cmd("SET")
+                .arg(code_key(&code))
+                .arg(serde_json::to_string(&stored)?)
+                .arg("PX")
+                .arg(ctx.config().oauth_code_timeout.as_millis() as u64)
+                .query_async::<_, ()>(&mut redis)
+                .await?;
+
+            ("code", code)
+        } else {
+            ("error", str!("access_denied"))
+        };
+
+        {
+            let mut query = redirect_url.query_pairs_mut();
+            query.append_pair(key, &value);
+
+            if let Some(ref state) = request.state {
+                query.append_pair("state", state);
+            }
+        }
+
+        Ok(OauthRedirectOutput {
+            redirect_url: redirect_url.into(),
+        })
+    }
+
+    async fn take_code(ctx: &ServiceContext<'_>, code: &str) -> Result<OauthCode> {
+        let mut redis = ctx.redis_connect().await?;
+        let stored: Option<String> = redis::cmd("GETDEL")
+            .arg(code_key(code))
+            .query_async(&mut redis)
+            .await?;
+
+        match stored {
+            Some(stored) => Ok(serde_json::from_str(&stored)?),
+            None => Err(Error::OauthInvalidGrant),
+        }
+    }
+
+    // Tokens
+
+    /// Handles a request to the token endpoint.
+    ///
+    /// This exchanges either an authorization code or a refresh token
+    /// for a new pair of tokens. Refresh tokens can only be used once.
+    pub async fn exchange(
+        ctx: &ServiceContext<'_>,
+        request: OauthTokenRequest,
+    ) -> Result<OauthTokenOutput> {
+        info!(
+            "Handling OAuth token request for client ID {} (grant type '{}')",
+            request.client_id, request.grant_type,
+        );
+
+        let client = Self::authenticate_client(
+            ctx,
+            &request.client_id,
+            request.client_secret.as_deref(),
+        )
+        .await?;
+
+        match request.grant_type.as_str() {
+            "authorization_code" => Self::exchange_code(ctx, &client, &request).await,
+            "refresh_token" => Self::exchange_refresh_token(ctx, &client, &request).await,
+            _ => Err(Error::OauthInvalidRequest("unsupported grant type")),
+        }
+    }
+
+    async fn exchange_code(
+        ctx: &ServiceContext<'_>,
+        client: &OauthClientModel,
+        request: &OauthTokenRequest,
+    ) -> Result<OauthTokenOutput> {
+        let (code, redirect_uri, code_verifier) =
+            match (&request.code, &request.redirect_uri, &request.code_verifier) {
+                (Some(code), Some(redirect_uri), Some(code_verifier)) => {
+                    (code, redirect_uri, code_verifier)
+                }
+                _ => {
+                    return Err(Error::OauthInvalidRequest(
+                        "code, redirect URI, and code verifier are required",
+                    ))
+                }
+            };
+
+        let code = Self::take_code(ctx, code).await?;
+        if code.oauth_client_id != client.oauth_client_id {
+            warn!("Authorization code was issued to a different OAuth client");
+            return Err(Error::OauthInvalidGrant);
+        }
+
+        if code.redirect_uri != *redirect_uri {
+            warn!("Redirect URI does not match the authorization request");
+            return Err(Error::OauthInvalidGrant);
+        }
+
+        if !(43..=128).contains(&code_verifier.len())
+            || pkce_challenge(code_verifier) != code.code_challenge
+        {
+            warn!("PKCE code verifier does not match the code challenge");
+            return Err(Error::OauthInvalidGrant);
+        }
+
+        let user = UserService::get(ctx, Reference::Id(code.user_id)).await?;
+        if user.deleted_at.is_some() {
+            error!("Authorization code was issued for a deleted user");
+            return Err(Error::OauthInvalidGrant);
+        }
+
+        Self::issue(ctx, client, code.user_id, code.scopes).await
+    }
+
+    async fn exchange_refresh_token(
+        ctx: &ServiceContext<'_>,
+        client: &OauthClientModel,
+        request: &OauthTokenRequest,
+    ) -> Result<OauthTokenOutput> {
+        let refresh_token = match request.refresh_token {
+            Some(ref refresh_token) => refresh_token,
+            None => return Err(Error::OauthInvalidRequest("refresh token is required")),
+        };
+
+        let txn = ctx.transaction();
+        let token = OauthToken::find()
+            .filter(oauth_token::Column::RefreshTokenHash.eq(hash_token(refresh_token)))
+            .one(txn)
+            .await?
+            .ok_or(Error::OauthInvalidGrant)?;
+
+        if token.oauth_client_id != client.oauth_client_id {
+            warn!("Refresh token was issued to a different OAuth client");
+            return Err(Error::OauthInvalidGrant);
+        }
+
+        if token.revoked_at.is_some() {
+            // Refresh tokens are replaced each time they are used, so this one
+            // may have been stolen. We can't tell which party is legitimate,
+            // so revoke the entire grant.
+            //
+            // This has to happen in a job, since this request's transaction
+            // is rolled back when returning an error.
+            warn!(
+                "Revoked refresh token was used, revoking all tokens for OAuth client ID {} and user ID {}",
+                token.oauth_client_id, token.user_id,
+            );
+
+            JobService::queue_job(
+                ctx,
+                &Job::RevokeOauthGrant {
+                    oauth_client_id: token.oauth_client_id,
+                    user_id: token.user_id,
+                },
+                None,
+            )
+            .await?;
+
+            return Err(Error::OauthInvalidGrant);
+        }
+
+        if token.refresh_expires_at <= now() {
+            warn!("Refresh token is expired");
+            return Err(Error::OauthInvalidGrant);
+        }
+
+        // The client may ask for fewer scopes than it was originally granted
+        let granted_scopes = parse_scopes(&token.scopes.join(" "))?;
+        let scopes = match request.scope {
+            None => granted_scopes,
+            Some(ref scope) => {
+                let scopes = parse_scopes(scope)?;
+                if !scopes
+                    .iter()
+                    .all(|&scope| ApiScope::any_grants(&granted_scopes, scope))
+                {
+                    warn!("OAuth client requested scopes beyond its original grant");
+                    return Err(Error::OauthInvalidScope);
+                }
+
+                scopes
+            }
+        };
+
+        // Only one request may use the refresh token, so revoke it only if
+        // no other request has done so since we checked above.
+        let UpdateResult { rows_affected } = OauthToken::update_many()
+            .set(oauth_token::ActiveModel {
+                revoked_at: Set(Some(now())),
+                ..Default::default()
+            })
+            .filter(
+                Condition::all()
+                    .add(oauth_token::Column::OauthTokenId.eq(token.oauth_token_id))
+                    .add(oauth_token::Column::RevokedAt.is_null()),
+            )
+            .exec(txn)
+            .await?;
+
+        if rows_affected != 1 {
+            warn!("Refresh token was used concurrently by another request");
+            return Err(Error::OauthInvalidGrant);
+        }
+
+        Self::issue(ctx, client, token.user_id, scopes).await
+    }
+
+    async fn issue(
+        ctx: &ServiceContext<'_>,
+        client: &OauthClientModel,
+        user_id: i64,
+        scopes: Vec<ApiScope>,
+    ) -> Result<OauthTokenOutput> {
+        info!(
+            "Issuing OAuth tokens for client ID {} to user ID {user_id}",
+            client.oauth_client_id,
+        );
+
+        let config = ctx.config();
+        let (access_token, refresh_token) = {
+            let mut rng = thread_rng();
+            assert_is_csprng(&rng);
+
+            let mut access_token =
+                Alphanumeric.sample_string(&mut rng, config.oauth_token_length);
+            access_token.insert_str(0, &config.oauth_access_token_prefix);
+
+            let mut refresh_token =
+                Alphanumeric.sample_string(&mut rng, config.oauth_token_length);
+            refresh_token.insert_str(0, &config.oauth_refresh_token_prefix);
+
+            (access_token, refresh_token)
+        };
+
+        let txn = ctx.transaction();
+        let now = now();
+        let scope = format_scopes(&scopes);
+        let model = oauth_token::ActiveModel {
+            oauth_client_id: Set(client.oauth_client_id),
+            user_id: Set(user_id),
+            created_at: Set(now),
+            scopes: Set(scopes.into_iter().map(String::from).collect()),
+            access_token_hash: Set(hash_token(&access_token)),
+            access_expires_at: Set(now + config.oauth_access_token_duration),
+            refresh_token_hash: Set(hash_token(&refresh_token)),
+            refresh_expires_at: Set(now + config.oauth_refresh_token_duration),
+            ..Default::default()
+        };
+        model.insert(txn).await?;
+
+        Ok(OauthTokenOutput {
+            access_token,
+            token_type: "Bearer",
+            expires_in: config.oauth_access_token_duration.whole_seconds(),
+            refresh_token,
+            scope,
+        })
+    }
+
+    /// Determines if this token is an OAuth access token, rather than a session token.
+    ///
+    /// This only checks the prefix, not whether the token is valid.
+    #[inline]
+    pub fn is_access_token(config: &Config, token: &str) -> bool {
+        token.starts_with(&config.oauth_access_token_prefix)
+    }
+
+    /// Looks up an active OAuth access token, and marks it as used.
+    ///
+    /// The last use time is only updated once it is stale, as with API tokens.
+    /// Yields `None` if the token does not exist, was revoked, or is expired.
+    pub async fn authenticate(
+        ctx: &ServiceContext<'_>,
+        token: &str,
+    ) -> Result<Option<OauthTokenModel>> {
+        debug!("Looking up OAuth access token");
+
+        let txn = ctx.transaction();
+        let now = now();
+        let model = OauthToken::find()
+            .filter(
+                Condition::all()
+                    .add(oauth_token::Column::AccessTokenHash.eq(hash_token(token)))
+                    .add(oauth_token::Column::RevokedAt.is_null())
+                    .add(oauth_token::Column::AccessExpiresAt.gt(now)),
+            )
+            .one(txn)
+            .await?;
+
+        let model = match model {
+            Some(model) => model,
+            None => return Ok(None),
+        };
+
+        if let Some(last_used_at) = model.last_used_at {
+            if now - last_used_at < LAST_USED_PRECISION {
+                return Ok(Some(model));
+            }
+        }
+
+        let active_model = oauth_token::ActiveModel {
+            oauth_token_id: Set(model.oauth_token_id),
+            last_used_at: Set(Some(now)),
+            ..Default::default()
+        };
+
+        let model = active_model.update(txn).await?;
+        Ok(Some(model))
+    }
+
+    /// Produces a session for a request made with an OAuth access token.
+    ///
+    /// Like API tokens, nothing is stored and the session is not bound
+    /// to a network or device. See `SessionService::get_scoped()`.
+    pub async fn get_session(
+        ctx: &ServiceContext<'_>,
+        token: &str,
+        ip_address: IpAddr,
+        user_agent: &str,
+    ) -> Result<Option<ScopedSession>> {
+        let model = match Self::authenticate(ctx, token).await? {
+            Some(model) => model,
+            None => return Ok(None),
+        };
+
+        let scopes = ApiScope::parse_all(&model.scopes);
+        let now = now();
+        let session = SessionModel {
+            session_token: str!(token),
+            user_id: model.user_id,
+            created_at: now,
+            expires_at: model.access_expires_at,
+            last_used_at: now,
+            ip_address: ip_address.to_string(),
+            user_agent: str!(user_agent),
+            restricted: false,
+        };
+
+        Ok(Some(ScopedSession { session, scopes }))
+    }
+
+    /// Finds a token issued to this client, of either kind.
+    async fn find_client_token(
+        ctx: &ServiceContext<'_>,
+        client: &OauthClientModel,
+        token: &str,
+    ) -> Result<Option<(OauthTokenModel, bool)>> {
+        let config = ctx.config();
+        let (column, is_access) = if Self::is_access_token(config, token) {
+            (oauth_token::Column::AccessTokenHash, true)
+        } else if token.starts_with(&config.oauth_refresh_token_prefix) {
+            (oauth_token::Column::RefreshTokenHash, false)
+        } else {
+            return Ok(None);
+        };
+
+        let txn = ctx.transaction();
+        let model = OauthToken::find()
+            .filter(
+                Condition::all()
+                    .add(column.eq(hash_token(token)))
+                    .add(oauth_token::Column::OauthClientId.eq(client.oauth_client_id)),
+            )
+            .one(txn)
+            .await?;
+
+        Ok(model.map(|model| (model, is_access)))
+    }
+
+    /// Describes a token, per RFC 7662.
+    ///
+    /// Clients may only introspect their own tokens,
+    /// all others are reported as inactive.
+    pub async fn introspect(
+        ctx: &ServiceContext<'_>,
+        OauthClientTokenRequest {
+            token,
+            client_id,
+            client_secret,
+        }: OauthClientTokenRequest,
+    ) -> Result<OauthIntrospectOutput> {
+        info!("Introspecting token for OAuth client ID {client_id}");
+
+        let client =
+            Self::authenticate_client(ctx, &client_id, client_secret.as_deref()).await?;
+
+        let (model, is_access) =
+            match Self::find_client_token(ctx, &client, &token).await? {
+                Some(result) => result,
+                None => return Ok(OauthIntrospectOutput::default()),
+            };
+
+        let (token_type, expires_at) = if is_access {
+            ("access_token", model.access_expires_at)
+        } else {
+            ("refresh_token", model.refresh_expires_at)
+        };
+
+        if model.revoked_at.is_some() || expires_at <= now() {
+            return Ok(OauthIntrospectOutput::default());
+        }
+
+        Ok(OauthIntrospectOutput {
+            active: true,
+            scope: Some(model.scopes.join(" ")),
+            client_id: Some(client.client_id),
+            user_id: Some(model.user_id),
+            token_type: Some(token_type),
+            exp: Some(expires_at.unix_timestamp()),
+            iat: Some(model.created_at.unix_timestamp()),
+        })
+    }
+
+    /// Revokes a token on behalf of the client it was issued to, per RFC 7009.
+    ///
+    /// Access and refresh tokens are issued together, so revoking
+    /// either one revokes both. Unknown tokens are ignored.
+    pub async fn revoke_token(
+        ctx: &ServiceContext<'_>,
+        OauthClientTokenRequest {
+            token,
+            client_id,
+            client_secret,
+        }: OauthClientTokenRequest,
+    ) -> Result<()> {
+        info!("Revoking token for OAuth client ID {client_id}");
+
+        let client =
+            Self::authenticate_client(ctx, &client_id, client_secret.as_deref()).await?;
+
+        if let Some((model, _)) = Self::find_client_token(ctx, &client, &token).await? {
+            if model.revoked_at.is_none() {
+                let txn = ctx.transaction();
+                let model = oauth_token::ActiveModel {
+                    oauth_token_id: Set(model.oauth_token_id),
+                    revoked_at: Set(Some(now())),
+                    ..Default::default()
+                };
+                model.update(txn).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Grants
+
+    /// Gets all clients a user has currently authorized.
+    pub async fn get_grants(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+    ) -> Result<Vec<OauthGrantOutput>> {
+        info!("Getting all OAuth grants for user ID {user_id}");
+
+        let txn = ctx.transaction();
+        let tokens = OauthToken::find()
+            .find_also_related(OauthClient)
+            .filter(
+                Condition::all()
+                    .add(oauth_token::Column::UserId.eq(user_id))
+                    .add(oauth_token::Column::RevokedAt.is_null())
+                    .add(oauth_token::Column::RefreshExpiresAt.gt(now())),
+            )
+            .order_by_asc(oauth_token::Column::OauthTokenId)
+            .all(txn)
+            .await?;
+
+        let mut grants = BTreeMap::<i64, OauthGrantOutput>::new();
+        for (token, client) in tokens {
+            let client = match client {
+                Some(client) => client,
+                None => continue,
+            };
+
+            let grant = grants.entry(client.oauth_client_id).or_insert_with(|| {
+                OauthGrantOutput {
+                    oauth_client_id: client.oauth_client_id,
+                    client_name: client.name,
+                    scopes: vec![],
+                    created_at: token.created_at,
+                    last_used_at: None,
+                }
+            });
+
+            for scope in token.scopes {
+                if !grant.scopes.contains(&scope) {
+                    grant.scopes.push(scope);
+                }
+            }
+
+            grant.last_used_at = grant.last_used_at.max(token.last_used_at);
+        }
+
+        Ok(grants.into_values().collect())
+    }
+
+    /// Revokes all tokens a client has for a user.
+    ///
+    /// This is used when a user withdraws their authorization, or when
+    /// a refresh token is reused. If the user authorizes the client
+    /// again, it will be issued new tokens as normal.
+    ///
+    /// # Returns
+    /// The number of tokens which were revoked.
+    pub async fn revoke_grant(
+        ctx: &ServiceContext<'_>,
+        oauth_client_id: i64,
+        user_id: i64,
+    ) -> Result<u64> {
+        info!(
+            "Revoking OAuth grant for client ID {oauth_client_id} and user ID {user_id}"
+        );
+
+        let txn = ctx.transaction();
+        let UpdateResult { rows_affected } = OauthToken::update_many()
+            .set(oauth_token::ActiveModel {
+                revoked_at: Set(Some(now())),
+                ..Default::default()
+            })
+            .filter(
+                Condition::all()
+                    .add(oauth_token::Column::OauthClientId.eq(oauth_client_id))
+                    .add(oauth_token::Column::UserId.eq(user_id))
+                    .add(oauth_token::Column::RevokedAt.is_null()),
+            )
+            .exec(txn)
+            .await?;
+
+        debug!("{rows_affected} OAuth tokens were revoked");
+        Ok(rows_affected)
+    }
+
+    /// Revokes all tokens issued to any client for a user.
+    ///
+    /// # Returns
+    /// The number of tokens which were revoked.
+    pub async fn revoke_user(ctx: &ServiceContext<'_>, user_id: i64) -> Result<u64> {
+        info!("Revoking all OAuth tokens for user ID {user_id}");
+
+        let txn = ctx.transaction();
+        let UpdateResult { rows_affected } = OauthToken::update_many()
+            .set(oauth_token::ActiveModel {
+                revoked_at: Set(Some(now())),
+                ..Default::default()
+            })
+            .filter(
+                Condition::all()
+                    .add(oauth_token::Column::UserId.eq(user_id))
+                    .add(oauth_token::Column::RevokedAt.is_null()),
+            )
+            .exec(txn)
+            .await?;
+
+        debug!("{rows_affected} OAuth tokens were revoked");
+        Ok(rows_affected)
+    }
+
+    /// Deletes all tokens whose refresh token has expired.
+    ///
+    /// Revoked tokens are kept until then, so that
+    /// reuse of a replaced refresh token can be detected.
+    pub async fn prune(ctx: &ServiceContext<'_>) -> Result<u64> {
+        info!("Pruning all expired OAuth tokens");
+
+        let txn = ctx.transaction();
+        let DeleteResult { rows_affected } = OauthToken::delete_many()
+            .filter(oauth_token::Column::RefreshExpiresAt.lte(now()))
+            .exec(txn)
+            .await?;
+
+        debug!("{rows_affected} expired OAuth tokens were pruned");
+        Ok(rows_affected)
+    }
+}
+
+fn hash_token(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
+}
+
+fn code_key(code: &str) -> String {
+    format!("oauth-code:{code}")
+}
@@ -0,0 +1,312 @@
+/*
+ * services/oauth/structs.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::models::oauth_client::Model as OauthClientModel;
+use crate::services::api_token::ApiScope;
+use crate::services::{Error, Result};
+use reqwest::Url;
+use time::OffsetDateTime;
+
+/// Parses a space-separated list of scopes, as used in OAuth requests.
+pub fn parse_scopes(value: &str) -> Result<Vec<ApiScope>> {
+    value
+        .split_whitespace()
+        .map(|scope| scope.parse().map_err(|_| Error::OauthInvalidScope))
+        .collect()
+}
+
+/// Formats a list of scopes for OAuth responses.
+pub fn format_scopes<S: ToString>(scopes: &[S]) -> String {
+    scopes
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Determines if a client may register this redirect URI.
+///
+/// It must be an absolute URL without a fragment, and use HTTPS, unless it is
+/// for a tool running locally, which may use HTTP on a loopback address.
+pub fn valid_redirect_uri(value: &str) -> bool {
+    let url = match Url::parse(value) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+
+    if url.fragment().is_some() {
+        return false;
+    }
+
+    match url.scheme() {
+        "https" => url.host().is_some(),
+        "http" => matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")),
+        _ => false,
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreateOauthClient {
+    pub user_id: i64,
+    pub session_token: String,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub scopes: Vec<ApiScope>,
+
+    /// Whether this client can keep a secret, such as a server-side application.
+    /// Public clients, like browser or desktop applications, only use PKCE.
+    pub confidential: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CreateOauthClientOutput {
+    pub oauth_client_id: i64,
+    pub client_id: String,
+
+    /// The client secret, for confidential clients.
+    /// This is the only time it is available.
+    pub client_secret: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetOauthClients {
+    pub user_id: i64,
+    pub session_token: String,
+}
+
+pub type GetOauthGrants = GetOauthClients;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DeleteOauthClient {
+    pub oauth_client_id: i64,
+    pub user_id: i64,
+    pub session_token: String,
+}
+
+pub type RevokeOauthGrant = DeleteOauthClient;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OauthClientOutput {
+    pub oauth_client_id: i64,
+    pub created_by: i64,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+
+    pub name: String,
+    pub client_id: String,
+    pub confidential: bool,
+    pub redirect_uris: Vec<String>,
+    pub scopes: Vec<String>,
+}
+
+impl From<OauthClientModel> for OauthClientOutput {
+    fn from(model: OauthClientModel) -> Self {
+        OauthClientOutput {
+            oauth_client_id: model.oauth_client_id,
+            created_by: model.created_by,
+            created_at: model.created_at,
+            name: model.name,
+            client_id: model.client_id,
+            confidential: model.client_secret_hash.is_some(),
+            redirect_uris: model.redirect_uris,
+            scopes: model.scopes,
+        }
+    }
+}
+
+/// An authorization request, from the query string the client sent the user with.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AuthorizationRequest {
+    pub client_id: String,
+    pub redirect_uri: String,
+
+    #[serde(default)]
+    pub response_type: String,
+
+    #[serde(default)]
+    pub scope: String,
+
+    #[serde(default)]
+    pub state: Option<String>,
+
+    #[serde(default)]
+    pub code_challenge: String,
+
+    #[serde(default)]
+    pub code_challenge_method: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StartOauthAuthorization {
+    #[serde(flatten)]
+    pub request: AuthorizationRequest,
+    pub user_id: i64,
+    pub session_token: String,
+}
+
+/// What to show the user when asking for their consent.
+#[derive(Serialize, Debug, Clone)]
+pub struct OauthConsentOutput {
+    pub client_name: String,
+    pub scopes: Vec<ApiScope>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FinishOauthAuthorization {
+    #[serde(flatten)]
+    pub request: AuthorizationRequest,
+    pub user_id: i64,
+    pub session_token: String,
+
+    /// Whether the user consented to this request.
+    pub approve: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OauthRedirectOutput {
+    /// Where to send the user back to, with either a code or an error.
+    pub redirect_url: String,
+}
+
+/// What we keep about an authorization code until it is exchanged.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OauthCode {
+    pub oauth_client_id: i64,
+    pub user_id: i64,
+    pub redirect_uri: String,
+    pub scopes: Vec<ApiScope>,
+    pub code_challenge: String,
+}
+
+/// A request to the token endpoint.
+///
+/// Client credentials may be sent in the body or with HTTP basic
+/// authentication, either way they are passed here.
+#[derive(Deserialize, Debug, Clone)]
+pub struct OauthTokenRequest {
+    pub grant_type: String,
+    pub client_id: String,
+
+    #[serde(default)]
+    pub client_secret: Option<String>,
+
+    // For "authorization_code"
+    #[serde(default)]
+    pub code: Option<String>,
+
+    #[serde(default)]
+    pub redirect_uri: Option<String>,
+
+    #[serde(default)]
+    pub code_verifier: Option<String>,
+
+    // For "refresh_token"
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OauthTokenOutput {
+    pub access_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+    pub refresh_token: String,
+    pub scope: String,
+}
+
+/// A request to introspect or revoke a token, on behalf of a client.
+#[derive(Deserialize, Debug, Clone)]
+pub struct OauthClientTokenRequest {
+    pub token: String,
+    pub client_id: String,
+
+    #[serde(default)]
+    pub client_secret: Option<String>,
+}
+
+/// Token information, per RFC 7662.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct OauthIntrospectOutput {
+    pub active: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<&'static str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+}
+
+/// A client a user has authorized, for listing in their settings.
+#[derive(Serialize, Debug, Clone)]
+pub struct OauthGrantOutput {
+    pub oauth_client_id: i64,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_used_at: Option<OffsetDateTime>,
+}
+
+#[test]
+fn scopes() {
+    let scopes = parse_scopes("read-pages  vote@42 ").expect("Unable to parse scopes");
+    assert_eq!(scopes.len(), 2);
+    assert_eq!(format_scopes(&scopes), "read-pages vote@42");
+
+    assert!(parse_scopes("").unwrap().is_empty());
+    assert!(parse_scopes("read-pages openid").is_err());
+}
+
+#[test]
+fn redirect_uris() {
+    assert!(valid_redirect_uri("https://tool.example.com/callback"));
+    assert!(valid_redirect_uri(
+        "https://tool.example.com/callback?source=wikijump"
+    ));
+    assert!(valid_redirect_uri("http://localhost:8000/callback"));
+    assert!(valid_redirect_uri("http://127.0.0.1/callback"));
+
+    assert!(!valid_redirect_uri("/callback"));
+    assert!(!valid_redirect_uri("http://tool.example.com/callback"));
+    assert!(!valid_redirect_uri(
+        "https://tool.example.com/callback#fragment"
+    ));
+    assert!(!valid_redirect_uri("javascript:alert(1)"));
+    assert!(!valid_redirect_uri("tool://callback"));
+}
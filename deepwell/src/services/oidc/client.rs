@@ -0,0 +1,443 @@
+/*
+ * services/oidc/client.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The HTTP client used to talk to OpenID Connect providers.
+//!
+//! Each provider's metadata and signing keys are cached, so a login does not
+//! need to fetch them every time. If an ID token is signed by a key we have
+//! not seen, the keys are fetched again in case the provider rotated them.
+//!
+//! ID tokens are verified using `jsonwebtoken`, see
+//! <https://openid.net/specs/openid-connect-core-1_0.html#IDTokenValidation>.
+
+use super::prelude::*;
+use crate::types::OidcProvider;
+use jsonwebtoken::jwk::{JwkSet, PublicKeyUse};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long to wait on a provider before giving up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a provider's metadata is cached for.
+const METADATA_CACHE_TIME: Duration = Duration::from_secs(60 * 60);
+
+/// How often a provider's keys may be fetched because of an unknown key ID.
+const KEYS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How far our clock and the provider's may disagree, in seconds.
+const CLOCK_SKEW_SECS: u64 = 60;
+
+/// The algorithms accepted for ID tokens.
+///
+/// Only asymmetric algorithms are allowed, since we never share a
+/// signing key with the provider.
+const ALGORITHMS: [Algorithm; 7] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+#[derive(Debug, Default)]
+struct ProviderCache {
+    metadata: Option<(ProviderMetadata, Instant)>,
+    keys: Option<(Arc<JwkSet>, Instant)>,
+}
+
+#[derive(Debug)]
+pub struct OidcClient {
+    client: reqwest::Client,
+    cache: Mutex<HashMap<String, ProviderCache>>,
+}
+
+impl OidcClient {
+    pub fn new() -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+
+        Ok(OidcClient {
+            client,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Gets the provider's metadata from its discovery document.
+    pub async fn metadata(&self, provider: &OidcProvider) -> Result<ProviderMetadata> {
+        {
+            let cache = self.cache.lock().expect("OIDC cache lock poisoned");
+            if let Some((metadata, fetched_at)) = cache
+                .get(&provider.name)
+                .and_then(|entry| entry.metadata.as_ref())
+            {
+                if fetched_at.elapsed() < METADATA_CACHE_TIME {
+                    return Ok(metadata.clone());
+                }
+            }
+        }
+
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            provider.issuer.trim_end_matches('/'),
+        );
+
+        debug!("Fetching OpenID Connect provider metadata from {url}");
+        let metadata: ProviderMetadata = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if metadata.issuer != provider.issuer {
+            error!(
+                "Provider metadata has issuer '{}', expected '{}'",
+                metadata.issuer, provider.issuer,
+            );
+            return Err(Error::OidcInvalid(
+                "provider metadata is for a different issuer",
+            ));
+        }
+
+        let mut cache = self.cache.lock().expect("OIDC cache lock poisoned");
+        let entry = cache.entry(provider.name.clone()).or_default();
+        entry.metadata = Some((metadata.clone(), Instant::now()));
+        Ok(metadata)
+    }
+
+    /// Gets the provider's signing keys.
+    ///
+    /// If `refresh` is set, the keys are fetched again unless that
+    /// was done recently, otherwise cached keys are used if present.
+    async fn keys(
+        &self,
+        provider: &OidcProvider,
+        metadata: &ProviderMetadata,
+        refresh: bool,
+    ) -> Result<Arc<JwkSet>> {
+        {
+            let cache = self.cache.lock().expect("OIDC cache lock poisoned");
+            if let Some((keys, fetched_at)) = cache
+                .get(&provider.name)
+                .and_then(|entry| entry.keys.as_ref())
+            {
+                if !refresh || fetched_at.elapsed() < KEYS_REFRESH_INTERVAL {
+                    return Ok(Arc::clone(keys));
+                }
+            }
+        }
+
+        debug!(
+            "Fetching OpenID Connect provider keys from {}",
+            metadata.jwks_uri,
+        );
+
+        let keys: Arc<JwkSet> = Arc::new(
+            self.client
+                .get(&metadata.jwks_uri)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?,
+        );
+
+        let mut cache = self.cache.lock().expect("OIDC cache lock poisoned");
+        let entry = cache.entry(provider.name.clone()).or_default();
+        entry.keys = Some((Arc::clone(&keys), Instant::now()));
+        Ok(keys)
+    }
+
+    /// Exchanges an authorization code for an ID token.
+    pub async fn exchange_code(
+        &self,
+        provider: &OidcProvider,
+        metadata: &ProviderMetadata,
+        code: &str,
+        code_verifier: &str,
+    ) -> Result<String> {
+        debug!("Exchanging authorization code for ID token");
+
+        let response: TokenResponse = self
+            .client
+            .post(&metadata.token_endpoint)
+            .basic_auth(&provider.client_id, Some(&provider.client_secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &provider.redirect_uri),
+                ("client_id", &provider.client_id),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response.id_token)
+    }
+
+    /// Verifies an ID token's signature and claims, returning the claims if valid.
+    pub async fn verify_id_token(
+        &self,
+        provider: &OidcProvider,
+        metadata: &ProviderMetadata,
+        token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims> {
+        let keys = self.keys(provider, metadata, false).await?;
+        if let Some(claims) =
+            verify_id_token(token, &keys, &provider.issuer, &provider.client_id, nonce)?
+        {
+            return Ok(claims);
+        }
+
+        // The provider may have rotated its keys since we fetched them
+        warn!("ID token signing key not found, fetching provider keys again");
+        let keys = self.keys(provider, metadata, true).await?;
+        verify_id_token(token, &keys, &provider.issuer, &provider.client_id, nonce)?
+            .ok_or(Error::OidcInvalid("ID token signing key not found"))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// The claims from an ID token we make use of.
+///
+/// The issuer and expiry are checked by `jsonwebtoken` itself.
+#[derive(Deserialize, Debug, Clone)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    aud: Audience,
+
+    #[serde(default)]
+    azp: Option<String>,
+
+    pub iat: i64,
+
+    #[serde(default)]
+    pub nonce: Option<String>,
+
+    #[serde(default)]
+    pub email: Option<String>,
+
+    #[serde(default)]
+    pub email_verified: bool,
+
+    #[serde(default)]
+    pub preferred_username: Option<String>,
+
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+impl IdTokenClaims {
+    /// Checks the claims not already validated by `jsonwebtoken`.
+    fn check(&self, client_id: &str, nonce: &str, now: i64) -> Result<()> {
+        // If there are several audiences, then we must be the authorized party
+        let audience_matches = match self.aud {
+            Audience::One(ref aud) => aud == client_id,
+            Audience::Many(ref auds) => {
+                auds.iter().any(|aud| aud == client_id)
+                    && (auds.len() == 1 || self.azp.as_deref() == Some(client_id))
+            }
+        };
+
+        if !audience_matches {
+            return Err(Error::OidcInvalid("ID token is for a different client"));
+        }
+
+        if self.iat - CLOCK_SKEW_SECS as i64 > now {
+            return Err(Error::OidcInvalid("ID token was issued in the future"));
+        }
+
+        if self.nonce.as_deref() != Some(nonce) {
+            return Err(Error::OidcInvalid("ID token nonce does not match"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Verifies an ID token against the provider's keys.
+///
+/// # Returns
+/// The token's claims, or `None` if none of the keys could have signed it.
+fn verify_id_token(
+    token: &str,
+    keys: &JwkSet,
+    issuer: &str,
+    client_id: &str,
+    nonce: &str,
+) -> Result<Option<IdTokenClaims>> {
+    let header = jsonwebtoken::decode_header(token)
+        .map_err(|_| Error::OidcInvalid("malformed ID token header"))?;
+
+    if !ALGORITHMS.contains(&header.alg) {
+        return Err(Error::OidcInvalid("unsupported ID token algorithm"));
+    }
+
+    // Providers with only one key may not give it an ID
+    let jwk = match header.kid {
+        Some(ref kid) => keys.find(kid),
+        None if keys.keys.len() == 1 => keys.keys.first(),
+        None => None,
+    };
+
+    let jwk = match jwk {
+        Some(jwk) if jwk.common.public_key_use != Some(PublicKeyUse::Encryption) => jwk,
+        _ => return Ok(None),
+    };
+
+    let key = DecodingKey::from_jwk(jwk)
+        .map_err(|_| Error::OidcInvalid("invalid JSON web key"))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.leeway = CLOCK_SKEW_SECS;
+    validation.set_issuer(&[issuer]);
+    validation.set_audience(&[client_id]);
+    validation.set_required_spec_claims(&["exp", "iat", "iss", "aud", "sub"]);
+
+    let claims = jsonwebtoken::decode::<IdTokenClaims>(token, &key, &validation)
+        .map_err(|error| {
+            warn!("ID token failed validation: {error}");
+            Error::OidcInvalid("ID token is invalid")
+        })?
+        .claims;
+
+    claims.check(client_id, nonce, now().unix_timestamp())?;
+    Ok(Some(claims))
+}
+
+#[test]
+fn id_tokens() {
+    use data_encoding::BASE64URL_NOPAD;
+    use jsonwebtoken::{EncodingKey, Header};
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+    use serde_json::json;
+
+    const ISSUER: &str = "https://sso.wikijump.test";
+    const CLIENT_ID: &str = "wikijump";
+    const NONCE: &str = "nonce";
+
+    // Software "provider", which signs tokens with a P-256 key
+    let rng = SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+        .expect("Unable to generate key");
+    let key_pair =
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+            .expect("Unable to load key");
+    let signing_key = EncodingKey::from_ec_der(pkcs8.as_ref());
+
+    // Uncompressed point, 0x04 || x || y
+    let point = key_pair.public_key().as_ref();
+    let keys: JwkSet = serde_json::from_value(json!({
+        "keys": [{
+            "kty": "EC",
+            "kid": "key-1",
+            "alg": "ES256",
+            "use": "sig",
+            "crv": "P-256",
+            "x": BASE64URL_NOPAD.encode(&point[1..33]),
+            "y": BASE64URL_NOPAD.encode(&point[33..]),
+        }],
+    }))
+    .expect("Unable to parse key set");
+
+    let sign = |kid: &str, claims: &serde_json::Value| {
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(str!(kid));
+        jsonwebtoken::encode(&header, claims, &signing_key).expect("Unable to sign token")
+    };
+
+    let now = now().unix_timestamp();
+    let claims = json!({
+        "iss": ISSUER,
+        "sub": "user-1234",
+        "aud": CLIENT_ID,
+        "exp": now + 300,
+        "iat": now,
+        "nonce": NONCE,
+        "email": "user@example.com",
+        "email_verified": true,
+    });
+
+    let verify = |token: &str| {
+        verify_id_token(token, &keys, ISSUER, CLIENT_ID, NONCE)
+            .ok()
+            .flatten()
+    };
+    let with_claim = |key: &str, value: serde_json::Value| {
+        let mut claims = claims.clone();
+        claims[key] = value;
+        sign("key-1", &claims)
+    };
+
+    // Valid token
+    let token = sign("key-1", &claims);
+    let output = verify(&token).expect("Valid ID token was rejected");
+    assert_eq!(output.sub, "user-1234");
+    assert_eq!(output.email.as_deref(), Some("user@example.com"));
+    assert!(output.email_verified);
+
+    // Audience lists
+    assert!(verify(&with_claim("aud", json!([CLIENT_ID]))).is_some());
+    assert!(verify(&with_claim("aud", json!([CLIENT_ID, "other"]))).is_none());
+
+    // Invalid claims
+    assert!(verify(&with_claim("iss", json!("https://evil.test"))).is_none());
+    assert!(verify(&with_claim("aud", json!("other"))).is_none());
+    assert!(verify(&with_claim("exp", json!(now - 300))).is_none());
+    assert!(verify(&with_claim("iat", json!(now + 300))).is_none());
+    assert!(verify(&with_claim("nonce", json!("other"))).is_none());
+
+    // Tampered payload
+    let (_, signature) = token.rsplit_once('.').unwrap();
+    let forged = with_claim("sub", json!("admin"));
+    let (forged, _) = forged.rsplit_once('.').unwrap();
+    assert!(verify(&format!("{forged}.{signature}")).is_none());
+
+    // Unsigned tokens are rejected, unknown keys ask for a refresh
+    let unsigned = format!(
+        "{}.{}.",
+        BASE64URL_NOPAD.encode(br#"{"alg":"none"}"#),
+        BASE64URL_NOPAD.encode(claims.to_string().as_bytes()),
+    );
+    assert!(verify_id_token(&unsigned, &keys, ISSUER, CLIENT_ID, NONCE).is_err());
+    assert!(matches!(
+        verify_id_token(&sign("key-2", &claims), &keys, ISSUER, CLIENT_ID, NONCE),
+        Ok(None),
+    ));
+}
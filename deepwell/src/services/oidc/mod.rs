@@ -0,0 +1,50 @@
+/*
+ * services/oidc/mod.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The OpenID Connect service, for logging in with external identity providers.
+//!
+//! Providers are configured by the server administrator, and users are sent
+//! through the authorization code flow with PKCE. Once the provider sends the
+//! user back, the code is exchanged for an ID token, whose signature and claims
+//! are verified before it is trusted.
+//!
+//! Identities are linked to users by the provider name and the subject claim.
+//! An identity can be linked to an existing account by starting the flow while
+//! logged in, or if the provider allows it, signing in with an unknown identity
+//! creates a new account. Identities are never linked by matching emails.
+//!
+//! As with WebAuthn, the state for each attempt is kept in Redis until it is
+//! used or expires, so each one can only be completed once. The state is also
+//! set in a cookie by the caller, and must match when the user returns, so an
+//! attempt cannot be completed in a different browser than the one it began in.
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use super::super::prelude::*;
+    pub use super::structs::*;
+}
+
+mod client;
+mod service;
+mod structs;
+
+pub use self::client::{IdTokenClaims, OidcClient};
+pub use self::service::OidcService;
+pub use self::structs::*;
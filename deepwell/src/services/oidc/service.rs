@@ -0,0 +1,423 @@
+/*
+ * services/oidc/service.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use super::IdTokenClaims;
+use crate::models::sea_orm_active_enums::UserType;
+use crate::models::user_external_identity::{
+    self, Entity as UserExternalIdentity, Model as UserExternalIdentityModel,
+};
+use crate::services::rate_limit::{RateLimitAction, RateLimitScope};
+use crate::services::user::CreateUser;
+use crate::services::{RateLimitService, UserService};
+use crate::types::OidcProvider;
+use crate::utils::{assert_is_csprng, pkce_challenge};
+use rand::distributions::{Alphanumeric, DistString};
+use rand::thread_rng;
+use reqwest::Url;
+use subtle::ConstantTimeEq;
+
+#[derive(Debug)]
+pub struct OidcService;
+
+impl OidcService {
+    /// Lists the configured providers, for display to users.
+    pub fn get_providers(config: &Config) -> Vec<OidcProviderOutput> {
+        config
+            .oidc_providers
+            .iter()
+            .map(|provider| OidcProviderOutput {
+                name: provider.name.clone(),
+                display_name: provider.display_name.clone(),
+            })
+            .collect()
+    }
+
+    fn get_provider<'c>(config: &'c Config, name: &str) -> Result<&'c OidcProvider> {
+        config
+            .oidc_providers
+            .iter()
+            .find(|provider| provider.name == name)
+            .ok_or(Error::OidcProviderNotFound)
+    }
+
+    /// Begins logging in with, or linking, an external identity.
+    ///
+    /// If `link_user_id` is set, the caller is responsible for verifying
+    /// that the request is from that user.
+    ///
+    /// # Returns
+    /// The URL to send the user to, to authenticate with the provider,
+    /// and the state which the caller must keep in a cookie.
+    pub async fn start(
+        ctx: &ServiceContext<'_>,
+        provider_name: &str,
+        link_user_id: Option<i64>,
+    ) -> Result<StartOidcOutput> {
+        info!("Starting OpenID Connect flow with provider '{provider_name}'");
+
+        let config = ctx.config();
+        let provider = Self::get_provider(config, provider_name)?;
+        let metadata = ctx.oidc().metadata(provider).await?;
+
+        let (state, nonce, code_verifier) = {
+            let mut rng = thread_rng();
+            assert_is_csprng(&rng);
+
+            (
+                Alphanumeric.sample_string(&mut rng, 32),
+                Alphanumeric.sample_string(&mut rng, 32),
+                Alphanumeric.sample_string(&mut rng, 64),
+            )
+        };
+
+        let url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", &provider.client_id),
+                ("redirect_uri", &provider.redirect_uri),
+                ("scope", &provider.scopes.join(" ")),
+                ("state", &state),
+                ("nonce", &nonce),
+                ("code_challenge", &pkce_challenge(&code_verifier)),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|_| Error::OidcInvalid("invalid authorization endpoint"))?;
+
+        let stored = OidcState {
+            provider: provider.name.clone(),
+            code_verifier,
+            nonce,
+            link_user_id,
+        };
+
+        let mut redis = ctx.redis_connect().await?;
+        redis::cmd("SET")
+            .arg(state_key(&state))
+            .arg(serde_json::to_string(&stored)?)
+            .arg("PX")
+            .arg(config.oidc_state_timeout.as_millis() as u64)
+            .query_async::<_, ()>(&mut redis)
+            .await?;
+
+        Ok(StartOidcOutput {
+            authorization_url: url.into(),
+            state,
+        })
+    }
+
+    /// Handles the user returning from the provider.
+    ///
+    /// The authorization code is exchanged for an ID token, which is verified.
+    /// Then the identity is either linked to the user who started the flow,
+    /// or used to log in, creating a new account if the provider allows it.
+    ///
+    /// The `state` the provider sent back must match the one from the cookie,
+    /// otherwise someone could have the user complete an attempt they started.
+    ///
+    /// This does not create a session, the caller is responsible for that.
+    pub async fn authenticate(
+        ctx: &ServiceContext<'_>,
+        FinishOidcLogin {
+            state,
+            code,
+            cookie_state,
+            locales,
+            ..
+        }: FinishOidcLogin,
+    ) -> Result<OidcAuthenticated> {
+        if !bool::from(state.as_bytes().ct_eq(cookie_state.as_bytes())) {
+            error!("OpenID Connect state does not match the one from the cookie");
+            return Err(Error::OidcInvalid("login attempt was started elsewhere"));
+        }
+
+        let state = Self::take_state(ctx, &state).await?;
+        let provider = Self::get_provider(ctx.config(), &state.provider)?;
+        info!(
+            "Finishing OpenID Connect flow with provider '{}'",
+            provider.name
+        );
+
+        let client = ctx.oidc();
+        let metadata = client.metadata(provider).await?;
+        let id_token = client
+            .exchange_code(provider, &metadata, &code, &state.code_verifier)
+            .await?;
+        let claims = client
+            .verify_id_token(provider, &metadata, &id_token, &state.nonce)
+            .await?;
+
+        if let Some(user_id) = state.link_user_id {
+            Self::link(ctx, user_id, provider, &claims).await?;
+            return Ok(OidcAuthenticated::Linked {
+                user_id,
+                provider: state.provider,
+            });
+        }
+
+        let txn = ctx.transaction();
+        match Self::get_optional(ctx, &provider.name, &claims.sub).await? {
+            Some(identity) => {
+                let user = UserService::get(ctx, Reference::Id(identity.user_id)).await?;
+                if user.deleted_at.is_some() {
+                    error!("External identity is linked to a deleted user");
+                    return Err(Error::InvalidAuthentication);
+                }
+
+                let model = user_external_identity::ActiveModel {
+                    user_external_identity_id: Set(identity.user_external_identity_id),
+                    last_used_at: Set(Some(now())),
+                    ..Default::default()
+                };
+                model.update(txn).await?;
+
+                Ok(OidcAuthenticated::Login {
+                    user_id: identity.user_id,
+                    created: false,
+                })
+            }
+            None if provider.allow_signup => {
+                let user_id = Self::create_user(ctx, provider, &claims, locales).await?;
+                Ok(OidcAuthenticated::Login {
+                    user_id,
+                    created: true,
+                })
+            }
+            None => {
+                warn!("External identity is not linked and sign-up is not allowed");
+                Err(Error::ExternalIdentityNotFound)
+            }
+        }
+    }
+
+    async fn take_state(ctx: &ServiceContext<'_>, state: &str) -> Result<OidcState> {
+        let mut redis = ctx.redis_connect().await?;
+        let stored: Option<String> = redis::cmd("GETDEL")
+            .arg(state_key(state))
+            .query_async(&mut redis)
+            .await?;
+
+        match stored {
+            Some(stored) => Ok(serde_json::from_str(&stored)?),
+            None => Err(Error::OidcInvalid("login attempt is unknown or expired")),
+        }
+    }
+
+    /// Creates a new account for an external identity.
+    ///
+    /// This goes through the same checks as any other new user, such as the
+    /// name and email filters. The account has no password, so it can only
+    /// be signed into using this identity until one is set.
+    async fn create_user(
+        ctx: &ServiceContext<'_>,
+        provider: &OidcProvider,
+        claims: &IdTokenClaims,
+        locales: Vec<String>,
+    ) -> Result<i64> {
+        // Emails must be unique, so don't let anyone claim one they don't own
+        let email = match claims.email {
+            Some(ref email) if claims.email_verified => email.clone(),
+            _ => {
+                error!("Provider did not supply a verified email for the new user");
+                return Err(Error::OidcInvalid("no verified email address in ID token"));
+            }
+        };
+
+        let name = claims
+            .preferred_username
+            .as_ref()
+            .or(claims.name.as_ref())
+            .cloned()
+            .unwrap_or_else(|| match email.split_once('@') {
+                Some((local, _)) => str!(local),
+                None => email.clone(),
+            });
+
+        info!(
+            "Creating new user '{name}' for identity from provider '{}'",
+            provider.name,
+        );
+
+        RateLimitService::check(
+            ctx,
+            RateLimitAction::UserCreate,
+            RateLimitScope::Global,
+            ctx.config().rate_limit_user_create,
+        )
+        .await?;
+
+        let output = UserService::create(
+            ctx,
+            CreateUser {
+                user_type: UserType::Regular,
+                name,
+                email,
+                locales,
+                password: String::new(),
+                bypass_filter: false,
+                bypass_email_verification: false,
+            },
+        )
+        .await?;
+
+        Self::insert(ctx, output.user_id, provider, claims).await?;
+        Ok(output.user_id)
+    }
+
+    /// Links an external identity to an existing user.
+    ///
+    /// Linking an identity which is already linked to this user does nothing.
+    async fn link(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+        provider: &OidcProvider,
+        claims: &IdTokenClaims,
+    ) -> Result<()> {
+        info!(
+            "Linking identity from provider '{}' to user ID {user_id}",
+            provider.name,
+        );
+
+        if let Some(identity) =
+            Self::get_optional(ctx, &provider.name, &claims.sub).await?
+        {
+            if identity.user_id == user_id {
+                debug!("Identity is already linked to this user");
+                return Ok(());
+            }
+
+            error!("Identity is already linked to user ID {}", identity.user_id,);
+            return Err(Error::ExternalIdentityExists);
+        }
+
+        Self::insert(ctx, user_id, provider, claims).await
+    }
+
+    async fn insert(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+        provider: &OidcProvider,
+        claims: &IdTokenClaims,
+    ) -> Result<()> {
+        let txn = ctx.transaction();
+
+        // Each user may have one identity per provider
+        let existing = UserExternalIdentity::find()
+            .filter(
+                Condition::all()
+                    .add(user_external_identity::Column::UserId.eq(user_id))
+                    .add(user_external_identity::Column::Provider.eq(&provider.name)),
+            )
+            .one(txn)
+            .await?;
+
+        if existing.is_some() {
+            error!(
+                "User already has an identity from provider '{}'",
+                provider.name
+            );
+            return Err(Error::ExternalIdentityExists);
+        }
+
+        let model = user_external_identity::ActiveModel {
+            user_id: Set(user_id),
+            provider: Set(provider.name.clone()),
+            subject: Set(claims.sub.clone()),
+            email: Set(claims.email.clone()),
+            last_used_at: Set(Some(now())),
+            ..Default::default()
+        };
+        model.insert(txn).await?;
+
+        Ok(())
+    }
+
+    async fn get_optional(
+        ctx: &ServiceContext<'_>,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<UserExternalIdentityModel>> {
+        let txn = ctx.transaction();
+        let identity = UserExternalIdentity::find()
+            .filter(
+                Condition::all()
+                    .add(user_external_identity::Column::Provider.eq(provider))
+                    .add(user_external_identity::Column::Subject.eq(subject)),
+            )
+            .one(txn)
+            .await?;
+
+        Ok(identity)
+    }
+
+    /// Gets all external identities linked to a user.
+    pub async fn get_all(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+    ) -> Result<Vec<UserExternalIdentityModel>> {
+        info!("Getting all external identities for user ID {user_id}");
+
+        let txn = ctx.transaction();
+        let identities = UserExternalIdentity::find()
+            .filter(user_external_identity::Column::UserId.eq(user_id))
+            .order_by_asc(user_external_identity::Column::UserExternalIdentityId)
+            .all(txn)
+            .await?;
+
+        Ok(identities)
+    }
+
+    /// Unlinks a user's identity from the given provider.
+    ///
+    /// If the user has no password, their last identity cannot be removed,
+    /// since they would have no way to sign in.
+    pub async fn unlink(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+        provider: &str,
+    ) -> Result<()> {
+        info!("Unlinking identity from provider '{provider}' for user ID {user_id}");
+
+        let identities = Self::get_all(ctx, user_id).await?;
+        let identity = identities
+            .iter()
+            .find(|identity| identity.provider == provider)
+            .ok_or(Error::ExternalIdentityNotFound)?;
+
+        let user = UserService::get(ctx, Reference::Id(user_id)).await?;
+        if identities.len() == 1 && user.password.is_none() {
+            error!("Cannot unlink the only identity of a user without a password");
+            return Err(Error::ExternalIdentityRequired);
+        }
+
+        let txn = ctx.transaction();
+        UserExternalIdentity::delete_by_id(identity.user_external_identity_id)
+            .exec(txn)
+            .await?;
+
+        Ok(())
+    }
+}
+
+fn state_key(state: &str) -> String {
+    format!("oidc-state:{state}")
+}
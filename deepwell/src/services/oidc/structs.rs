@@ -0,0 +1,127 @@
+/*
+ * services/oidc/structs.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::net::IpAddr;
+
+/// The provider's metadata, from its discovery document.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TokenResponse {
+    pub id_token: String,
+}
+
+/// What we keep about an attempt while the user is at the provider.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OidcState {
+    pub provider: String,
+    pub code_verifier: String,
+    pub nonce: String,
+
+    /// If present, the identity is being linked to this user,
+    /// rather than used to log in.
+    pub link_user_id: Option<i64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OidcProviderOutput {
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StartOidcLogin {
+    pub provider: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StartOidcLink {
+    pub provider: String,
+    pub user_id: i64,
+    pub session_token: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StartOidcOutput {
+    /// Where to send the user to authenticate with the provider.
+    pub authorization_url: String,
+
+    /// Identifies this attempt, which the caller must store in a cookie
+    /// and send with `cookie_state` when finishing.
+    pub state: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FinishOidcLogin {
+    pub state: String,
+    pub code: String,
+
+    /// The state from the cookie set when the attempt was started.
+    pub cookie_state: String,
+
+    pub ip_address: IpAddr,
+    pub user_agent: String,
+
+    /// The locales to use if a new account is created.
+    pub locales: Vec<String>,
+}
+
+/// The outcome of returning from the provider, before any session is created.
+#[derive(Debug, Clone)]
+pub enum OidcAuthenticated {
+    Login { user_id: i64, created: bool },
+    Linked { user_id: i64, provider: String },
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", tag = "result")]
+pub enum FinishOidcOutput {
+    Login {
+        session_token: String,
+        needs_mfa: bool,
+        user_id: i64,
+
+        /// Whether a new account was created for this identity.
+        created: bool,
+    },
+    Linked {
+        user_id: i64,
+        provider: String,
+    },
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetExternalIdentities {
+    pub user_id: i64,
+    pub session_token: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UnlinkExternalIdentity {
+    pub provider: String,
+    pub user_id: i64,
+    pub session_token: String,
+}
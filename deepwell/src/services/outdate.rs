@@ -21,7 +21,7 @@
 use super::prelude::*;
 use crate::models::page::Model as PageModel;
 use crate::services::{JobService, LinkService, PageService};
-use crate::types::{ConnectionType, PageOrder};
+use crate::types::{ConnectionType, PageOrder, Pagination};
 use crate::utils::split_category_name;
 
 #[derive(Debug)]
@@ -103,7 +103,8 @@ impl OutdateService {
         page_id: i64,
         depth: u32,
     ) -> Result<()> {
-        const CONNECTION_TYPES: &[ConnectionType] = &[ConnectionType::Link];
+        const CONNECTION_TYPES: &[ConnectionType] =
+            &[ConnectionType::Link, ConnectionType::Redirect];
 
         for id in LinkService::get_to(ctx, page_id, Some(CONNECTION_TYPES))
             .await?
@@ -167,6 +168,7 @@ impl OutdateService {
                 category_select,
                 Some(false),
                 PageOrder::default(),
+                Pagination::default(),
             )
             .await?;
 
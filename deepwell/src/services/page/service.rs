@@ -24,15 +24,19 @@ use crate::models::page_category::Model as PageCategoryModel;
 use crate::models::page_revision::Model as PageRevisionModel;
 use crate::models::sea_orm_active_enums::PageRevisionType;
 use crate::services::filter::{FilterClass, FilterType};
+use crate::services::link::redirect_wikitext;
 use crate::services::page_revision::{
     CreateFirstPageRevision, CreateFirstPageRevisionOutput, CreatePageRevision,
     CreatePageRevisionBody, CreatePageRevisionOutput, CreateResurrectionPageRevision,
     CreateTombstonePageRevision,
 };
 use crate::services::{
-    CategoryService, FilterService, PageRevisionService, SiteService, TextService,
+    CategoryService, FilterService, PageRevisionService, RelationService, SiteService,
+    TextService,
+};
+use crate::types::{
+    cursor_condition, parse_hidden_fields, PageHiddenField, PageOrder, Pagination,
 };
-use crate::types::PageOrder;
 use crate::utils::{get_category_name, trim_default};
 use ftml::layout::Layout;
 use sea_orm::ActiveValue;
@@ -226,6 +230,7 @@ impl PageService {
             last_revision_id,
             revision_comments: comments,
             user_id,
+            leave_redirect,
         }: MovePage<'_>,
     ) -> Result<MovePageOutput> {
         let txn = ctx.transaction();
@@ -257,6 +262,7 @@ impl PageService {
         // Get latest revision
         let last_revision =
             PageRevisionService::get_latest(ctx, site_id, page_id).await?;
+        let title = last_revision.title.clone();
 
         // Create revision for move
         let revision_input = CreatePageRevision {
@@ -299,6 +305,31 @@ impl PageService {
         let page = model.update(txn).await?;
         assert_latest_revision(&page);
 
+        // Leave a page at the old slug pointing to the new one, if requested.
+        // This is done after the move, since otherwise the old slug is taken.
+        let redirect_page_id = if leave_redirect {
+            debug!("Creating redirect page from {old_slug} to {new_slug}");
+            let CreatePageOutput { page_id, .. } = Self::create(
+                ctx,
+                CreatePage {
+                    site_id,
+                    wikitext: redirect_wikitext(&new_slug),
+                    title,
+                    alt_title: None,
+                    slug: old_slug.clone(),
+                    layout: None,
+                    revision_comments: format!("Redirect to {new_slug}"),
+                    user_id,
+                    bypass_filter: true,
+                },
+            )
+            .await?;
+
+            Some(page_id)
+        } else {
+            None
+        };
+
         // Build and return
 
         match revision_output {
@@ -312,6 +343,7 @@ impl PageService {
                 revision_id,
                 revision_number,
                 parser_errors,
+                redirect_page_id,
             }),
             None => {
                 error!("Page move did not create new revision");
@@ -471,8 +503,15 @@ impl PageService {
             PageRevisionService::get_latest(ctx, site_id, page_id),
         )?;
 
-        // TODO Handle hidden fields, see https://scuttle.atlassian.net/browse/WJ-1285
-        let _ = target_revision.hidden;
+        // Rolling back copies the target revision's contents into a new,
+        // visible revision. So if any of them are hidden, only staff may do this.
+        let hidden: Vec<PageHiddenField> = parse_hidden_fields(&target_revision.hidden)?;
+        if hidden.iter().any(|field| field.is_content())
+            && !RelationService::is_site_staff(ctx, site_id, user_id).await?
+        {
+            warn!("Target revision has hidden fields, denying rollback");
+            return Err(Error::CannotRollbackHiddenRevision);
+        }
 
         // Check last revision ID
         check_last_revision(Some(&last_revision), latest_revision_id, last_revision_id)?;
@@ -727,13 +766,16 @@ impl PageService {
     /// * If it is `None`, then it returns all pages regardless of deletion status.
     ///
     /// For the `order` argument, see documentation on `PageOrder`.
-    // TODO add pagination
+    ///
+    /// For the `pagination` argument, see documentation on `Pagination`.
+    /// The cursor is a page ID.
     pub async fn get_all(
         ctx: &ServiceContext<'_>,
         site_id: i64,
         category: Option<Reference<'_>>,
         deleted: Option<bool>,
         order: PageOrder,
+        pagination: Pagination,
     ) -> Result<Vec<PageModel>> {
         let txn = ctx.transaction();
 
@@ -753,14 +795,32 @@ impl PageService {
             None => None,
         };
 
+        let order_column = order.column.into_column();
+        let cursor_condition = match pagination.cursor {
+            None => None,
+            Some(page_id) => {
+                let cursor = Self::get_direct(ctx, page_id, true).await?;
+                Some(cursor_condition(
+                    order_column,
+                    page::Column::PageId,
+                    &order.direction,
+                    cursor.get(order_column),
+                    page_id,
+                ))
+            }
+        };
+
         let pages = Page::find()
             .filter(
                 Condition::all()
                     .add(page::Column::SiteId.eq(site_id))
                     .add_option(category_condition)
-                    .add_option(deleted_condition),
+                    .add_option(deleted_condition)
+                    .add_option(cursor_condition),
             )
-            .order_by(order.column.into_column(), order.direction)
+            .order_by(order_column, order.direction.clone())
+            .order_by(page::Column::PageId, order.direction)
+            .limit(pagination.limit)
             .all(txn)
             .await?;
 
@@ -22,7 +22,7 @@ use super::prelude::*;
 use crate::models::sea_orm_active_enums::PageRevisionType;
 use crate::services::page_revision::CreatePageRevisionOutput;
 use crate::services::score::ScoreValue;
-use crate::types::PageDetails;
+use crate::types::{PageDetails, PageHiddenField, PageOrderColumn, Pagination};
 use ftml::layout::Layout;
 use ftml::parsing::ParseError;
 use time::OffsetDateTime;
@@ -71,6 +71,26 @@ pub struct GetPageSlug {
     pub slug: String,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetPageList<'a> {
+    pub site_id: i64,
+
+    #[serde(default)]
+    pub category: Option<Reference<'a>>,
+
+    #[serde(default)]
+    pub deleted: Option<bool>,
+
+    #[serde(default)]
+    pub order: PageOrderColumn,
+
+    #[serde(default)]
+    pub descending: bool,
+
+    #[serde(flatten)]
+    pub pagination: Pagination,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct GetPageDirect {
     pub site_id: i64,
@@ -112,15 +132,15 @@ pub struct GetPageOutput {
     #[serde(with = "time::serde::rfc3339")]
     pub revision_created_at: OffsetDateTime,
     pub revision_number: i32,
-    pub revision_user_id: i64,
+    pub revision_user_id: Option<i64>,
     pub wikitext: Option<String>,
     pub compiled_html: Option<String>,
 
     #[serde(with = "time::serde::rfc3339")]
     pub compiled_at: OffsetDateTime,
     pub compiled_generator: String,
-    pub revision_comments: String,
-    pub hidden_fields: Vec<String>,
+    pub revision_comments: Option<String>,
+    pub hidden_fields: Vec<PageHiddenField>,
     pub title: String,
     pub alt_title: Option<String>,
     pub slug: String,
@@ -144,7 +164,7 @@ pub struct GetDeletedPageOutput {
     pub page_revision_count: i32,
     pub site_id: i64,
     pub discussion_thread_id: Option<i64>,
-    pub hidden_fields: Vec<String>,
+    pub hidden_fields: Vec<PageHiddenField>,
     pub title: String,
     pub alt_title: Option<String>,
     pub slug: String,
@@ -187,6 +207,10 @@ pub struct MovePage<'a> {
     pub new_slug: String,
     pub revision_comments: String,
     pub user_id: i64,
+
+    /// Whether to create a page at the old slug which redirects to the new one.
+    #[serde(default)]
+    pub leave_redirect: bool,
     // NOTE: slug field is a parameter, not in the body
 }
 
@@ -197,6 +221,7 @@ pub struct MovePageOutput {
     pub revision_id: i64,
     pub revision_number: i32,
     pub parser_errors: Option<Vec<ParseError>>,
+    pub redirect_page_id: Option<i64>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -23,13 +23,18 @@ use crate::models::page_revision::{
     self, Entity as PageRevision, Model as PageRevisionModel,
 };
 use crate::models::sea_orm_active_enums::PageRevisionType;
+use crate::services::diff::FieldDiff;
+use crate::services::link::parse_redirect;
 use crate::services::render::RenderOutput;
 use crate::services::score::ScoreValue;
 use crate::services::{
-    LinkService, OutdateService, PageService, ParentService, RenderService, ScoreService,
-    SettingsService, SiteService, TextService,
+    DiffService, LinkService, OutdateService, PageService, ParentService, RenderService,
+    ScoreService, SettingsService, SiteService, TextService,
+};
+use crate::types::{
+    hidden_fields_column, parse_hidden_fields, FetchDirection, PageDetails,
+    PageHiddenField,
 };
-use crate::types::FetchDirection;
 use crate::utils::{split_category, split_category_name};
 use ftml::data::PageInfo;
 use ftml::layout::Layout;
@@ -129,7 +134,6 @@ impl PageRevisionService {
             mut compiled_hash,
             mut compiled_at,
             mut compiled_generator,
-            hidden,
             mut title,
             mut alt_title,
             mut slug,
@@ -321,7 +325,7 @@ impl PageRevisionService {
             compiled_at: Set(compiled_at),
             compiled_generator: Set(compiled_generator),
             comments: Set(comments),
-            hidden: Set(hidden),
+            hidden: Set(vec![]),
             title: Set(title),
             alt_title: Set(alt_title),
             slug: Set(slug),
@@ -523,7 +527,6 @@ impl PageRevisionService {
         let PageRevisionModel {
             wikitext_hash,
             mut compiled_hash,
-            hidden,
             title,
             alt_title,
             slug: old_slug,
@@ -583,7 +586,7 @@ impl PageRevisionService {
             compiled_at: Set(compiled_at),
             compiled_generator: Set(compiled_generator),
             comments: Set(comments),
-            hidden: Set(hidden),
+            hidden: Set(vec![]),
             title: Set(title),
             alt_title: Set(alt_title),
             slug: Set(new_slug),
@@ -636,10 +639,19 @@ impl PageRevisionService {
         };
 
         // Parse and render
+        // Redirects aren't handled by the parser, so they're found separately
+        let redirect = parse_redirect(&wikitext).map(|redirect| redirect.to_owned());
         let output = RenderService::render(ctx, wikitext, &page_info, &settings).await?;
 
         // Update backlinks
-        LinkService::update(ctx, site_id, page_id, &output.html_output.backlinks).await?;
+        LinkService::update(
+            ctx,
+            site_id,
+            page_id,
+            &output.html_output.backlinks,
+            redirect.as_ref(),
+        )
+        .await?;
 
         Ok(output)
     }
@@ -737,6 +749,8 @@ impl PageRevisionService {
     /// true. In addition to `rerender()`, staff are able to change
     /// the `hidden` column, causing some fields of the revision to be hidden,
     /// for instance, if it contains spam, abuse, or harassment.
+    ///
+    /// This replaces the revision's hidden fields with the given list.
     pub async fn update(
         ctx: &ServiceContext<'_>,
         UpdatePageRevision {
@@ -749,26 +763,18 @@ impl PageRevisionService {
     ) -> Result<()> {
         let txn = ctx.transaction();
 
-        // Unfortunately, we cannot do .contains() on Vec<String> because
-        // it wans to compare with &String, not &str.
-        #[inline]
-        fn contains(items: &[String], query: &str) -> bool {
-            for item in items {
-                if item == query {
-                    return true;
-                }
-            }
-
-            false
-        }
-
-        // The wikitext changes to a page are visible even if that part
+        // The content changes to a page are visible even if that part
         // of the revision is hidden, so current revisions are not allowed
-        // to have that field hidden. It should be reverted first, and then
+        // to have those fields hidden. It should be reverted first, and then
         // the diff can be hidden like any other.
+        //
+        // Comments and the user who made the revision are not exposed
+        // by the page, and so can be hidden at any time.
 
         let latest = Self::get_latest(ctx, site_id, page_id).await?;
-        if revision_id == latest.revision_id && contains(&hidden, "wikitext") {
+        if revision_id == latest.revision_id
+            && hidden.iter().any(|field| field.is_content())
+        {
             return Err(Error::CannotHideLatestRevision);
         }
 
@@ -780,7 +786,7 @@ impl PageRevisionService {
         let model = page_revision::ActiveModel {
             updated_at: Set(Some(now())),
             revision_id: Set(revision_id),
-            hidden: Set(hidden),
+            hidden: Set(hidden_fields_column(&hidden, PageHiddenField::name)),
             ..Default::default()
         };
 
@@ -933,6 +939,177 @@ impl PageRevisionService {
 
         Ok(revisions)
     }
+
+    /// Strips hidden fields from a revision, and fetches its text if requested.
+    ///
+    /// Revisions returned to API consumers should pass through here, so that
+    /// hidden fields are redacted the same way in every read path.
+    pub async fn filter_and_populate(
+        ctx: &ServiceContext<'_>,
+        model: PageRevisionModel,
+        mut details: PageDetails,
+    ) -> Result<PageRevisionModelFiltered> {
+        let PageRevisionModel {
+            revision_id,
+            revision_type,
+            created_at,
+            updated_at,
+            from_wikidot,
+            revision_number,
+            page_id,
+            site_id,
+            user_id,
+            changes,
+            wikitext_hash,
+            compiled_hash,
+            compiled_at,
+            compiled_generator,
+            comments,
+            hidden,
+            title,
+            mut alt_title,
+            slug,
+            tags,
+        } = model;
+
+        // Strip hidden fields
+        let hidden: Vec<PageHiddenField> = parse_hidden_fields(&hidden)?;
+        let mut user_id = Some(user_id);
+        let mut comments = Some(comments);
+        let mut title = Some(title);
+        // alt-title is already Option and we're not doubling up
+        let mut slug = Some(slug);
+        let mut tags = Some(tags);
+
+        for field in &hidden {
+            match field {
+                PageHiddenField::Wikitext => details.wikitext = false,
+                PageHiddenField::Compiled => details.compiled_html = false,
+                PageHiddenField::Comments => comments = None,
+                PageHiddenField::User => user_id = None,
+                PageHiddenField::Title => title = None,
+                PageHiddenField::AltTitle => alt_title = None,
+                PageHiddenField::Slug => slug = None,
+                PageHiddenField::Tags => tags = None,
+            }
+        }
+
+        // Get text data, if requested
+        let (wikitext, compiled_html) = try_join!(
+            TextService::get_maybe(ctx, details.wikitext, &wikitext_hash),
+            TextService::get_maybe(ctx, details.compiled_html, &compiled_hash),
+        )?;
+
+        Ok(PageRevisionModelFiltered {
+            revision_id,
+            revision_type,
+            created_at,
+            updated_at,
+            from_wikidot,
+            revision_number,
+            page_id,
+            site_id,
+            user_id,
+            changes,
+            wikitext,
+            compiled_html,
+            compiled_at,
+            compiled_generator,
+            comments,
+            hidden,
+            title,
+            alt_title,
+            slug,
+            tags,
+        })
+    }
+
+    pub async fn filter_and_populate_all(
+        ctx: &ServiceContext<'_>,
+        revisions: Vec<PageRevisionModel>,
+        details: PageDetails,
+    ) -> Result<Vec<PageRevisionModelFiltered>> {
+        let mut f_revisions = Vec::new();
+
+        for revision in revisions {
+            let f_revision = Self::filter_and_populate(ctx, revision, details).await?;
+            f_revisions.push(f_revision)
+        }
+
+        Ok(f_revisions)
+    }
+
+    /// Compares two revisions of a page.
+    ///
+    /// The revisions do not need to be adjacent, and may be given in
+    /// either order, the diff is always from `from_revision_number` to
+    /// `to_revision_number`.
+    ///
+    /// If a field is hidden in either revision, then it is reported as hidden
+    /// rather than diffed, since a diff would expose its contents.
+    pub async fn diff(
+        ctx: &ServiceContext<'_>,
+        GetPageRevisionDiff {
+            site_id,
+            page_id,
+            from_revision_number,
+            to_revision_number,
+            context_lines,
+        }: GetPageRevisionDiff,
+    ) -> Result<PageRevisionDiffOutput> {
+        let (from, to) = try_join!(
+            Self::get(ctx, site_id, page_id, from_revision_number),
+            Self::get(ctx, site_id, page_id, to_revision_number),
+        )?;
+
+        let hidden: Vec<PageHiddenField> = parse_hidden_fields(&from.hidden)?
+            .into_iter()
+            .chain(parse_hidden_fields(&to.hidden)?)
+            .collect();
+
+        let is_hidden = |field| hidden.contains(&field);
+
+        let wikitext = if is_hidden(PageHiddenField::Wikitext) {
+            FieldDiff::Hidden
+        } else if from.wikitext_hash == to.wikitext_hash {
+            FieldDiff::Unchanged
+        } else {
+            let (old_wikitext, new_wikitext) = try_join!(
+                TextService::get(ctx, &from.wikitext_hash),
+                TextService::get(ctx, &to.wikitext_hash),
+            )?;
+
+            FieldDiff::Changed(DiffService::text(
+                &old_wikitext,
+                &new_wikitext,
+                context_lines,
+            ))
+        };
+
+        let tags =
+            DiffService::list(&from.tags, &to.tags, is_hidden(PageHiddenField::Tags));
+        let title =
+            DiffService::value(from.title, to.title, is_hidden(PageHiddenField::Title));
+        let alt_title = DiffService::value(
+            from.alt_title,
+            to.alt_title,
+            is_hidden(PageHiddenField::AltTitle),
+        );
+        let slug =
+            DiffService::value(from.slug, to.slug, is_hidden(PageHiddenField::Slug));
+
+        Ok(PageRevisionDiffOutput {
+            from_revision_id: from.revision_id,
+            from_revision_number,
+            to_revision_id: to.revision_id,
+            to_revision_number,
+            wikitext,
+            title,
+            alt_title,
+            slug,
+            tags,
+        })
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
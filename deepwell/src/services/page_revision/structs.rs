@@ -20,7 +20,8 @@
 
 use super::prelude::*;
 use crate::models::sea_orm_active_enums::PageRevisionType;
-use crate::types::{FetchDirection, PageDetails};
+use crate::services::diff::{FieldDiff, ListChange, TextDiff, ValueChange};
+use crate::types::{FetchDirection, PageDetails, PageHiddenField};
 use ftml::layout::Layout;
 use ftml::parsing::ParseError;
 use std::num::NonZeroI32;
@@ -109,7 +110,7 @@ pub struct UpdatePageRevision {
     pub page_id: i64,
     pub revision_id: i64,
     pub user_id: i64,
-    pub hidden: Vec<String>,
+    pub hidden: Vec<PageHiddenField>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -139,6 +140,32 @@ pub struct GetPageRevisionRangeDetails {
     pub details: PageDetails,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetPageRevisionDiff {
+    pub site_id: i64,
+    pub page_id: i64,
+    pub from_revision_number: i32,
+    pub to_revision_number: i32,
+
+    /// How many lines of unchanged wikitext to include around each change.
+    /// If unset, then the full wikitext is included in the diff.
+    #[serde(default)]
+    pub context_lines: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PageRevisionDiffOutput {
+    pub from_revision_id: i64,
+    pub from_revision_number: i32,
+    pub to_revision_id: i64,
+    pub to_revision_number: i32,
+    pub wikitext: FieldDiff<TextDiff>,
+    pub title: FieldDiff<ValueChange<String>>,
+    pub alt_title: FieldDiff<ValueChange<Option<String>>>,
+    pub slug: FieldDiff<ValueChange<String>>,
+    pub tags: FieldDiff<ListChange<String>>,
+}
+
 /// Information about the revisions currently associated with a page.
 ///
 /// A lot of this information is not strictly necessary:
@@ -169,7 +196,7 @@ pub struct PageRevisionModelFiltered {
     pub revision_number: i32,
     pub page_id: i64,
     pub site_id: i64,
-    pub user_id: i64,
+    pub user_id: Option<i64>,
     pub changes: Vec<String>,
     pub wikitext: Option<String>,
     pub compiled_html: Option<String>,
@@ -178,7 +205,7 @@ pub struct PageRevisionModelFiltered {
     pub compiled_at: OffsetDateTime,
     pub compiled_generator: String,
     pub comments: Option<String>,
-    pub hidden: Vec<String>,
+    pub hidden: Vec<PageHiddenField>,
     pub title: Option<String>,
     pub alt_title: Option<String>,
     pub slug: Option<String>,
@@ -0,0 +1,416 @@
+/*
+ * services/quota/service.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use crate::models::file_revision::Entity as FileRevision;
+use crate::models::storage_quota::{self, Entity as StorageQuotaEntity};
+use sea_orm::{DatabaseBackend, FromQueryResult, Statement, Value};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Only revisions which changed the blob are counted, since other
+/// revisions (renames, moves, etc) carry forward the existing blob.
+const BLOB_REVISION_CONDITION: &str = "'blob' = ANY(changes)";
+
+#[derive(Debug)]
+pub struct QuotaService;
+
+impl QuotaService {
+    /// Sets the storage quota for a site or user, overriding the default.
+    pub async fn set(
+        ctx: &ServiceContext<'_>,
+        SetStorageQuota {
+            owner,
+            maximum_bytes,
+        }: SetStorageQuota,
+    ) -> Result<()> {
+        info!("Setting storage quota for {owner:?} to {maximum_bytes:?}");
+
+        if maximum_bytes.is_some_and(|maximum| maximum < 0) {
+            error!("Storage quota cannot be negative");
+            return Err(Error::BadRequest);
+        }
+
+        let txn = ctx.transaction();
+        match Self::get_override(ctx, owner).await? {
+            Some(model) => {
+                let mut model = model.into_active_model();
+                model.maximum_bytes = Set(maximum_bytes);
+                model.updated_at = Set(Some(now()));
+                model.update(txn).await?;
+            }
+            None => {
+                let (site_id, user_id) = match owner {
+                    QuotaOwner::Site(site_id) => (Some(site_id), None),
+                    QuotaOwner::User(user_id) => (None, Some(user_id)),
+                };
+
+                let model = storage_quota::ActiveModel {
+                    site_id: Set(site_id),
+                    user_id: Set(user_id),
+                    maximum_bytes: Set(maximum_bytes),
+                    ..Default::default()
+                };
+                model.insert(txn).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes the storage quota override for a site or user, so the default applies.
+    pub async fn reset(ctx: &ServiceContext<'_>, owner: QuotaOwner) -> Result<()> {
+        info!("Resetting storage quota for {owner:?} to the default");
+
+        let txn = ctx.transaction();
+        if let Some(model) = Self::get_override(ctx, owner).await? {
+            model.delete(txn).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_override(
+        ctx: &ServiceContext<'_>,
+        owner: QuotaOwner,
+    ) -> Result<Option<storage_quota::Model>> {
+        let txn = ctx.transaction();
+        let condition = match owner {
+            QuotaOwner::Site(site_id) => storage_quota::Column::SiteId.eq(site_id),
+            QuotaOwner::User(user_id) => storage_quota::Column::UserId.eq(user_id),
+        };
+
+        let model = StorageQuotaEntity::find()
+            .filter(condition)
+            .one(txn)
+            .await?;
+
+        Ok(model)
+    }
+
+    /// Gets the storage quota for a site or user.
+    pub async fn get_quota(
+        ctx: &ServiceContext<'_>,
+        owner: QuotaOwner,
+    ) -> Result<StorageQuota> {
+        let quota = match Self::get_override(ctx, owner).await? {
+            Some(model) => StorageQuota {
+                maximum_bytes: model.maximum_bytes,
+                is_default: false,
+            },
+            None => {
+                let config = ctx.config();
+                let maximum_bytes = match owner {
+                    QuotaOwner::Site(_) => config.default_site_quota,
+                    QuotaOwner::User(_) => config.default_user_quota,
+                };
+
+                StorageQuota {
+                    maximum_bytes,
+                    is_default: true,
+                }
+            }
+        };
+
+        Ok(quota)
+    }
+
+    /// Gets how much storage a site or user is using, in bytes.
+    pub async fn get_usage(ctx: &ServiceContext<'_>, owner: QuotaOwner) -> Result<i64> {
+        #[derive(Debug, FromQueryResult)]
+        struct Usage {
+            usage: i64,
+        }
+
+        let txn = ctx.transaction();
+        let query = Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            format!(
+                "
+                SELECT COALESCE(SUM(size), 0)::BIGINT AS usage
+                FROM (
+                    SELECT DISTINCT ON (s3_hash) size
+                    FROM file_revision
+                    WHERE {} = $1
+                    AND {BLOB_REVISION_CONDITION}
+                ) AS blob
+                ",
+                owner.column(),
+            ),
+            [Value::from(owner.id())],
+        );
+
+        let Usage { usage } = FileRevision::find()
+            .from_raw_sql(query)
+            .into_model::<Usage>()
+            .one(txn)
+            .await?
+            .ok_or(Error::GeneralNotFound)?;
+
+        Ok(usage)
+    }
+
+    /// Determines if a blob already counts towards the storage used by a site or user.
+    async fn counts_blob(
+        ctx: &ServiceContext<'_>,
+        owner: QuotaOwner,
+        s3_hash: &[u8],
+    ) -> Result<bool> {
+        #[derive(Debug, FromQueryResult)]
+        struct Counted {
+            counted: bool,
+        }
+
+        let txn = ctx.transaction();
+        let query = Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            format!(
+                "
+                SELECT EXISTS (
+                    SELECT 1
+                    FROM file_revision
+                    WHERE {} = $1
+                    AND s3_hash = $2
+                    AND {BLOB_REVISION_CONDITION}
+                ) AS counted
+                ",
+                owner.column(),
+            ),
+            [Value::from(owner.id()), Value::from(s3_hash.to_vec())],
+        );
+
+        let Counted { counted } = FileRevision::find()
+            .from_raw_sql(query)
+            .into_model::<Counted>()
+            .one(txn)
+            .await?
+            .ok_or(Error::GeneralNotFound)?;
+
+        Ok(counted)
+    }
+
+    /// Locks the storage quota of a site or user until the transaction ends.
+    ///
+    /// Most owners have no `storage_quota` row, since the default applies,
+    /// so the `site` or `user` row is locked instead. This way concurrent
+    /// uploads for the same owner are checked one after the other, rather
+    /// than each seeing the usage from before the others were added.
+    async fn lock(ctx: &ServiceContext<'_>, owner: QuotaOwner) -> Result<()> {
+        let txn = ctx.transaction();
+        let table = match owner {
+            QuotaOwner::Site(_) => "site",
+            QuotaOwner::User(_) => "\"user\"",
+        };
+
+        txn.execute(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            format!(
+                "SELECT 1 FROM {table} WHERE {} = $1 FOR UPDATE",
+                owner.column()
+            ),
+            [Value::from(owner.id())],
+        ))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Checks that adding a blob would not exceed the storage quotas of a site or user.
+    ///
+    /// Blobs which already count towards a quota do not use any more of it.
+    pub async fn check(
+        ctx: &ServiceContext<'_>,
+        site_id: i64,
+        user_id: i64,
+        s3_hash: &[u8],
+        size: i64,
+    ) -> Result<()> {
+        for owner in [QuotaOwner::Site(site_id), QuotaOwner::User(user_id)] {
+            let maximum = match Self::get_quota(ctx, owner).await?.maximum_bytes {
+                Some(maximum) => maximum,
+                None => continue,
+            };
+
+            Self::lock(ctx, owner).await?;
+            if Self::counts_blob(ctx, owner, s3_hash).await? {
+                debug!("Blob already counts towards storage quota for {owner:?}");
+                continue;
+            }
+
+            let usage = Self::get_usage(ctx, owner).await?;
+            check_usage(owner, usage, size, maximum)?;
+        }
+
+        Ok(())
+    }
+
+    /// Produces a report of the storage used by a site or user, by page and file.
+    ///
+    /// Blobs used by multiple files are counted for the file they were first uploaded to.
+    pub async fn get_report(
+        ctx: &ServiceContext<'_>,
+        owner: QuotaOwner,
+    ) -> Result<StorageUsageOutput> {
+        #[derive(Debug, FromQueryResult)]
+        struct BlobUsage {
+            site_id: i64,
+            page_id: i64,
+            file_id: i64,
+            name: String,
+            deleted: bool,
+            size: i64,
+        }
+
+        info!("Getting storage usage report for {owner:?}");
+
+        let txn = ctx.transaction();
+        let query = Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            format!(
+                "
+                SELECT
+                    f.site_id AS site_id,
+                    f.page_id AS page_id,
+                    f.file_id AS file_id,
+                    f.name AS name,
+                    f.deleted_at IS NOT NULL AS deleted,
+                    blob.size AS size
+                FROM (
+                    SELECT DISTINCT ON (s3_hash) file_id, size
+                    FROM file_revision
+                    WHERE {} = $1
+                    AND {BLOB_REVISION_CONDITION}
+                    ORDER BY s3_hash, revision_id
+                ) AS blob
+                JOIN file AS f
+                    ON f.file_id = blob.file_id
+                ",
+                owner.column(),
+            ),
+            [Value::from(owner.id())],
+        );
+
+        let blobs = FileRevision::find()
+            .from_raw_sql(query)
+            .into_model::<BlobUsage>()
+            .all(txn)
+            .await?;
+
+        let mut pages = HashMap::new();
+        for BlobUsage {
+            site_id,
+            page_id,
+            file_id,
+            name,
+            deleted,
+            size,
+        } in blobs
+        {
+            let page = pages.entry(page_id).or_insert_with(|| PageStorageUsage {
+                site_id,
+                page_id,
+                usage: 0,
+                files: Vec::new(),
+            });
+
+            page.usage += size;
+            match page.files.iter_mut().find(|file| file.file_id == file_id) {
+                Some(file) => file.usage += size,
+                None => page.files.push(FileStorageUsage {
+                    file_id,
+                    name,
+                    deleted,
+                    usage: size,
+                }),
+            }
+        }
+
+        // Largest first
+        let mut pages = pages.into_values().collect::<Vec<_>>();
+        for page in &mut pages {
+            page.files.sort_by_key(|file| Reverse(file.usage));
+        }
+        pages.sort_by_key(|page| Reverse(page.usage));
+
+        let usage = pages.iter().map(|page| page.usage).sum();
+        let StorageQuota {
+            maximum_bytes,
+            is_default,
+        } = Self::get_quota(ctx, owner).await?;
+
+        Ok(StorageUsageOutput {
+            owner,
+            usage,
+            maximum_bytes,
+            is_default,
+            pages,
+        })
+    }
+}
+
+/// Checks that adding `size` bytes to `usage` stays within the quota.
+fn check_usage(owner: QuotaOwner, usage: i64, size: i64, maximum: i64) -> Result<()> {
+    if usage + size > maximum {
+        warn!("Storage quota for {owner:?} exceeded ({usage} + {size} > {maximum})");
+        return Err(Error::StorageQuotaExceeded {
+            usage,
+            size,
+            maximum,
+        });
+    }
+
+    Ok(())
+}
+
+#[test]
+fn usage() {
+    macro_rules! check {
+        ($usage:expr, $size:expr, $maximum:expr, $allowed:expr $(,)?) => {{
+            let result = check_usage(QuotaOwner::Site(1), $usage, $size, $maximum);
+            assert_eq!(
+                result.is_ok(),
+                $allowed,
+                "Quota check for {} + {} of {} did not match expected",
+                $usage,
+                $size,
+                $maximum,
+            );
+
+            if let Err(error) = result {
+                assert!(
+                    matches!(
+                        error,
+                        Error::StorageQuotaExceeded { usage, size, maximum }
+                            if usage == $usage && size == $size && maximum == $maximum,
+                    ),
+                    "Unexpected error from quota check: {error:?}",
+                );
+            }
+        }};
+    }
+
+    check!(0, 0, 0, true);
+    check!(0, 100, 100, true);
+    check!(50, 50, 100, true);
+    check!(50, 51, 100, false);
+    check!(100, 1, 100, false);
+    check!(0, 101, 100, false);
+    check!(200, 0, 100, false);
+}
@@ -0,0 +1,82 @@
+/*
+ * services/quota/structs.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/// What a storage quota applies to.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaOwner {
+    Site(i64),
+    User(i64),
+}
+
+impl QuotaOwner {
+    /// The column in `file_revision` and `storage_quota` for this owner.
+    pub fn column(self) -> &'static str {
+        match self {
+            QuotaOwner::Site(_) => "site_id",
+            QuotaOwner::User(_) => "user_id",
+        }
+    }
+
+    pub fn id(self) -> i64 {
+        match self {
+            QuotaOwner::Site(id) | QuotaOwner::User(id) => id,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SetStorageQuota {
+    pub owner: QuotaOwner,
+
+    /// The new quota in bytes, or `None` for unlimited storage.
+    pub maximum_bytes: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StorageQuota {
+    pub maximum_bytes: Option<i64>,
+    pub is_default: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StorageUsageOutput {
+    pub owner: QuotaOwner,
+    pub usage: i64,
+    pub maximum_bytes: Option<i64>,
+    pub is_default: bool,
+    pub pages: Vec<PageStorageUsage>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PageStorageUsage {
+    pub site_id: i64,
+    pub page_id: i64,
+    pub usage: i64,
+    pub files: Vec<FileStorageUsage>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FileStorageUsage {
+    pub file_id: i64,
+    pub name: String,
+    pub deleted: bool,
+    pub usage: i64,
+}
@@ -0,0 +1,163 @@
+/*
+ * services/rate_limit/service.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use crate::types::RateLimit;
+use once_cell::sync::Lazy;
+use rand::{thread_rng, Rng};
+use redis::Script;
+use std::time::Duration as StdDuration;
+
+/// Records an attempt in a sliding window, unless the window is already full.
+///
+/// The time is taken from Redis itself so that all instances agree on it.
+/// Each attempt is stored with a random suffix, since several attempts
+/// could arrive within the same millisecond.
+///
+/// Returns `1` if the attempt was permitted, or `0` if it was rejected.
+static SLIDING_WINDOW: Lazy<Script> = Lazy::new(|| {
+    Script::new(
+        r"
+        local time = redis.call('TIME')
+        local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+        local window = tonumber(ARGV[1])
+        local limit = tonumber(ARGV[2])
+
+        redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - window)
+        if redis.call('ZCARD', KEYS[1]) >= limit then
+            return 0
+        end
+
+        redis.call('ZADD', KEYS[1], now, now .. '-' .. ARGV[3])
+        redis.call('PEXPIRE', KEYS[1], window)
+        return 1
+        ",
+    )
+});
+
+#[derive(Debug)]
+pub struct RateLimitService;
+
+impl RateLimitService {
+    /// Records an attempt at an action, failing if the limit has been reached.
+    pub async fn check(
+        ctx: &ServiceContext<'_>,
+        action: RateLimitAction,
+        scope: RateLimitScope<'_>,
+        limit: RateLimit,
+    ) -> Result<()> {
+        if Self::attempt(ctx, action, scope, limit).await? {
+            Ok(())
+        } else {
+            warn!(
+                "Rate limit reached for {} ({scope}), {} attempts in {} seconds",
+                action.name(),
+                limit.attempts,
+                limit.window_secs,
+            );
+            Err(Error::RateLimited)
+        }
+    }
+
+    /// Records an attempt at an action, if it is still within the limit.
+    ///
+    /// # Returns
+    /// Whether the attempt was permitted. Rejected attempts are not
+    /// recorded, so they do not extend how long the limit lasts.
+    pub async fn attempt(
+        ctx: &ServiceContext<'_>,
+        action: RateLimitAction,
+        scope: RateLimitScope<'_>,
+        limit: RateLimit,
+    ) -> Result<bool> {
+        let key = rate_limit_key(action, scope);
+        let window_ms = limit.window().as_millis() as u64;
+        let nonce: u32 = thread_rng().gen();
+        debug!("Recording attempt for rate limit key {key}");
+
+        let mut redis = ctx.redis_connect().await?;
+        let permitted: u8 = SLIDING_WINDOW
+            .key(&key)
+            .arg(window_ms)
+            .arg(limit.attempts)
+            .arg(nonce)
+            .invoke_async(&mut redis)
+            .await?;
+
+        Ok(permitted != 0)
+    }
+
+    /// Forgets all recorded attempts at an action for this scope.
+    pub async fn clear(
+        ctx: &ServiceContext<'_>,
+        action: RateLimitAction,
+        scope: RateLimitScope<'_>,
+    ) -> Result<()> {
+        let key = rate_limit_key(action, scope);
+        debug!("Clearing attempts for rate limit key {key}");
+
+        let mut redis = ctx.redis_connect().await?;
+        redis::cmd("DEL")
+            .arg(&key)
+            .query_async::<_, ()>(&mut redis)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Locks an account for the given duration.
+    pub async fn lock(
+        ctx: &ServiceContext<'_>,
+        scope: RateLimitScope<'_>,
+        duration: StdDuration,
+    ) -> Result<()> {
+        let key = lockout_key(scope);
+        info!(
+            "Locking account ({scope}) for {} seconds",
+            duration.as_secs()
+        );
+
+        let mut redis = ctx.redis_connect().await?;
+        redis::cmd("SET")
+            .arg(&key)
+            .arg(1)
+            .arg("PX")
+            .arg(duration.as_millis() as u64)
+            .query_async::<_, ()>(&mut redis)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Determines if an account is currently locked.
+    pub async fn is_locked(
+        ctx: &ServiceContext<'_>,
+        scope: RateLimitScope<'_>,
+    ) -> Result<bool> {
+        let key = lockout_key(scope);
+        let mut redis = ctx.redis_connect().await?;
+        let exists: bool = redis::cmd("EXISTS")
+            .arg(&key)
+            .query_async(&mut redis)
+            .await?;
+
+        Ok(exists)
+    }
+}
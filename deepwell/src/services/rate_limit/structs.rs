@@ -0,0 +1,136 @@
+/*
+ * services/rate_limit/structs.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::types::ip_network;
+use std::fmt::{self, Display};
+use std::net::IpAddr;
+
+/// The kinds of actions which are rate limited.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum RateLimitAction {
+    Login,
+    LoginFailure,
+    MfaVerify,
+    UserCreate,
+    EmailValidate,
+    MessageSend,
+}
+
+impl RateLimitAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            RateLimitAction::Login => "login",
+            RateLimitAction::LoginFailure => "login-failure",
+            RateLimitAction::MfaVerify => "mfa-verify",
+            RateLimitAction::UserCreate => "user-create",
+            RateLimitAction::EmailValidate => "email-validate",
+            RateLimitAction::MessageSend => "message-send",
+        }
+    }
+}
+
+/// Who attempts at an action are counted against.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum RateLimitScope<'a> {
+    /// All attempts, regardless of who made them.
+    Global,
+
+    /// Attempts from one IP address.
+    ///
+    /// IPv6 addresses are grouped by their /64 network, since
+    /// that is usually what is allocated to a single host.
+    Ip(IpAddr),
+
+    /// Attempts by or against an existing user.
+    User(i64),
+
+    /// Attempts against an account name or email which may not exist.
+    Account(&'a str),
+}
+
+impl Display for RateLimitScope<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RateLimitScope::Global => write!(f, "global"),
+            RateLimitScope::Ip(ip_address) => {
+                write!(f, "ip:{}", ip_network(*ip_address, 32, 64))
+            }
+            RateLimitScope::User(user_id) => write!(f, "user:{user_id}"),
+            RateLimitScope::Account(name) => {
+                write!(f, "account:{}", name.trim().to_lowercase())
+            }
+        }
+    }
+}
+
+/// Gets the Redis key storing recent attempts at an action.
+pub fn rate_limit_key(action: RateLimitAction, scope: RateLimitScope) -> String {
+    format!("rate-limit:{}:{scope}", action.name())
+}
+
+/// Gets the Redis key marking an account as locked.
+pub fn lockout_key(scope: RateLimitScope) -> String {
+    format!("lockout:{scope}")
+}
+
+#[test]
+fn keys() {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    macro_rules! check {
+        ($action:expr, $scope:expr, $expected:expr $(,)?) => {
+            assert_eq!(
+                rate_limit_key($action, $scope),
+                $expected,
+                "Actual rate limit key doesn't match expected",
+            );
+        };
+    }
+
+    check!(
+        RateLimitAction::Login,
+        RateLimitScope::Global,
+        "rate-limit:login:global",
+    );
+    check!(
+        RateLimitAction::Login,
+        RateLimitScope::Ip(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 44))),
+        "rate-limit:login:ip:192.0.2.44/32",
+    );
+    check!(
+        RateLimitAction::MfaVerify,
+        RateLimitScope::Ip(IpAddr::V6(Ipv6Addr::new(
+            0x2001, 0xdb8, 0xa, 0xb, 0x1, 0x2, 0x3, 0x4,
+        ))),
+        "rate-limit:mfa-verify:ip:2001:db8:a:b::/64",
+    );
+    check!(
+        RateLimitAction::MessageSend,
+        RateLimitScope::User(42),
+        "rate-limit:message-send:user:42",
+    );
+    check!(
+        RateLimitAction::LoginFailure,
+        RateLimitScope::Account(" Alice@Example.com "),
+        "rate-limit:login-failure:account:alice@example.com",
+    );
+
+    assert_eq!(lockout_key(RateLimitScope::User(42)), "lockout:user:42");
+}
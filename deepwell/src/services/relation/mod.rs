@@ -28,6 +28,7 @@
 //!
 //! For example:
 //! * `site` / `member` / `user` &mdash; User is a site member
+//! * `site` / `staff` / `user` &mdash; User is site staff
 //! * `user` / `block` / `user` &mdash; User has blocked another user
 
 #[allow(unused_imports)]
@@ -46,6 +47,7 @@ mod page_star;
 mod page_watch;
 mod site_ban;
 mod site_member;
+mod site_staff;
 mod site_user;
 mod structs;
 mod user_block;
@@ -56,6 +58,7 @@ pub use self::page_star::*;
 pub use self::page_watch::*;
 pub use self::site_ban::*;
 pub use self::site_member::*;
+pub use self::site_staff::*;
 pub use self::site_user::*;
 pub use self::structs::*;
 pub use self::user_block::*;
@@ -0,0 +1,58 @@
+/*
+ * services/relation/site_staff.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Site staff, who may act on hidden revision contents.
+//!
+//! Staff are appointed and removed by platform administrators, see
+//! the `platform-admins` configuration option.
+
+use super::prelude::*;
+
+impl_relation!(SiteStaff, Site, site_id, User, user_id, (), NO_CREATE_IMPL);
+
+impl RelationService {
+    pub async fn create_site_staff(
+        ctx: &ServiceContext<'_>,
+        CreateSiteStaff {
+            site_id,
+            user_id,
+            created_by,
+            metadata: (),
+        }: CreateSiteStaff,
+    ) -> Result<()> {
+        // Only members of the site can be made staff
+        if !Self::site_member_exists(ctx, GetSiteMember { site_id, user_id }).await? {
+            error!("User ID {user_id} is not a member of site ID {site_id}");
+            return Err(Error::RelationNotFound);
+        }
+
+        create_operation!(ctx, SiteStaff, Site, site_id, User, user_id, created_by)
+    }
+
+    /// Determines if a user is staff of the given site.
+    #[inline]
+    pub async fn is_site_staff(
+        ctx: &ServiceContext<'_>,
+        site_id: i64,
+        user_id: i64,
+    ) -> Result<bool> {
+        Self::site_staff_exists(ctx, GetSiteStaff { site_id, user_id }).await
+    }
+}
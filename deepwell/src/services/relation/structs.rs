@@ -131,6 +131,7 @@ pub enum RelationType {
     #[allow(dead_code)] // TEMP
     SiteApplication,
     SiteMember,
+    SiteStaff,
     PageStar,
     PageWatch,
     UserFollow,
@@ -152,6 +153,7 @@ impl RelationType {
             RelationType::SiteBan => "ban",
             RelationType::SiteApplication => "application",
             RelationType::SiteMember => "member",
+            RelationType::SiteStaff => "staff",
             RelationType::PageStar => "star",
             RelationType::PageWatch => "watch",
             RelationType::UserFollow => "follow",
@@ -176,6 +178,7 @@ impl RelationType {
             RelationType::SiteBan => t!(Site, User),
             RelationType::SiteApplication => t!(Site, User),
             RelationType::SiteMember => t!(Site, User),
+            RelationType::SiteStaff => t!(Site, User),
             RelationType::PageStar => t!(Page, User),
             RelationType::PageWatch => t!(Page, User),
             RelationType::UserFollow => t!(User, User),
@@ -185,3 +188,25 @@ impl RelationType {
         }
     }
 }
+
+/// Input to appoint a user as staff of a site.
+///
+/// This must be done by a platform administrator, as given by `created_by`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AppointSiteStaff {
+    pub site_id: i64,
+    pub user_id: i64,
+    pub created_by: i64,
+    pub session_token: String,
+}
+
+/// Input to remove a user from the staff of a site.
+///
+/// This must be done by a platform administrator, as given by `removed_by`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DismissSiteStaff {
+    pub site_id: i64,
+    pub user_id: i64,
+    pub removed_by: i64,
+    pub session_token: String,
+}
@@ -29,13 +29,25 @@
 //! which represents the current session. It has a somewhat short
 //! expiry (30 minutes) which needs to be renewed by the client
 //! periodically.
+//!
+//! Sessions are also bound to the IP address and user agent they were
+//! created with, depending on the configured policy. If a session is used
+//! from somewhere else, it is either restricted or invalidated.
 
+use super::device::describe_user_agent;
 use super::prelude::*;
+use crate::models::sea_orm_active_enums::UserType;
 use crate::models::session::{self, Entity as Session, Model as SessionModel};
 use crate::models::user::{self, Entity as User, Model as UserModel};
-use crate::utils::assert_is_csprng;
+use crate::services::api_token::{ApiAction, ApiScope};
+use crate::services::{ApiTokenService, OauthService, PageService, UserService};
+use crate::types::{
+    ip_network, parse_inet, Reference, SessionBinding, SessionBindingAction,
+};
+use crate::utils::{assert_is_csprng, LAST_USED_PRECISION};
 use rand::distributions::{Alphanumeric, DistString};
 use rand::thread_rng;
+use std::net::IpAddr;
 
 #[derive(Debug)]
 pub struct SessionService;
@@ -71,7 +83,8 @@ impl SessionService {
             user_id: Set(user_id),
             created_at: Set(now),
             expires_at: Set(expiry),
-            ip_address: Set(str!(ip_address)), // TODO inet type?
+            last_used_at: Set(now),
+            ip_address: Set(ip_address.to_string()),
             user_agent: Set(user_agent),
             restricted: Set(restricted),
         };
@@ -124,6 +137,159 @@ impl SessionService {
         Ok(session)
     }
 
+    /// Determines if this is a scoped token, rather than a session token.
+    ///
+    /// This only checks the prefix, not whether the token is valid.
+    pub fn is_scoped_token(config: &Config, token: &str) -> bool {
+        ApiTokenService::is_api_token(config, token)
+            || OauthService::is_access_token(config, token)
+    }
+
+    /// Gets the session for a scoped token, such as a bot's API token.
+    ///
+    /// These can be used anywhere a session token can, but only for what
+    /// their scopes permit, which callers must check.
+    /// Yields `None` if the token is not valid.
+    pub async fn get_scoped(
+        ctx: &ServiceContext<'_>,
+        token: &str,
+        ip_address: IpAddr,
+        user_agent: &str,
+    ) -> Result<Option<ScopedSession>> {
+        let config = ctx.config();
+        if ApiTokenService::is_api_token(config, token) {
+            ApiTokenService::get_session(ctx, token, ip_address, user_agent).await
+        } else if OauthService::is_access_token(config, token) {
+            OauthService::get_session(ctx, token, ip_address, user_agent).await
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Checks that a request is permitted by the token it was made with.
+    ///
+    /// Requests made with a scoped token are only permitted if it belongs to
+    /// the acting user, and grants the action on each site affected. If no
+    /// site is affected, then any scope for the action suffices.
+    ///
+    /// Bots can only act through API tokens, so any request acting as one
+    /// without a scoped token is refused.
+    pub async fn check_scope(
+        ctx: &ServiceContext<'_>,
+        CheckScope {
+            scoped_token,
+            user_id,
+            user,
+            site_id,
+            destination_site_id,
+            page_id,
+        }: CheckScope,
+        action: ApiAction,
+    ) -> Result<()> {
+        let user = match (user_id, user) {
+            (Some(user_id), _) => {
+                Some(UserService::get(ctx, Reference::Id(user_id)).await?)
+            }
+            (None, Some(reference)) => Some(UserService::get(ctx, reference).await?),
+            (None, None) => None,
+        };
+
+        let config = ctx.config();
+        let token = match (scoped_token, &user) {
+            (Some(token), _) if Self::is_scoped_token(config, &token) => token,
+            (_, Some(user)) if user.user_type == UserType::Bot => {
+                error!(
+                    "Bot user ID {} cannot act without an API token",
+                    user.user_id
+                );
+                return Err(Error::ScopeNotGranted);
+            }
+            _ => return Ok(()),
+        };
+
+        let (token_user_id, scopes) = if ApiTokenService::is_api_token(config, &token) {
+            let model = ApiTokenService::authenticate(ctx, &token)
+                .await?
+                .ok_or(Error::InvalidSessionToken)?;
+
+            (model.bot_user_id, ApiScope::parse_all(&model.scopes))
+        } else {
+            let model = OauthService::authenticate(ctx, &token)
+                .await?
+                .ok_or(Error::InvalidSessionToken)?;
+
+            (model.user_id, ApiScope::parse_all(&model.scopes))
+        };
+
+        // Methods which don't name the acting user still need the scope,
+        // they just can't be checked against the token's user.
+        if let Some(user) = user {
+            if token_user_id != user.user_id {
+                error!(
+                    "Acting user ID ({}) does not match scoped token ({})",
+                    user.user_id, token_user_id,
+                );
+
+                return Err(Error::SessionUserId {
+                    active_user_id: user.user_id,
+                    session_user_id: token_user_id,
+                });
+            }
+        }
+
+        let site_id = match (site_id, page_id) {
+            (Some(site_id), _) => Some(site_id),
+            (None, Some(page_id)) => {
+                Some(PageService::get_direct(ctx, page_id, true).await?.site_id)
+            }
+            (None, None) => None,
+        };
+
+        let granted = match (site_id, destination_site_id) {
+            (None, None) => scopes.iter().any(|scope| scope.action == action),
+            (site_id, destination_site_id) => [site_id, destination_site_id]
+                .into_iter()
+                .flatten()
+                .all(|site_id| {
+                    ApiScope::any_grants(
+                        &scopes,
+                        ApiScope {
+                            action,
+                            site_id: Some(site_id),
+                        },
+                    )
+                }),
+        };
+
+        if !granted {
+            warn!(
+                "Token for user ID {} does not grant {} (site {:?}, destination site {:?})",
+                token_user_id,
+                action.name(),
+                site_id,
+                destination_site_id,
+            );
+            return Err(Error::ScopeNotGranted);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that a request was not made with a scoped token.
+    ///
+    /// This is for methods not covered by any API action, such as account
+    /// deletion. Scoped tokens are refused for these entirely, since none
+    /// of their scopes could have been meant to permit them.
+    pub fn check_unscoped(config: &Config, scoped_token: Option<&str>) -> Result<()> {
+        match scoped_token {
+            Some(token) if Self::is_scoped_token(config, token) => {
+                warn!("Scoped tokens cannot be used for this method");
+                Err(Error::ScopeNotGranted)
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Gets the associated `UserModel` from an active session.
     ///
     /// Performs a join rather than two separate fetches.
@@ -174,13 +340,189 @@ impl SessionService {
         Ok(sessions)
     }
 
+    /// Checks that a session is being used from where it was created.
+    ///
+    /// What counts as a match depends on the configured `SessionBinding`.
+    /// If the session matches, then its last used time is updated, if it
+    /// is older than `LAST_USED_PRECISION`. Otherwise the configured
+    /// `SessionBindingAction` is applied.
+    ///
+    /// # Returns
+    /// The updated session, which may now be restricted,
+    /// or `None` if it was invalidated.
+    pub async fn check_binding(
+        ctx: &ServiceContext<'_>,
+        session: SessionModel,
+        ip_address: IpAddr,
+        user_agent: &str,
+    ) -> Result<Option<SessionModel>> {
+        let txn = ctx.transaction();
+        let config = ctx.config();
+        let now = now();
+
+        if Self::binding_matches(config, &session, ip_address, user_agent) {
+            if now - session.last_used_at < LAST_USED_PRECISION {
+                return Ok(Some(session));
+            }
+
+            let model = session::ActiveModel {
+                session_token: Set(session.session_token),
+                last_used_at: Set(now),
+                ..Default::default()
+            };
+
+            let session = model.update(txn).await?;
+            return Ok(Some(session));
+        }
+
+        warn!(
+            "Session for user ID {} used from a different network or device ({} / '{}')",
+            session.user_id, ip_address, user_agent,
+        );
+
+        match config.session_binding_action {
+            SessionBindingAction::Invalidate => {
+                Self::invalidate(ctx, session.session_token).await?;
+                Ok(None)
+            }
+            SessionBindingAction::Restrict if session.restricted => Ok(Some(session)),
+            SessionBindingAction::Restrict => {
+                // Restricted sessions don't last as long, so this may also shorten it
+                let expires_at = session
+                    .expires_at
+                    .min(now + config.restricted_session_duration);
+
+                let model = session::ActiveModel {
+                    session_token: Set(session.session_token),
+                    expires_at: Set(expires_at),
+                    restricted: Set(true),
+                    ..Default::default()
+                };
+
+                let session = model.update(txn).await?;
+                Ok(Some(session))
+            }
+        }
+    }
+
+    /// Determines if a session may be used from this IP address and user agent.
+    fn binding_matches(
+        config: &Config,
+        session: &SessionModel,
+        ip_address: IpAddr,
+        user_agent: &str,
+    ) -> bool {
+        let network = |ip_address| {
+            ip_network(
+                ip_address,
+                config.session_binding_ipv4_prefix,
+                config.session_binding_ipv6_prefix,
+            )
+        };
+
+        let session_ip_address = match parse_inet(&session.ip_address) {
+            Some(ip_address) => ip_address,
+            None => {
+                error!(
+                    "Invalid IP address stored in session: {}",
+                    session.ip_address
+                );
+                return false;
+            }
+        };
+
+        match config.session_binding {
+            SessionBinding::Off => true,
+            SessionBinding::Strict => {
+                session_ip_address.to_canonical() == ip_address.to_canonical()
+                    && session.user_agent == user_agent
+            }
+            SessionBinding::Subnet => {
+                network(session_ip_address) == network(ip_address)
+                    && session.user_agent == user_agent
+            }
+        }
+    }
+
+    /// Describes a session for listing to users.
+    pub fn describe(config: &Config, session: &SessionModel) -> SessionInfo {
+        let network = match parse_inet(&session.ip_address) {
+            Some(ip_address) => ip_network(
+                ip_address,
+                config.session_binding_ipv4_prefix,
+                config.session_binding_ipv6_prefix,
+            ),
+            None => str!("unknown"),
+        };
+
+        SessionInfo {
+            created_at: session.created_at,
+            expires_at: session.expires_at,
+            last_used_at: session.last_used_at,
+            device: describe_user_agent(&session.user_agent),
+            network,
+            restricted: session.restricted,
+        }
+    }
+
     /// Renews a session, invalidating the old one and creating a new one.
     ///
+    /// Restricted sessions cannot be renewed, see `upgrade()` instead.
+    ///
     /// # Returns
     /// The new session token.
     /// After this point, the previous session token will be invalid.
-    pub async fn renew(
+    pub async fn renew(ctx: &ServiceContext<'_>, input: RenewSession) -> Result<String> {
+        info!("Renewing session ID {}", input.old_session_token);
+
+        let old_session = Self::get(ctx, &input.old_session_token).await?;
+        if old_session.restricted {
+            error!("Cannot renew a restricted session");
+            return Err(Error::InvalidSessionToken);
+        }
+
+        // Renewal would move the session to wherever it is being renewed from,
+        // so it must be done from somewhere the session is bound to.
+        if !Self::binding_matches(
+            ctx.config(),
+            &old_session,
+            input.ip_address,
+            &input.user_agent,
+        ) {
+            error!("Cannot renew a session from a different network or device");
+            return Err(Error::SessionBindingMismatch);
+        }
+
+        Self::replace(ctx, old_session, input).await
+    }
+
+    /// Replaces a restricted session with a normal one.
+    ///
+    /// This is used once a user has verified their login with MFA.
+    ///
+    /// # Returns
+    /// The new session token.
+    pub async fn upgrade(
         ctx: &ServiceContext<'_>,
+        input: RenewSession,
+    ) -> Result<String> {
+        info!(
+            "Upgrading restricted session ID {}",
+            input.old_session_token
+        );
+
+        let old_session = Self::get(ctx, &input.old_session_token).await?;
+        if !old_session.restricted {
+            error!("Cannot upgrade a session which is not restricted");
+            return Err(Error::InvalidSessionToken);
+        }
+
+        Self::replace(ctx, old_session, input).await
+    }
+
+    async fn replace(
+        ctx: &ServiceContext<'_>,
+        old_session: SessionModel,
         RenewSession {
             old_session_token,
             user_id,
@@ -188,10 +530,7 @@ impl SessionService {
             user_agent,
         }: RenewSession,
     ) -> Result<String> {
-        info!("Renewing session ID {old_session_token}");
-
-        // Get existing session to ensure the token matches the passed user ID.
-        let old_session = Self::get(ctx, &old_session_token).await?;
+        // Ensure the token matches the passed user ID.
         if old_session.user_id != user_id {
             error!(
                 "Requested session renewal, user IDs do not match! (current: {}, request: {})",
@@ -302,3 +641,34 @@ impl SessionService {
         Ok(rows_affected)
     }
 }
+
+#[test]
+fn unscoped_methods() {
+    let config = Config::example();
+    let session_token = format!("{}0123456789", config.session_token_prefix);
+
+    // Sessions, and requests without any token, are checked by the method itself
+    assert!(SessionService::check_unscoped(&config, None).is_ok());
+    assert!(SessionService::check_unscoped(&config, Some(&session_token)).is_ok());
+
+    // However narrow or broad its scopes, a bot's token cannot be used for
+    // methods without an API action, such as "user_delete" or "vote_action"
+    let api_token = format!("{}0123456789", config.api_token_prefix);
+    assert!(matches!(
+        SessionService::check_unscoped(&config, Some(&api_token)),
+        Err(Error::ScopeNotGranted),
+    ));
+}
+
+#[test]
+fn unscoped_methods_oauth() {
+    let config = Config::example();
+
+    // Likewise for third-party clients, even if only granted "read-pages",
+    // such as "user_delete" or "oauth_grant_revoke"
+    let access_token = format!("{}0123456789", config.oauth_access_token_prefix);
+    assert!(matches!(
+        SessionService::check_unscoped(&config, Some(&access_token)),
+        Err(Error::ScopeNotGranted),
+    ));
+}
@@ -19,7 +19,10 @@
  */
 
 use crate::models::session::Model as SessionModel;
+use crate::services::api_token::ApiScope;
+use crate::types::Reference;
 use std::net::IpAddr;
+use time::OffsetDateTime;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct CreateSession {
@@ -37,12 +40,74 @@ pub struct RenewSession {
     pub user_agent: String,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetSession {
+    pub session_token: String,
+    pub ip_address: IpAddr,
+    pub user_agent: String,
+
+    /// What the caller intends to do with this session.
+    ///
+    /// Only used for scoped tokens, which must grant this scope.
+    #[serde(default)]
+    pub scope: Option<ApiScope>,
+}
+
+/// The fields of a request which are checked against a scoped token.
+///
+/// This is parsed from the parameters of any method which requires a scope,
+/// see `SessionService::check_scope()`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct CheckScope {
+    /// The token the request was made with, as forwarded by framerail.
+    pub scoped_token: Option<String>,
+
+    // The user performing the action, for methods which use either field
+    pub user_id: Option<i64>,
+    pub user: Option<Reference<'static>>,
+
+    // The sites the action affects, otherwise determined from the page
+    pub site_id: Option<i64>,
+    pub destination_site_id: Option<i64>,
+    pub page_id: Option<i64>,
+}
+
+/// The token a request was made with, for methods without an API action.
+///
+/// See `SessionService::check_unscoped()`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ScopedToken {
+    pub scoped_token: Option<String>,
+}
+
 pub type GetOtherSessions = InvalidateOtherSessions;
 
 #[derive(Serialize, Debug, Clone)]
 pub struct GetOtherSessionsOutput {
-    pub current: SessionModel,
-    pub others: Vec<SessionModel>,
+    pub current: SessionInfo,
+    pub others: Vec<SessionInfo>,
+}
+
+/// Describes a session for users, without exposing its token.
+#[derive(Serialize, Debug, Clone)]
+pub struct SessionInfo {
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_used_at: OffsetDateTime,
+
+    /// A rough description of the browser and operating system.
+    pub device: String,
+
+    /// The network the session was created from, e.g. `203.0.113.0/24`.
+    pub network: String,
+    pub restricted: bool,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -50,3 +115,21 @@ pub struct InvalidateOtherSessions {
     pub session_token: String,
     pub user_id: i64,
 }
+
+/// A session for a request made with a scoped token, rather than a session token.
+///
+/// Scoped tokens are API tokens issued to bots, and OAuth access tokens
+/// issued to third-party applications. These sessions are not stored,
+/// they only live as long as the request.
+#[derive(Debug, Clone)]
+pub struct ScopedSession {
+    pub session: SessionModel,
+    pub scopes: Vec<ApiScope>,
+}
+
+impl ScopedSession {
+    #[inline]
+    pub fn grants(&self, required: ApiScope) -> bool {
+        ApiScope::any_grants(&self.scopes, required)
+    }
+}
@@ -0,0 +1,39 @@
+/*
+ * services/thumbnail/mod.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The thumbnail service, for derived copies and metadata of image files.
+//!
+//! When a file revision with a new image blob is created, a job decodes it,
+//! records its dimensions (and duration, if animated), then scales it down
+//! to fit within each configured size. Thumbnails are stored as WebP blobs
+//! of their own, linked to the hash of the blob they were made from, so
+//! each distinct image is only processed once however many files use it.
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use super::super::prelude::*;
+    pub use super::structs::*;
+}
+
+mod service;
+mod structs;
+
+pub use self::service::ThumbnailService;
+pub use self::structs::*;
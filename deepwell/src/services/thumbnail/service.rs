@@ -0,0 +1,503 @@
+/*
+ * services/thumbnail/service.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use crate::hash::{blob_hash_to_hex, BlobHash};
+use crate::models::blob_media::{self, Entity as BlobMedia, Model as BlobMediaModel};
+use crate::models::blob_thumbnail::{
+    self, Entity as BlobThumbnail, Model as BlobThumbnailModel,
+};
+use crate::services::blob::BlobMetadata;
+use crate::services::file::GetFile;
+use crate::services::job::Job;
+use crate::services::{
+    BlobService, DomainService, FileRevisionService, FileService, JobService,
+    PageService, SiteService,
+};
+use crate::types::{parse_hidden_fields, FileHiddenField};
+use crate::utils::{animation_duration, decode_image, image_format, run_blocking};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+/// MIME type of stored thumbnails.
+const THUMBNAIL_MIME: &str = "image/webp";
+
+#[derive(Debug)]
+pub struct ThumbnailService;
+
+impl ThumbnailService {
+    /// Queues processing of a blob used by a new file revision, if it is an image.
+    ///
+    /// Images which have already been processed are skipped.
+    pub async fn queue(
+        ctx: &ServiceContext<'_>,
+        s3_hash: &[u8],
+        mime: &str,
+    ) -> Result<()> {
+        if image_format(mime).is_none() {
+            return Ok(());
+        }
+
+        let txn = ctx.transaction();
+        if BlobMedia::find_by_id(s3_hash.to_vec())
+            .one(txn)
+            .await?
+            .is_some()
+        {
+            debug!("Image blob already processed, not queuing thumbnails");
+            return Ok(());
+        }
+
+        let job = Job::GenerateThumbnails {
+            s3_hash: blob_hash_to_hex(s3_hash).to_string(),
+        };
+
+        JobService::queue_job(ctx, &job, None).await
+    }
+
+    /// Extracts metadata from an image blob and generates its thumbnails.
+    pub async fn generate(ctx: &ServiceContext<'_>, source_hash: BlobHash) -> Result<()> {
+        let hex_hash = blob_hash_to_hex(&source_hash);
+        info!("Generating thumbnails for blob {hex_hash}");
+
+        let txn = ctx.transaction();
+        if BlobMedia::find_by_id(source_hash.to_vec())
+            .one(txn)
+            .await?
+            .is_some()
+        {
+            debug!("Image blob already processed, skipping");
+            return Ok(());
+        }
+
+        let BlobMetadata { mime, .. } =
+            BlobService::get_metadata(ctx, &source_hash).await?;
+        let format = match image_format(&mime) {
+            Some(format) => format,
+            None => {
+                warn!("Blob {hex_hash} is not an image ({mime}), skipping");
+                return Ok(());
+            }
+        };
+
+        // Decoding and resizing images is expensive, so do it off the runtime
+        let data = BlobService::get(ctx, &source_hash).await?;
+        let sizes = ctx.config().thumbnail_sizes.clone();
+        let processed = run_blocking(move || process(&data, format, &sizes)).await;
+        let ProcessedImage {
+            width,
+            height,
+            duration_ms,
+            thumbnails,
+        } = match processed {
+            Some(processed) => processed,
+            None => {
+                warn!("Blob {hex_hash} could not be decoded, skipping");
+                return Ok(());
+            }
+        };
+
+        let model = blob_media::ActiveModel {
+            s3_hash: Set(source_hash.to_vec()),
+            width: Set(width),
+            height: Set(height),
+            duration_ms: Set(duration_ms),
+            ..Default::default()
+        };
+        model.insert(txn).await?;
+
+        for Thumbnail {
+            size,
+            width,
+            height,
+            bytes,
+        } in thumbnails
+        {
+            let output = BlobService::derived_upload(ctx, bytes).await?;
+            let model = blob_thumbnail::ActiveModel {
+                source_hash: Set(source_hash.to_vec()),
+                size: Set(size.try_into().expect("Thumbnail size exceeds i16")),
+                s3_hash: Set(output.s3_hash.to_vec()),
+                width: Set(width),
+                height: Set(height),
+            };
+            model.insert(txn).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Gets the media metadata and thumbnails for a file's blob, if it is an image.
+    ///
+    /// If `urls` is set, each thumbnail includes a presigned download URL.
+    pub async fn get_media(
+        ctx: &ServiceContext<'_>,
+        s3_hash: &[u8],
+        urls: bool,
+    ) -> Result<Option<FileMedia>> {
+        let txn = ctx.transaction();
+        let BlobMediaModel {
+            width,
+            height,
+            duration_ms,
+            ..
+        } = match BlobMedia::find_by_id(s3_hash.to_vec()).one(txn).await? {
+            Some(model) => model,
+            None => return Ok(None),
+        };
+
+        let mut thumbnails = Vec::new();
+        for BlobThumbnailModel {
+            size,
+            s3_hash,
+            width,
+            height,
+            ..
+        } in Self::get_thumbnails(ctx, s3_hash).await?
+        {
+            let url = if urls {
+                let url =
+                    BlobService::get_download_url(ctx, &s3_hash, THUMBNAIL_MIME, None)
+                        .await?;
+
+                Some(url)
+            } else {
+                None
+            };
+
+            thumbnails.push(FileThumbnail {
+                size,
+                width,
+                height,
+                url,
+            });
+        }
+
+        Ok(Some(FileMedia {
+            width,
+            height,
+            duration_ms,
+            thumbnails,
+        }))
+    }
+
+    /// Gets all thumbnails of a blob, smallest first.
+    /// Gets media information for several blobs, such as for a file listing.
+    ///
+    /// Unlike `get_media()`, thumbnail URLs are not included, and the media and
+    /// thumbnails for all the blobs are each fetched in one query.
+    ///
+    /// # Returns
+    /// A map of blob hash to its media information. Blobs which
+    /// are not media have no entry.
+    pub async fn get_media_many(
+        ctx: &ServiceContext<'_>,
+        s3_hashes: &[Vec<u8>],
+    ) -> Result<HashMap<Vec<u8>, FileMedia>> {
+        if s3_hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let txn = ctx.transaction();
+        let mut media = BlobMedia::find()
+            .filter(blob_media::Column::S3Hash.is_in(s3_hashes.iter().cloned()))
+            .all(txn)
+            .await?
+            .into_iter()
+            .map(
+                |BlobMediaModel {
+                     s3_hash,
+                     width,
+                     height,
+                     duration_ms,
+                     ..
+                 }| {
+                    let media = FileMedia {
+                        width,
+                        height,
+                        duration_ms,
+                        thumbnails: Vec::new(),
+                    };
+
+                    (s3_hash, media)
+                },
+            )
+            .collect::<HashMap<_, _>>();
+
+        let thumbnails = BlobThumbnail::find()
+            .filter(blob_thumbnail::Column::SourceHash.is_in(media.keys().cloned()))
+            .order_by_asc(blob_thumbnail::Column::Size)
+            .all(txn)
+            .await?;
+
+        for BlobThumbnailModel {
+            source_hash,
+            size,
+            width,
+            height,
+            ..
+        } in thumbnails
+        {
+            if let Some(media) = media.get_mut(&source_hash) {
+                media.thumbnails.push(FileThumbnail {
+                    size,
+                    width,
+                    height,
+                    url: None,
+                });
+            }
+        }
+
+        Ok(media)
+    }
+
+    async fn get_thumbnails(
+        ctx: &ServiceContext<'_>,
+        source_hash: &[u8],
+    ) -> Result<Vec<BlobThumbnailModel>> {
+        let txn = ctx.transaction();
+        let thumbnails = BlobThumbnail::find()
+            .filter(blob_thumbnail::Column::SourceHash.eq(source_hash))
+            .order_by_asc(blob_thumbnail::Column::Size)
+            .all(txn)
+            .await?;
+
+        Ok(thumbnails)
+    }
+
+    /// Gets a download URL for an image file, as referred to by ftml image blocks.
+    ///
+    /// If a size is requested, then the smallest thumbnail which is at least
+    /// that large is used instead, falling back to the original image.
+    pub async fn get_image_url(
+        ctx: &ServiceContext<'_>,
+        GetImageUrl {
+            domain,
+            page_slug,
+            filename,
+            size,
+        }: GetImageUrl,
+    ) -> Result<Option<String>> {
+        info!("Getting image URL for {domain}/{page_slug}/{filename} (size {size:?})");
+
+        // Like 'scp-wiki.wjfiles.com', otherwise a regular site domain
+        let site = match domain.strip_suffix(&ctx.config().files_domain) {
+            Some(site_slug) => {
+                SiteService::get_optional(ctx, Reference::Slug(cow!(site_slug))).await?
+            }
+            None => DomainService::site_from_domain_optional(ctx, &domain).await?,
+        };
+
+        let site = match site {
+            Some(site) => site,
+            None => return Ok(None),
+        };
+
+        let page = match PageService::get_optional(
+            ctx,
+            site.site_id,
+            Reference::from(page_slug.as_str()),
+        )
+        .await?
+        {
+            Some(page) => page,
+            None => return Ok(None),
+        };
+
+        let file = match FileService::get_optional(
+            ctx,
+            GetFile {
+                site_id: site.site_id,
+                page_id: page.page_id,
+                file: Reference::from(filename.as_str()),
+            },
+        )
+        .await?
+        {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+
+        let revision = FileRevisionService::get_latest(
+            ctx,
+            file.site_id,
+            file.page_id,
+            file.file_id,
+        )
+        .await?;
+
+        // Hard deleted files have no blob to link to
+        let hidden_fields = parse_hidden_fields(&revision.hidden)?;
+        if hidden_fields.contains(&FileHiddenField::S3Hash) {
+            return Ok(None);
+        }
+
+        if let Some(size) = size {
+            let thumbnail = Self::get_thumbnails(ctx, &revision.s3_hash)
+                .await?
+                .into_iter()
+                .find(|thumbnail| {
+                    thumbnail.size >= i16::try_from(size).unwrap_or(i16::MAX)
+                });
+
+            if let Some(thumbnail) = thumbnail {
+                let url = BlobService::get_download_url(
+                    ctx,
+                    &thumbnail.s3_hash,
+                    THUMBNAIL_MIME,
+                    None,
+                )
+                .await?;
+
+                return Ok(Some(url));
+            }
+        }
+
+        let url = BlobService::get_download_url(
+            ctx,
+            &revision.s3_hash,
+            &revision.mime,
+            Some(&file.name),
+        )
+        .await?;
+
+        Ok(Some(url))
+    }
+
+    /// Deletes the metadata and thumbnails for a blob, as part of a hard deletion.
+    pub async fn delete(ctx: &ServiceContext<'_>, source_hash: BlobHash) -> Result<()> {
+        let txn = ctx.transaction();
+        let thumbnails = Self::get_thumbnails(ctx, &source_hash).await?;
+        if !thumbnails.is_empty() {
+            info!(
+                "Deleting {} thumbnails of blob {}",
+                thumbnails.len(),
+                blob_hash_to_hex(&source_hash),
+            );
+        }
+
+        BlobThumbnail::delete_many()
+            .filter(blob_thumbnail::Column::SourceHash.eq(source_hash.as_slice()))
+            .exec(txn)
+            .await?;
+
+        BlobMedia::delete_by_id(source_hash.to_vec())
+            .exec(txn)
+            .await?;
+
+        // Identical thumbnails may be shared with other images
+        for thumbnail in thumbnails {
+            BlobService::hard_delete_unused(ctx, &thumbnail.s3_hash).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Media metadata and encoded thumbnails for an image.
+#[derive(Debug)]
+struct ProcessedImage {
+    width: i32,
+    height: i32,
+    duration_ms: Option<i32>,
+    thumbnails: Vec<Thumbnail>,
+}
+
+#[derive(Debug)]
+struct Thumbnail {
+    size: u16,
+    width: i32,
+    height: i32,
+    bytes: Vec<u8>,
+}
+
+/// Decodes an image and produces a thumbnail for each size smaller than it.
+///
+/// This is blocking, so it should be called using `run_blocking()`.
+fn process(data: &[u8], format: ImageFormat, sizes: &[u16]) -> Option<ProcessedImage> {
+    let image = decode_image(data, format)?;
+    let duration_ms = animation_duration(data, format)
+        .map(|duration| i32::try_from(duration).unwrap_or(i32::MAX));
+
+    let largest = image.width().max(image.height());
+    let thumbnails = sizes
+        .iter()
+        // Thumbnails never scale up
+        .filter(|&&size| u32::from(size) < largest)
+        .filter_map(|&size| {
+            let thumbnail =
+                image.resize(u32::from(size), u32::from(size), FilterType::Lanczos3);
+
+            Some(Thumbnail {
+                size,
+                width: dimension(thumbnail.width()),
+                height: dimension(thumbnail.height()),
+                bytes: encode(&thumbnail)?,
+            })
+        })
+        .collect();
+
+    Some(ProcessedImage {
+        width: dimension(image.width()),
+        height: dimension(image.height()),
+        duration_ms,
+        thumbnails,
+    })
+}
+
+/// Encodes a thumbnail as WebP.
+///
+/// Failures are logged and the thumbnail skipped, rather than failing
+/// the whole job, since the original image remains usable.
+fn encode(image: &DynamicImage) -> Option<Vec<u8>> {
+    // The WebP encoder only supports 8-bit RGB(A)
+    let image = DynamicImage::ImageRgba8(image.to_rgba8());
+
+    let mut buffer = Cursor::new(Vec::new());
+    if let Err(error) = image.write_to(&mut buffer, ImageFormat::WebP) {
+        error!("Unable to encode thumbnail: {error}");
+        return None;
+    }
+
+    Some(buffer.into_inner())
+}
+
+/// Converts an image dimension for storage.
+///
+/// Decoding limits images to `MAXIMUM_IMAGE_DIMENSION`, so this always fits.
+#[inline]
+fn dimension(value: u32) -> i32 {
+    i32::try_from(value).expect("Image dimension exceeds i32")
+}
+
+#[test]
+fn thumbnail_encode() {
+    let image = DynamicImage::new_rgb8(640, 480);
+    let thumbnail = image.resize(160, 160, FilterType::Lanczos3);
+    assert_eq!((thumbnail.width(), thumbnail.height()), (160, 120));
+
+    let bytes = encode(&thumbnail).expect("Unable to encode");
+    let output = image::load_from_memory_with_format(&bytes, ImageFormat::WebP)
+        .expect("Unable to decode encoded thumbnail");
+
+    assert_eq!((output.width(), output.height()), (160, 120));
+}
@@ -0,0 +1,48 @@
+/*
+ * services/thumbnail/structs.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FileMedia {
+    pub width: i32,
+    pub height: i32,
+    pub duration_ms: Option<i32>,
+    pub thumbnails: Vec<FileThumbnail>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FileThumbnail {
+    pub size: i16,
+    pub width: i32,
+    pub height: i32,
+    pub url: Option<String>,
+}
+
+/// Refers to an image file the way ftml image blocks do.
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetImageUrl {
+    /// The domain requested, either on the files domain or one of the site's own.
+    pub domain: String,
+    pub page_slug: String,
+    pub filename: String,
+
+    /// If set, use the smallest thumbnail which fits at least this size.
+    #[serde(default)]
+    pub size: Option<u16>,
+}
@@ -21,11 +21,14 @@
 use super::prelude::*;
 use crate::models::sea_orm_active_enums::{AliasType, UserType};
 use crate::models::user::{self, Entity as User, Model as UserModel};
+use crate::models::user_name_change::{
+    self, Entity as UserNameChange, Model as UserNameChangeModel,
+};
 use crate::services::alias::CreateAlias;
 use crate::services::blob::{BlobService, FinalizeBlobUploadOutput};
 use crate::services::email::{EmailClassification, EmailService};
 use crate::services::filter::{FilterClass, FilterType};
-use crate::services::{AliasService, FilterService, PasswordService};
+use crate::services::{AliasService, AvatarService, FilterService, PasswordService};
 use crate::utils::regex_replace_in_place;
 use once_cell::sync::Lazy;
 use regex::Regex;
@@ -141,25 +144,26 @@ impl UserService {
 
         // Set up password field depending on type
         let password = match user_type {
+            UserType::Regular if password.is_empty() => {
+                // Can only sign in using a linked identity, see OidcService
+                info!("Creating regular user '{slug}' without password");
+                None
+            }
             UserType::Regular => {
                 info!("Creating regular user '{slug}' with password");
-                PasswordService::new_hash(&password)?
+                Some(PasswordService::new_hash(&password)?)
             }
-            UserType::System | UserType::Site => {
-                info!("Creating site or system user '{slug}'");
+            UserType::System | UserType::Site | UserType::Bot => {
+                // Bots authenticate using API tokens, see ApiTokenService
+                info!("Creating site, system, or bot user '{slug}'");
 
                 if !password.is_empty() {
-                    warn!("Password was specified for site or system user");
+                    warn!("Password was specified for site, system, or bot user");
                     return Err(Error::BadRequest);
                 }
 
                 // Disabled password
-                str!("!")
-            }
-            UserType::Bot => {
-                info!("Creating bot user '{slug}'");
-                // TODO assign bot token
-                format!("TODO bot token: {}", password)
+                None
             }
         };
 
@@ -175,7 +179,7 @@ impl UserService {
         // Also bypass email verification if it's empty (obviously invalid).
         // We've already checked for empty emails above (e.g. system users can have empty emails).
         let email_is_alias = if !bypass_email_verification && !email.is_empty() {
-            let email_validation_output = EmailService::validate(&email).await?;
+            let email_validation_output = EmailService::validate(ctx, &email).await?;
 
             match email_validation_output.classification {
                 EmailClassification::Normal => {
@@ -368,7 +372,7 @@ impl UserService {
             }
 
             // Validate email
-            let email_validation_output = EmailService::validate(&email).await?;
+            let email_validation_output = EmailService::validate(ctx, &email).await?;
 
             let is_alias = match email_validation_output.classification {
                 EmailClassification::Normal => false,
@@ -389,7 +393,7 @@ impl UserService {
 
         if let Maybe::Set(password) = input.password {
             let password_hash = PasswordService::new_hash(&password)?;
-            model.password = Set(password_hash);
+            model.password = Set(Some(password_hash));
         }
 
         if let Maybe::Set(locales) = input.locales {
@@ -422,7 +426,7 @@ impl UserService {
         }
 
         if let Maybe::Set(uploaded_blob_id) = input.avatar_uploaded_blob_id {
-            let s3_hash = match uploaded_blob_id {
+            let upload = match uploaded_blob_id {
                 None => None,
                 Some(uploaded_blob_id) => {
                     let config = ctx.config();
@@ -435,14 +439,16 @@ impl UserService {
                             "Uploaded avatar size is too big {} > {}",
                             size, config.maximum_avatar_size,
                         );
+                        BlobService::hard_delete_unused(ctx, &s3_hash).await?;
                         return Err(Error::BlobTooBig);
                     }
 
-                    Some(s3_hash.to_vec())
+                    // Decoded and re-encoded into the standard sizes
+                    Some(s3_hash)
                 }
             };
 
-            model.avatar_s3_hash = Set(s3_hash);
+            AvatarService::set(ctx, user.user_id, upload).await?;
         }
 
         // Update user
@@ -497,6 +503,10 @@ impl UserService {
         if new_slug == user.slug {
             debug!("User slug is the same, rename is free");
 
+            if new_name != user.name {
+                Self::add_name_change(ctx, user, &new_name, &new_slug, false).await?;
+            }
+
             // Set model, but return early, we don't deduct a
             // name change token or create a new user alias.
             model.name = Set(new_name);
@@ -510,6 +520,7 @@ impl UserService {
 
             // Swap user alias for old slug
             AliasService::swap(ctx, alias.alias_id, old_slug).await?;
+            Self::add_name_change(ctx, user, &new_name, &new_slug, false).await?;
 
             // Set model, but return early, we don't deduct a name change token
             model.name = Set(new_name);
@@ -553,6 +564,8 @@ impl UserService {
             old_slug, new_slug,
         );
 
+        Self::add_name_change(ctx, user, &new_name, &new_slug, true).await?;
+
         model.name_changes_left = Set(user.name_changes_left - 1);
         model.name = Set(new_name);
         model.slug = Set(new_slug);
@@ -573,6 +586,52 @@ impl UserService {
         Ok(())
     }
 
+    /// Records a rename in the user's public name history.
+    async fn add_name_change(
+        ctx: &ServiceContext<'_>,
+        user: &UserModel,
+        new_name: &str,
+        new_slug: &str,
+        used_token: bool,
+    ) -> Result<()> {
+        let txn = ctx.transaction();
+
+        debug!(
+            "Recording name change for user ID {} ('{}' -> '{}')",
+            user.user_id, user.name, new_name,
+        );
+
+        let model = user_name_change::ActiveModel {
+            user_id: Set(user.user_id),
+            old_name: Set(user.name.clone()),
+            old_slug: Set(user.slug.clone()),
+            new_name: Set(str!(new_name)),
+            new_slug: Set(str!(new_slug)),
+            used_token: Set(used_token),
+            ..Default::default()
+        };
+
+        model.insert(txn).await?;
+        Ok(())
+    }
+
+    /// Gets all past renames for this user, most recent first.
+    pub async fn get_name_history(
+        ctx: &ServiceContext<'_>,
+        reference: Reference<'_>,
+    ) -> Result<Vec<UserNameChangeModel>> {
+        let txn = ctx.transaction();
+        let user_id = Self::get_id(ctx, reference).await?;
+        let changes = UserNameChange::find()
+            .filter(user_name_change::Column::UserId.eq(user_id))
+            .order_by_desc(user_name_change::Column::CreatedAt)
+            .order_by_desc(user_name_change::Column::UserNameChangeId)
+            .all(txn)
+            .await?;
+
+        Ok(changes)
+    }
+
     pub async fn refresh_name_change_tokens(ctx: &ServiceContext<'_>) -> Result<()> {
         info!("Refreshing name change tokens for all users who need one");
 
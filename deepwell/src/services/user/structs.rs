@@ -22,6 +22,7 @@ use super::prelude::*;
 use crate::models::alias::Model as AliasModel;
 use crate::models::sea_orm_active_enums::UserType;
 use crate::models::user::Model as UserModel;
+use crate::services::avatar::AvatarOutput;
 use crate::types::Bytes;
 use time::Date;
 
@@ -55,6 +56,7 @@ pub struct GetUserOutput {
     #[serde(flatten)]
     pub user: UserModel,
     pub aliases: Vec<AliasModel>,
+    pub avatars: Vec<AvatarOutput>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
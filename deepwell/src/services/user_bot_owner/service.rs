@@ -47,6 +47,16 @@ impl UserBotOwnerService {
         Ok(owners)
     }
 
+    /// Determines if the given human user is an owner of the given bot user.
+    pub async fn is_owner(
+        ctx: &ServiceContext<'_>,
+        bot_user_id: i64,
+        human_user_id: i64,
+    ) -> Result<bool> {
+        let owner = Self::get_optional(ctx, bot_user_id, human_user_id).await?;
+        Ok(owner.is_some())
+    }
+
     async fn get_optional(
         ctx: &ServiceContext<'_>,
         bot_user_id: i64,
@@ -30,12 +30,10 @@ pub struct CreateBotUser {
     pub owners: Vec<BotOwner>,
     pub bypass_filter: bool,
     pub bypass_email_verification: bool,
-    pub authorization_token: String, // TODO add authorization token service
-                                     // format: [flag]-[uuid]
-                                     //         for instance B-1F305167-AE64-4486-809A-09D14659AB4A
-                                     //
-                                     //         B: create a bot user
-                                     //         S: create a site
+
+    /// The user creating this bot, who must be one of its owners.
+    pub user_id: i64,
+    pub session_token: String,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -0,0 +1,40 @@
+/*
+ * services/user_deletion/mod.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The user deletion service, for users deleting their own accounts.
+//!
+//! A deletion is requested by the user, then carried out by a job once
+//! the configured grace period has passed, unless it is canceled first.
+//!
+//! What happens to each class of the user's data is decided by the deletion
+//! policy in the configuration, see `DeletionPolicies`. Regardless of policy,
+//! the account itself is anonymized and all of its credentials are removed.
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use super::super::prelude::*;
+    pub use super::structs::*;
+}
+
+mod service;
+mod structs;
+
+pub use self::service::UserDeletionService;
+pub use self::structs::*;
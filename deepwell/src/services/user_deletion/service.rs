@@ -0,0 +1,432 @@
+/*
+ * services/user_deletion/service.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use crate::models::file_revision::{self, Entity as FileRevision};
+use crate::models::message::{self, Entity as Message};
+use crate::models::message_draft::{self, Entity as MessageDraft};
+use crate::models::message_record::{self, Entity as MessageRecord};
+use crate::models::message_report::{self, Entity as MessageReport};
+use crate::models::page_revision::{self, Entity as PageRevision};
+use crate::models::page_vote::{self, Entity as PageVote};
+use crate::models::relation::{self, Entity as Relation};
+use crate::models::sea_orm_active_enums::{RelationObjectType, UserType};
+use crate::models::session::{self, Entity as Session};
+use crate::models::user::{self, Model as UserModel};
+use crate::models::user_bot_owner::{self, Entity as UserBotOwner};
+use crate::models::user_deletion::{
+    self, Entity as UserDeletion, Model as UserDeletionModel,
+};
+use crate::models::user_external_identity::{self, Entity as UserExternalIdentity};
+use crate::models::user_name_change::{self, Entity as UserNameChange};
+use crate::models::webauthn_credential::{self, Entity as WebauthnCredential};
+use crate::services::job::{Job, JobService};
+use crate::services::{
+    ApiTokenService, AvatarService, ExportService, OauthService, UserService,
+};
+use crate::types::{
+    hidden_fields_column, parse_hidden_fields, DeletionPolicy, FileHiddenField,
+    PageHiddenField,
+};
+use sea_orm::UpdateResult;
+use std::time::Duration as StdDuration;
+
+#[derive(Debug)]
+pub struct UserDeletionService;
+
+impl UserDeletionService {
+    /// Schedules the deletion of a user's account.
+    ///
+    /// This does not verify the session, the caller is responsible for that.
+    pub async fn request(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+    ) -> Result<UserDeletionModel> {
+        info!("Requesting deletion of user ID {user_id}");
+
+        let user = UserService::get(ctx, Reference::Id(user_id)).await?;
+        if user.user_type != UserType::Regular {
+            error!("Only regular users can delete their own accounts");
+            return Err(Error::BadRequest);
+        }
+
+        if Self::get_pending(ctx, user_id).await?.is_some() {
+            error!("User ID {user_id} already has a pending deletion");
+            return Err(Error::UserDeletionExists);
+        }
+
+        let txn = ctx.transaction();
+        let grace_period = ctx.config().user_deletion_grace_period;
+        let now = now();
+        let model = user_deletion::ActiveModel {
+            user_id: Set(user_id),
+            created_at: Set(now),
+            scheduled_at: Set(now + grace_period),
+            ..Default::default()
+        };
+
+        let deletion = model.insert(txn).await?;
+        JobService::queue_job(
+            ctx,
+            &Job::DeleteUser {
+                user_deletion_id: deletion.user_deletion_id,
+            },
+            Some(grace_period.unsigned_abs()),
+        )
+        .await?;
+
+        Ok(deletion)
+    }
+
+    /// Cancels a pending deletion, before its grace period has ended.
+    ///
+    /// This does not verify the session, the caller is responsible for that.
+    pub async fn cancel(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+    ) -> Result<UserDeletionModel> {
+        info!("Canceling deletion of user ID {user_id}");
+
+        let txn = ctx.transaction();
+        let deletion = Self::get_pending(ctx, user_id)
+            .await?
+            .ok_or(Error::UserDeletionNotFound)?;
+
+        let model = user_deletion::ActiveModel {
+            user_deletion_id: Set(deletion.user_deletion_id),
+            canceled_at: Set(Some(now())),
+            ..Default::default()
+        };
+
+        let deletion = model.update(txn).await?;
+        Ok(deletion)
+    }
+
+    /// Gets the user's deletion which has neither been canceled nor carried out, if any.
+    pub async fn get_pending(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+    ) -> Result<Option<UserDeletionModel>> {
+        let txn = ctx.transaction();
+        let deletion = UserDeletion::find()
+            .filter(
+                Condition::all()
+                    .add(user_deletion::Column::UserId.eq(user_id))
+                    .add(user_deletion::Column::CanceledAt.is_null())
+                    .add(user_deletion::Column::CompletedAt.is_null()),
+            )
+            .one(txn)
+            .await?;
+
+        Ok(deletion)
+    }
+
+    /// Carries out a deletion, if it is still pending.
+    ///
+    /// # Returns
+    /// If the deletion is not due yet, how much longer until it is.
+    pub async fn run(
+        ctx: &ServiceContext<'_>,
+        user_deletion_id: i64,
+    ) -> Result<Option<StdDuration>> {
+        let txn = ctx.transaction();
+        let deletion = match UserDeletion::find_by_id(user_deletion_id).one(txn).await? {
+            Some(deletion) => deletion,
+            None => {
+                warn!("User deletion ID {user_deletion_id} does not exist, skipping");
+                return Ok(None);
+            }
+        };
+
+        if deletion.canceled_at.is_some() || deletion.completed_at.is_some() {
+            debug!("User deletion ID {user_deletion_id} is no longer pending, skipping");
+            return Ok(None);
+        }
+
+        let now = now();
+        if deletion.scheduled_at > now {
+            return Ok(Some((deletion.scheduled_at - now).unsigned_abs()));
+        }
+
+        let user_id = deletion.user_id;
+        info!("Deleting user ID {user_id} (deletion ID {user_deletion_id})");
+
+        let policy = ctx.config().user_deletion_policy;
+        Self::delete_messages(ctx, user_id, policy.messages).await?;
+        Self::delete_votes(ctx, user_id, policy.votes).await?;
+        Self::delete_revisions(ctx, user_id, policy.revisions).await?;
+        Self::delete_relations(ctx, user_id, policy.relations).await?;
+        Self::delete_credentials(ctx, user_id).await?;
+        ExportService::delete_account_exports(ctx, user_id).await?;
+        Self::anonymize(ctx, user_id).await?;
+
+        let model = user_deletion::ActiveModel {
+            user_deletion_id: Set(user_deletion_id),
+            completed_at: Set(Some(now)),
+            ..Default::default()
+        };
+        model.update(txn).await?;
+
+        Ok(None)
+    }
+
+    /// Removes the user's messages.
+    ///
+    /// Drafts were never sent to anyone, so they are always deleted.
+    async fn delete_messages(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+        policy: DeletionPolicy,
+    ) -> Result<()> {
+        let txn = ctx.transaction();
+        MessageDraft::delete_many()
+            .filter(message_draft::Column::UserId.eq(user_id))
+            .exec(txn)
+            .await?;
+
+        if policy == DeletionPolicy::Anonymize {
+            return Ok(());
+        }
+
+        debug!("Purging messages for user ID {user_id}");
+
+        // The user's own copies, and any reports on them
+        let message_ids: Vec<i64> = Message::find()
+            .filter(message::Column::UserId.eq(user_id))
+            .all(txn)
+            .await?
+            .into_iter()
+            .map(|message| message.internal_id)
+            .collect();
+
+        MessageReport::delete_many()
+            .filter(message_report::Column::MessageId.is_in(message_ids.clone()))
+            .exec(txn)
+            .await?;
+
+        Message::delete_many()
+            .filter(message::Column::InternalId.is_in(message_ids))
+            .exec(txn)
+            .await?;
+
+        // Recipients have their own copies, so sent messages are retracted instead
+        MessageRecord::update_many()
+            .set(message_record::ActiveModel {
+                retracted_at: Set(Some(now())),
+                ..Default::default()
+            })
+            .filter(
+                Condition::all()
+                    .add(message_record::Column::SenderId.eq(user_id))
+                    .add(message_record::Column::RetractedAt.is_null()),
+            )
+            .exec(txn)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_votes(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+        policy: DeletionPolicy,
+    ) -> Result<()> {
+        if policy == DeletionPolicy::Anonymize {
+            return Ok(());
+        }
+
+        debug!("Purging votes for user ID {user_id}");
+
+        let txn = ctx.transaction();
+        let DeleteResult { rows_affected } = PageVote::delete_many()
+            .filter(page_vote::Column::UserId.eq(user_id))
+            .exec(txn)
+            .await?;
+
+        debug!("{rows_affected} votes were deleted");
+        Ok(())
+    }
+
+    /// Hides the comments and authorship of the user's revisions.
+    ///
+    /// Revisions make up the history of pages that others have worked on,
+    /// so they are never deleted. These fields are not part of the contents,
+    /// so they can be hidden even on the latest revision.
+    async fn delete_revisions(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+        policy: DeletionPolicy,
+    ) -> Result<()> {
+        if policy == DeletionPolicy::Anonymize {
+            return Ok(());
+        }
+
+        debug!("Hiding revisions for user ID {user_id}");
+
+        let txn = ctx.transaction();
+        let page_revisions = PageRevision::find()
+            .filter(page_revision::Column::UserId.eq(user_id))
+            .all(txn)
+            .await?;
+
+        for revision in page_revisions {
+            let mut hidden: Vec<PageHiddenField> = parse_hidden_fields(&revision.hidden)?;
+            hidden.extend([PageHiddenField::Comments, PageHiddenField::User]);
+
+            let model = page_revision::ActiveModel {
+                revision_id: Set(revision.revision_id),
+                hidden: Set(hidden_fields_column(&hidden, PageHiddenField::name)),
+                ..Default::default()
+            };
+            model.update(txn).await?;
+        }
+
+        let file_revisions = FileRevision::find()
+            .filter(file_revision::Column::UserId.eq(user_id))
+            .all(txn)
+            .await?;
+
+        for revision in file_revisions {
+            let mut hidden: Vec<FileHiddenField> = parse_hidden_fields(&revision.hidden)?;
+            hidden.extend([FileHiddenField::Comments, FileHiddenField::User]);
+
+            let model = file_revision::ActiveModel {
+                revision_id: Set(revision.revision_id),
+                hidden: Set(hidden_fields_column(&hidden, FileHiddenField::name)),
+                ..Default::default()
+            };
+            model.update(txn).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn delete_relations(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+        policy: DeletionPolicy,
+    ) -> Result<()> {
+        if policy == DeletionPolicy::Anonymize {
+            return Ok(());
+        }
+
+        debug!("Purging relations for user ID {user_id}");
+
+        let txn = ctx.transaction();
+        let DeleteResult { rows_affected } = Relation::delete_many()
+            .filter(
+                Condition::any()
+                    .add(
+                        Condition::all()
+                            .add(relation::Column::FromType.eq(RelationObjectType::User))
+                            .add(relation::Column::FromId.eq(user_id)),
+                    )
+                    .add(
+                        Condition::all()
+                            .add(relation::Column::DestType.eq(RelationObjectType::User))
+                            .add(relation::Column::DestId.eq(user_id)),
+                    ),
+            )
+            .exec(txn)
+            .await?;
+
+        debug!("{rows_affected} relations were deleted");
+        Ok(())
+    }
+
+    /// Removes every way of acting as the user.
+    async fn delete_credentials(ctx: &ServiceContext<'_>, user_id: i64) -> Result<()> {
+        debug!("Removing all credentials for user ID {user_id}");
+
+        let txn = ctx.transaction();
+        Session::delete_many()
+            .filter(session::Column::UserId.eq(user_id))
+            .exec(txn)
+            .await?;
+
+        WebauthnCredential::delete_many()
+            .filter(webauthn_credential::Column::UserId.eq(user_id))
+            .exec(txn)
+            .await?;
+
+        UserExternalIdentity::delete_many()
+            .filter(user_external_identity::Column::UserId.eq(user_id))
+            .exec(txn)
+            .await?;
+
+        UserBotOwner::delete_many()
+            .filter(user_bot_owner::Column::HumanUserId.eq(user_id))
+            .exec(txn)
+            .await?;
+
+        ApiTokenService::revoke_created_by(ctx, user_id).await?;
+        OauthService::revoke_user(ctx, user_id).await?;
+
+        for client in OauthService::get_clients(ctx, user_id).await? {
+            OauthService::delete_client(ctx, client.oauth_client_id, user_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Marks the user as deleted, and clears everything identifying them.
+    async fn anonymize(ctx: &ServiceContext<'_>, user_id: i64) -> Result<UserModel> {
+        debug!("Anonymizing user ID {user_id}");
+
+        // Marks as deleted and removes aliases
+        UserService::delete(ctx, Reference::Id(user_id)).await?;
+
+        let txn = ctx.transaction();
+
+        // Remove all copies of their avatar
+        AvatarService::set(ctx, user_id, None).await?;
+
+        // Past names would identify the user
+        UserNameChange::delete_many()
+            .filter(user_name_change::Column::UserId.eq(user_id))
+            .exec(txn)
+            .await?;
+
+        let name = format!("deleted-user-{user_id}");
+        let model = user::ActiveModel {
+            user_id: Set(user_id),
+            updated_at: Set(Some(now())),
+            name: Set(name.clone()),
+            slug: Set(name),
+            email: Set(String::new()),
+            email_is_alias: Set(None),
+            email_verified_at: Set(None),
+            password: Set(None),
+            multi_factor_secret: Set(None),
+            multi_factor_recovery_codes: Set(None),
+            avatar_s3_hash: Set(None),
+            real_name: Set(None),
+            gender: Set(None),
+            birthday: Set(None),
+            location: Set(None),
+            biography: Set(None),
+            user_page: Set(None),
+            ..Default::default()
+        };
+
+        let user = model.update(txn).await?;
+        Ok(user)
+    }
+}
@@ -33,23 +33,31 @@ use super::prelude::*;
 use crate::models::page::Model as PageModel;
 use crate::models::page_revision::Model as PageRevisionModel;
 use crate::models::site::Model as SiteModel;
+use crate::services::api_token::{ApiAction, ApiScope};
 use crate::services::domain::SiteDomainResult;
 use crate::services::render::RenderOutput;
+use crate::services::session::ScopedSession;
 use crate::services::special_page::{GetSpecialPageOutput, SpecialPageType};
 use crate::services::{
-    DomainService, PageRevisionService, PageService, SessionService, SpecialPageService,
-    TextService, UserService,
+    AvatarService, DomainService, LinkService, PageRevisionService, PageService,
+    SessionService, SpecialPageService, TextService, UserService,
 };
+use crate::types::PageDetails;
 use crate::utils::split_category;
 use fluent::{FluentArgs, FluentValue};
 use ftml::prelude::*;
 use ftml::render::html::HtmlOutput;
 use ref_map::*;
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::mem;
+use std::net::IpAddr;
 use unic_langid::LanguageIdentifier;
 use wikidot_normalize::normalize;
 
+/// The maximum number of redirects to follow when viewing a page.
+const MAX_REDIRECT_HOPS: usize = 8;
+
 #[derive(Debug)]
 pub struct ViewService;
 
@@ -61,6 +69,8 @@ impl ViewService {
             locales: locales_str,
             route,
             session_token,
+            ip_address,
+            user_agent,
         }: GetPageView,
     ) -> Result<GetPageViewOutput> {
         info!(
@@ -83,6 +93,8 @@ impl ViewService {
             &mut locales,
             &domain,
             session_token.ref_map(|s| s.as_str()),
+            ip_address,
+            &user_agent,
         )
         .await?
         {
@@ -98,7 +110,7 @@ impl ViewService {
             Some(PageRoute { slug, extra }) => (slug, extra),
         };
 
-        let redirect_page = Self::should_redirect_page(page_full_slug);
+        let mut redirect_page = Self::should_redirect_page(page_full_slug);
         let options = PageOptions::parse(page_extra);
 
         // Get page, revision, and text fields
@@ -172,6 +184,10 @@ impl ViewService {
                 if Self::can_access_page(ctx, user_permissions).await? {
                     debug!("User has page access, return text data");
 
+                    if redirect_page.is_none() && !options.no_redirect {
+                        redirect_page = Self::follow_redirects(ctx, &page).await?;
+                    }
+
                     if options.rerender
                         && Self::can_edit_page(ctx, user_permissions).await?
                     {
@@ -258,8 +274,6 @@ impl ViewService {
             }
         };
 
-        // TODO Check if user-agent and IP match?
-
         let viewer = Viewer {
             site,
             redirect_site,
@@ -274,7 +288,12 @@ impl ViewService {
                 viewer,
                 options,
                 page,
-                page_revision,
+                page_revision: PageRevisionService::filter_and_populate(
+                    ctx,
+                    page_revision,
+                    PageDetails::default(),
+                )
+                .await?,
                 redirect_page,
                 wikitext,
                 compiled_html,
@@ -312,6 +331,8 @@ impl ViewService {
             locales: locales_str,
             user: user_ref,
             session_token,
+            ip_address,
+            user_agent,
         }: GetUserView<'_>,
     ) -> Result<GetUserViewOutput> {
         info!(
@@ -329,6 +350,8 @@ impl ViewService {
             &mut locales,
             &domain,
             session_token.ref_map(|s| s.as_str()),
+            ip_address,
+            &user_agent,
         )
         .await?
         {
@@ -338,20 +361,47 @@ impl ViewService {
             }
         };
 
-        // TODO Check if user-agent and IP match?
-
         // Get data to return for this user.
-        let user = match user_ref {
-            Some(user_ref) => UserService::get_optional(ctx, user_ref).await?,
+        //
+        // If the user was found via an old name (i.e. a user alias),
+        // then the client should redirect to their current slug.
+        let (user, requested_slug) = match user_ref {
+            Some(user_ref) => {
+                let requested_slug = match user_ref {
+                    Reference::Slug(ref slug) => Some(slug.to_string()),
+                    Reference::Id(_) => None,
+                };
+
+                let user = UserService::get_optional(ctx, user_ref).await?;
+                (user, requested_slug)
+            }
             // For users visiting their own user info page
-            None => viewer
-                .user_session
-                .as_ref()
-                .map(|session| session.user.clone()),
+            None => {
+                let user = viewer
+                    .user_session
+                    .as_ref()
+                    .map(|session| session.user.clone());
+
+                (user, None)
+            }
         };
 
         let output = match user {
-            Some(user) => GetUserViewOutput::UserFound { viewer, user },
+            Some(user) => {
+                let redirect_user = match requested_slug {
+                    Some(slug) if slug != user.slug => Some(user.slug.clone()),
+                    _ => None,
+                };
+
+                let avatars = AvatarService::get_all(ctx, user.user_id).await?;
+
+                GetUserViewOutput::UserFound {
+                    viewer,
+                    user,
+                    avatars,
+                    redirect_user,
+                }
+            }
             None => GetUserViewOutput::UserMissing { viewer },
         };
 
@@ -364,6 +414,8 @@ impl ViewService {
             domain,
             locales: locales_str,
             session_token,
+            ip_address,
+            user_agent,
         }: GetAdminView,
     ) -> Result<GetAdminViewOutput> {
         info!(
@@ -382,6 +434,8 @@ impl ViewService {
             &mut locales,
             &domain,
             session_token.ref_map(|s| s.as_str()),
+            ip_address,
+            &user_agent,
         )
         .await?
         {
@@ -465,6 +519,10 @@ impl ViewService {
     /// * Hostname of request → Site ID and data
     /// * Session token → User ID and their permissions
     ///
+    /// The session is also checked against the request's IP address and user
+    /// agent. If it is not bound to them, or is restricted, then the request is
+    /// treated as though there was no session.
+    ///
     /// Then using this information, the caller can perform some common
     /// operations, such as slug normalization or redirect site aliases.
     pub async fn get_viewer(
@@ -472,15 +530,43 @@ impl ViewService {
         locales: &mut Vec<LanguageIdentifier>,
         domain: &str,
         session_token: Option<&str>,
+        ip_address: IpAddr,
+        user_agent: &str,
     ) -> Result<ViewerResult> {
         info!("Getting viewer data from domain '{domain}' and session token");
 
-        // Get user data from session token (if present)
-        let user_session = match session_token {
-            None => None,
-            Some("") => None,
+        // Get session from token (if present), as long as it's valid here
+        let mut scoped_session = None;
+        let session = match session_token {
+            None | Some("") => None,
+            Some(token) if SessionService::is_scoped_token(ctx.config(), token) => {
+                let ScopedSession { session, scopes } =
+                    SessionService::get_scoped(ctx, token, ip_address, user_agent)
+                        .await?
+                        .ok_or(Error::InvalidSessionToken)?;
+
+                // Scopes are checked once we know which site this is
+                scoped_session = Some(scopes);
+                Some(session)
+            }
             Some(token) => {
                 let session = SessionService::get(ctx, token).await?;
+                match SessionService::check_binding(ctx, session, ip_address, user_agent)
+                    .await?
+                {
+                    Some(session) if !session.restricted => Some(session),
+                    _ => {
+                        warn!("Session cannot be used for this request, ignoring");
+                        None
+                    }
+                }
+            }
+        };
+
+        // Get user data from session
+        let user_session = match session {
+            None => None,
+            Some(session) => {
                 let user = UserService::get(ctx, Reference::Id(session.user_id)).await?;
 
                 // Prefer what the user has set over what the browser is requesting
@@ -541,6 +627,23 @@ impl ViewService {
                 }
             };
 
+        // Scoped tokens may only view sites they have been granted access to
+        let user_session = match scoped_session {
+            Some(scopes)
+                if !ApiScope::any_grants(
+                    &scopes,
+                    ApiScope {
+                        action: ApiAction::ReadPages,
+                        site_id: Some(site.site_id),
+                    },
+                ) =>
+            {
+                warn!("Token does not grant access to this site, ignoring");
+                None
+            }
+            _ => user_session,
+        };
+
         Ok(ViewerResult::FoundSite(Viewer {
             site,
             redirect_site,
@@ -634,6 +737,45 @@ impl ViewService {
         }
     }
 
+    /// Follows any redirects from this page, returning the slug of the final page.
+    ///
+    /// Only redirects to other pages on the same site are followed.
+    /// If a redirect loop is found, or there are more than `MAX_REDIRECT_HOPS`
+    /// redirects in a row, then nothing is followed and the page is shown as-is.
+    async fn follow_redirects(
+        ctx: &ServiceContext<'_>,
+        page: &PageModel,
+    ) -> Result<Option<String>> {
+        let mut visited = HashSet::new();
+        let mut current_page_id = page.page_id;
+        let mut target = None;
+        visited.insert(current_page_id);
+
+        while let Some(next_page_id) =
+            LinkService::get_redirect(ctx, current_page_id).await?
+        {
+            if !visited.insert(next_page_id) {
+                warn!("Redirect loop found from page ID {}", page.page_id);
+                return Ok(None);
+            }
+
+            if visited.len() > MAX_REDIRECT_HOPS + 1 {
+                warn!("Too many redirects from page ID {}", page.page_id);
+                return Ok(None);
+            }
+
+            match PageService::get_direct_optional(ctx, next_page_id, false).await? {
+                Some(next_page) if next_page.site_id == page.site_id => {
+                    current_page_id = next_page_id;
+                    target = Some(next_page.slug);
+                }
+                _ => break,
+            }
+        }
+
+        Ok(target)
+    }
+
     fn should_redirect_page(slug: &str) -> Option<String> {
         // Fix typos in the page slug.
         // See https://scuttle.atlassian.net/browse/WJ-330
@@ -21,10 +21,12 @@
 use super::options::PageOptions;
 use super::prelude::*;
 use crate::models::page::Model as PageModel;
-use crate::models::page_revision::Model as PageRevisionModel;
 use crate::models::session::Model as SessionModel;
 use crate::models::site::Model as SiteModel;
 use crate::models::user::Model as UserModel;
+use crate::services::avatar::AvatarOutput;
+use crate::services::page_revision::PageRevisionModelFiltered;
+use std::net::IpAddr;
 
 // TODO replace with actual user permissions type
 #[derive(Serialize, Deserialize, Debug, Copy, Clone)]
@@ -41,6 +43,8 @@ impl UserPermissions {
 pub struct GetPageView {
     pub domain: String,
     pub session_token: Option<String>,
+    pub ip_address: IpAddr,
+    pub user_agent: String,
     pub route: Option<PageRoute>,
     pub locales: Vec<String>,
 }
@@ -59,7 +63,7 @@ pub enum GetPageViewOutput {
         viewer: Viewer,
         options: PageOptions,
         page: PageModel,
-        page_revision: PageRevisionModel,
+        page_revision: PageRevisionModelFiltered,
         redirect_page: Option<String>,
         wikitext: String,
         compiled_html: String,
@@ -92,6 +96,8 @@ pub enum GetPageViewOutput {
 pub struct GetUserView<'a> {
     pub domain: String,
     pub session_token: Option<String>,
+    pub ip_address: IpAddr,
+    pub user_agent: String,
     pub user: Option<Reference<'a>>,
     pub locales: Vec<String>,
 }
@@ -103,6 +109,8 @@ pub enum GetUserViewOutput {
         #[serde(flatten)]
         viewer: Viewer,
         user: UserModel,
+        avatars: Vec<AvatarOutput>,
+        redirect_user: Option<String>,
     },
 
     UserMissing {
@@ -119,6 +127,8 @@ pub enum GetUserViewOutput {
 pub struct GetAdminView {
     pub domain: String,
     pub session_token: Option<String>,
+    pub ip_address: IpAddr,
+    pub user_agent: String,
     pub locales: Vec<String>,
 }
 
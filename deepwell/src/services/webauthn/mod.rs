@@ -0,0 +1,44 @@
+/*
+ * services/webauthn/mod.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The WebAuthn service, for passkeys and security keys.
+//!
+//! Users may register several named credentials, which can be used either
+//! as a second factor after a password login (alongside TOTP), or on their
+//! own as a passwordless login, provided the authenticator verifies the user.
+//!
+//! Verification is done by `webauthn-rs`. The state for each ceremony is
+//! kept in Redis until it is used or expires, so each one can only be
+//! completed once.
+
+#[allow(unused_imports)]
+mod prelude {
+    pub use super::super::prelude::*;
+    pub use super::structs::*;
+}
+
+mod service;
+mod structs;
+
+#[cfg(test)]
+mod soft_passkey;
+
+pub use self::service::WebauthnService;
+pub use self::structs::*;
@@ -0,0 +1,686 @@
+/*
+ * services/webauthn/service.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::prelude::*;
+use crate::config::Config;
+use crate::models::sea_orm_active_enums::UserType;
+use crate::models::user::Model as UserModel;
+use crate::models::webauthn_credential::{
+    self, Entity as WebauthnCredential, Model as WebauthnCredentialModel,
+};
+use crate::utils::assert_is_csprng;
+use data_encoding::BASE64URL_NOPAD;
+use rand::{thread_rng, Rng};
+use webauthn_rs::prelude::{
+    AuthenticationResult, CreationChallengeResponse, DiscoverableKey, Passkey,
+    PublicKeyCredential, RegisterPublicKeyCredential, RequestChallengeResponse, Url,
+    Uuid, Webauthn, WebauthnBuilder, WebauthnError,
+};
+
+/// How many random bytes are in each ceremony ID.
+const CEREMONY_ID_LENGTH: usize = 32;
+
+/// The maximum length of a credential's name, in characters.
+pub const MAXIMUM_NAME_LENGTH: usize = 100;
+
+#[derive(Debug)]
+pub struct WebauthnService;
+
+impl WebauthnService {
+    /// Begins registering a new credential for a user.
+    pub async fn start_registration(
+        ctx: &ServiceContext<'_>,
+        user: &UserModel,
+    ) -> Result<StartCeremonyOutput<CreationChallengeResponse>> {
+        info!(
+            "Starting WebAuthn registration for user ID {}",
+            user.user_id
+        );
+
+        // Only regular accounts can have MFA
+        if user.user_type != UserType::Regular {
+            error!("Only regular users may have WebAuthn credentials");
+            return Err(Error::BadRequest);
+        }
+
+        let exclude_credentials = Self::get_all(ctx, user.user_id)
+            .await?
+            .into_iter()
+            .map(|credential| credential.credential_id.into())
+            .collect();
+
+        let (options, state) = build_webauthn(ctx.config())?.start_passkey_registration(
+            user_uuid(user.user_id),
+            &user.slug,
+            &user.name,
+            Some(exclude_credentials),
+        )?;
+
+        let ceremony_id = Self::store_ceremony(
+            ctx,
+            CeremonyState::Registration {
+                user_id: user.user_id,
+                state,
+            },
+        )
+        .await?;
+
+        Ok(StartCeremonyOutput {
+            ceremony_id,
+            options,
+        })
+    }
+
+    /// Completes registration, storing the new credential.
+    pub async fn finish_registration(
+        ctx: &ServiceContext<'_>,
+        RegisterCredential {
+            user_id,
+            ceremony_id,
+            name,
+            credential,
+            ..
+        }: RegisterCredential,
+    ) -> Result<WebauthnCredentialModel> {
+        info!("Finishing WebAuthn registration for user ID {user_id}");
+
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > MAXIMUM_NAME_LENGTH {
+            error!("WebAuthn credential name is empty or too long");
+            return Err(Error::BadRequest);
+        }
+
+        let state = Self::take_ceremony(ctx, &ceremony_id).await?;
+        let passkey = verify_registration(ctx.config(), user_id, state, &credential)?;
+        let credential_id = passkey.cred_id().to_vec();
+
+        // Check for conflicts
+        let txn = ctx.transaction();
+        let existing = WebauthnCredential::find()
+            .filter(
+                Condition::any()
+                    .add(
+                        webauthn_credential::Column::CredentialId
+                            .eq(credential_id.as_slice()),
+                    )
+                    .add(
+                        Condition::all()
+                            .add(webauthn_credential::Column::UserId.eq(user_id))
+                            .add(webauthn_credential::Column::Name.eq(name)),
+                    ),
+            )
+            .one(txn)
+            .await?;
+
+        if existing.is_some() {
+            error!("WebAuthn credential with this ID or name already exists");
+            return Err(Error::WebauthnCredentialExists);
+        }
+
+        let model = webauthn_credential::ActiveModel {
+            user_id: Set(user_id),
+            name: Set(str!(name)),
+            credential_id: Set(credential_id),
+            passkey: Set(serde_json::to_value(&passkey)?),
+            ..Default::default()
+        };
+
+        let credential = model.insert(txn).await?;
+        Ok(credential)
+    }
+
+    /// Begins authenticating with a credential.
+    ///
+    /// If a user is given, this is a second factor for them, and only their
+    /// credentials may be used. Otherwise this is a passwordless login,
+    /// where the authenticator chooses a discoverable credential.
+    ///
+    /// Either way, the authenticator must verify the user.
+    pub async fn start_authentication(
+        ctx: &ServiceContext<'_>,
+        user_id: Option<i64>,
+    ) -> Result<StartCeremonyOutput<RequestChallengeResponse>> {
+        let webauthn = build_webauthn(ctx.config())?;
+        let (options, state) = match user_id {
+            None => {
+                info!("Starting passwordless WebAuthn authentication");
+                let (options, state) = webauthn.start_discoverable_authentication()?;
+                (options, CeremonyState::Discoverable { state })
+            }
+            Some(user_id) => {
+                info!("Starting WebAuthn authentication for user ID {user_id}");
+                let passkeys = Self::get_all(ctx, user_id)
+                    .await?
+                    .iter()
+                    .map(parse_passkey)
+                    .collect::<Result<Vec<_>>>()?;
+
+                if passkeys.is_empty() {
+                    error!("User ID {user_id} has no WebAuthn credentials");
+                    return Err(Error::WebauthnCredentialNotFound);
+                }
+
+                let (options, state) =
+                    webauthn.start_passkey_authentication(&passkeys)?;
+                (options, CeremonyState::Authentication { user_id, state })
+            }
+        };
+
+        let ceremony_id = Self::store_ceremony(ctx, state).await?;
+        Ok(StartCeremonyOutput {
+            ceremony_id,
+            options,
+        })
+    }
+
+    /// Gets the user a passwordless assertion claims to be from.
+    ///
+    /// This is not verified, it is only so the account's lockout
+    /// can be checked before the assertion is.
+    pub fn identify(assertion: &WebauthnAssertion) -> Result<i64> {
+        assertion
+            .credential
+            .get_user_unique_id()
+            .and_then(|user_handle| Uuid::from_slice(user_handle).ok())
+            .and_then(uuid_user)
+            .ok_or(Error::InvalidAuthentication)
+    }
+
+    /// Verifies an assertion from an authenticator.
+    ///
+    /// All failures produce the same generic authentication error,
+    /// the reason is only logged.
+    ///
+    /// # Returns
+    /// The ID of the user the credential belongs to.
+    pub async fn authenticate(
+        ctx: &ServiceContext<'_>,
+        user_id: Option<i64>,
+        assertion: &WebauthnAssertion,
+    ) -> Result<i64> {
+        match Self::authenticate_internal(ctx, user_id, assertion).await {
+            Err(Error::WebauthnInvalid(error)) => {
+                warn!("WebAuthn assertion rejected: {error}");
+                Err(Error::InvalidAuthentication)
+            }
+            Err(Error::WebauthnCredentialNotFound) => {
+                warn!("WebAuthn assertion used unknown credential");
+                Err(Error::InvalidAuthentication)
+            }
+            result => result,
+        }
+    }
+
+    async fn authenticate_internal(
+        ctx: &ServiceContext<'_>,
+        user_id: Option<i64>,
+        WebauthnAssertion {
+            ceremony_id,
+            credential: assertion,
+        }: &WebauthnAssertion,
+    ) -> Result<i64> {
+        let state = Self::take_ceremony(ctx, ceremony_id).await?;
+
+        // Get credential, its owner is checked against the ceremony
+        let credential = find_or_error!(
+            Self::get_by_credential_id(ctx, assertion.get_credential_id()),
+            WebauthnCredential,
+        )?;
+
+        let mut passkey = parse_passkey(&credential)?;
+        let result = verify_assertion(
+            ctx.config(),
+            user_id,
+            state,
+            credential.user_id,
+            &passkey,
+            assertion,
+        )?;
+
+        // Update usage information
+        let txn = ctx.transaction();
+        let mut model = webauthn_credential::ActiveModel {
+            webauthn_credential_id: Set(credential.webauthn_credential_id),
+            last_used_at: Set(Some(now())),
+            ..Default::default()
+        };
+
+        if passkey.update_credential(&result) == Some(true) {
+            model.passkey = Set(serde_json::to_value(&passkey)?);
+        }
+
+        model.update(txn).await?;
+        Ok(credential.user_id)
+    }
+
+    pub async fn get_all(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+    ) -> Result<Vec<WebauthnCredentialModel>> {
+        let txn = ctx.transaction();
+        let credentials = WebauthnCredential::find()
+            .filter(webauthn_credential::Column::UserId.eq(user_id))
+            .order_by_asc(webauthn_credential::Column::CreatedAt)
+            .all(txn)
+            .await?;
+
+        Ok(credentials)
+    }
+
+    pub async fn has_credentials(ctx: &ServiceContext<'_>, user_id: i64) -> Result<bool> {
+        let txn = ctx.transaction();
+        let count = WebauthnCredential::find()
+            .filter(webauthn_credential::Column::UserId.eq(user_id))
+            .count(txn)
+            .await?;
+
+        Ok(count > 0)
+    }
+
+    async fn get_by_credential_id(
+        ctx: &ServiceContext<'_>,
+        credential_id: &[u8],
+    ) -> Result<Option<WebauthnCredentialModel>> {
+        let txn = ctx.transaction();
+        let credential = WebauthnCredential::find()
+            .filter(webauthn_credential::Column::CredentialId.eq(credential_id))
+            .one(txn)
+            .await?;
+
+        Ok(credential)
+    }
+
+    /// Removes one of a user's credentials.
+    pub async fn delete(
+        ctx: &ServiceContext<'_>,
+        user_id: i64,
+        webauthn_credential_id: i64,
+    ) -> Result<()> {
+        info!("Deleting WebAuthn credential ID {webauthn_credential_id} for user ID {user_id}");
+
+        let txn = ctx.transaction();
+        let result = WebauthnCredential::delete_many()
+            .filter(
+                Condition::all()
+                    .add(
+                        webauthn_credential::Column::WebauthnCredentialId
+                            .eq(webauthn_credential_id),
+                    )
+                    .add(webauthn_credential::Column::UserId.eq(user_id)),
+            )
+            .exec(txn)
+            .await?;
+
+        if result.rows_affected == 0 {
+            return Err(Error::WebauthnCredentialNotFound);
+        }
+
+        Ok(())
+    }
+
+    /// Stores the state of a ceremony until it expires.
+    ///
+    /// # Returns
+    /// The ID to retrieve the ceremony state with.
+    async fn store_ceremony(
+        ctx: &ServiceContext<'_>,
+        state: CeremonyState,
+    ) -> Result<String> {
+        let ceremony_id = {
+            let mut rng = thread_rng();
+            assert_is_csprng(&rng);
+
+            let mut buffer = [0; CEREMONY_ID_LENGTH];
+            rng.fill(&mut buffer);
+            BASE64URL_NOPAD.encode(&buffer)
+        };
+
+        let timeout = ctx.config().webauthn_timeout;
+        let mut redis = ctx.redis_connect().await?;
+        redis::cmd("SET")
+            .arg(ceremony_key(&ceremony_id))
+            .arg(serde_json::to_string(&state)?)
+            .arg("PX")
+            .arg(timeout.as_millis() as u64)
+            .query_async::<_, ()>(&mut redis)
+            .await?;
+
+        Ok(ceremony_id)
+    }
+
+    /// Retrieves and removes the state of a ceremony.
+    ///
+    /// Removing it ensures that each ceremony can only be completed once.
+    async fn take_ceremony(
+        ctx: &ServiceContext<'_>,
+        ceremony_id: &str,
+    ) -> Result<Option<CeremonyState>> {
+        let mut redis = ctx.redis_connect().await?;
+        let state: Option<String> = redis::cmd("GETDEL")
+            .arg(ceremony_key(ceremony_id))
+            .query_async(&mut redis)
+            .await?;
+
+        match state {
+            Some(state) => Ok(Some(serde_json::from_str(&state)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Builds the relying party from the configuration.
+///
+/// Credentials are bound to the main domain, but may be used from any site
+/// subdomain. Plain HTTP is only allowed for `localhost` domains, and other
+/// ports only if enabled for local development.
+fn build_webauthn(config: &Config) -> Result<Webauthn> {
+    let rp_id = &config.main_domain_no_dot;
+    let scheme = if rp_id == "localhost" || rp_id.ends_with(".localhost") {
+        "http"
+    } else {
+        "https"
+    };
+
+    let origin = Url::parse(&format!("{scheme}://{rp_id}"))
+        .map_err(|_| WebauthnError::Configuration)?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &origin)?
+        .rp_name(&config.webauthn_name)
+        .allow_subdomains(true)
+        .allow_any_port(config.webauthn_allow_any_port)
+        .timeout(config.webauthn_timeout)
+        .build()?;
+
+    Ok(webauthn)
+}
+
+/// Checks a registration response against the ceremony it was made for.
+fn verify_registration(
+    config: &Config,
+    user_id: i64,
+    state: Option<CeremonyState>,
+    credential: &RegisterPublicKeyCredential,
+) -> Result<Passkey> {
+    let state = match state {
+        Some(CeremonyState::Registration {
+            user_id: state_user_id,
+            state,
+        }) if state_user_id == user_id => state,
+        _ => return Err(WebauthnError::ChallengeNotFound.into()),
+    };
+
+    let passkey =
+        build_webauthn(config)?.finish_passkey_registration(credential, &state)?;
+    Ok(passkey)
+}
+
+/// Checks an assertion against the ceremony it was made for,
+/// and the stored credential it was made with.
+///
+/// If a user is given, this is a second factor for their restricted
+/// session, and the ceremony must have been started for them.
+/// Otherwise it must be a passwordless login.
+fn verify_assertion(
+    config: &Config,
+    user_id: Option<i64>,
+    state: Option<CeremonyState>,
+    credential_user_id: i64,
+    passkey: &Passkey,
+    assertion: &PublicKeyCredential,
+) -> Result<AuthenticationResult> {
+    let webauthn = build_webauthn(config)?;
+    let result = match (state, user_id) {
+        (
+            Some(CeremonyState::Authentication {
+                user_id: state_user_id,
+                state,
+            }),
+            Some(user_id),
+        ) if state_user_id == user_id && credential_user_id == user_id => {
+            webauthn.finish_passkey_authentication(assertion, &state)?
+        }
+        (Some(CeremonyState::Discoverable { state }), None) => {
+            // Passwordless logins identify the user by the handle
+            // given when the credential was registered.
+            let (user_uuid, _) =
+                webauthn.identify_discoverable_authentication(assertion)?;
+            if uuid_user(user_uuid) != Some(credential_user_id) {
+                return Err(WebauthnError::InvalidUserUniqueId.into());
+            }
+
+            let key = DiscoverableKey::from(passkey);
+            webauthn.finish_discoverable_authentication(assertion, state, &[key])?
+        }
+        _ => return Err(WebauthnError::ChallengeNotFound.into()),
+    };
+
+    Ok(result)
+}
+
+fn parse_passkey(credential: &WebauthnCredentialModel) -> Result<Passkey> {
+    let passkey = serde_json::from_value(credential.passkey.clone())?;
+    Ok(passkey)
+}
+
+/// The user handle for a user, which is their ID.
+fn user_uuid(user_id: i64) -> Uuid {
+    Uuid::from_u64_pair(0, user_id as u64)
+}
+
+fn uuid_user(uuid: Uuid) -> Option<i64> {
+    match uuid.as_u64_pair() {
+        (0, user_id) => i64::try_from(user_id).ok(),
+        _ => None,
+    }
+}
+
+fn ceremony_key(ceremony_id: &str) -> String {
+    format!("webauthn-ceremony:{ceremony_id}")
+}
+
+/// Registers a new software authenticator for a user.
+#[cfg(test)]
+fn soft_register(
+    config: &Config,
+    origin: &str,
+    user_id: i64,
+) -> Result<(Passkey, super::soft_passkey::SoftPasskey)> {
+    let mut authenticator = super::soft_passkey::SoftPasskey::new(origin);
+    let (options, state) = build_webauthn(config)?.start_passkey_registration(
+        user_uuid(user_id),
+        "test-user",
+        "Test User",
+        None,
+    )?;
+
+    let credential = authenticator.register(&options);
+    let state = CeremonyState::Registration { user_id, state };
+    let passkey = verify_registration(config, user_id, Some(state), &credential)?;
+    Ok((passkey, authenticator))
+}
+
+#[test]
+fn webauthn_registration() {
+    let mut config = Config::example();
+
+    let (passkey, _) = soft_register(&config, "https://wikijump.com", 1)
+        .expect("Registration from main domain failed");
+    assert!(!passkey.cred_id().is_empty());
+
+    soft_register(&config, "https://scp-wiki.wikijump.com", 1)
+        .expect("Registration from site subdomain failed");
+
+    assert!(matches!(
+        soft_register(&config, "https://wikijump.example", 1),
+        Err(Error::WebauthnInvalid(_)),
+    ));
+
+    // Other ports are only allowed for local development
+    assert!(matches!(
+        soft_register(&config, "https://wikijump.com:8443", 1),
+        Err(Error::WebauthnInvalid(_)),
+    ));
+
+    config.webauthn_allow_any_port = true;
+    soft_register(&config, "https://wikijump.com:8443", 1)
+        .expect("Registration from other port failed");
+
+    // Ceremony must be for the same user
+    let mut authenticator = super::soft_passkey::SoftPasskey::new("https://wikijump.com");
+    let (options, state) = build_webauthn(&config)
+        .unwrap()
+        .start_passkey_registration(user_uuid(1), "test-user", "Test User", None)
+        .unwrap();
+
+    let credential = authenticator.register(&options);
+    let state = CeremonyState::Registration { user_id: 1, state };
+    assert!(matches!(
+        verify_registration(&config, 2, Some(state), &credential),
+        Err(Error::WebauthnInvalid(WebauthnError::ChallengeNotFound)),
+    ));
+    assert!(matches!(
+        verify_registration(&config, 1, None, &credential),
+        Err(Error::WebauthnInvalid(WebauthnError::ChallengeNotFound)),
+    ));
+}
+
+#[test]
+fn webauthn_second_factor() {
+    let config = Config::example();
+    let webauthn = build_webauthn(&config).unwrap();
+    let (passkey, mut authenticator) =
+        soft_register(&config, "https://wikijump.com", 1).unwrap();
+
+    macro_rules! check {
+        ($state_user_id:expr, $user_id:expr, $credential_user_id:expr, $ok:expr $(,)?) => {{
+            let (options, state) = webauthn
+                .start_passkey_authentication(std::slice::from_ref(&passkey))
+                .unwrap();
+
+            let assertion = authenticator.authenticate(&options);
+            let state = CeremonyState::Authentication {
+                user_id: $state_user_id,
+                state,
+            };
+
+            let result = verify_assertion(
+                &config,
+                $user_id,
+                Some(state),
+                $credential_user_id,
+                &passkey,
+                &assertion,
+            );
+
+            assert_eq!(
+                result.is_ok(),
+                $ok,
+                "Unexpected assertion result: {:?}",
+                result.map(|_| ()),
+            );
+        }};
+    }
+
+    // The restricted session's user, their credential
+    check!(1, Some(1), 1, true);
+
+    // Ceremony was started for another user's restricted session
+    check!(2, Some(1), 1, false);
+
+    // Credential belongs to another user
+    check!(1, Some(1), 2, false);
+
+    // Second factor ceremony used for a passwordless login
+    check!(1, None, 1, false);
+}
+
+#[test]
+fn webauthn_passwordless() {
+    let config = Config::example();
+    let webauthn = build_webauthn(&config).unwrap();
+    let (passkey, mut authenticator) =
+        soft_register(&config, "https://wikijump.com", 1).unwrap();
+
+    macro_rules! check {
+        ($user_id:expr, $credential_user_id:expr, $ok:expr $(,)?) => {{
+            let (options, state) = webauthn.start_discoverable_authentication().unwrap();
+            let assertion = authenticator.authenticate(&options);
+            let state = CeremonyState::Discoverable { state };
+
+            let result = verify_assertion(
+                &config,
+                $user_id,
+                Some(state),
+                $credential_user_id,
+                &passkey,
+                &assertion,
+            );
+
+            assert_eq!(
+                result.is_ok(),
+                $ok,
+                "Unexpected assertion result: {:?}",
+                result.map(|_| ()),
+            );
+        }};
+    }
+
+    check!(None, 1, true);
+
+    // User handle doesn't match the credential's owner
+    check!(None, 2, false);
+
+    // Passwordless ceremony used as a second factor
+    check!(Some(1), 1, false);
+}
+
+#[test]
+fn webauthn_sign_counter() {
+    let config = Config::example();
+    let webauthn = build_webauthn(&config).unwrap();
+    let (mut passkey, mut authenticator) =
+        soft_register(&config, "https://wikijump.com", 1).unwrap();
+
+    let authenticate = |authenticator: &mut super::soft_passkey::SoftPasskey,
+                        passkey: &Passkey| {
+        let (options, state) = webauthn
+            .start_passkey_authentication(std::slice::from_ref(passkey))
+            .unwrap();
+
+        let assertion = authenticator.authenticate(&options);
+        let state = CeremonyState::Authentication { user_id: 1, state };
+        verify_assertion(&config, Some(1), Some(state), 1, passkey, &assertion)
+    };
+
+    for _ in 0..3 {
+        let result =
+            authenticate(&mut authenticator, &passkey).expect("Assertion failed");
+        assert_eq!(passkey.update_credential(&result), Some(true));
+    }
+
+    // A counter which goes backwards indicates a cloned authenticator
+    authenticator.counter = 1;
+    assert!(matches!(
+        authenticate(&mut authenticator, &passkey),
+        Err(Error::WebauthnInvalid(
+            WebauthnError::CredentialPossibleCompromise
+        )),
+    ));
+}
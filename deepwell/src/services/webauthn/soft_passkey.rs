@@ -0,0 +1,186 @@
+/*
+ * services/webauthn/soft_passkey.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! A software authenticator, for testing WebAuthn ceremonies.
+//!
+//! It holds a single ES256 key, uses "none" attestation, and always
+//! reports the user as present and verified, like a platform passkey.
+
+use data_encoding::BASE64URL_NOPAD;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use serde_cbor_2::Value as CborValue;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use webauthn_rs::prelude::{
+    CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential,
+    RequestChallengeResponse,
+};
+
+/// Authenticator data flags: user present, user verified.
+const FLAGS_UP_UV: u8 = 0x01 | 0x04;
+
+/// Authenticator data flag: attested credential data included.
+const FLAG_AT: u8 = 0x40;
+
+#[derive(Debug)]
+pub struct SoftPasskey {
+    key: EcdsaKeyPair,
+    credential_id: Vec<u8>,
+    user_handle: Vec<u8>,
+    origin: String,
+
+    /// The signature counter, incremented before each assertion.
+    pub counter: u32,
+}
+
+impl SoftPasskey {
+    /// Creates an authenticator which responds as if from the given origin.
+    pub fn new(origin: &str) -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng)
+            .expect("Unable to generate key");
+        let key = EcdsaKeyPair::from_pkcs8(
+            &ECDSA_P256_SHA256_ASN1_SIGNING,
+            pkcs8.as_ref(),
+            &rng,
+        )
+        .expect("Unable to load generated key");
+
+        let credential_id = Sha256::digest(key.public_key().as_ref())[..16].to_vec();
+
+        SoftPasskey {
+            key,
+            credential_id,
+            user_handle: Vec::new(),
+            origin: str!(origin),
+            counter: 0,
+        }
+    }
+
+    /// Creates a credential in response to registration options.
+    pub fn register(
+        &mut self,
+        options: &CreationChallengeResponse,
+    ) -> RegisterPublicKeyCredential {
+        let options = &options.public_key;
+        self.user_handle = options.user.id.to_vec();
+
+        // COSE_Key for an ES256 public key, which is 0x04 || x || y
+        let point = self.key.public_key().as_ref();
+        let cose_key = cbor_map([
+            (CborValue::Integer(1), CborValue::Integer(2)),
+            (CborValue::Integer(3), CborValue::Integer(-7)),
+            (CborValue::Integer(-1), CborValue::Integer(1)),
+            (
+                CborValue::Integer(-2),
+                CborValue::Bytes(point[1..33].to_vec()),
+            ),
+            (
+                CborValue::Integer(-3),
+                CborValue::Bytes(point[33..].to_vec()),
+            ),
+        ]);
+
+        let mut auth_data = self.auth_data(&options.rp.id, FLAGS_UP_UV | FLAG_AT);
+        auth_data.extend_from_slice(&[0; 16]); // AAGUID
+        auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.credential_id);
+        auth_data.extend(serde_cbor_2::to_vec(&cose_key).unwrap());
+
+        let attestation_object = cbor_map([
+            (str!("fmt").into(), str!("none").into()),
+            (str!("attStmt").into(), cbor_map([])),
+            (str!("authData").into(), CborValue::Bytes(auth_data)),
+        ]);
+
+        let client_data = self.client_data("webauthn.create", &options.challenge);
+        let credential_id = BASE64URL_NOPAD.encode(&self.credential_id);
+
+        serde_json::from_value(json!({
+            "id": credential_id,
+            "rawId": credential_id,
+            "type": "public-key",
+            "response": {
+                "attestationObject": BASE64URL_NOPAD.encode(
+                    &serde_cbor_2::to_vec(&attestation_object).unwrap(),
+                ),
+                "clientDataJSON": BASE64URL_NOPAD.encode(&client_data),
+            },
+        }))
+        .expect("Invalid registration response")
+    }
+
+    /// Produces an assertion in response to authentication options.
+    pub fn authenticate(
+        &mut self,
+        options: &RequestChallengeResponse,
+    ) -> PublicKeyCredential {
+        let options = &options.public_key;
+        self.counter += 1;
+
+        let auth_data = self.auth_data(&options.rp_id, FLAGS_UP_UV);
+        let client_data = self.client_data("webauthn.get", &options.challenge);
+
+        let mut message = auth_data.clone();
+        message.extend(Sha256::digest(&client_data));
+        let signature = self
+            .key
+            .sign(&SystemRandom::new(), &message)
+            .expect("Unable to sign assertion");
+
+        let credential_id = BASE64URL_NOPAD.encode(&self.credential_id);
+
+        serde_json::from_value(json!({
+            "id": credential_id,
+            "rawId": credential_id,
+            "type": "public-key",
+            "response": {
+                "authenticatorData": BASE64URL_NOPAD.encode(&auth_data),
+                "clientDataJSON": BASE64URL_NOPAD.encode(&client_data),
+                "signature": BASE64URL_NOPAD.encode(signature.as_ref()),
+                "userHandle": BASE64URL_NOPAD.encode(&self.user_handle),
+            },
+        }))
+        .expect("Invalid assertion response")
+    }
+
+    fn auth_data(&self, rp_id: &str, flags: u8) -> Vec<u8> {
+        let mut auth_data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        auth_data.push(flags);
+        auth_data.extend_from_slice(&self.counter.to_be_bytes());
+        auth_data
+    }
+
+    fn client_data(&self, ceremony_type: &str, challenge: &[u8]) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "type": ceremony_type,
+            "challenge": BASE64URL_NOPAD.encode(challenge),
+            "origin": self.origin,
+            "crossOrigin": false,
+        }))
+        .unwrap()
+    }
+}
+
+fn cbor_map<const N: usize>(entries: [(CborValue, CborValue); N]) -> CborValue {
+    CborValue::Map(BTreeMap::from(entries))
+}
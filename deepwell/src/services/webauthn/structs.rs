@@ -0,0 +1,120 @@
+/*
+ * services/webauthn/structs.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::models::webauthn_credential::Model as WebauthnCredentialModel;
+use time::OffsetDateTime;
+use webauthn_rs::prelude::{
+    DiscoverableAuthentication, PasskeyAuthentication, PasskeyRegistration,
+    PublicKeyCredential, RegisterPublicKeyCredential,
+};
+
+/// The state kept between the start and finish of a ceremony.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "ceremony", rename_all = "snake_case")]
+pub enum CeremonyState {
+    Registration {
+        user_id: i64,
+        state: PasskeyRegistration,
+    },
+    Authentication {
+        user_id: i64,
+        state: PasskeyAuthentication,
+    },
+    Discoverable {
+        state: DiscoverableAuthentication,
+    },
+}
+
+/// The options to pass to the browser to begin a ceremony.
+///
+/// The ceremony ID must be passed back when finishing it.
+#[derive(Serialize, Debug, Clone)]
+pub struct StartCeremonyOutput<T> {
+    pub ceremony_id: String,
+    pub options: T,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StartRegistration {
+    pub user_id: i64,
+    pub session_token: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RegisterCredential {
+    pub user_id: i64,
+    pub session_token: String,
+    pub ceremony_id: String,
+    pub name: String,
+    pub credential: RegisterPublicKeyCredential,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GetCredentials {
+    pub user_id: i64,
+    pub session_token: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DeleteCredential {
+    pub user_id: i64,
+    pub session_token: String,
+    pub webauthn_credential_id: i64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StartAuthentication {
+    /// The restricted session token, if this is for MFA.
+    ///
+    /// If absent, this is a passwordless login, and any
+    /// discoverable credential may be used.
+    #[serde(default)]
+    pub session_token: Option<String>,
+}
+
+/// The response from an authenticator during authentication.
+#[derive(Deserialize, Debug, Clone)]
+pub struct WebauthnAssertion {
+    pub ceremony_id: String,
+    pub credential: PublicKeyCredential,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct WebauthnCredentialOutput {
+    pub webauthn_credential_id: i64,
+    pub name: String,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_used_at: Option<OffsetDateTime>,
+}
+
+impl From<WebauthnCredentialModel> for WebauthnCredentialOutput {
+    fn from(model: WebauthnCredentialModel) -> Self {
+        WebauthnCredentialOutput {
+            webauthn_credential_id: model.webauthn_credential_id,
+            name: model.name,
+            created_at: model.created_at,
+            last_used_at: model.last_used_at,
+        }
+    }
+}
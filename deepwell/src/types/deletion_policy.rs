@@ -0,0 +1,46 @@
+/*
+ * types/deletion_policy.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/// What happens to one class of a user's data when their account is deleted.
+///
+/// In either case the account itself is anonymized, with its profile
+/// cleared and its name replaced, so data which is kept can no longer
+/// be tied to the person who created it.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeletionPolicy {
+    /// Keep the data, attributed to the anonymized account.
+    Anonymize,
+
+    /// Remove the data, or hide it where others depend on it.
+    Purge,
+}
+
+/// The deletion policy for each class of user data.
+///
+/// See `config.example.toml` for what each policy does for each class.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct DeletionPolicies {
+    pub messages: DeletionPolicy,
+    pub votes: DeletionPolicy,
+    pub revisions: DeletionPolicy,
+    pub relations: DeletionPolicy,
+}
@@ -21,6 +21,9 @@
 #[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq)]
 #[serde(default)]
 pub struct FileDetails {
-    /// Include the contents of the file.
-    pub data: bool,
+    /// Include a presigned URL to download the file.
+    pub url: bool,
+
+    /// Include image dimensions and thumbnails, if the file is an image.
+    pub media: bool,
 }
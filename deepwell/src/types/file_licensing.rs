@@ -0,0 +1,236 @@
+/*
+ * types/file_licensing.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::Url;
+use serde_json::Value as JsonValue;
+
+/// Matches a custom SPDX license identifier, such as `LicenseRef-Fair-Use`.
+///
+/// These are for licenses which are not on the SPDX license list.
+static LICENSE_REF_IDENTIFIER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^LicenseRef-[A-Za-z0-9.\-]{1,64}$").unwrap());
+
+const MAXIMUM_AUTHOR_LENGTH: usize = 256;
+const MAXIMUM_SOURCE_URL_LENGTH: usize = 2048;
+const MAXIMUM_DERIVATIVE_OF_LENGTH: usize = 512;
+
+/// Licensing information for a file, as stored on each of its revisions.
+///
+/// Either a license is given, or the file is explicitly marked as having
+/// an unknown license, so that such files can be found and dealt with.
+///
+/// This is displayed in the file listing on each page, and in full
+/// on each file's own page.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct FileLicensing {
+    /// The SPDX identifier of the license, such as `CC-BY-SA-3.0`.
+    pub license: Option<String>,
+
+    /// The author or copyright holder of the file.
+    pub author: Option<String>,
+
+    /// Where the file was originally found.
+    pub source_url: Option<String>,
+
+    /// The work this file is derived from, if any.
+    pub derivative_of: Option<String>,
+
+    /// Whether the license of this file has not been determined.
+    pub unknown: bool,
+}
+
+impl FileLicensing {
+    /// Licensing for a file which has not been determined.
+    pub fn unknown() -> Self {
+        FileLicensing {
+            unknown: true,
+            ..Default::default()
+        }
+    }
+
+    /// Reads licensing from a file revision.
+    ///
+    /// Revisions from before licensing was structured may hold
+    /// `null` or other values, these are treated as unknown.
+    pub fn from_json(value: &JsonValue) -> Self {
+        match serde_json::from_value(value.clone()) {
+            Ok(licensing) => licensing,
+            Err(_) => Self::unknown(),
+        }
+    }
+
+    /// Converts licensing for storage in a file revision.
+    pub fn to_json(&self) -> JsonValue {
+        serde_json::to_value(self).expect("Unable to serialize file licensing")
+    }
+
+    /// Checks that this licensing is consistent and well-formed.
+    ///
+    /// On failure, returns a description of the problem.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        match (&self.license, self.unknown) {
+            (Some(_), true) => return Err("license cannot be set if unknown"),
+            (None, false) => return Err("license must be set unless unknown"),
+            (Some(license), false) if !is_license_identifier(license) => {
+                return Err("license is not a valid SPDX identifier");
+            }
+            _ => (),
+        }
+
+        if let Some(author) = &self.author {
+            if author.trim().is_empty() {
+                return Err("author cannot be empty");
+            }
+
+            if author.len() > MAXIMUM_AUTHOR_LENGTH {
+                return Err("author is too long");
+            }
+        }
+
+        if let Some(derivative_of) = &self.derivative_of {
+            if derivative_of.trim().is_empty() {
+                return Err("derivative-of cannot be empty");
+            }
+
+            if derivative_of.len() > MAXIMUM_DERIVATIVE_OF_LENGTH {
+                return Err("derivative-of is too long");
+            }
+        }
+
+        if let Some(source_url) = &self.source_url {
+            if source_url.len() > MAXIMUM_SOURCE_URL_LENGTH {
+                return Err("source URL is too long");
+            }
+
+            match Url::parse(source_url) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") => (),
+                _ => return Err("source URL is not a valid web address"),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Determines if this is a single SPDX license identifier.
+///
+/// It must either be on the SPDX license list exactly, such as `CC-BY-SA-3.0`,
+/// or be a custom `LicenseRef-` identifier. Compound expressions such as
+/// `MIT OR Apache-2.0`, or the `+` suffix, are not accepted.
+fn is_license_identifier(license: &str) -> bool {
+    if license.starts_with("LicenseRef-") {
+        return LICENSE_REF_IDENTIFIER.is_match(license);
+    }
+
+    match spdx::license_id(license) {
+        Some(id) => id.name == license,
+        None => false,
+    }
+}
+
+#[test]
+fn file_licensing_validate() {
+    use serde_json::json;
+
+    macro_rules! check {
+        ($licensing:expr, $expected:expr $(,)?) => {{
+            let licensing: FileLicensing =
+                serde_json::from_value($licensing).expect("Invalid licensing JSON");
+
+            assert_eq!(
+                licensing.validate(),
+                $expected,
+                "Unexpected validation result for {licensing:?}",
+            );
+        }};
+    }
+
+    check!(json!({ "unknown": true }), Ok(()));
+    check!(json!({ "license": "CC-BY-SA-3.0" }), Ok(()));
+    check!(
+        json!({
+            "license": "LicenseRef-Fair-Use",
+            "author": "Dr. Bright",
+            "source_url": "https://example.com/image.png",
+            "derivative_of": "SCP-173 original sculpture",
+        }),
+        Ok(()),
+    );
+    check!(json!({}), Err("license must be set unless unknown"));
+    check!(
+        json!({ "license": "CC-BY-SA-3.0", "unknown": true }),
+        Err("license cannot be set if unknown"),
+    );
+    check!(json!({ "license": "CC-BY-NC-ND-4.0" }), Ok(()));
+    check!(json!({ "license": "GFDL-1.3-or-later" }), Ok(()));
+    check!(
+        json!({ "license": "MIT OR Apache-2.0" }),
+        Err("license is not a valid SPDX identifier"),
+    );
+    check!(
+        json!({ "license": "foo" }),
+        Err("license is not a valid SPDX identifier"),
+    );
+    check!(
+        json!({ "license": "Totally-Free-1.0" }),
+        Err("license is not a valid SPDX identifier"),
+    );
+    check!(
+        json!({ "license": "cc-by-sa-3.0" }),
+        Err("license is not a valid SPDX identifier"),
+    );
+    check!(
+        json!({ "license": "MIT+" }),
+        Err("license is not a valid SPDX identifier"),
+    );
+    check!(
+        json!({ "license": "LicenseRef-" }),
+        Err("license is not a valid SPDX identifier"),
+    );
+    check!(
+        json!({ "license": "LicenseRef-Fair Use" }),
+        Err("license is not a valid SPDX identifier"),
+    );
+    check!(
+        json!({ "license": "MIT", "author": " " }),
+        Err("author cannot be empty"),
+    );
+    check!(
+        json!({ "license": "MIT", "source_url": "javascript:alert(1)" }),
+        Err("source URL is not a valid web address"),
+    );
+}
+
+#[test]
+fn file_licensing_from_json() {
+    assert_eq!(
+        FileLicensing::from_json(&JsonValue::Null),
+        FileLicensing::unknown(),
+    );
+
+    let licensing = FileLicensing {
+        license: Some(str!("CC0-1.0")),
+        ..Default::default()
+    };
+    assert_eq!(FileLicensing::from_json(&licensing.to_json()), licensing);
+}
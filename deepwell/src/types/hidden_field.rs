@@ -0,0 +1,248 @@
+/*
+ * types/hidden_field.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Fields of a revision which can be hidden by staff.
+//!
+//! These are stored in the `hidden` column of `page_revision` and `file_revision`,
+//! and determine which parts of the revision are redacted when it is read.
+//! See also the `CHECK` constraints on those columns.
+
+use crate::services::Error as ServiceError;
+use std::str::FromStr;
+use strum_macros::EnumIter;
+
+#[derive(
+    EnumIter,
+    Serialize,
+    Deserialize,
+    Debug,
+    Copy,
+    Clone,
+    Hash,
+    PartialOrd,
+    Ord,
+    PartialEq,
+    Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PageHiddenField {
+    Wikitext,
+    Compiled,
+    Comments,
+    User,
+    Title,
+    AltTitle,
+    Slug,
+    Tags,
+}
+
+impl PageHiddenField {
+    pub fn name(self) -> &'static str {
+        match self {
+            PageHiddenField::Wikitext => "wikitext",
+            PageHiddenField::Compiled => "compiled",
+            PageHiddenField::Comments => "comments",
+            PageHiddenField::User => "user",
+            PageHiddenField::Title => "title",
+            PageHiddenField::AltTitle => "alt_title",
+            PageHiddenField::Slug => "slug",
+            PageHiddenField::Tags => "tags",
+        }
+    }
+
+    /// Whether this field is part of the page's contents.
+    ///
+    /// Content fields are exposed by the page itself while the revision
+    /// is current, so they cannot be hidden on the latest revision, and
+    /// rolling back to a revision with them hidden would reveal them.
+    pub fn is_content(self) -> bool {
+        !matches!(self, PageHiddenField::Comments | PageHiddenField::User)
+    }
+}
+
+impl FromStr for PageHiddenField {
+    type Err = ServiceError;
+
+    fn from_str(value: &str) -> Result<PageHiddenField, ServiceError> {
+        match value {
+            "wikitext" => Ok(PageHiddenField::Wikitext),
+            "compiled" => Ok(PageHiddenField::Compiled),
+            "comments" => Ok(PageHiddenField::Comments),
+            "user" => Ok(PageHiddenField::User),
+            "title" => Ok(PageHiddenField::Title),
+            "alt_title" => Ok(PageHiddenField::AltTitle),
+            "slug" => Ok(PageHiddenField::Slug),
+            "tags" => Ok(PageHiddenField::Tags),
+            _ => Err(ServiceError::InvalidEnumValue),
+        }
+    }
+}
+
+#[derive(
+    EnumIter,
+    Serialize,
+    Deserialize,
+    Debug,
+    Copy,
+    Clone,
+    Hash,
+    PartialOrd,
+    Ord,
+    PartialEq,
+    Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum FileHiddenField {
+    Comments,
+    User,
+    Name,
+    S3Hash,
+    Mime,
+    Size,
+    Licensing,
+}
+
+impl FileHiddenField {
+    pub fn name(self) -> &'static str {
+        match self {
+            FileHiddenField::Comments => "comments",
+            FileHiddenField::User => "user",
+            FileHiddenField::Name => "name",
+            FileHiddenField::S3Hash => "s3_hash",
+            FileHiddenField::Mime => "mime",
+            FileHiddenField::Size => "size",
+            FileHiddenField::Licensing => "licensing",
+        }
+    }
+
+    /// Whether this field is part of the file's contents.
+    ///
+    /// See `PageHiddenField::is_content()`.
+    pub fn is_content(self) -> bool {
+        !matches!(self, FileHiddenField::Comments | FileHiddenField::User)
+    }
+}
+
+impl FromStr for FileHiddenField {
+    type Err = ServiceError;
+
+    fn from_str(value: &str) -> Result<FileHiddenField, ServiceError> {
+        match value {
+            "comments" => Ok(FileHiddenField::Comments),
+            "user" => Ok(FileHiddenField::User),
+            "name" => Ok(FileHiddenField::Name),
+            "s3_hash" => Ok(FileHiddenField::S3Hash),
+            "mime" => Ok(FileHiddenField::Mime),
+            "size" => Ok(FileHiddenField::Size),
+            "licensing" => Ok(FileHiddenField::Licensing),
+            _ => Err(ServiceError::InvalidEnumValue),
+        }
+    }
+}
+
+/// Parses the `hidden` column of a revision into its fields.
+pub fn parse_hidden_fields<T>(hidden: &[String]) -> Result<Vec<T>, ServiceError>
+where
+    T: FromStr<Err = ServiceError>,
+{
+    hidden.iter().map(|field| field.parse()).collect()
+}
+
+/// Converts hidden fields into the form stored in the `hidden` column.
+///
+/// The output is sorted and deduplicated.
+pub fn hidden_fields_column<T, F>(fields: &[T], name: F) -> Vec<String>
+where
+    T: Ord + Copy,
+    F: Fn(T) -> &'static str,
+{
+    let mut fields = fields.to_vec();
+    fields.sort();
+    fields.dedup();
+    fields.into_iter().map(|field| str!(name(field))).collect()
+}
+
+/// Ensure `PageHiddenField::name()` produces the same output as serde.
+#[test]
+fn page_name_serde() {
+    use strum::IntoEnumIterator;
+
+    for variant in PageHiddenField::iter() {
+        let output = serde_json::to_string(&variant).expect("Unable to serialize JSON");
+        let serde_name: String =
+            serde_json::from_str(&output).expect("Unable to deserialize JSON");
+
+        assert_eq!(
+            &serde_name,
+            variant.name(),
+            "Serde name does not match variant name",
+        );
+
+        let converted: PageHiddenField =
+            serde_name.as_str().parse().expect("Could not convert item");
+
+        assert_eq!(converted, variant, "Converted item does not match variant");
+    }
+}
+
+/// Ensure `FileHiddenField::name()` produces the same output as serde.
+#[test]
+fn file_name_serde() {
+    use strum::IntoEnumIterator;
+
+    for variant in FileHiddenField::iter() {
+        let output = serde_json::to_string(&variant).expect("Unable to serialize JSON");
+        let serde_name: String =
+            serde_json::from_str(&output).expect("Unable to deserialize JSON");
+
+        assert_eq!(
+            &serde_name,
+            variant.name(),
+            "Serde name does not match variant name",
+        );
+
+        let converted: FileHiddenField =
+            serde_name.as_str().parse().expect("Could not convert item");
+
+        assert_eq!(converted, variant, "Converted item does not match variant");
+    }
+}
+
+#[test]
+fn column_conversion() {
+    let column = hidden_fields_column(
+        &[
+            PageHiddenField::Tags,
+            PageHiddenField::Wikitext,
+            PageHiddenField::Tags,
+        ],
+        PageHiddenField::name,
+    );
+
+    assert_eq!(column, ["wikitext", "tags"]);
+
+    let fields: Vec<PageHiddenField> =
+        parse_hidden_fields(&column).expect("Unable to parse hidden fields");
+
+    assert_eq!(fields, [PageHiddenField::Wikitext, PageHiddenField::Tags]);
+
+    let result: Result<Vec<FileHiddenField>, _> = parse_hidden_fields(&column);
+    assert!(result.is_err(), "Page fields parsed as file fields");
+}
@@ -22,20 +22,44 @@
 
 mod bytes;
 mod connection_type;
+mod deletion_policy;
 mod fetch_direction;
 mod file_details;
+mod file_licensing;
 mod file_order;
+mod hidden_field;
 mod maybe;
+mod oidc_provider;
 mod page_details;
 mod page_order;
+mod pagination;
+mod rate_limit;
 mod reference;
+mod scanner_backend;
+mod session_binding;
+mod storage_backend;
 
 pub use self::bytes::Bytes;
 pub use self::connection_type::ConnectionType;
+pub use self::deletion_policy::{DeletionPolicies, DeletionPolicy};
 pub use self::fetch_direction::FetchDirection;
 pub use self::file_details::FileDetails;
+pub use self::file_licensing::FileLicensing;
 pub use self::file_order::{FileOrder, FileOrderColumn};
+pub use self::hidden_field::{
+    hidden_fields_column, parse_hidden_fields, FileHiddenField, PageHiddenField,
+};
 pub use self::maybe::Maybe;
+pub use self::oidc_provider::OidcProvider;
 pub use self::page_details::PageDetails;
 pub use self::page_order::{PageOrder, PageOrderColumn};
+pub use self::pagination::{
+    cursor_condition, deserialize_limit, Pagination, DEFAULT_LIMIT, MAXIMUM_LIMIT,
+};
+pub use self::rate_limit::RateLimit;
 pub use self::reference::Reference;
+pub use self::scanner_backend::ScannerBackend;
+pub use self::session_binding::{
+    ip_network, parse_inet, SessionBinding, SessionBindingAction,
+};
+pub use self::storage_backend::StorageBackend;
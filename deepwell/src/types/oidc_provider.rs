@@ -0,0 +1,53 @@
+/*
+ * types/oidc_provider.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/// An external OpenID Connect identity provider users can log in with.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct OidcProvider {
+    /// The internal name for this provider.
+    ///
+    /// This is stored with each linked identity, so it should
+    /// not be changed once users have signed in with it.
+    pub name: String,
+
+    /// The name of this provider as shown to users.
+    pub display_name: String,
+
+    /// The issuer URL, exactly as it appears in ID tokens.
+    ///
+    /// The provider's metadata is discovered from
+    /// `{issuer}/.well-known/openid-configuration`.
+    pub issuer: String,
+
+    pub client_id: String,
+    pub client_secret: String,
+
+    /// Where the provider sends users back to after they authenticate.
+    pub redirect_uri: String,
+
+    /// The scopes to request. Must include `openid`.
+    pub scopes: Vec<String>,
+
+    /// Whether new accounts can be created by signing in with this provider.
+    ///
+    /// Otherwise the identity must first be linked to an existing account.
+    pub allow_signup: bool,
+}
@@ -0,0 +1,180 @@
+/*
+ * types/pagination.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use sea_orm::query::Order;
+use sea_orm::{ColumnTrait, Condition, Value};
+use serde::{Deserialize, Deserializer};
+
+/// How many items are returned to API callers which do not specify a limit.
+pub const DEFAULT_LIMIT: u64 = 100;
+
+/// The largest number of items API callers may request at once.
+pub const MAXIMUM_LIMIT: u64 = 500;
+
+/// Describes which slice of a list of items should be retrieved.
+///
+/// This is cursor-based: the `cursor` is the ID of the last item
+/// in the previous batch, and only items which come after it in the
+/// requested order are returned. Unlike offsets, this remains stable
+/// if items are added or removed between calls.
+///
+/// If `limit` is `None`, then all remaining items are returned.
+/// This is only possible for internal callers, when deserialized
+/// the limit defaults to `DEFAULT_LIMIT` and is capped at `MAXIMUM_LIMIT`.
+#[derive(Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Pagination {
+    pub cursor: Option<i64>,
+
+    #[serde(
+        default = "default_limit",
+        deserialize_with = "deserialize_optional_limit"
+    )]
+    pub limit: Option<u64>,
+}
+
+#[inline]
+fn default_limit() -> Option<u64> {
+    Some(DEFAULT_LIMIT)
+}
+
+fn deserialize_optional_limit<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let limit = Option::<u64>::deserialize(deserializer)?.unwrap_or(DEFAULT_LIMIT);
+    Ok(Some(limit.min(MAXIMUM_LIMIT)))
+}
+
+/// Deserializes a required limit, capping it at `MAXIMUM_LIMIT`.
+pub fn deserialize_limit<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let limit = u64::deserialize(deserializer)?;
+    Ok(limit.min(MAXIMUM_LIMIT))
+}
+
+/// Builds the condition selecting rows after the cursor row.
+///
+/// Rows are ordered by `column`, then by `id_column` to break ties,
+/// both in the given direction. PostgreSQL sorts `NULL` values last
+/// in ascending order and first in descending order, so nullable
+/// columns need to be accounted for here.
+///
+/// The `value` is the value of `column` in the cursor row, and `id`
+/// is the value of `id_column`.
+pub fn cursor_condition<C: ColumnTrait>(
+    column: C,
+    id_column: C,
+    direction: &Order,
+    value: Value,
+    id: i64,
+) -> Condition {
+    // Value has no direct null check, but a null value is
+    // the same as its nulled form.
+    let is_null = value == value.as_null();
+
+    match (direction, is_null) {
+        (Order::Desc, false) => Condition::any()
+            .add(column.lt(value.clone()))
+            .add(Condition::all().add(column.eq(value)).add(id_column.lt(id))),
+        (Order::Desc, true) => Condition::any()
+            .add(column.is_not_null())
+            .add(Condition::all().add(column.is_null()).add(id_column.lt(id))),
+        (_, false) => Condition::any()
+            .add(column.gt(value.clone()))
+            .add(column.is_null())
+            .add(Condition::all().add(column.eq(value)).add(id_column.gt(id))),
+        (_, true) => Condition::all().add(column.is_null()).add(id_column.gt(id)),
+    }
+}
+
+#[test]
+fn cursor_conditions() {
+    use crate::models::page::{self, Entity as Page};
+    use sea_orm::{DbBackend, EntityTrait, QueryFilter, QueryTrait};
+
+    macro_rules! check {
+        ($direction:expr, $value:expr, $expected:expr $(,)?) => {{
+            let condition = cursor_condition(
+                page::Column::DeletedAt,
+                page::Column::PageId,
+                &$direction,
+                $value,
+                10,
+            );
+
+            let query = Page::find()
+                .filter(condition)
+                .build(DbBackend::Postgres)
+                .to_string();
+
+            let (_, actual) = query.split_once(" WHERE ").expect("No WHERE clause");
+            assert_eq!(actual, $expected, "Actual condition doesn't match expected");
+        }};
+    }
+
+    check!(
+        Order::Asc,
+        Value::BigInt(Some(5)),
+        r#""page"."deleted_at" > 5 OR "page"."deleted_at" IS NULL OR ("page"."deleted_at" = 5 AND "page"."page_id" > 10)"#,
+    );
+    check!(
+        Order::Asc,
+        Value::BigInt(None),
+        r#""page"."deleted_at" IS NULL AND "page"."page_id" > 10"#,
+    );
+    check!(
+        Order::Desc,
+        Value::BigInt(Some(5)),
+        r#""page"."deleted_at" < 5 OR ("page"."deleted_at" = 5 AND "page"."page_id" < 10)"#,
+    );
+    check!(
+        Order::Desc,
+        Value::BigInt(None),
+        r#""page"."deleted_at" IS NOT NULL OR ("page"."deleted_at" IS NULL AND "page"."page_id" < 10)"#,
+    );
+}
+
+#[test]
+fn pagination_limits() {
+    use serde_json::json;
+
+    macro_rules! check {
+        ($input:expr, $expected:expr $(,)?) => {{
+            let pagination: Pagination =
+                serde_json::from_value($input).expect("Unable to deserialize");
+            assert_eq!(
+                pagination.limit, $expected,
+                "Actual limit doesn't match expected"
+            );
+        }};
+    }
+
+    check!(json!({}), Some(DEFAULT_LIMIT));
+    check!(json!({ "limit": null }), Some(DEFAULT_LIMIT));
+    check!(json!({ "limit": 20 }), Some(20));
+    check!(json!({ "limit": MAXIMUM_LIMIT + 1 }), Some(MAXIMUM_LIMIT));
+    check!(json!({ "cursor": 4 }), Some(DEFAULT_LIMIT));
+
+    // Internal callers can still list everything
+    assert_eq!(Pagination::default().limit, None);
+}
@@ -0,0 +1,43 @@
+/*
+ * types/rate_limit.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::time::Duration as StdDuration;
+
+/// A limit on how often an action may be performed.
+///
+/// Attempts are counted over a sliding window, so once the limit
+/// is reached, further attempts are allowed again gradually as
+/// earlier attempts age out of the window.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct RateLimit {
+    /// How many attempts are permitted within the window.
+    pub attempts: u32,
+
+    /// The length of the sliding window, in seconds.
+    pub window_secs: u64,
+}
+
+impl RateLimit {
+    #[inline]
+    pub fn window(self) -> StdDuration {
+        StdDuration::from_secs(self.window_secs)
+    }
+}
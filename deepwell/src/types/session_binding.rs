@@ -0,0 +1,110 @@
+/*
+ * types/session_binding.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// How strictly a session is tied to where it was created.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SessionBinding {
+    /// The IP address and user agent must both match exactly.
+    Strict,
+
+    /// The IP address must be in the same subnet, and the user agent must match.
+    Subnet,
+
+    /// Sessions can be used from anywhere.
+    Off,
+}
+
+/// What happens to a session when used from somewhere it is not bound to.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SessionBindingAction {
+    /// The session becomes restricted, and must be verified with MFA to be used again.
+    Restrict,
+
+    /// The session is deleted, requiring the user to log in again.
+    Invalidate,
+}
+
+/// Gets the network an IP address is in, as a CIDR string.
+///
+/// IPv4-mapped IPv6 addresses are treated as IPv4 addresses.
+pub fn ip_network(ip_address: IpAddr, ipv4_prefix: u8, ipv6_prefix: u8) -> String {
+    match ip_address.to_canonical() {
+        IpAddr::V4(address) => {
+            let prefix = ipv4_prefix.min(32);
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            let network = Ipv4Addr::from(u32::from(address) & mask);
+            format!("{network}/{prefix}")
+        }
+        IpAddr::V6(address) => {
+            let prefix = ipv6_prefix.min(128);
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            let network = Ipv6Addr::from(u128::from(address) & mask);
+            format!("{network}/{prefix}")
+        }
+    }
+}
+
+/// Parses an IP address from PostgreSQL's text form of `INET`.
+///
+/// This may have a netmask suffix (e.g. `/32`) which is ignored.
+pub fn parse_inet(value: &str) -> Option<IpAddr> {
+    let address = match value.split_once('/') {
+        Some((address, _)) => address,
+        None => value,
+    };
+
+    address.parse().ok()
+}
+
+#[test]
+fn networks() {
+    macro_rules! check {
+        ($address:expr, $expected:expr $(,)?) => {{
+            let address = $address.parse().expect("Invalid IP address");
+            let actual = ip_network(address, 24, 48);
+            assert_eq!(actual, $expected, "Actual network doesn't match expected");
+        }};
+    }
+
+    check!("203.0.113.57", "203.0.113.0/24");
+    check!("10.1.2.3", "10.1.2.0/24");
+    check!("::ffff:203.0.113.57", "203.0.113.0/24");
+    check!("2001:db8:abcd:12::1", "2001:db8:abcd::/48");
+    check!("::1", "::/48");
+
+    let address = "198.51.100.7".parse().unwrap();
+    assert_eq!(ip_network(address, 32, 128), "198.51.100.7/32");
+    assert_eq!(ip_network(address, 0, 0), "0.0.0.0/0");
+}
+
+#[test]
+fn inet() {
+    let expected: IpAddr = "198.51.100.7".parse().unwrap();
+    assert_eq!(parse_inet("198.51.100.7/32"), Some(expected));
+    assert_eq!(parse_inet("198.51.100.7"), Some(expected));
+
+    let expected: IpAddr = "2001:db8::1".parse().unwrap();
+    assert_eq!(parse_inet("2001:db8::1/128"), Some(expected));
+    assert_eq!(parse_inet("not an address"), None);
+}
@@ -20,7 +20,9 @@
 
 //! Utilities related to cryptographic operations and primitives.
 
+use data_encoding::BASE64URL_NOPAD;
 use rand::CryptoRng;
+use sha2::{Digest, Sha256};
 
 /// Statically verifies that this random number generator is secure.
 ///
@@ -28,3 +30,18 @@ use rand::CryptoRng;
 /// (cryptographically-secure psuedorandom number generator).
 #[inline]
 pub fn assert_is_csprng(_: &dyn CryptoRng) {}
+
+/// Derives the PKCE code challenge for a code verifier, using the `S256` method.
+///
+/// See <https://www.rfc-editor.org/rfc/rfc7636#section-4.2>.
+pub fn pkce_challenge(code_verifier: &str) -> String {
+    BASE64URL_NOPAD.encode(&Sha256::digest(code_verifier.as_bytes()))
+}
+
+#[test]
+fn pkce() {
+    assert_eq!(
+        pkce_challenge("dBjftJeZ4CVP-mJ92K9Tr2gM7wdJk6qxhIgYXWTh"),
+        "VZ4dfryKHdGyOsuRHGz2IEBGzQd45Op9h3AtU1a3ut8",
+    );
+}
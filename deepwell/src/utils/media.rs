@@ -0,0 +1,135 @@
+/*
+ * utils/media.rs
+ *
+ * DEEPWELL - Wikijump API provider and database manager
+ * Copyright (C) 2019-2025 Wikijump Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, Frames, ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+
+/// The largest width or height of an image which will be decoded for processing.
+pub const MAXIMUM_IMAGE_DIMENSION: u32 = 16384;
+
+/// Gets the image format for a MIME type, if it is a supported image.
+///
+/// The MIME type may have parameters, such as those produced by `MimeAnalyzer`.
+pub fn image_format(mime: &str) -> Option<ImageFormat> {
+    let essence = mime.split(';').next().unwrap_or(mime).trim();
+    ImageFormat::from_mime_type(essence)
+}
+
+/// Decodes an image for processing, returning `None` if it is invalid or too large.
+///
+/// For animated images, only the first frame is decoded.
+pub fn decode_image(data: &[u8], format: ImageFormat) -> Option<DynamicImage> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAXIMUM_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAXIMUM_IMAGE_DIMENSION);
+
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+
+    match reader.decode() {
+        Ok(image) => Some(image),
+        Err(error) => {
+            warn!("Unable to decode {format:?} image: {error}");
+            None
+        }
+    }
+}
+
+/// Gets the total duration of an animated image, in milliseconds.
+///
+/// Returns `None` for still images, or formats which cannot be animated.
+pub fn animation_duration(data: &[u8], format: ImageFormat) -> Option<u32> {
+    fn total(frames: Frames) -> Option<u32> {
+        let mut duration = 0;
+        let mut count = 0;
+        for frame in frames {
+            let (numerator, denominator) = frame.ok()?.delay().numer_denom_ms();
+            duration += numerator / denominator.max(1);
+            count += 1;
+        }
+
+        (count > 1).then_some(duration)
+    }
+
+    let cursor = Cursor::new(data);
+    match format {
+        ImageFormat::Gif => total(GifDecoder::new(cursor).ok()?.into_frames()),
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(cursor).ok()?;
+            if !decoder.has_animation() {
+                return None;
+            }
+
+            total(decoder.into_frames())
+        }
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(cursor).ok()?;
+            if !decoder.is_apng().ok()? {
+                return None;
+            }
+
+            total(decoder.apng().ok()?.into_frames())
+        }
+        _ => None,
+    }
+}
+
+#[test]
+fn media_image_format() {
+    assert_eq!(
+        image_format("image/png; charset=binary"),
+        Some(ImageFormat::Png)
+    );
+    assert_eq!(image_format("image/webp"), Some(ImageFormat::WebP));
+    assert_eq!(image_format("text/plain; charset=us-ascii"), None);
+}
+
+#[test]
+fn media_animation_duration() {
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, Frame, RgbaImage};
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut bytes);
+        for _ in 0..3 {
+            let frame = Frame::from_parts(
+                RgbaImage::new(4, 4),
+                0,
+                0,
+                Delay::from_numer_denom_ms(100, 1),
+            );
+            encoder.encode_frame(frame).expect("Unable to encode frame");
+        }
+    }
+
+    assert_eq!(animation_duration(&bytes, ImageFormat::Gif), Some(300));
+    assert!(decode_image(&bytes, ImageFormat::Gif).is_some());
+
+    let mut still = Vec::new();
+    DynamicImage::new_rgba8(4, 4)
+        .write_to(&mut Cursor::new(&mut still), ImageFormat::Png)
+        .expect("Unable to encode");
+
+    assert_eq!(animation_duration(&still, ImageFormat::Png), None);
+}
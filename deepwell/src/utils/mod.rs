@@ -24,14 +24,18 @@ mod category;
 mod crypto;
 mod debug;
 mod locale;
+mod media;
 mod slug;
 mod string;
+mod task;
 mod time;
 
 pub use self::category::*;
 pub use self::crypto::*;
 pub use self::debug::*;
 pub use self::locale::*;
+pub use self::media::*;
 pub use self::slug::*;
 pub use self::string::*;
+pub use self::task::*;
 pub use self::time::*;
@@ -18,7 +18,13 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use time::OffsetDateTime;
+use time::{Duration, OffsetDateTime};
+
+/// How out of date a "last used" timestamp may be before it is updated.
+///
+/// These are checked on every request, so only writing them when
+/// they are this stale avoids a database write each time.
+pub const LAST_USED_PRECISION: Duration = Duration::minutes(1);
 
 #[inline]
 pub fn now() -> OffsetDateTime {
@@ -27,12 +27,12 @@ declare namespace App {
       revision_type: any
       revision_created_at: string
       revision_number: number
-      revision_user_id: number
+      revision_user_id: number | null
       wikitext: string | null
       compiled_html: string | null
       compiled_at: string
       compiled_generator: string
-      revision_comments: string
+      revision_comments: string | null
       hidden_fields: string[]
       title: string
       alt_title: string | null
@@ -75,13 +75,13 @@ declare namespace App {
       revision_number: number
       page_id: number
       site_id: number
-      user_id: number
+      user_id: number | null
       changes: string[]
       wikitext: string | null
       compiled_html: string | null
       compiled_at: string | null
       compiled_generator: string
-      comments: string
+      comments: string | null
       hidden: string[]
       title: string | null
       alt_title: string | null
@@ -0,0 +1,8 @@
+import type { Handle } from "@sveltejs/kit"
+import { requestToken } from "$lib/server/auth/getSession"
+import { withRequestToken } from "$lib/server/deepwell"
+
+/** Sends the token of each request with the DEEPWELL calls made to handle it. */
+export const handle: Handle = async ({ event, resolve }) => {
+  return withRequestToken(requestToken(event), () => resolve(event))
+}
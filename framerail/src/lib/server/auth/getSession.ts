@@ -1,5 +1,37 @@
 import { client } from "$lib/server/deepwell"
 
-export async function authGetSession(sessionToken: string | undefined): Promise<object> {
-  return client.request("session_get", [sessionToken ?? ""])
+/**
+ * Gets the token a request was made with.
+ *
+ * Browsers use the session cookie, while bots and OAuth clients
+ * send their token in the Authorization header instead.
+ */
+export function requestToken(event): string | undefined {
+  let header = event.request.headers.get("Authorization")
+  if (header?.startsWith("Bearer ")) return header.slice("Bearer ".length).trim()
+
+  return event.cookies.get("wikijump_token")
+}
+
+/**
+ * Gets the session for this request.
+ *
+ * If a bot or OAuth client is using a scoped token instead, then `scope` is
+ * what the request will do (e.g. "edit-pages@42"), which the token must grant.
+ */
+export async function authGetSession(
+  sessionToken: string | undefined,
+  ipAddress: string,
+  userAgent: string | null,
+  scope?: string
+): Promise<object | null> {
+  const session = await client.request("session_get", {
+    session_token: sessionToken ?? "",
+    ip_address: ipAddress,
+    user_agent: userAgent ?? "",
+    scope: scope ?? null
+  })
+
+  // Restricted sessions can only be used to finish logging in
+  return session?.restricted ? null : session
 }
@@ -0,0 +1,131 @@
+import { client } from "$lib/server/deepwell"
+
+/** The parameters of an authorization request, from its query string. */
+export const AUTHORIZATION_PARAMS = [
+  "response_type",
+  "client_id",
+  "redirect_uri",
+  "scope",
+  "state",
+  "code_challenge",
+  "code_challenge_method"
+]
+
+// Errors from DEEPWELL which can be reported to the client, by their code
+const OAUTH_ERRORS: Record<number, [string, number]> = {
+  4035: ["invalid_request", 400],
+  4036: ["invalid_scope", 400],
+  5006: ["invalid_client", 401],
+  5007: ["invalid_grant", 400]
+}
+
+export function authorizationRequest(
+  params: URLSearchParams | FormData
+): Record<string, string | null> {
+  let request: Record<string, string | null> = {}
+  for (let key of AUTHORIZATION_PARAMS) {
+    request[key] = params.get(key)?.toString() ?? null
+  }
+
+  return request
+}
+
+export async function oauthAuthorizeStart(
+  request: Record<string, string | null>,
+  userId: number,
+  sessionToken: string
+): Promise<object> {
+  return client.request("oauth_authorize_start", {
+    ...request,
+    user_id: userId,
+    session_token: sessionToken
+  })
+}
+
+export async function oauthAuthorizeFinish(
+  request: Record<string, string | null>,
+  userId: number,
+  sessionToken: string,
+  approve: boolean
+): Promise<object> {
+  return client.request("oauth_authorize_finish", {
+    ...request,
+    user_id: userId,
+    session_token: sessionToken,
+    approve
+  })
+}
+
+/**
+ * Gets the URL to send the user back to the client with an error.
+ *
+ * DEEPWELL only returns these errors once it has checked the redirect URI,
+ * otherwise there is nowhere safe to send the user, so this yields null.
+ */
+export function authorizationErrorUrl(
+  request: Record<string, string | null>,
+  err
+): string | null {
+  let [error] = OAUTH_ERRORS[err.code] ?? []
+  if (!error || error === "invalid_client" || !request.redirect_uri) return null
+
+  let url = new URL(request.redirect_uri)
+  url.searchParams.set("error", error)
+  if (request.state) url.searchParams.set("state", request.state)
+  return url.toString()
+}
+
+/**
+ * Reads the request body from a client, along with its credentials.
+ *
+ * Clients may authenticate with HTTP basic authentication,
+ * or by including their credentials in the body.
+ */
+export async function clientRequest(request: Request): Promise<Record<string, string>> {
+  let data = await request.formData()
+  let body: Record<string, string> = {}
+  for (let [key, value] of data.entries()) {
+    body[key] = value.toString()
+  }
+
+  let header = request.headers.get("Authorization")
+  if (header?.startsWith("Basic ")) {
+    let credentials = Buffer.from(header.slice("Basic ".length), "base64").toString()
+    let [clientId, clientSecret] = credentials.split(":", 2)
+    body.client_id = decodeURIComponent(clientId)
+    if (clientSecret) body.client_secret = decodeURIComponent(clientSecret)
+  }
+
+  return body
+}
+
+/** Responds to a client, per RFC 6749 section 5. */
+export function clientResponse(body: object, status = 200): Response {
+  return new Response(JSON.stringify(body), {
+    status,
+    headers: {
+      "Content-Type": "application/json",
+      "Cache-Control": "no-store",
+      "Pragma": "no-cache"
+    }
+  })
+}
+
+export function clientErrorResponse(err): Response {
+  let [error, status] = OAUTH_ERRORS[err.code] ?? ["invalid_request", 400]
+  return clientResponse({ error, error_description: err.message }, status)
+}
+
+export async function oauthToken(body: Record<string, string>): Promise<object> {
+  return client.request("oauth_token", body)
+}
+
+export async function oauthTokenIntrospect(
+  body: Record<string, string>
+): Promise<object> {
+  return client.request("oauth_token_introspect", body)
+}
+
+export async function oauthTokenRevoke(body: Record<string, string>): Promise<void> {
+  return client.request("oauth_token_revoke", body)
+}
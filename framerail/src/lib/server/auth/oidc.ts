@@ -0,0 +1,23 @@
+import { client } from "$lib/server/deepwell"
+
+export async function authOidcLoginStart(provider: string): Promise<object> {
+  return client.request("oidc_login_start", { provider })
+}
+
+export async function authOidcFinish(
+  state: string,
+  code: string,
+  cookieState: string,
+  ipAddress: string,
+  userAgent: string | null,
+  locales: string[]
+): Promise<object> {
+  return client.request("oidc_finish", {
+    state,
+    code,
+    cookie_state: cookieState,
+    ip_address: ipAddress,
+    user_agent: userAgent ?? "",
+    locales
+  })
+}
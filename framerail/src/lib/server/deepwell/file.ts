@@ -1,15 +1,29 @@
 import { client } from "$lib/server/deepwell"
 
-export async function getFileByHash(
+/** Gets a presigned download URL and metadata for a blob. */
+export async function getBlobByHash(
   /** Either a Uint8Array or a hex string */
   fileHash: Uint8Array | string
-): Promise<Blob> {
-  let res = await client.request(
+): Promise<{ url: string; mime: string; size: number; created_at: string }> {
+  return await client.request(
     "blob_get",
     typeof fileHash === "string" ? fileHash : Buffer.from(fileHash).toString("hex")
   )
+}
 
-  return new Blob([new Uint8Array(res.data)], { type: res.mime })
+/** Gets a download URL for an image file as referenced by ftml, optionally a thumbnail. */
+export async function getImageUrl(
+  domain: string,
+  pageSlug: string,
+  filename: string,
+  size: number | null
+): Promise<string | null> {
+  return await client.request("file_image_url", {
+    domain,
+    page_slug: pageSlug,
+    filename,
+    size
+  })
 }
 
 export async function startBlobUpload(userId: number, blobSize: number) {
@@ -26,9 +40,103 @@ export async function cancelBlobUpload(userId: number, pendingBlobId: string) {
   })
 }
 
-export async function uploadToPresignUrl(url: string, file: File) {
+export async function uploadToPresignUrl(url: string, file: Blob) {
   return await fetch(url, {
     method: "PUT",
     body: file
   })
 }
+
+/** Reads a chunk of a blob through a presigned URL, when using local storage */
+export async function localBlobGet(
+  path: string,
+  query: Record<string, string>,
+  offset: number
+): Promise<{
+  data: Buffer
+  total_size: number
+  content_type: string
+  cache_control: string | null
+  content_disposition: string | null
+}> {
+  let res = await client.request("blob_local_get", { path, query, offset })
+  return { ...res, data: Buffer.from(res.data, "hex") }
+}
+
+/** Writes a chunk of a blob through a presigned URL, when using local storage */
+export async function localBlobPut(
+  path: string,
+  query: Record<string, string>,
+  offset: number,
+  data: Uint8Array,
+  last: boolean
+): Promise<{ etag: string | null }> {
+  return await client.request("blob_local_put", {
+    path,
+    query,
+    offset,
+    data: Buffer.from(data).toString("hex"),
+    last
+  })
+}
+
+/** Files larger than this are uploaded in multiple parts */
+const MULTIPART_THRESHOLD = 64 * 1024 * 1024
+
+/** How many times to try uploading each part before giving up */
+const MULTIPART_ATTEMPTS = 3
+
+/**
+ * Uploads a file, returning the ID of the pending blob.
+ * Large files are uploaded in parts, so failed parts can be retried.
+ */
+export async function uploadBlob(userId: number, file: File): Promise<string> {
+  if (file.size <= MULTIPART_THRESHOLD) {
+    let presign = await startBlobUpload(userId, file.size)
+    await uploadToPresignUrl(presign.presign_url, file)
+    return presign.pending_blob_id
+  }
+
+  let upload = await client.request("blob_multipart_upload", {
+    user_id: userId,
+    blob_size: file.size
+  })
+
+  try {
+    for (let partNumber = 1; partNumber <= upload.part_count; partNumber++) {
+      const start = (partNumber - 1) * upload.part_size
+      const chunk = file.slice(start, start + upload.part_size)
+      let etag = null
+
+      for (let attempt = 0; attempt < MULTIPART_ATTEMPTS && etag === null; attempt++) {
+        let part = await client.request("blob_multipart_part", {
+          user_id: userId,
+          pending_blob_id: upload.pending_blob_id,
+          part_number: partNumber
+        })
+
+        let res = await uploadToPresignUrl(part.presign_url, chunk)
+        if (res.ok) etag = res.headers.get("ETag")
+      }
+
+      if (etag === null) throw new Error(`Unable to upload part ${partNumber}`)
+
+      await client.request("blob_multipart_part_finish", {
+        user_id: userId,
+        pending_blob_id: upload.pending_blob_id,
+        part_number: partNumber,
+        etag
+      })
+    }
+
+    await client.request("blob_multipart_complete", {
+      user_id: userId,
+      pending_blob_id: upload.pending_blob_id
+    })
+  } catch (error) {
+    await cancelBlobUpload(userId, upload.pending_blob_id)
+    throw error
+  }
+
+  return upload.pending_blob_id
+}
@@ -1,5 +1,6 @@
 // TODO refactor into proper TS service
 
+import { AsyncLocalStorage } from "node:async_hooks"
 import { JSONRPCClient, type JSONRPCRequest } from "json-rpc-2.0"
 
 export const DEEPWELL_HOST = process.env.DEEPWELL_HOST || "localhost"
@@ -7,7 +8,28 @@ export const DEEPWELL_PORT = 2747
 export const DEEPWELL_URL = `http://${DEEPWELL_HOST}:${DEEPWELL_PORT}/jsonrpc`
 export const client = new JSONRPCClient(processRawRequest)
 
+/**
+ * The token of the request currently being handled, if any.
+ *
+ * It is sent along with every DEEPWELL request made while handling it,
+ * so that DEEPWELL can check scoped tokens grant what they are used for.
+ */
+const requestToken = new AsyncLocalStorage<string | undefined>()
+
+/** Makes DEEPWELL requests within this function on behalf of this token. */
+export function withRequestToken<T>(
+  token: string | undefined,
+  fn: () => Promise<T>
+): Promise<T> {
+  return requestToken.run(token, fn)
+}
+
 async function processRawRequest(request: JSONRPCRequest): Promise<void> {
+  const token = requestToken.getStore()
+  if (token && request.params && !Array.isArray(request.params)) {
+    request = { ...request, params: { ...request.params, scoped_token: token } }
+  }
+
   const response = await fetch(DEEPWELL_URL, {
     method: "POST",
     headers: { "content-type": "application/json" },
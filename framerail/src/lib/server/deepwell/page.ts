@@ -73,7 +73,8 @@ export async function pageMove(
   slug: string,
   lastRevisionId: number,
   newSlug: string,
-  revisionComments: Optional<string>
+  revisionComments: Optional<string>,
+  leaveRedirect?: boolean
 ): Promise<object> {
   return client.request("page_move", {
     site_id: siteId,
@@ -81,7 +82,8 @@ export async function pageMove(
     new_slug: newSlug,
     user_id: userId,
     last_revision_id: lastRevisionId,
-    revision_comments: revisionComments
+    revision_comments: revisionComments,
+    leave_redirect: leaveRedirect ?? false
   })
 }
 
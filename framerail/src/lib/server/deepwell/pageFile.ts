@@ -1,8 +1,31 @@
 import defaults from "$lib/defaults"
 import { client } from "$lib/server/deepwell"
-import { startBlobUpload, uploadToPresignUrl } from "$lib/server/deepwell/file"
+import { uploadBlob } from "$lib/server/deepwell/file"
 import type { Optional } from "$lib/types"
 
+export interface FileLicensing {
+  /** SPDX identifier, such as "CC-BY-SA-3.0" */
+  license: string | null
+  author: string | null
+  source_url: string | null
+  derivative_of: string | null
+  unknown: boolean
+}
+
+/** Reads licensing fields from a file form. No license means it is unknown. */
+export function licensingFromForm(data: FormData): FileLicensing {
+  let field = (name: string) => data.get(name)?.toString().trim() || null
+  let license = field("license")
+
+  return {
+    license,
+    author: field("author"),
+    source_url: field("source-url"),
+    derivative_of: field("derivative-of"),
+    unknown: license === null
+  }
+}
+
 export async function pageFileList(
   siteId: number,
   pageId: number,
@@ -18,17 +41,16 @@ export async function pageFileList(
 export async function pageFileGet(
   siteId: number,
   pageId: number,
-  userId: number,
-  fileId: number,
-  /** Also request the contents of the file */
-  data: boolean
+  /** The file ID or name */
+  file: number | string,
+  /** Also request a download URL and image thumbnails for the file */
+  details: boolean
 ) {
   return await client.request("file_get", {
     site_id: siteId,
     page_id: pageId,
-    user_id: userId,
-    file: fileId,
-    data
+    file,
+    details: { url: details, media: details }
   })
 }
 
@@ -38,11 +60,10 @@ export async function pageFileCreate(
   userId: number,
   name: Optional<string>,
   file: File,
-  licensing: any,
+  licensing: FileLicensing,
   revisionComments: Optional<string>
 ) {
-  let presign = await startBlobUpload(userId, file.size)
-  await uploadToPresignUrl(presign.presign_url, file)
+  let pendingBlobId = await uploadBlob(userId, file)
 
   return await client.request("file_create", {
     site_id: siteId,
@@ -50,7 +71,7 @@ export async function pageFileCreate(
     user_id: userId,
     name: name ?? file.name,
     licensing,
-    uploaded_blob_id: presign.pending_blob_id,
+    uploaded_blob_id: pendingBlobId,
     revision_comments: revisionComments
   })
 }
@@ -80,15 +101,13 @@ export async function pageFileEdit(
   fileId: string,
   name: string,
   file: Optional<File>,
-  licensing: Optional<any>,
+  licensing: Optional<FileLicensing>,
   lastRevisionId: number,
   revisionComments: Optional<string>
 ) {
   let presignId = undefined
   if (file && file instanceof File) {
-    let presign = await startBlobUpload(userId, file.size)
-    await uploadToPresignUrl(presign.presign_url, file)
-    presignId = presign.pending_blob_id
+    presignId = await uploadBlob(userId, file)
   }
 
   return await client.request("file_edit", {
@@ -126,6 +145,68 @@ export async function pageFileMove(
   })
 }
 
+export async function pageFileCopy(
+  siteId: number,
+  pageId: number,
+  destinationSiteId: number,
+  destinationPage: string | number,
+  userId: number,
+  fileId: string,
+  name: Optional<string>,
+  revisionComments: Optional<string>
+) {
+  return await client.request("file_copy", {
+    site_id: siteId,
+    page_id: pageId,
+    file: fileId,
+    destination_site_id: destinationSiteId,
+    destination_page: destinationPage,
+    user_id: userId,
+    name,
+    revision_comments: revisionComments
+  })
+}
+
+/** Creates a file on the page for each file in a zip or tar archive. */
+export async function pageFileArchiveImport(
+  siteId: number,
+  pageId: number,
+  userId: number,
+  archive: File,
+  licensing: FileLicensing,
+  revisionComments: Optional<string>
+) {
+  let pendingBlobId = await uploadBlob(userId, archive)
+
+  return await client.request("file_archive_import", {
+    site_id: siteId,
+    page_id: pageId,
+    user_id: userId,
+    licensing,
+    uploaded_blob_id: pendingBlobId,
+    revision_comments: revisionComments
+  })
+}
+
+/** Requests a zip archive of the page's files, which is built in the background. */
+export async function pageFileArchiveCreate(
+  siteId: number,
+  pageId: number,
+  userId: number
+) {
+  return await client.request("file_archive_create", {
+    site_id: siteId,
+    page_id: pageId,
+    user_id: userId
+  })
+}
+
+export async function pageFileArchiveGet(fileArchiveId: number): Promise<object> {
+  return client.request("file_archive_get", {
+    file_archive_id: fileArchiveId
+  })
+}
+
 export async function pageFileRestore(
   siteId: number,
   pageId: number,
@@ -0,0 +1,22 @@
+import { client } from "$lib/server/deepwell"
+import type { Optional } from "$lib/types"
+
+export async function siteFromDomain(domain: string): Promise<Record<string, any> | null> {
+  return client.request("site_from_domain", [domain])
+}
+
+export async function siteRecentChangesFeed(
+  siteId: number,
+  format: "atom" | "rss",
+  limit: number,
+  category: Optional<string>,
+  user: Optional<string>
+): Promise<{ mime: string; body: string }> {
+  return client.request("site_recent_changes_feed", {
+    site_id: siteId,
+    format,
+    limit,
+    category,
+    user
+  })
+}
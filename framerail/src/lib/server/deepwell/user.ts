@@ -6,11 +6,15 @@ export async function userView(
   domain: string,
   locales: string[],
   sessionToken: Optional<string>,
+  ipAddress: string,
+  userAgent: Optional<string>,
   username?: string
 ): Promise<object> {
   return client.request("user_view", {
     domain,
     session_token: sessionToken,
+    ip_address: ipAddress,
+    user_agent: userAgent ?? "",
     locales,
     user: username
   })
@@ -10,12 +10,16 @@ export async function pageView(
   domain: string,
   locales: string[],
   route: Optional<PageRoute>,
-  sessionToken: Optional<string>
+  sessionToken: Optional<string>,
+  ipAddress: string,
+  userAgent: Optional<string>
 ): Promise<object> {
   return client.request("page_view", {
     domain,
     locales,
     session_token: sessionToken,
+    ip_address: ipAddress,
+    user_agent: userAgent ?? "",
     route
   })
 }
@@ -23,11 +27,15 @@ export async function pageView(
 export async function adminView(
   domain: string,
   locales: string[],
-  sessionToken: Optional<string>
+  sessionToken: Optional<string>,
+  ipAddress: string,
+  userAgent: Optional<string>
 ): Promise<object> {
   return client.request("admin_view", {
     domain,
     locales,
-    session_token: sessionToken
+    session_token: sessionToken,
+    ip_address: ipAddress,
+    user_agent: userAgent ?? ""
   })
 }
@@ -5,15 +5,16 @@ import { adminView } from "$lib/server/deepwell/views"
 import type { TranslateKeys } from "$lib/types"
 import { error } from "@sveltejs/kit"
 
-export async function loadAdminPage(request, cookies) {
+export async function loadAdminPage(request, cookies, ipAddress: string) {
   const url = new URL(request.url)
   const domain = url.hostname
   const sessionToken = cookies.get("wikijump_token")
+  const userAgent = request.headers.get("User-Agent")
   let locales = parseAcceptLangHeader(request)
 
   if (!locales.includes(defaults.fallbackLocale)) locales.push(defaults.fallbackLocale)
 
-  const response = await adminView(domain, locales, sessionToken)
+  const response = await adminView(domain, locales, sessionToken, ipAddress, userAgent)
 
   let translateKeys: TranslateKeys = {
     ...defaults.translateKeys
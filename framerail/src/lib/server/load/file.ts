@@ -0,0 +1,22 @@
+import { pageFileGet } from "$lib/server/deepwell/pageFile"
+import { loadPage } from "$lib/server/load/page"
+import { error } from "@sveltejs/kit"
+
+/** Loads a page along with one of its files, for the file's own page. */
+export async function loadFile(
+  slug: string,
+  name: string,
+  request,
+  cookies,
+  ipAddress: string
+) {
+  const viewData = await loadPage(slug, null, request, cookies, ipAddress)
+  const file = await pageFileGet(viewData.site.site_id, viewData.page.page_id, name, true)
+
+  if (file === null) {
+    error(404, viewData)
+  }
+
+  viewData.file = file
+  return viewData
+}
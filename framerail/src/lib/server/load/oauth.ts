@@ -0,0 +1,64 @@
+import defaults from "$lib/defaults"
+import { parseAcceptLangHeader } from "$lib/locales"
+import { authGetSession } from "$lib/server/auth/getSession"
+import {
+  authorizationErrorUrl,
+  authorizationRequest,
+  oauthAuthorizeStart
+} from "$lib/server/auth/oauth"
+import { translate } from "$lib/server/deepwell/translate"
+import type { TranslateKeys } from "$lib/types"
+import { error, redirect } from "@sveltejs/kit"
+
+export async function loadOauthAuthorizePage(request, cookies, ipAddress: string) {
+  // Set up parameters
+  const url = new URL(request.url)
+  const sessionToken = cookies.get("wikijump_token")
+  const userAgent = request.headers.get("User-Agent")
+  const oauthRequest = authorizationRequest(url.searchParams)
+  let locales = parseAcceptLangHeader(request)
+
+  if (!locales.includes(defaults.fallbackLocale)) locales.push(defaults.fallbackLocale)
+
+  let viewData: Record<string, any> = {
+    isLoggedIn: false,
+    request: oauthRequest
+  }
+
+  let session = sessionToken
+    ? await authGetSession(sessionToken, ipAddress, userAgent)
+    : null
+
+  let client = ""
+  if (session) {
+    let res
+    try {
+      res = await oauthAuthorizeStart(oauthRequest, session.user_id, sessionToken)
+    } catch (err) {
+      let errorUrl = authorizationErrorUrl(oauthRequest, err)
+      if (errorUrl) redirect(303, errorUrl)
+      error(400, err.message)
+    }
+
+    client = res.client_name
+    viewData.isLoggedIn = true
+    viewData.scopes = res.scopes
+  }
+
+  const translateKeys: TranslateKeys = {
+    ...defaults.translateKeys,
+
+    "wiki-auth-oauth-authorize": {},
+    "wiki-auth-oauth-authorize.intro": { client },
+    "wiki-auth-oauth-authorize.approve": {},
+    "wiki-auth-oauth-authorize.deny": {},
+    "wiki-auth-oauth-authorize.login-required": {}
+  }
+
+  const translated = await translate(locales, translateKeys)
+
+  viewData.internationalization = translated
+
+  // Return to page for rendering
+  return viewData
+}
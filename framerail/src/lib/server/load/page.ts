@@ -11,13 +11,15 @@ export async function loadPage(
   slug: Optional<string>,
   extra: Optional<string>,
   request,
-  cookies
+  cookies,
+  ipAddress: string
 ) {
   // Set up parameters
   const url = new URL(request.url)
   const domain = url.hostname
   const route = slug || extra ? { slug, extra } : null
   const sessionToken = cookies.get("wikijump_token")
+  const userAgent = request.headers.get("User-Agent")
   let locales = parseAcceptLangHeader(request)
 
   // TODO insert user preference at the beginning of the list
@@ -28,7 +30,9 @@ export async function loadPage(
     domain,
     [...locales, defaults.fallbackLocale],
     route,
-    sessionToken
+    sessionToken,
+    ipAddress,
+    userAgent
   )
 
   if (response.data?.site?.locale && !locales.includes(response.data.site.locale)) {
@@ -130,10 +134,18 @@ export async function loadPage(
       "wiki-page-file-select": {},
       "wiki-page-file-name": {},
       "wiki-page-file-license": {},
+      "wiki-page-file-author": {},
+      "wiki-page-file-source-url": {},
+      "wiki-page-file-derivative-of": {},
       "wiki-page-file.name": {},
       "wiki-page-file.created-at": {},
       "wiki-page-file.updated-at": {},
       "wiki-page-file.license": {},
+      "wiki-page-file.license-unknown": {},
+      "wiki-page-file.author": {},
+      "wiki-page-file.source": {},
+      "wiki-page-file.derivative-of": {},
+      "wiki-page-file.download": {},
       "wiki-page-file.mime": {},
       "wiki-page-file.size": {},
       "wiki-page-file.page": {},
@@ -1,20 +1,33 @@
 import defaults from "$lib/defaults"
 import { parseAcceptLangHeader } from "$lib/locales"
-import { getFileByHash } from "$lib/server/deepwell/file"
+import { getBlobByHash } from "$lib/server/deepwell/file"
 import { translate } from "$lib/server/deepwell/translate"
 import { userView } from "$lib/server/deepwell/user"
 import type { TranslateKeys } from "$lib/types"
 import { error, redirect } from "@sveltejs/kit"
 
-export async function loadUser(username?: string, request, cookies) {
+export async function loadUser(
+  username?: string,
+  request,
+  cookies,
+  ipAddress: string
+) {
   const url = new URL(request.url)
   const domain = url.hostname
   const sessionToken = cookies.get("wikijump_token")
+  const userAgent = request.headers.get("User-Agent")
   let locales = parseAcceptLangHeader(request)
 
   if (!locales.includes(defaults.fallbackLocale)) locales.push(defaults.fallbackLocale)
 
-  const response = await userView(domain, locales, sessionToken, username)
+  const response = await userView(
+    domain,
+    locales,
+    sessionToken,
+    ipAddress,
+    userAgent,
+    username
+  )
 
   let translateKeys: TranslateKeys = {
     ...defaults.translateKeys
@@ -36,8 +49,9 @@ export async function loadUser(username?: string, request, cookies) {
       errorStatus = 404
   }
 
-  if (errorStatus === null && username && viewData.user.slug !== username) {
-    redirect(308, `/-/user/${viewData.user.slug}`)
+  // Old names and non-canonical slugs redirect to the user's current slug
+  if (errorStatus === null && viewData.redirect_user) {
+    redirect(308, `/-/user/${viewData.redirect_user}`)
   }
 
   if (errorStatus !== null) {
@@ -56,13 +70,15 @@ export async function loadUser(username?: string, request, cookies) {
       delete viewData.user[sensitiveKeys[i]]
     }
 
-    // Get user avatar image
-    if (viewData.user.avatar_s3_hash !== null) {
-      let avatar = await getFileByHash(new Uint8Array(viewData.user.avatar_s3_hash))
-      let dataurl = `data:${avatar.type};base64,${Buffer.from(
-        await avatar.arrayBuffer()
-      ).toString("base64")}`
-      viewData.user.avatar = dataurl
+    // Get user avatar image URL, preferring the largest WebP copy
+    const avatar = viewData.avatars
+      .filter((avatar) => avatar.format === "webp")
+      .sort((a, b) => b.size - a.size)[0]
+    if (avatar) {
+      viewData.user.avatar = avatar.url
+    } else if (viewData.user.avatar_s3_hash !== null) {
+      let blob = await getBlobByHash(new Uint8Array(viewData.user.avatar_s3_hash))
+      viewData.user.avatar = blob.url
     }
 
     translateKeys = {
@@ -1,5 +1,5 @@
 import { loadPage } from "$lib/server/load/page"
 
-export async function load({ request, cookies }) {
-  return loadPage(null, null, request, cookies)
+export async function load({ request, cookies, getClientAddress }) {
+  return loadPage(null, null, request, cookies, getClientAddress())
 }
@@ -1,5 +1,5 @@
 import { loadPage } from "$lib/server/load/page"
 
-export async function load({ params, request, cookies }) {
-  return loadPage(params.slug, params.extra, request, cookies)
+export async function load({ params, request, cookies, getClientAddress }) {
+  return loadPage(params.slug, params.extra, request, cookies, getClientAddress())
 }
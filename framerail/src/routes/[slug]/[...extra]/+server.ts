@@ -1,33 +1,99 @@
-import { authGetSession } from "$lib/server/auth/getSession"
+import { error } from "@sveltejs/kit"
+import { authGetSession, requestToken } from "$lib/server/auth/getSession"
 import * as page from "$lib/server/deepwell/page"
 import * as pageFile from "$lib/server/deepwell/pageFile"
 
 // Handling of server events from client
 
+const READ_FLAGS = [
+  "history",
+  "revision",
+  "vote-get",
+  "parent-get",
+  "deleted-get",
+  "score",
+  "file-list",
+  "file-history",
+  "file-archive-get"
+]
+
+const ACTIONS = [
+  ...READ_FLAGS,
+  "edit",
+  "move",
+  "rollback",
+  "vote-cast",
+  "vote-cancel",
+  "layout",
+  "parent-set",
+  "restore",
+  "file-upload",
+  "file-delete",
+  "file-edit",
+  "file-move",
+  "file-copy",
+  "file-restore",
+  "file-rollback",
+  "file-archive-import",
+  "file-archive-create"
+]
+
+/**
+ * Gets the action requested by the path.
+ *
+ * Exactly one action must be given, so that the scope checked
+ * is always the one for the action which is performed.
+ */
+function pageAction(extra: string[]): string {
+  let actions = extra.filter((flag) => ACTIONS.includes(flag))
+  if (actions.length !== 1) error(400, "Exactly one page action must be given")
+  return actions[0]
+}
+
+/** The scope a bot's API token must grant to perform this action. */
+function apiScope(action: string, siteId: number | null): string {
+  let scope = "edit-pages"
+  if (READ_FLAGS.includes(action)) {
+    scope = "read-pages"
+  } else if (action === "vote-cast" || action === "vote-cancel") {
+    scope = "vote"
+  } else if (action.startsWith("file-")) {
+    scope = "upload-files"
+  }
+
+  return siteId === null ? scope : `${scope}@${siteId}`
+}
+
 export async function POST(event) {
   let data = await event.request.formData()
   let slug = event.params.slug
 
-  let sessionToken = event.cookies.get("wikijump_token")
+  let sessionToken = requestToken(event)
   let ipAddr = event.getClientAddress()
-  let userAgent = event.cookies.get("User-Agent")
-
-  let session = await authGetSession(sessionToken)
+  let userAgent = event.request.headers.get("User-Agent")
 
   let extra = event.params.extra
     ?.toLowerCase()
     .split("/")
     .filter((flag) => flag.length)
+  let action = pageAction(extra)
 
   let pageIdVal = data.get("page-id")?.toString()
   let pageId = pageIdVal ? parseInt(pageIdVal) : null
   let siteIdVal = data.get("site-id")?.toString()
   let siteId = siteIdVal ? parseInt(siteIdVal) : null
 
+  let session = await authGetSession(
+    sessionToken,
+    ipAddr,
+    userAgent,
+    apiScope(action, siteId)
+  )
+
   let res: object = {}
 
   try {
-    if (extra.includes("edit")) {
+    if (action === "edit") {
       /** Edit or create page. */
       let comments = data.get("comments")?.toString() ?? ""
       let wikitext = data.get("wikitext")?.toString()
@@ -53,7 +119,7 @@ export async function POST(event) {
         tags,
         layout
       )
-    } else if (extra.includes("history")) {
+    } else if (action === "history") {
       /** Retrieve page revision list. */
       let revisionNumberStr = data.get("revision-number")?.toString()
       let revisionNumber = revisionNumberStr ? parseInt(revisionNumberStr) : null
@@ -61,12 +127,13 @@ export async function POST(event) {
       let limit = limitStr ? parseInt(limitStr) : null
 
       res = await page.pageHistory(siteId, pageId, revisionNumber, limit)
-    } else if (extra.includes("move")) {
+    } else if (action === "move") {
       /** Move page to new slug. */
       let comments = data.get("comments")?.toString() ?? ""
       let newSlug = data.get("new-slug")?.toString()
       let lastRevIdStr = data.get("last-revision-id")?.toString().trim()
       let lastRevId = lastRevIdStr ? parseInt(lastRevIdStr) : null
+      let leaveRedirect = data.get("leave-redirect")?.toString() === "true"
 
       res = await page.pageMove(
         siteId,
@@ -75,9 +142,10 @@ export async function POST(event) {
         slug,
         lastRevId,
         newSlug,
-        comments
+        comments,
+        leaveRedirect
       )
-    } else if (extra.includes("revision")) {
+    } else if (action === "revision") {
       let revisionNumberStr = data.get("revision-number")?.toString()
       let compiledHtml = data.get("compiled-html")?.toString() === "true"
       let wikitext = data.get("wikitext")?.toString() === "true"
@@ -90,7 +158,7 @@ export async function POST(event) {
         compiledHtml,
         wikitext
       )
-    } else if (extra.includes("rollback")) {
+    } else if (action === "rollback") {
       let revisionNumberStr = data.get("revision-number")?.toString()
       let revisionNumber = revisionNumberStr ? parseInt(revisionNumberStr) : null
       let comments = data.get("comments")?.toString() ?? ""
@@ -106,20 +174,20 @@ export async function POST(event) {
         revisionNumber,
         comments
       )
-    } else if (extra.includes("vote-get")) {
+    } else if (action === "vote-get") {
       res = await page.pageVoteList(siteId, pageId)
-    } else if (extra.includes("vote-cast")) {
+    } else if (action === "vote-cast") {
       let valueStr = data.get("value")?.toString()
       let value = valueStr ? parseInt(valueStr) : null
 
       res = await page.pageVoteCast(siteId, pageId, session?.user_id, value)
-    } else if (extra.includes("vote-cancel")) {
+    } else if (action === "vote-cancel") {
       res = await page.pageVoteRemove(siteId, pageId, session?.user_id)
-    } else if (extra.includes("layout")) {
+    } else if (action === "layout") {
       let layout = data.get("layout")?.toString().trim() ?? null
 
       res = await page.pageLayout(siteId, pageId, session?.user_id, layout)
-    } else if (extra.includes("parent-set")) {
+    } else if (action === "parent-set") {
       let addParentStr = data.get("add-parents")?.toString().trim() ?? ""
       let addParents = addParentStr.split(" ").filter((p) => p)
       let removeParentStr = data.get("remove-parents")?.toString().trim() ?? ""
@@ -133,17 +201,17 @@ export async function POST(event) {
           addParents.length ? addParents : undefined,
           removeParents.length ? removeParents : undefined
         )
-    } else if (extra.includes("parent-get")) {
+    } else if (action === "parent-get") {
       res = await page.pageParentGet(siteId, pageId, slug)
-    } else if (extra.includes("deleted-get")) {
+    } else if (action === "deleted-get") {
       res = await page.pageDeletedGet(siteId, slug)
-    } else if (extra.includes("restore")) {
+    } else if (action === "restore") {
       let comments = data.get("comments")?.toString() ?? ""
 
       res = await page.pageRestore(siteId, pageId, session?.user_id, comments)
-    } else if (extra.includes("score")) {
+    } else if (action === "score") {
       res = await page.pageScore(siteId, pageId, slug)
-    } else if (extra.includes("file-list")) {
+    } else if (action === "file-list") {
       let deleted = data.get("deleted")?.toString() ?? false
 
       res = await pageFile.pageFileList(
@@ -151,7 +219,7 @@ export async function POST(event) {
         pageId,
         !["false", "null", "", false].includes(deleted)
       )
-    } else if (extra.includes("file-upload")) {
+    } else if (action === "file-upload") {
       let file = data.get("file")?.valueOf()
       let name = data.get("name")?.toString().trim()
       if (name === "") name = undefined // use default file name
@@ -163,10 +231,32 @@ export async function POST(event) {
         session?.user_id,
         name,
         file,
-        null,
+        pageFile.licensingFromForm(data),
+        comments
+      )
+    } else if (action === "file-archive-import") {
+      /** Create a file for each file in an uploaded archive. */
+      let archive = data.get("file")?.valueOf()
+      let comments = data.get("comments")?.toString() ?? ""
+
+      res = await pageFile.pageFileArchiveImport(
+        siteId,
+        pageId,
+        session?.user_id,
+        archive,
+        pageFile.licensingFromForm(data),
         comments
       )
-    } else if (extra.includes("file-delete")) {
+    } else if (action === "file-archive-create") {
+      /** Request a zip archive of the page's files. */
+      res = await pageFile.pageFileArchiveCreate(siteId, pageId, session?.user_id)
+    } else if (action === "file-archive-get") {
+      /** Check on a requested archive, including its download URL once written. */
+      let fileArchiveIdStr = data.get("file-archive-id")?.toString().trim()
+      let fileArchiveId = fileArchiveIdStr ? parseInt(fileArchiveIdStr) : null
+
+      res = await pageFile.pageFileArchiveGet(fileArchiveId)
+    } else if (action === "file-delete") {
       let fileIdStr = data.get("file-id")?.toString().trim()
       let fileId = fileIdStr ? parseInt(fileIdStr) : null
       let lastRevIdStr = data.get("last-revision-id")?.toString().trim()
@@ -181,7 +271,7 @@ export async function POST(event) {
         lastRevId,
         comments
       )
-    } else if (extra.includes("file-edit")) {
+    } else if (action === "file-edit") {
       let fileIdStr = data.get("file-id")?.toString().trim()
       let fileId = fileIdStr ? parseInt(fileIdStr) : null
       let lastRevIdStr = data.get("last-revision-id")?.toString().trim()
@@ -198,11 +288,11 @@ export async function POST(event) {
         fileId,
         name,
         file,
-        null,
+        pageFile.licensingFromForm(data),
         lastRevId,
         comments
       )
-    } else if (extra.includes("file-move")) {
+    } else if (action === "file-move") {
       let fileIdStr = data.get("file-id")?.toString().trim()
       let fileId = fileIdStr ? parseInt(fileIdStr) : null
       let lastRevIdStr = data.get("last-revision-id")?.toString().trim()
@@ -222,7 +312,46 @@ export async function POST(event) {
         name,
         comments
       )
-    } else if (extra.includes("file-restore")) {
+    } else if (action === "file-copy") {
+      let fileIdStr = data.get("file-id")?.toString().trim()
+      let fileId = fileIdStr ? parseInt(fileIdStr) : null
+      let destinationSiteIdStr = data.get("destination-site-id")?.toString().trim()
+      let destinationSiteId = destinationSiteIdStr
+        ? parseInt(destinationSiteIdStr)
+        : siteId
+      let destinationPage = data.get("destination-page")?.toString()
+      let name = data.get("name")?.toString().trim()
+      if (name === "") name = undefined
+      let comments = data.get("comments")?.toString() ?? ""
+
+      // Copying to another site also needs permission there
+      if (destinationSiteId !== siteId) {
+        let destinationSession = await authGetSession(
+          sessionToken,
+          ipAddr,
+          userAgent,
+          apiScope(action, destinationSiteId)
+        )
+
+        if (destinationSession === null) {
+          return new Response(
+            JSON.stringify({ message: "Not permitted to copy files to this site" }),
+            { status: 403 }
+          )
+        }
+      }
+
+      res = await pageFile.pageFileCopy(
+        siteId,
+        pageId,
+        destinationSiteId,
+        destinationPage,
+        session?.user_id,
+        fileId,
+        name,
+        comments
+      )
+    } else if (action === "file-restore") {
       let fileIdStr = data.get("file-id")?.toString().trim()
       let fileId = fileIdStr ? parseInt(fileIdStr) : null
       let newPage = data.get("new-page")?.toString().trim()
@@ -240,7 +369,7 @@ export async function POST(event) {
         newName,
         comments
       )
-    } else if (extra.includes("file-history")) {
+    } else if (action === "file-history") {
       /** Retrieve file revision list. */
       let fileIdStr = data.get("file-id")?.toString().trim()
       let fileId = fileIdStr ? parseInt(fileIdStr) : null
@@ -250,7 +379,7 @@ export async function POST(event) {
       let limit = limitStr ? parseInt(limitStr) : null
 
       res = await pageFile.pageFileHistory(siteId, pageId, fileId, revisionNumber, limit)
-    } else if (extra.includes("file-rollback")) {
+    } else if (action === "file-rollback") {
       let fileIdStr = data.get("file-id")?.toString().trim()
       let fileId = fileIdStr ? parseInt(fileIdStr) : null
       let revisionNumberStr = data.get("revision-number")?.toString()
@@ -287,16 +416,21 @@ export async function DELETE(event) {
   let data = await event.request.formData()
   let slug = event.params.slug
 
-  let sessionToken = event.cookies.get("wikijump_token")
+  let sessionToken = requestToken(event)
   let ipAddr = event.getClientAddress()
-  let userAgent = event.cookies.get("User-Agent")
-
-  let session = await authGetSession(sessionToken)
+  let userAgent = event.request.headers.get("User-Agent")
 
   let pageIdVal = data.get("page-id")?.toString()
   let pageId = pageIdVal ? parseInt(pageIdVal) : null
   let siteIdVal = data.get("site-id")?.toString()
   let siteId = siteIdVal ? parseInt(siteIdVal) : null
+
+  let session = await authGetSession(
+    sessionToken,
+    ipAddr,
+    userAgent,
+    apiScope("delete", siteId)
+  )
   let comments = data.get("comments")?.toString() ?? ""
   let lastRevIdStr = data.get("last-revision-id")?.toString().trim()
   let lastRevId = lastRevIdStr ? parseInt(lastRevIdStr) : null
@@ -0,0 +1,5 @@
+import { loadFile } from "$lib/server/load/file"
+
+export async function load({ params, request, cookies, getClientAddress }) {
+  return loadFile(params.slug, params.name, request, cookies, getClientAddress())
+}
@@ -0,0 +1,95 @@
+import { localBlobGet, localBlobPut } from "$lib/server/deepwell/file"
+
+// Presigned URLs for blobs, when DEEPWELL keeps them in local storage.
+// Requests are checked and served by DEEPWELL, this only passes them along.
+
+/** The largest chunk read or written in one request, the same as in DEEPWELL */
+const CHUNK_SIZE = 4 * 1024 * 1024
+
+/** Error codes from DEEPWELL with their own HTTP status */
+const ERROR_STATUS = {
+  2016: 404, // BlobNotFound
+  5000: 403 // InvalidAuthentication
+}
+
+function errorResponse(error): Response {
+  return new Response(error.message, { status: ERROR_STATUS[error.code] ?? 400 })
+}
+
+/** Download blob. */
+export async function GET(event) {
+  let path = event.params.path
+  let query = Object.fromEntries(event.url.searchParams)
+
+  let first
+  try {
+    first = await localBlobGet(path, query, 0)
+  } catch (error) {
+    return errorResponse(error)
+  }
+
+  let offset = first.data.length
+  let stream = new ReadableStream({
+    start(controller) {
+      controller.enqueue(first.data)
+      if (offset >= first.total_size) controller.close()
+    },
+    async pull(controller) {
+      let chunk = await localBlobGet(path, query, offset)
+      offset += chunk.data.length
+      controller.enqueue(chunk.data)
+      if (offset >= chunk.total_size || !chunk.data.length) controller.close()
+    }
+  })
+
+  let headers = {
+    "Content-Type": first.content_type,
+    "Content-Length": first.total_size.toString()
+  }
+  if (first.cache_control) headers["Cache-Control"] = first.cache_control
+  if (first.content_disposition)
+    headers["Content-Disposition"] = first.content_disposition
+
+  return new Response(stream, { headers })
+}
+
+/** Upload blob, or one part of a multipart upload. */
+export async function PUT(event) {
+  let path = event.params.path
+  let query = Object.fromEntries(event.url.searchParams)
+  let reader = event.request.body?.getReader()
+
+  try {
+    let offset = 0
+    let pending = new Uint8Array(0)
+
+    // Chunks are forwarded as the body arrives. A full chunk is held
+    // until more data follows, so the final one can be marked as last.
+    while (reader) {
+      let { done, value } = await reader.read()
+      if (done) break
+
+      pending = concatBytes(pending, value)
+      while (pending.length > CHUNK_SIZE) {
+        await localBlobPut(path, query, offset, pending.subarray(0, CHUNK_SIZE), false)
+        offset += CHUNK_SIZE
+        pending = pending.subarray(CHUNK_SIZE)
+      }
+    }
+
+    let res = await localBlobPut(path, query, offset, pending, true)
+    let headers = res.etag ? { ETag: res.etag } : {}
+    return new Response(null, { headers })
+  } catch (error) {
+    await reader?.cancel()
+    return errorResponse(error)
+  }
+}
+
+function concatBytes(a: Uint8Array, b: Uint8Array): Uint8Array {
+  if (!a.length) return b
+  let result = new Uint8Array(a.length + b.length)
+  result.set(a)
+  result.set(b, a.length)
+  return result
+}
@@ -0,0 +1,36 @@
+import { error } from "@sveltejs/kit"
+import { siteFromDomain, siteRecentChangesFeed } from "$lib/server/deepwell/site"
+
+const DEFAULT_LIMIT = 50
+const MAXIMUM_LIMIT = 200
+
+// Atom or RSS feed of recent changes on the site
+
+export async function GET(event) {
+  let format = event.params.format
+  if (format !== "atom" && format !== "rss") {
+    error(404, "Unknown feed format")
+  }
+
+  let site = await siteFromDomain(event.url.hostname)
+  if (site === null) {
+    error(404, "Site not found")
+  }
+
+  let params = event.url.searchParams
+  let limit = parseInt(params.get("limit") ?? "") || DEFAULT_LIMIT
+  let category = params.get("category") ?? undefined
+  let user = params.get("user") ?? undefined
+
+  let feed = await siteRecentChangesFeed(
+    site.site_id,
+    format,
+    Math.min(Math.max(limit, 1), MAXIMUM_LIMIT),
+    category,
+    user
+  )
+
+  return new Response(feed.body, {
+    headers: { "content-type": `${feed.mime}; charset=utf-8` }
+  })
+}
@@ -0,0 +1,69 @@
+import defaults from "$lib/defaults"
+import { parseAcceptLangHeader } from "$lib/locales"
+import { authOidcFinish, authOidcLoginStart } from "$lib/server/auth/oidc"
+import { error, redirect } from "@sveltejs/kit"
+
+// Logging in with an external identity provider.
+//
+// Visiting this route sends the user to the provider, which then
+// sends them back here with an authorization code to finish logging in.
+//
+// The state for the attempt is kept in a cookie, so that it can only
+// be finished in the same browser it was started in.
+
+const STATE_COOKIE = "wikijump_oidc_state"
+const STATE_COOKIE_PATH = "/-/login/oidc"
+
+export async function GET(event) {
+  let params = event.url.searchParams
+  let state = params.get("state")
+  let code = params.get("code")
+
+  if (!state || !code) {
+    let res
+    try {
+      res = await authOidcLoginStart(event.params.provider)
+    } catch (err) {
+      error(400, err.message)
+    }
+
+    event.cookies.set(STATE_COOKIE, res.state, {
+      path: STATE_COOKIE_PATH,
+      httpOnly: true,
+      secure: true,
+      sameSite: "lax"
+    })
+
+    redirect(303, res.authorization_url)
+  }
+
+  let cookieState = event.cookies.get(STATE_COOKIE)
+  event.cookies.delete(STATE_COOKIE, { path: STATE_COOKIE_PATH })
+  if (!cookieState) {
+    error(400, "Login attempt was not started in this browser")
+  }
+
+  let ipAddress = event.getClientAddress()
+  let userAgent = event.request.headers.get("User-Agent")
+  let locales = parseAcceptLangHeader(event.request)
+  if (!locales.includes(defaults.fallbackLocale)) locales.push(defaults.fallbackLocale)
+
+  let res
+  try {
+    res = await authOidcFinish(state, code, cookieState, ipAddress, userAgent, locales)
+  } catch (err) {
+    error(400, err.message)
+  }
+
+  if (res.session_token) {
+    event.cookies.set("wikijump_token", res.session_token, {
+      path: "/",
+      httpOnly: true,
+      secure: true,
+      sameSite: "lax"
+    })
+  }
+
+  // Users with MFA still need to verify it on the login page
+  redirect(303, res.needs_mfa ? "/-/login" : "/")
+}
@@ -0,0 +1,5 @@
+import { loadOauthAuthorizePage } from "$lib/server/load/oauth"
+
+export async function load({ request, cookies, getClientAddress }) {
+  return loadOauthAuthorizePage(request, cookies, getClientAddress())
+}
@@ -0,0 +1,32 @@
+import { authGetSession } from "$lib/server/auth/getSession"
+import { authorizationRequest, oauthAuthorizeFinish } from "$lib/server/auth/oauth"
+
+export async function POST(event) {
+  let data = await event.request.formData()
+
+  let sessionToken = event.cookies.get("wikijump_token")
+  let ipAddr = event.getClientAddress()
+  let userAgent = event.request.headers.get("User-Agent")
+
+  let approve = data.get("approve")?.toString() === "true"
+
+  try {
+    let session = await authGetSession(sessionToken, ipAddr, userAgent)
+    let res = await oauthAuthorizeFinish(
+      authorizationRequest(data),
+      session?.user_id,
+      sessionToken,
+      approve
+    )
+
+    return new Response(JSON.stringify(res))
+  } catch (error) {
+    return new Response(
+      JSON.stringify({
+        message: error.message,
+        code: error.code,
+        data: error.data
+      })
+    )
+  }
+}
@@ -0,0 +1,18 @@
+import {
+  clientErrorResponse,
+  clientRequest,
+  clientResponse,
+  oauthTokenIntrospect
+} from "$lib/server/auth/oauth"
+
+// Token introspection, per RFC 7662.
+
+export async function POST(event) {
+  let body = await clientRequest(event.request)
+
+  try {
+    return clientResponse(await oauthTokenIntrospect(body))
+  } catch (err) {
+    return clientErrorResponse(err)
+  }
+}
@@ -0,0 +1,19 @@
+import {
+  clientErrorResponse,
+  clientRequest,
+  clientResponse,
+  oauthTokenRevoke
+} from "$lib/server/auth/oauth"
+
+// Token revocation, per RFC 7009.
+
+export async function POST(event) {
+  let body = await clientRequest(event.request)
+
+  try {
+    await oauthTokenRevoke(body)
+    return clientResponse({})
+  } catch (err) {
+    return clientErrorResponse(err)
+  }
+}
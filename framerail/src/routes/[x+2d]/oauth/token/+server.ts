@@ -0,0 +1,19 @@
+import {
+  clientErrorResponse,
+  clientRequest,
+  clientResponse,
+  oauthToken
+} from "$lib/server/auth/oauth"
+
+// The OAuth token endpoint, for clients to exchange
+// authorization codes and refresh tokens.
+
+export async function POST(event) {
+  let body = await clientRequest(event.request)
+
+  try {
+    return clientResponse(await oauthToken(body))
+  } catch (err) {
+    return clientErrorResponse(err)
+  }
+}
@@ -1,12 +1,14 @@
-import { authGetSession } from "$lib/server/auth/getSession"
+import { authGetSession, requestToken } from "$lib/server/auth/getSession"
 import { userEdit } from "$lib/server/deepwell/user"
 
 export async function POST(event) {
   let data = await event.request.formData()
-  let sessionToken = event.cookies.get("wikijump_token")
+  let sessionToken = requestToken(event)
+  let ipAddr = event.getClientAddress()
+  let userAgent = event.request.headers.get("User-Agent")
 
   try {
-    let session = await authGetSession(sessionToken)
+    let session = await authGetSession(sessionToken, ipAddr, userAgent, "edit-profile")
 
     let name = data.get("name")?.toString().trim()
     let email = data.get("email")?.toString().trim()
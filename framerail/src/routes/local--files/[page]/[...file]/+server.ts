@@ -0,0 +1,20 @@
+import { getImageUrl } from "$lib/server/deepwell/file"
+
+// File links as written in wikitext, such as for image blocks.
+// Redirects to a download URL, or to a thumbnail if "?size=" is given.
+
+export async function GET(event) {
+  let size = event.url.searchParams.get("size")
+  let url = await getImageUrl(
+    event.url.hostname,
+    event.params.page,
+    event.params.file,
+    size ? parseInt(size, 10) || null : null
+  )
+
+  if (url === null) {
+    return new Response("File not found", { status: 404 })
+  }
+
+  return new Response(null, { status: 302, headers: { Location: url } })
+}
@@ -18,3 +18,20 @@ password-recovery = Password Recovery
   .email-sent =
     An email has been sent to the provided email address.
     Click the link inside of it to change your account's password.
+
+wiki-auth-account-locked =
+  .subject = Your account has been temporarily locked
+  .body =
+    There were too many failed attempts to log in to your account, so logins have been disabled for { $minutes ->
+      [1] 1 minute.
+      *[other] { $minutes } minutes.
+    }
+
+    If this was you, you can try again once the lockout expires.
+    If it was not, someone may be trying to guess your password. Consider changing it, and enabling multi-factor authentication.
+
+wiki-auth-oauth-authorize = Authorize Application
+  .intro = { $client } would like to access your account. It will be able to:
+  .approve = Allow
+  .deny = Deny
+  .login-required = You must be logged in to authorize an application.
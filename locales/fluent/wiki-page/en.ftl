@@ -64,6 +64,12 @@ wiki-page-file-name = File name:
 
 wiki-page-file-license = File license:
 
+wiki-page-file-author = Author:
+
+wiki-page-file-source-url = Source URL:
+
+wiki-page-file-derivative-of = Derived from:
+
 wiki-page-file-move-destination-page = Destination page
 
 wiki-page-file =
@@ -71,9 +77,14 @@ wiki-page-file =
   .created-at = Created at
   .updated-at = Updated at
   .license = License
+  .license-unknown = Unknown
   .mime = File type
   .size = File size
   .page = Page
+  .author = Author
+  .source = Source
+  .derivative-of = Derived from
+  .download = Download
 
 wiki-page-file-revision-type = Type
   .create = Create
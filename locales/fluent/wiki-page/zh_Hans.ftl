@@ -64,6 +64,12 @@ wiki-page-file-name = 档案名：
 
 wiki-page-file-license = 授权协议：
 
+wiki-page-file-author = 作者：
+
+wiki-page-file-source-url = 来源网址：
+
+wiki-page-file-derivative-of = 衍生自：
+
 wiki-page-file-move-destination-page = 新页面网址
 
 wiki-page-file =
@@ -71,9 +77,14 @@ wiki-page-file =
   .created-at = 创建日期
   .updated-at = 最后编辑日期
   .license = 授权协议
+  .license-unknown = 未知
   .mime = 档案类型
   .size = 档案大小
   .page = 页面
+  .author = 作者
+  .source = 来源
+  .derivative-of = 衍生自
+  .download = 下载
 
 wiki-page-file-revision-type = 类型
   .create = 创建